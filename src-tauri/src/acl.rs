@@ -0,0 +1,197 @@
+//! POSIX ACLs alongside plain mode bits, read with `getfacl` and written
+//! with `setfacl` over exec, for servers that grant access through ACLs.
+
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::errors::{AppError, AppResult};
+use crate::ssh_manager::{shell_quote, SshSession, Transport};
+
+// ─── Data Structures ───────────────────────────────────────────────────
+
+/// One ACL entry, e.g. `user:alice:r-x` or `default:group::r--`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AclEntry {
+    /// "user", "group", "mask" or "other".
+    pub tag: String,
+    /// User or group name; `None` for the owning user/group, mask and other.
+    #[serde(default)]
+    pub qualifier: Option<String>,
+    /// Three characters from `rwx-`, e.g. "r-x".
+    pub perms: String,
+    /// Part of a directory's default ACL (inherited by new children).
+    #[serde(default)]
+    pub default: bool,
+}
+
+/// Mode bits plus ACL of a remote path (safe to send to JS).
+#[derive(Debug, Clone, Serialize)]
+pub struct FilePermissions {
+    pub path: String,
+    pub mode: Option<u32>,
+    pub owner: Option<String>,
+    pub group: Option<String>,
+    /// `false` when `getfacl` is missing or the filesystem has no ACLs;
+    /// the editor should then fall back to mode bits only.
+    pub acl_supported: bool,
+    pub acl: Vec<AclEntry>,
+}
+
+// ─── Parsing ───────────────────────────────────────────────────────────
+
+/// Parse `getfacl` output into (owner, group, entries).
+fn parse_getfacl(text: &str) -> (Option<String>, Option<String>, Vec<AclEntry>) {
+    let mut owner = None;
+    let mut group = None;
+    let mut entries = Vec::new();
+    for line in text.lines() {
+        let line = line.trim();
+        if let Some(rest) = line.strip_prefix("# owner:") {
+            owner = Some(rest.trim().to_string());
+            continue;
+        }
+        if let Some(rest) = line.strip_prefix("# group:") {
+            group = Some(rest.trim().to_string());
+            continue;
+        }
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        // Drop trailing "#effective:r--" annotations.
+        let line = line.split('#').next().unwrap_or(line).trim();
+        let (default, line) = match line.strip_prefix("default:") {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        let fields: Vec<&str> = line.splitn(3, ':').collect();
+        let [tag, qualifier, perms] = fields[..] else {
+            continue;
+        };
+        entries.push(AclEntry {
+            tag: tag.to_string(),
+            qualifier: (!qualifier.is_empty()).then(|| qualifier.to_string()),
+            perms: perms.trim().to_string(),
+            default,
+        });
+    }
+    (owner, group, entries)
+}
+
+/// Render entries as a `setfacl --set` spec, validating each field.
+fn acl_spec(entries: &[AclEntry]) -> AppResult<String> {
+    let mut parts = Vec::with_capacity(entries.len());
+    for e in entries {
+        if !matches!(e.tag.as_str(), "user" | "group" | "mask" | "other") {
+            return Err(AppError::Other(format!("Invalid ACL tag: {}", e.tag)));
+        }
+        let perms_ok = e.perms.len() == 3
+            && e.perms
+                .chars()
+                .zip(['r', 'w', 'x'])
+                .all(|(c, want)| c == want || c == '-');
+        if !perms_ok {
+            return Err(AppError::Other(format!(
+                "Invalid ACL permissions: {}",
+                e.perms
+            )));
+        }
+        let qualifier = e.qualifier.as_deref().unwrap_or("");
+        if qualifier.contains([',', ':']) || qualifier.contains(char::is_whitespace) {
+            return Err(AppError::Other(format!(
+                "Invalid ACL qualifier: {qualifier}"
+            )));
+        }
+        let prefix = if e.default { "default:" } else { "" };
+        parts.push(format!("{prefix}{}:{qualifier}:{}", e.tag, e.perms));
+    }
+    Ok(parts.join(","))
+}
+
+// ─── Operations ────────────────────────────────────────────────────────
+
+async fn mode_bits(session: &Arc<SshSession>, path: &str) -> Option<u32> {
+    match session.transport().await.ok()? {
+        Transport::Sftp(sftp) => {
+            let meta = sftp.metadata(path).await.ok()?;
+            meta.permissions.map(|p| p & 0o7777)
+        }
+        Transport::Scp => {
+            let output = session
+                .exec(&format!("stat -L -c '%a' -- {}", shell_quote(path)))
+                .await
+                .ok()?;
+            u32::from_str_radix(String::from_utf8_lossy(&output.stdout).trim(), 8).ok()
+        }
+    }
+}
+
+/// Read mode bits and, where `getfacl` works, the ACL of `path`. A server
+/// that refuses exec still reports the mode bits, without an ACL.
+pub async fn get_permissions(session: &Arc<SshSession>, path: &str) -> AppResult<FilePermissions> {
+    let start = std::time::Instant::now();
+    let mode = mode_bits(session, path).await;
+
+    let getfacl = session
+        .exec(&format!("getfacl -p -- {}", shell_quote(path)))
+        .await;
+    let (owner, group, acl, acl_supported) = match getfacl {
+        Ok(output) if output.exit_status == Some(0) => {
+            let (owner, group, acl) = parse_getfacl(&String::from_utf8_lossy(&output.stdout));
+            (owner, group, acl, true)
+        }
+        Ok(output) => {
+            log::info!(
+                "[SFTP] getfacl unavailable for \"{}\": {}",
+                path,
+                String::from_utf8_lossy(&output.stderr).trim(),
+            );
+            (None, None, Vec::new(), false)
+        }
+        // Without exec there is nothing but the SFTP mode bits to go on.
+        Err(e) if mode.is_some() => {
+            log::info!("[SFTP] getfacl could not run for \"{}\": {}", path, e);
+            (None, None, Vec::new(), false)
+        }
+        Err(e) => return Err(e),
+    };
+
+    log::info!(
+        "[PERF] get_permissions \"{}\" — {:.2}ms | acl entries: {}",
+        path,
+        start.elapsed().as_secs_f64() * 1000.0,
+        acl.len(),
+    );
+    Ok(FilePermissions {
+        path: path.to_string(),
+        mode,
+        owner,
+        group,
+        acl_supported,
+        acl,
+    })
+}
+
+/// Replace the ACL of `path` with `entries` (`setfacl --set`), then return
+/// the resulting permissions.
+pub async fn set_acl(
+    session: &Arc<SshSession>,
+    path: &str,
+    entries: &[AclEntry],
+) -> AppResult<FilePermissions> {
+    let spec = acl_spec(entries)?;
+    let output = session
+        .exec(&format!(
+            "setfacl --set {} -- {}",
+            shell_quote(&spec),
+            shell_quote(path)
+        ))
+        .await?;
+    if output.exit_status != Some(0) {
+        return Err(AppError::Sftp(format!(
+            "Failed to set ACL: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    log::info!("[SFTP] set ACL on \"{}\" — {}", path, spec);
+    get_permissions(session, path).await
+}
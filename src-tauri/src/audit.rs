@@ -0,0 +1,112 @@
+use serde::Serialize;
+use std::sync::Arc;
+
+use crate::errors::AppResult;
+use crate::sftp_ops::{self, TreeNode};
+use crate::ssh_manager::SshSession;
+
+/// Stop auditing after this many nodes so huge trees stay responsive.
+const MAX_AUDIT_NODES: usize = 100_000;
+
+/// Filename patterns that usually hold secrets.
+const SENSITIVE_NAMES: &[&str] = &[".env", ".htpasswd", "id_rsa", "id_ecdsa", "id_ed25519"];
+const SENSITIVE_EXTENSIONS: &[&str] = &["pem", "key", "p12", "pfx"];
+
+// ─── Data Structures ───────────────────────────────────────────────────
+
+/// Why a path was flagged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditIssue {
+    /// Anyone on the server can modify it (sticky directories excepted).
+    WorldWritable,
+    /// Owned by a different user than the audit root.
+    WrongOwner,
+    /// Secrets file (`.env`, private key…) readable beyond its owner.
+    LooseSecret,
+}
+
+/// A flagged path with its current mode and owner.
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditFinding {
+    pub path: String,
+    pub is_dir: bool,
+    pub mode: u32,
+    pub uid: Option<u32>,
+    pub issues: Vec<AuditIssue>,
+}
+
+/// Result of a permission audit.
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditReport {
+    pub root: String,
+    /// Owner of the root; other owners are reported as `wrong_owner`.
+    pub expected_uid: Option<u32>,
+    pub scanned: usize,
+    /// True if the walk stopped at the node limit.
+    pub truncated: bool,
+    pub findings: Vec<AuditFinding>,
+}
+
+// ─── Audit ─────────────────────────────────────────────────────────────
+
+fn is_sensitive(path: &str) -> bool {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    if SENSITIVE_NAMES.contains(&name) || name.starts_with(".env.") {
+        return true;
+    }
+    name.rsplit_once('.')
+        .is_some_and(|(_, ext)| SENSITIVE_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+}
+
+fn check(node: &TreeNode, expected_uid: Option<u32>) -> Vec<AuditIssue> {
+    let mut issues = Vec::new();
+    let sticky_dir = node.is_dir && node.mode & 0o1000 != 0;
+    if node.mode & 0o002 != 0 && !sticky_dir {
+        issues.push(AuditIssue::WorldWritable);
+    }
+    if expected_uid.is_some() && node.uid.is_some() && node.uid != expected_uid {
+        issues.push(AuditIssue::WrongOwner);
+    }
+    if !node.is_dir && node.mode & 0o077 != 0 && is_sensitive(&node.path) {
+        issues.push(AuditIssue::LooseSecret);
+    }
+    issues
+}
+
+/// Walk `root` and flag world-writable paths, paths not owned by the root's
+/// owner, and secrets files with group/other permissions.
+pub async fn permission_audit(session: &Arc<SshSession>, root: &str) -> AppResult<AuditReport> {
+    let start = std::time::Instant::now();
+    let (nodes, truncated) = sftp_ops::walk_tree(session, root, MAX_AUDIT_NODES).await?;
+    let expected_uid = nodes.first().and_then(|n| n.uid);
+
+    let findings: Vec<AuditFinding> = nodes
+        .iter()
+        .filter_map(|node| {
+            let issues = check(node, expected_uid);
+            (!issues.is_empty()).then(|| AuditFinding {
+                path: node.path.clone(),
+                is_dir: node.is_dir,
+                mode: node.mode,
+                uid: node.uid,
+                issues,
+            })
+        })
+        .collect();
+
+    log::info!(
+        "[PERF] permission_audit \"{}\" — {:.2}ms | scanned: {} | findings: {}",
+        root,
+        start.elapsed().as_secs_f64() * 1000.0,
+        nodes.len(),
+        findings.len(),
+    );
+    Ok(AuditReport {
+        root: root.to_string(),
+        expected_uid,
+        scanned: nodes.len(),
+        truncated,
+        findings,
+    })
+}
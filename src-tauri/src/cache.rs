@@ -0,0 +1,346 @@
+//! Shared on-disk cache for thumbnails, full images, file previews and
+//! directory listings.
+//!
+//! Each entry is a data file `<key>.<ext>` plus a `<key>.meta` JSON sidecar
+//! recording the remote mtime and size it was built from. Freshness is an
+//! exact match on those validators (like an HTTP ETag), and eviction is LRU
+//! over whole entries, with hits refreshing the entry's access time.
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::errors::{AppError, AppResult};
+use crate::memory;
+
+/// Free space to leave on the device; caching stops below this.
+const MIN_FREE_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Uncached originals older than this are removed on the next bypass.
+const BYPASS_MAX_AGE: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+
+// ─── Cache Kinds ───────────────────────────────────────────────────────
+
+/// Static description of one cache: its subdirectory and size cap.
+pub struct CacheKind {
+    subdir: &'static str,
+    max_bytes: u64,
+    evicting: AtomicBool,
+}
+
+/// WebP thumbnails (50 MB cap).
+pub static THUMBNAILS: CacheKind = CacheKind {
+    subdir: "thumbnails",
+    max_bytes: 50 * 1024 * 1024,
+    evicting: AtomicBool::new(false),
+};
+
+/// Full-size images opened in the viewer (200 MB cap).
+pub static IMAGES: CacheKind = CacheKind {
+    subdir: "image_cache",
+    max_bytes: 200 * 1024 * 1024,
+    evicting: AtomicBool::new(false),
+};
+
+/// Text/binary previews as JSON (20 MB cap).
+pub static PREVIEWS: CacheKind = CacheKind {
+    subdir: "previews",
+    max_bytes: 20 * 1024 * 1024,
+    evicting: AtomicBool::new(false),
+};
+
+/// Directory listings saved for offline browsing (10 MB cap).
+pub static LISTINGS: CacheKind = CacheKind {
+    subdir: "listings",
+    max_bytes: 10 * 1024 * 1024,
+    evicting: AtomicBool::new(false),
+};
+
+impl CacheKind {
+    /// Size cap, quartered in low-memory mode.
+    fn max_bytes(&self) -> u64 {
+        memory::pick(self.max_bytes, self.max_bytes / 4)
+    }
+
+    /// Open this cache under the app cache root, creating its directory.
+    pub fn open(&'static self, root: &Path) -> AppResult<DiskCache> {
+        let dir = root.join(self.subdir);
+        std::fs::create_dir_all(&dir)
+            .map_err(|e| AppError::Io(format!("Cannot create {} cache dir: {e}", self.subdir)))?;
+        Ok(DiskCache { kind: self, dir })
+    }
+}
+
+/// Pick the cache root with the most free space among `candidates`
+/// (e.g. internal vs external storage on Android), skipping any that cannot
+/// be created.
+pub fn pick_root(candidates: &[PathBuf]) -> Option<PathBuf> {
+    candidates
+        .iter()
+        .filter(|dir| std::fs::create_dir_all(dir).is_ok())
+        .max_by_key(|dir| fs2::available_space(dir).unwrap_or(0))
+        .cloned()
+}
+
+/// Whether `dir` can take `needed` more bytes while keeping the reserve.
+/// Unknown free space is treated as enough; the write itself will tell.
+fn has_room(dir: &Path, needed: u64) -> bool {
+    fs2::available_space(dir).map_or(true, |free| free >= needed + MIN_FREE_BYTES)
+}
+
+// ─── Data Structures ───────────────────────────────────────────────────
+
+/// Remote attributes an entry is validated against. `None` means unknown.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Validator {
+    pub mtime: Option<u64>,
+    pub size: Option<u64>,
+}
+
+impl Validator {
+    pub fn is_known(&self) -> bool {
+        self.mtime.is_some() || self.size.is_some()
+    }
+
+    /// Every known field must equal the stored one.
+    fn matches(&self, stored: &Validator) -> bool {
+        (self.mtime.is_none() || self.mtime == stored.mtime)
+            && (self.size.is_none() || self.size == stored.size)
+    }
+}
+
+/// Sidecar metadata stored next to each entry.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EntryMeta {
+    pub validator: Validator,
+    /// Average colour of the image, for placeholders.
+    #[serde(default)]
+    pub color: Option<String>,
+}
+
+/// A cached entry on disk.
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub path: PathBuf,
+    pub meta: EntryMeta,
+}
+
+/// Result of a cache lookup.
+pub enum Lookup {
+    /// Matches the caller's validator.
+    Fresh(Entry),
+    /// Present, but there was no validator to check it against.
+    Unvalidated(Entry),
+    /// Present but built from a different remote version.
+    Stale(Entry),
+    Miss,
+}
+
+/// An opened cache directory.
+#[derive(Clone)]
+pub struct DiskCache {
+    kind: &'static CacheKind,
+    dir: PathBuf,
+}
+
+// ─── Operations ────────────────────────────────────────────────────────
+
+/// Stable cache key for a remote path (URL-safe base64, so it has no dots).
+pub fn key(remote_path: &str) -> String {
+    base64::Engine::encode(
+        &base64::engine::general_purpose::URL_SAFE_NO_PAD,
+        remote_path.as_bytes(),
+    )
+}
+
+impl DiskCache {
+    fn data_path(&self, key: &str, ext: &str) -> PathBuf {
+        self.dir.join(format!("{key}.{ext}"))
+    }
+
+    fn meta_path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{key}.meta"))
+    }
+
+    /// Read an entry's metadata without touching its data.
+    pub fn meta(&self, key: &str) -> Option<EntryMeta> {
+        let data = std::fs::read(self.meta_path(key)).ok()?;
+        serde_json::from_slice(&data).ok()
+    }
+
+    /// Look up `key` and classify it against `validator`. Hits refresh the
+    /// entry's access time for LRU eviction.
+    pub fn lookup(&self, key: &str, ext: &str, validator: Validator) -> Lookup {
+        let path = self.data_path(key, ext);
+        if !path.is_file() {
+            return Lookup::Miss;
+        }
+        // Entries without a sidecar predate validation; treat them as stale.
+        let meta = self.meta(key).unwrap_or_default();
+        let entry = Entry { path, meta };
+
+        if !validator.is_known() {
+            Lookup::Unvalidated(entry)
+        } else if validator.matches(&entry.meta.validator) {
+            touch(&entry.path);
+            Lookup::Fresh(entry)
+        } else {
+            Lookup::Stale(entry)
+        }
+    }
+
+    /// Write an entry and its sidecar, then evict in the background if the
+    /// cache is over its cap. Fails without writing when the device is low
+    /// on space, so callers can skip caching and serve the data directly.
+    pub async fn store(
+        &self,
+        key: &str,
+        ext: &str,
+        data: &[u8],
+        meta: &EntryMeta,
+    ) -> AppResult<PathBuf> {
+        let needed = data.len() as u64;
+        if !has_room(&self.dir, needed) {
+            // Try shrinking our own cache first; the device may just be full of us.
+            let dir = self.dir.clone();
+            let target = dir_size(&dir).saturating_sub(needed + MIN_FREE_BYTES);
+            tokio::task::spawn_blocking(move || evict_lru(&dir, target))
+                .await
+                .ok();
+            if !has_room(&self.dir, needed) {
+                return Err(AppError::Io(
+                    "Not enough free disk space to cache file".into(),
+                ));
+            }
+        }
+
+        let path = self.data_path(key, ext);
+        if let Err(e) = tokio::fs::write(&path, data).await {
+            // Don't leave a truncated entry behind (e.g. ENOSPC mid-write).
+            tokio::fs::remove_file(&path).await.ok();
+            return Err(AppError::Io(format!("Failed to write cache entry: {e}")));
+        }
+        let meta_json = serde_json::to_vec(meta)
+            .map_err(|e| AppError::Other(format!("Failed to serialize cache metadata: {e}")))?;
+        tokio::fs::write(self.meta_path(key), meta_json)
+            .await
+            .map_err(|e| AppError::Io(format!("Failed to write cache metadata: {e}")))?;
+        self.evict_in_background();
+        Ok(path)
+    }
+
+    /// Where to stream a file too large to cache. The `bypass` subdirectory
+    /// is outside the size cap, so one huge original cannot evict every
+    /// other entry; files there older than `BYPASS_MAX_AGE` are removed.
+    pub fn bypass_path(&self, key: &str, ext: &str) -> AppResult<PathBuf> {
+        let dir = self.dir.join("bypass");
+        std::fs::create_dir_all(&dir)
+            .map_err(|e| AppError::Io(format!("Cannot create bypass dir: {e}")))?;
+        for entry in std::fs::read_dir(&dir).into_iter().flatten().flatten() {
+            let expired = entry
+                .metadata()
+                .and_then(|m| m.modified())
+                .ok()
+                .and_then(|t| t.elapsed().ok())
+                .is_some_and(|age| age > BYPASS_MAX_AGE);
+            if expired {
+                std::fs::remove_file(entry.path()).ok();
+            }
+        }
+        Ok(dir.join(format!("{key}.{ext}")))
+    }
+
+    fn evict_in_background(&self) {
+        if self.kind.evicting.swap(true, Ordering::Relaxed) {
+            return;
+        }
+        let kind = self.kind;
+        let dir = self.dir.clone();
+        tokio::task::spawn_blocking(move || {
+            evict_lru(&dir, kind.max_bytes());
+            kind.evicting.store(false, Ordering::Relaxed);
+        });
+    }
+}
+
+/// Total size of the files directly inside `dir`.
+fn dir_size(dir: &Path) -> u64 {
+    std::fs::read_dir(dir)
+        .map(|rd| {
+            rd.filter_map(|e| e.ok()?.metadata().ok())
+                .filter(|m| m.is_file())
+                .map(|m| m.len())
+                .sum()
+        })
+        .unwrap_or(0)
+}
+
+/// Bump an entry's mtime, which eviction uses as its last-access time.
+fn touch(path: &Path) {
+    if let Ok(file) = std::fs::File::options().write(true).open(path) {
+        file.set_modified(std::time::SystemTime::now()).ok();
+    }
+}
+
+/// Evict least-recently-used entries (data file plus sidecars, grouped by
+/// key) until the directory is under `max_bytes`.
+fn evict_lru(dir: &Path, max_bytes: u64) {
+    let Ok(rd) = std::fs::read_dir(dir) else {
+        return;
+    };
+
+    // key → (files, total size, most recent mtime)
+    let mut entries: std::collections::HashMap<String, (Vec<PathBuf>, u64, u64)> =
+        std::collections::HashMap::new();
+    let mut total_size: u64 = 0;
+    for entry in rd.filter_map(|e| e.ok()) {
+        let Ok(meta) = entry.metadata() else {
+            continue;
+        };
+        if !meta.is_file() {
+            continue;
+        }
+        let name = entry.file_name().to_string_lossy().to_string();
+        let key = name.split('.').next().unwrap_or(&name).to_string();
+        let mtime = meta
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let group = entries.entry(key).or_default();
+        group.0.push(entry.path());
+        group.1 += meta.len();
+        group.2 = group.2.max(mtime);
+        total_size += meta.len();
+    }
+
+    if total_size <= max_bytes {
+        return;
+    }
+
+    let mut groups: Vec<_> = entries.into_values().collect();
+    groups.sort_by_key(|&(_, _, mtime)| mtime);
+
+    let to_free = total_size - max_bytes;
+    let mut freed: u64 = 0;
+    let mut evicted = 0u32;
+    for (files, size, _) in &groups {
+        if freed >= to_free {
+            break;
+        }
+        for file in files {
+            std::fs::remove_file(file).ok();
+        }
+        freed += size;
+        evicted += 1;
+    }
+
+    log::info!(
+        "[CACHE] eviction: removed {} entries, freed {:.1} MB (was {:.1} MB, cap {:.1} MB)",
+        evicted,
+        freed as f64 / (1024.0 * 1024.0),
+        total_size as f64 / (1024.0 * 1024.0),
+        max_bytes as f64 / (1024.0 * 1024.0),
+    );
+}
@@ -1,10 +1,65 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
 use std::sync::Arc;
-use tauri::{Manager, State};
+use tauri::ipc::Channel;
+use tauri::{Emitter, Manager, State};
 
+use crate::acl::{self, AclEntry, FilePermissions};
+use crate::audit::{self, AuditReport};
+use crate::cache::{self, DiskCache, Validator};
+use crate::credentials;
+use crate::crontab::{self, Crontab};
+use crate::dir_diff::DirDiff;
+use crate::discovery::{self, DiscoveredHost};
+use crate::disk_usage::{self, DiskUsage};
+use crate::doc_preview::{self, DocumentPreview};
+use crate::download_queue::{DownloadQueue, QueuedDownload, QueuedKind};
 use crate::errors::{AppError, AppResult};
+use crate::events::SessionEvent;
+use crate::file_types::{self, FileCategory};
+use crate::gallery::{self, GalleryExport};
+use crate::ignore;
 use crate::key_store::{KeyInfo, KeyStore, KeyType, SUPPORTED_KEY_TYPES};
-use crate::sftp_ops::{self, FileEntry, FilePreview};
-use crate::ssh_manager::SshSessionManager;
+use crate::known_hosts::{HostKeyInfo, HostKeyPin};
+use crate::markdown::{self, MarkdownPreview};
+use crate::memory::{self, MemoryStatus};
+use crate::network::{self, NetworkStatus, NetworkType};
+use crate::offline::{OfflinePin, OfflineStore, PinSyncSummary};
+use crate::op_tracker::PendingOp;
+use crate::open_with::{self, OpenWithApp};
+use crate::post_actions::{self, PostAction};
+use crate::profiles::{AuthMethod as ProfileAuth, Profile, ProfileConnection, ProfileStore};
+use crate::receipts::{ReceiptStore, UploadReceipt};
+use crate::search::{self, SearchMatch, SearchOptions, SearchSummary};
+use crate::search_hits;
+use crate::services::{self, ServiceAction, ServiceActionResult, ServiceUnit, Sudo};
+use crate::settings::{self, AppSettings, HostLimits, Keepalive, NetworkPolicy, SettingsStore};
+use crate::sftp_ops::{
+    self, ChecksumAlgo, DirSize, FileEntry, FilePreview, FsStats, ListProgress, PreviewChunk,
+    RemoteChecksum,
+};
+use crate::share::{self, ProfileShare};
+use crate::shell::{self, ShellEvent, ShellInput};
+use crate::snapshots::{self, DirSnapshot, OfflineFile};
+use crate::split::{self, JoinResult, SplitResult};
+use crate::ssh_manager::{
+    AuthPrompt, CommandOutput, RemoteEnv, SessionMotd, SshSession, SshSessionManager,
+    StartupOutput, DEFAULT_EXEC_TIMEOUT,
+};
+use crate::structured::{self, StructuredPreview};
+use crate::tail::{self, TailEvent};
+use crate::text_stats::{self, TextStats};
+use crate::timeouts;
+use crate::transfer::{
+    self, BackupSummary, SyncOptions, TransferEvent, TransferSummary, UploadItem, VerifyReport,
+};
+use crate::tree_ops::{self, DeleteSummary, TreeChange, TreeChangeSummary};
+use crate::type_stats::{self, TypeStats};
+use crate::vault_backend::{
+    passphrase_verifier, verify_passphrase, JsonVault, StrongholdVault, VaultBackend,
+    VaultBackendKind, VaultPaths,
+};
+use crate::windows::{WindowRegistry, WindowView};
 
 // ─── Key Management Commands ───────────────────────────────────────────
 
@@ -27,6 +82,204 @@ pub async fn delete_key(key_store: State<'_, Arc<KeyStore>>, name: String) -> Ap
     key_store.delete_key(&name).await
 }
 
+#[tauri::command]
+pub async fn export_key(
+    key_store: State<'_, Arc<KeyStore>>,
+    name: String,
+    dest_path: String,
+    passphrase: String,
+    current_passphrase: Option<String>,
+) -> AppResult<()> {
+    key_store
+        .export_key(
+            &name,
+            std::path::Path::new(&dest_path),
+            &passphrase,
+            current_passphrase.as_deref(),
+        )
+        .await
+}
+
+/// Load a vault key into the system ssh-agent so other tools can use it.
+/// Refused unless `user_confirmed` is set, i.e. the user approved exporting
+/// the key in a prompt. `confirm` asks the agent to confirm each use.
+#[tauri::command]
+pub async fn agent_add_key(
+    key_store: State<'_, Arc<KeyStore>>,
+    name: String,
+    passphrase: Option<String>,
+    lifetime: Option<u32>,
+    confirm: Option<bool>,
+    user_confirmed: Option<bool>,
+) -> AppResult<()> {
+    if !user_confirmed.unwrap_or(false) {
+        return Err(AppError::KeyStore(format!(
+            "Adding \"{name}\" to ssh-agent needs the user's confirmation; pass user_confirmed=true"
+        )));
+    }
+    log::info!("[KEYS] adding \"{}\" to ssh-agent", name);
+    key_store
+        .add_to_agent(
+            &name,
+            passphrase.as_deref(),
+            lifetime,
+            confirm.unwrap_or(false),
+        )
+        .await
+}
+
+/// Fail unless the vault can be unlocked again once locked: a Stronghold
+/// vault has its password, a JSON vault needs an unlock passphrase set.
+fn ensure_lockable(key_store: &KeyStore, settings: &AppSettings) -> AppResult<()> {
+    if key_store.backend_kind() == VaultBackendKind::Json
+        && settings.vault_passphrase_verifier.is_none()
+    {
+        return Err(AppError::KeyStore(
+            "Set a vault passphrase before locking the vault".into(),
+        ));
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn vault_lock(
+    key_store: State<'_, Arc<KeyStore>>,
+    settings: State<'_, Arc<SettingsStore>>,
+) -> AppResult<()> {
+    ensure_lockable(&key_store, &settings.get().await?)?;
+    key_store.lock_vault();
+    Ok(())
+}
+
+/// Set the passphrase that unlocks the JSON vault. Changing an existing
+/// one requires `current`.
+#[tauri::command]
+pub async fn vault_set_passphrase(
+    settings: State<'_, Arc<SettingsStore>>,
+    paths: State<'_, Arc<VaultPaths>>,
+    passphrase: String,
+    current: Option<String>,
+) -> AppResult<()> {
+    if passphrase.is_empty() {
+        return Err(AppError::KeyStore(
+            "The vault passphrase cannot be empty".into(),
+        ));
+    }
+    if let Some(verifier) = settings.get().await?.vault_passphrase_verifier {
+        let current = current.unwrap_or_default();
+        if !verify_passphrase(&paths.unlock_salt, &current, &verifier) {
+            return Err(AppError::KeyStore(
+                "The current vault passphrase is wrong".into(),
+            ));
+        }
+    }
+    let verifier = passphrase_verifier(&paths.unlock_salt, &passphrase);
+    settings.set_vault_passphrase_verifier(verifier).await?;
+    Ok(())
+}
+
+/// Unlock the vault with its passphrase: the Stronghold password, or the
+/// JSON vault's unlock passphrase. Nothing is unlocked unless it verifies.
+#[tauri::command]
+pub async fn vault_unlock(
+    key_store: State<'_, Arc<KeyStore>>,
+    settings: State<'_, Arc<SettingsStore>>,
+    paths: State<'_, Arc<VaultPaths>>,
+    passphrase: String,
+) -> AppResult<()> {
+    match key_store.backend_kind() {
+        VaultBackendKind::Stronghold => {
+            let vault = StrongholdVault::open(&paths.snapshot, &paths.salt, &passphrase)?;
+            key_store.set_backend(Arc::new(vault)).await;
+        }
+        VaultBackendKind::Json => {
+            let verifier = settings.get().await?.vault_passphrase_verifier;
+            let valid =
+                verifier.is_some_and(|v| verify_passphrase(&paths.unlock_salt, &passphrase, &v));
+            if !valid {
+                return Err(AppError::KeyStore("Wrong vault passphrase".into()));
+            }
+        }
+    }
+    key_store.unlock_vault();
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn vault_is_locked(key_store: State<'_, Arc<KeyStore>>) -> AppResult<bool> {
+    Ok(key_store.is_locked())
+}
+
+#[tauri::command]
+pub async fn vault_set_auto_lock(
+    key_store: State<'_, Arc<KeyStore>>,
+    settings: State<'_, Arc<SettingsStore>>,
+    secs: Option<u64>,
+) -> AppResult<AppSettings> {
+    if secs.is_some() {
+        ensure_lockable(&key_store, &settings.get().await?)?;
+    }
+    let updated = settings.set_vault_auto_lock_secs(secs).await?;
+    key_store.set_auto_lock(secs.map(std::time::Duration::from_secs));
+    Ok(updated)
+}
+
+#[tauri::command]
+pub async fn vault_backend(key_store: State<'_, Arc<KeyStore>>) -> AppResult<VaultBackendKind> {
+    Ok(key_store.backend_kind())
+}
+
+/// Open the Stronghold vault selected in settings with its password.
+#[tauri::command]
+pub async fn vault_open_stronghold(
+    key_store: State<'_, Arc<KeyStore>>,
+    paths: State<'_, Arc<VaultPaths>>,
+    password: String,
+) -> AppResult<()> {
+    let vault = StrongholdVault::open(&paths.snapshot, &paths.salt, &password)?;
+    key_store.set_backend(Arc::new(vault)).await;
+    Ok(())
+}
+
+/// Move all stored keys to `backend` and make it the vault from now on.
+/// `password` is required when moving to Stronghold. Returns the number of
+/// keys migrated.
+#[tauri::command]
+pub async fn vault_set_backend(
+    key_store: State<'_, Arc<KeyStore>>,
+    settings: State<'_, Arc<SettingsStore>>,
+    paths: State<'_, Arc<VaultPaths>>,
+    backend: VaultBackendKind,
+    password: Option<String>,
+) -> AppResult<usize> {
+    if key_store.backend_kind() == backend {
+        return Ok(0);
+    }
+    let json = JsonVault::new(paths.json.clone());
+    let target: Arc<dyn VaultBackend> = match backend {
+        VaultBackendKind::Json => Arc::new(JsonVault::new(paths.json.clone())),
+        VaultBackendKind::Stronghold => {
+            let password = password
+                .filter(|p| !p.is_empty())
+                .ok_or_else(|| AppError::KeyStore("A vault password is required".into()))?;
+            Arc::new(StrongholdVault::open(
+                &paths.snapshot,
+                &paths.salt,
+                &password,
+            )?)
+        }
+    };
+    let from = key_store.backend_kind();
+    let migrated = key_store.migrate_to(target).await?;
+    settings.set_vault_backend(backend).await?;
+    // Only now that settings point at the new backend is the plaintext
+    // JSON copy removed.
+    if from == VaultBackendKind::Json && backend != VaultBackendKind::Json {
+        json.remove()?;
+    }
+    Ok(migrated)
+}
+
 #[tauri::command]
 pub async fn list_supported_key_types() -> AppResult<Vec<KeyType>> {
     Ok(SUPPORTED_KEY_TYPES.to_vec())
@@ -37,33 +290,266 @@ pub async fn get_key(key_store: State<'_, Arc<KeyStore>>, name: String) -> AppRe
     key_store.retrieve_key_pem(&name).await
 }
 
+// ─── Settings Commands ────────────────────────────────────────────────
+
+#[tauri::command]
+pub async fn get_settings(settings: State<'_, Arc<SettingsStore>>) -> AppResult<AppSettings> {
+    settings.get().await
+}
+
+#[tauri::command]
+pub async fn set_download_dir(
+    settings: State<'_, Arc<SettingsStore>>,
+    dir: Option<String>,
+) -> AppResult<AppSettings> {
+    log::info!("[CMD] set_download_dir — dir={:?}", dir);
+    settings.set_download_dir(dir).await
+}
+
+#[tauri::command]
+pub async fn set_default_modes(
+    settings: State<'_, Arc<SettingsStore>>,
+    file_mode: Option<u32>,
+    dir_mode: Option<u32>,
+) -> AppResult<AppSettings> {
+    log::info!(
+        "[CMD] set_default_modes — file_mode={:?} dir_mode={:?}",
+        file_mode.map(|m| format!("{m:o}")),
+        dir_mode.map(|m| format!("{m:o}")),
+    );
+    settings.set_default_modes(file_mode, dir_mode).await
+}
+
+/// Force low-memory mode on or off, or pass `None` to detect it from device
+/// RAM. Takes effect immediately.
+#[tauri::command]
+pub async fn set_low_memory_mode(
+    settings: State<'_, Arc<SettingsStore>>,
+    enabled: Option<bool>,
+) -> AppResult<MemoryStatus> {
+    settings.set_low_memory_mode(enabled).await?;
+    Ok(memory::apply(enabled))
+}
+
+#[tauri::command]
+pub async fn memory_status(settings: State<'_, Arc<SettingsStore>>) -> AppResult<MemoryStatus> {
+    Ok(memory::apply(settings.get().await?.low_memory_mode))
+}
+
+/// Set the default keepalive interval and missed-reply limit for new
+/// connections.
+#[tauri::command]
+pub async fn set_keepalive(
+    settings: State<'_, Arc<SettingsStore>>,
+    keepalive: Keepalive,
+) -> AppResult<AppSettings> {
+    log::info!("[CMD] set_keepalive — {:?}", keepalive);
+    settings.set_keepalive(keepalive).await
+}
+
+/// Replace the user's file-type overrides, e.g. `{"log.1": "text"}`.
+/// Takes effect for listings made afterwards.
+#[tauri::command]
+pub async fn set_file_type_overrides(
+    settings: State<'_, Arc<SettingsStore>>,
+    overrides: HashMap<String, FileCategory>,
+) -> AppResult<AppSettings> {
+    log::info!(
+        "[CMD] set_file_type_overrides — {} entries",
+        overrides.len()
+    );
+    let updated = settings.set_file_type_overrides(overrides).await?;
+    file_types::apply(&updated.file_type_overrides);
+    Ok(updated)
+}
+
+/// Replace the global ignore patterns (`host` unset) or a host's extra
+/// ones. Takes effect for listings and transfers started afterwards.
+#[tauri::command]
+pub async fn set_ignore_patterns(
+    settings: State<'_, Arc<SettingsStore>>,
+    host: Option<String>,
+    patterns: Vec<String>,
+) -> AppResult<AppSettings> {
+    log::info!(
+        "[CMD] set_ignore_patterns — host={:?} {} patterns",
+        host,
+        patterns.len()
+    );
+    let updated = settings
+        .set_ignore_patterns(host.as_deref(), patterns)
+        .await?;
+    ignore::apply(&updated.ignore_patterns, &updated.host_ignore_patterns);
+    Ok(updated)
+}
+
+/// Set (or clear) the time limits for metadata requests and for each
+/// transfer chunk. Takes effect immediately.
+#[tauri::command]
+pub async fn set_timeouts(
+    settings: State<'_, Arc<SettingsStore>>,
+    metadata_secs: Option<u64>,
+    transfer_secs: Option<u64>,
+) -> AppResult<AppSettings> {
+    log::info!(
+        "[CMD] set_timeouts — metadata={:?} transfer={:?}",
+        metadata_secs,
+        transfer_secs,
+    );
+    let updated = settings.set_timeouts(metadata_secs, transfer_secs).await?;
+    timeouts::apply(metadata_secs, transfer_secs);
+    Ok(updated)
+}
+
+/// Record the network type detected by the webview. Paused transfers
+/// resume once the device leaves cellular.
+#[tauri::command]
+pub async fn set_network_type(network: NetworkType) -> AppResult<NetworkStatus> {
+    network::set_current(network);
+    Ok(network::status())
+}
+
+#[tauri::command]
+pub async fn network_status() -> AppResult<NetworkStatus> {
+    Ok(network::status())
+}
+
+#[tauri::command]
+pub async fn set_network_policy(
+    settings: State<'_, Arc<SettingsStore>>,
+    policy: NetworkPolicy,
+) -> AppResult<AppSettings> {
+    log::info!("[CMD] set_network_policy — {:?}", policy);
+    let updated = settings.set_network_policy(policy).await?;
+    network::apply(&updated.network_policy);
+    Ok(updated)
+}
+
+/// Set (or clear, with `None`) channel, parallelism and chunk-size limits
+/// for `host`. Applies to sessions opened afterwards.
+#[tauri::command]
+pub async fn set_host_limits(
+    settings: State<'_, Arc<SettingsStore>>,
+    host: String,
+    limits: Option<HostLimits>,
+) -> AppResult<AppSettings> {
+    log::info!("[CMD] set_host_limits — host={} limits={:?}", host, limits);
+    settings.set_host_limits(&host, limits).await
+}
+
+#[tauri::command]
+pub async fn set_upload_concurrency(
+    settings: State<'_, Arc<SettingsStore>>,
+    concurrency: Option<usize>,
+) -> AppResult<AppSettings> {
+    settings.set_upload_concurrency(concurrency).await
+}
+
+#[tauri::command]
+pub async fn set_max_in_memory_bytes(
+    settings: State<'_, Arc<SettingsStore>>,
+    limit: Option<u64>,
+) -> AppResult<AppSettings> {
+    settings.set_max_in_memory_bytes(limit).await
+}
+
+/// Set (or clear) the size above which recursive downloads need confirming.
+#[tauri::command]
+pub async fn set_confirm_dir_download_bytes(
+    settings: State<'_, Arc<SettingsStore>>,
+    limit: Option<u64>,
+) -> AppResult<AppSettings> {
+    settings.set_confirm_dir_download_bytes(limit).await
+}
+
+/// Set (or clear) the size above which full images are not cached.
+#[tauri::command]
+pub async fn set_image_cache_bypass_bytes(
+    settings: State<'_, Arc<SettingsStore>>,
+    limit: Option<u64>,
+) -> AppResult<AppSettings> {
+    settings.set_image_cache_bypass_bytes(limit).await
+}
+
+#[tauri::command]
+pub async fn set_transfer_retry(
+    settings: State<'_, Arc<SettingsStore>>,
+    attempts: Option<u32>,
+    backoff_ms: Option<u64>,
+) -> AppResult<AppSettings> {
+    log::info!(
+        "[CMD] set_transfer_retry — attempts={:?} backoff_ms={:?}",
+        attempts,
+        backoff_ms,
+    );
+    settings.set_transfer_retry(attempts, backoff_ms).await
+}
+
+#[tauri::command]
+pub async fn get_default_download_dir(
+    app: tauri::AppHandle,
+    settings: State<'_, Arc<SettingsStore>>,
+) -> AppResult<String> {
+    let dir = resolve_download_dir(&app, &settings, None).await?;
+    Ok(dir.to_string_lossy().to_string())
+}
+
 // ─── SSH Session Commands ─────────────────────────────────────────────
 
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn ssh_connect(
     session_mgr: State<'_, Arc<SshSessionManager>>,
+    settings: State<'_, Arc<SettingsStore>>,
     host: String,
     port: u16,
     user: String,
     key_name: Option<String>,
     passphrase: Option<String>,
     password: Option<String>,
+    new_password: Option<String>,
+    startup_commands: Option<Vec<String>>,
+    host_key_pin: Option<String>,
+    host_key_strict: Option<bool>,
+    keepalive: Option<Keepalive>,
+    attempt_id: Option<String>,
 ) -> AppResult<String> {
     log::info!("[SSH] Connecting to {}@{}:{}", user, host, port);
     let start = std::time::Instant::now();
-    let result = if let Some(pw) = password {
-        session_mgr
-            .connect_with_password(&host, port, &user, &pw)
-            .await
-    } else if let Some(ref kn) = key_name {
-        session_mgr
-            .connect_with_key(&host, port, &user, kn, passphrase.as_deref())
-            .await
-    } else {
-        Err(AppError::Ssh(
-            "Either key_name or password must be provided".into(),
-        ))
+    let current = settings.get().await?;
+    let limits = current.limits_for(&host);
+    let keepalive = keepalive.unwrap_or_default().or(current.keepalive);
+    let pin = HostKeyPin {
+        key: host_key_pin,
+        strict: host_key_strict.unwrap_or(false),
     };
+    let connect = async {
+        if let Some(pw) = password {
+            let new_password = new_password.as_deref();
+            session_mgr
+                .connect_with_password(
+                    &host,
+                    port,
+                    &user,
+                    &pw,
+                    new_password,
+                    limits,
+                    keepalive,
+                    pin,
+                )
+                .await
+        } else if let Some(ref kn) = key_name {
+            let passphrase = passphrase.as_deref();
+            session_mgr
+                .connect_with_key(&host, port, &user, kn, passphrase, limits, keepalive, pin)
+                .await
+        } else {
+            Err(AppError::Ssh(
+                "Either key_name or password must be provided".into(),
+            ))
+        }
+    };
+    let result = session_mgr.abortable(attempt_id.as_deref(), connect).await;
     match &result {
         Ok(session_id) => log::info!(
             "[SSH] Connected in {:.2}ms — session_id={}",
@@ -76,309 +562,2513 @@ pub async fn ssh_connect(
             e,
         ),
     }
-    result
+    let session_id = result?;
+
+    let commands = startup_commands.unwrap_or_default();
+    if !commands.is_empty() {
+        let session = session_mgr.get_session(&session_id).await?;
+        session.run_startup_commands(&commands).await;
+    }
+    Ok(session_id)
 }
 
+/// Connect with keyboard-interactive authentication (e.g. OTP prompts).
+/// Each round is emitted as an `auth-prompt` event carrying `auth_id`; the
+/// frontend answers it with `ssh_auth_respond`. With `share_session`, an
+/// open connection to the same account is reused without prompting.
 #[tauri::command]
-pub async fn ssh_test_connection(
+#[allow(clippy::too_many_arguments)]
+pub async fn ssh_connect_interactive(
+    app: tauri::AppHandle,
     session_mgr: State<'_, Arc<SshSessionManager>>,
+    settings: State<'_, Arc<SettingsStore>>,
     host: String,
     port: u16,
     user: String,
-    key_name: Option<String>,
-    passphrase: Option<String>,
-    password: Option<String>,
-) -> AppResult<()> {
-    log::info!("[SSH] Testing connection to {}@{}:{}", user, host, port);
+    auth_id: String,
+    startup_commands: Option<Vec<String>>,
+    host_key_pin: Option<String>,
+    host_key_strict: Option<bool>,
+    keepalive: Option<Keepalive>,
+    attempt_id: Option<String>,
+    share_session: Option<bool>,
+) -> AppResult<String> {
+    log::info!(
+        "[SSH] Connecting to {}@{}:{} (keyboard-interactive)",
+        user,
+        host,
+        port
+    );
     let start = std::time::Instant::now();
-    let result = if let Some(pw) = password {
-        session_mgr
-            .test_connection_with_password(&host, port, &user, &pw)
-            .await
-    } else if let Some(ref kn) = key_name {
-        session_mgr
-            .test_connection_with_key(&host, port, &user, kn, passphrase.as_deref())
-            .await
-    } else {
-        Err(AppError::Ssh(
-            "Either key_name or password must be provided".into(),
-        ))
+    let current = settings.get().await?;
+    let limits = current.limits_for(&host);
+    let keepalive = keepalive.unwrap_or_default().or(current.keepalive);
+    let pin = HostKeyPin {
+        key: host_key_pin,
+        strict: host_key_strict.unwrap_or(false),
+    };
+    let on_prompt = |prompt: &AuthPrompt| {
+        if let Err(e) = app.emit("auth-prompt", prompt) {
+            log::warn!("[SSH] failed to emit auth-prompt: {}", e);
+        }
     };
+    let share = share_session.unwrap_or(false);
+    let connect = session_mgr.connect_keyboard_interactive(
+        &host, port, &user, &auth_id, limits, keepalive, pin, share, on_prompt,
+    );
+    let result = session_mgr.abortable(attempt_id.as_deref(), connect).await;
     match &result {
-        Ok(()) => log::info!(
-            "[SSH] Test connection succeeded in {:.2}ms",
+        Ok(session_id) => log::info!(
+            "[SSH] Connected in {:.2}ms — session_id={}",
             start.elapsed().as_secs_f64() * 1000.0,
+            session_id,
         ),
         Err(e) => log::error!(
-            "[SSH] Test connection failed after {:.2}ms — {}",
+            "[SSH] Connection failed after {:.2}ms — {}",
             start.elapsed().as_secs_f64() * 1000.0,
             e,
         ),
     }
-    result
+    let session_id = result?;
+
+    let commands = startup_commands.unwrap_or_default();
+    if !commands.is_empty() {
+        let session = session_mgr.get_session(&session_id).await?;
+        session.run_startup_commands(&commands).await;
+    }
+    Ok(session_id)
+}
+
+/// Answer the keyboard-interactive prompt pending under `auth_id`, one
+/// answer per field. `None` cancels the login.
+#[tauri::command]
+pub async fn ssh_auth_respond(
+    session_mgr: State<'_, Arc<SshSessionManager>>,
+    auth_id: String,
+    answers: Option<Vec<String>>,
+) -> AppResult<bool> {
+    Ok(session_mgr.answer_prompt(&auth_id, answers))
+}
+
+/// Abort the connect started with `attempt_id`, whichever phase it is in;
+/// the connect command then fails as cancelled. Returns `false` if no such
+/// attempt is running.
+#[tauri::command]
+pub async fn abort_connect(
+    session_mgr: State<'_, Arc<SshSessionManager>>,
+    attempt_id: String,
+) -> AppResult<bool> {
+    log::info!("[CMD] abort_connect — attempt_id={}", attempt_id);
+    Ok(session_mgr.abort_connect(&attempt_id))
+}
+
+// ─── Profile Commands ──────────────────────────────────────────────────
+
+/// Saved connection profiles, sorted by name.
+#[tauri::command]
+pub async fn profile_list(profiles: State<'_, Arc<ProfileStore>>) -> AppResult<Vec<Profile>> {
+    profiles.list().await
+}
+
+/// Save a new profile (empty `id`) or update an existing one.
+#[tauri::command]
+pub async fn profile_save(
+    profiles: State<'_, Arc<ProfileStore>>,
+    profile: Profile,
+) -> AppResult<Profile> {
+    let saved = profiles.save(profile).await?;
+    log::info!("[CMD] profile_save \"{}\" ({})", saved.name, saved.id);
+    Ok(saved)
+}
+
+/// Delete a saved profile. Returns `false` if it did not exist.
+#[tauri::command]
+pub async fn profile_delete(profiles: State<'_, Arc<ProfileStore>>, id: String) -> AppResult<bool> {
+    profiles.delete(&id).await
+}
+
+/// Connect using a saved profile. Secrets are not stored, so `password`
+/// (or a key `passphrase`) is passed in; keyboard-interactive profiles emit
+/// `auth-prompt` events under `auth_id`, answered with `ssh_auth_respond`.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn profile_connect(
+    app: tauri::AppHandle,
+    session_mgr: State<'_, Arc<SshSessionManager>>,
+    settings: State<'_, Arc<SettingsStore>>,
+    profiles: State<'_, Arc<ProfileStore>>,
+    id: String,
+    password: Option<String>,
+    passphrase: Option<String>,
+    auth_id: Option<String>,
+    attempt_id: Option<String>,
+    share_session: Option<bool>,
+) -> AppResult<ProfileConnection> {
+    let start = std::time::Instant::now();
+    let profile = profiles.get(&id).await?;
+    log::info!(
+        "[SSH] Connecting to {}@{}:{} (profile \"{}\")",
+        profile.user,
+        profile.host,
+        profile.port,
+        profile.name,
+    );
+    let current = settings.get().await?;
+    let limits = current.limits_for(&profile.host);
+    let keepalive = current.keepalive;
+    let pin = HostKeyPin {
+        key: profile.host_key_pin.clone(),
+        strict: profile.host_key_strict,
+    };
+    let (host, port, user) = (&profile.host, profile.port, &profile.user);
+    let connect = async {
+        match profile.auth_method {
+            ProfileAuth::Key => {
+                let key_name = profile.key_name.as_deref().unwrap_or_default();
+                let passphrase = passphrase.as_deref();
+                session_mgr
+                    .connect_with_key(
+                        host, port, user, key_name, passphrase, limits, keepalive, pin,
+                    )
+                    .await
+            }
+            ProfileAuth::Password => {
+                let password = match (password, &profile.credential_source) {
+                    (Some(password), _) => Some(password),
+                    (None, Some(source)) => {
+                        credentials::lookup_password(source, host, port, user).await?
+                    }
+                    (None, None) => None,
+                };
+                let password = password
+                    .ok_or_else(|| AppError::Ssh("A password is needed for this profile".into()))?;
+                session_mgr
+                    .connect_with_password(
+                        host, port, user, &password, None, limits, keepalive, pin,
+                    )
+                    .await
+            }
+            ProfileAuth::Interactive => {
+                let auth_id = auth_id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+                let on_prompt = |prompt: &AuthPrompt| {
+                    if let Err(e) = app.emit("auth-prompt", prompt) {
+                        log::warn!("[SSH] failed to emit auth-prompt: {}", e);
+                    }
+                };
+                let share = share_session.unwrap_or(false);
+                session_mgr
+                    .connect_keyboard_interactive(
+                        host, port, user, &auth_id, limits, keepalive, pin, share, on_prompt,
+                    )
+                    .await
+            }
+        }
+    };
+    let session_id = session_mgr
+        .abortable(attempt_id.as_deref(), connect)
+        .await?;
+    log::info!(
+        "[SSH] Connected in {:.2}ms — session_id={}",
+        start.elapsed().as_secs_f64() * 1000.0,
+        session_id,
+    );
+    if profile.local_path.is_some() {
+        let session = session_mgr.get_session(&session_id).await?;
+        session.set_local_dir(profile.local_path.clone());
+    }
+    if !profile.startup_commands.is_empty() {
+        let session = session_mgr.get_session(&session_id).await?;
+        session
+            .run_startup_commands(&profile.startup_commands)
+            .await;
+    }
+    Ok(ProfileConnection {
+        session_id,
+        start_path: profile.start_path,
+        local_path: profile.local_path,
+    })
+}
+
+/// Import an OpenSSH `known_hosts` file (plain, wildcard, hashed and
+/// `[host]:port` entries). Returns the number of entries added.
+#[tauri::command]
+pub async fn known_hosts_import(
+    session_mgr: State<'_, Arc<SshSessionManager>>,
+    path: String,
+) -> AppResult<usize> {
+    log::info!("[SSH] importing known_hosts from {}", path);
+    let text = tokio::fs::read_to_string(&path)
+        .await
+        .map_err(|e| AppError::Io(format!("Failed to read {path}: {e}")))?;
+    session_mgr.import_known_hosts(&text)
+}
+
+/// Host key the server presented for a session, to pin in its profile.
+#[tauri::command]
+pub async fn ssh_session_host_key(
+    session_mgr: State<'_, Arc<SshSessionManager>>,
+    session_id: String,
+) -> AppResult<HostKeyInfo> {
+    let session = session_mgr.get_session(&session_id).await?;
+    Ok(session.host_key.clone())
+}
+
+/// Output of the profile's startup commands for a connected session.
+#[tauri::command]
+pub async fn ssh_startup_output(
+    session_mgr: State<'_, Arc<SshSessionManager>>,
+    session_id: String,
+) -> AppResult<Vec<StartupOutput>> {
+    Ok(session_mgr.get_session(&session_id).await?.startup_output())
+}
+
+/// Local directory paired with a session (from its profile, or set with
+/// `ssh_set_local_dir`), if any.
+#[tauri::command]
+pub async fn ssh_local_dir(
+    session_mgr: State<'_, Arc<SshSessionManager>>,
+    session_id: String,
+) -> AppResult<Option<String>> {
+    Ok(session_mgr.get_session(&session_id).await?.local_dir())
+}
+
+/// Pair a local directory with a session, so downloads without a
+/// `save_dir` land there and uploads read from it. `None` clears the
+/// pairing.
+#[tauri::command]
+pub async fn ssh_set_local_dir(
+    session_mgr: State<'_, Arc<SshSessionManager>>,
+    session_id: String,
+    path: Option<String>,
+) -> AppResult<()> {
+    log::info!(
+        "[CMD] ssh_set_local_dir — session={} path={:?}",
+        session_id,
+        path
+    );
+    if let Some(path) = &path {
+        if !std::path::Path::new(path).is_dir() {
+            return Err(AppError::Io(format!("{path} is not a local directory")));
+        }
+    }
+    session_mgr
+        .get_session(&session_id)
+        .await?
+        .set_local_dir(path);
+    Ok(())
+}
+
+/// A local path for a transfer on `session`, resolved against its paired
+/// directory: a relative `path` is taken inside it, and no `path` means
+/// the paired directory itself.
+fn paired_path(session: &SshSession, path: Option<String>) -> AppResult<PathBuf> {
+    match (path, session.local_dir()) {
+        (Some(path), _) if std::path::Path::new(&path).is_absolute() => Ok(PathBuf::from(path)),
+        (Some(path), Some(dir)) => Ok(PathBuf::from(dir).join(path)),
+        (None, Some(dir)) => Ok(PathBuf::from(dir)),
+        (Some(path), None) => Err(AppError::Io(format!(
+            "{path} is relative and no local directory is paired with the session"
+        ))),
+        (None, None) => Err(AppError::Io(
+            "No local directory given and none is paired with the session".into(),
+        )),
+    }
+}
+
+/// Run a shell command on the session's server (e.g. `df -h`) and return
+/// its output and exit status. Stops after `timeout_secs` (default 60).
+#[tauri::command]
+pub async fn ssh_exec(
+    session_mgr: State<'_, Arc<SshSessionManager>>,
+    session_id: String,
+    command: String,
+    timeout_secs: Option<u64>,
+) -> AppResult<CommandOutput> {
+    log::debug!("[CMD] ssh_exec called — command=\"{}\"", command);
+    let limit = timeout_secs.map_or(DEFAULT_EXEC_TIMEOUT, std::time::Duration::from_secs);
+    let session = session_mgr.get_session(&session_id).await?;
+    session.run_command(&command, limit).await
+}
+
+/// Pre-auth banner and login MOTD for a session, where servers often
+/// announce maintenance windows.
+#[tauri::command]
+pub async fn ssh_session_motd(
+    session_mgr: State<'_, Arc<SshSessionManager>>,
+    session_id: String,
+) -> AppResult<SessionMotd> {
+    Ok(session_mgr.get_session(&session_id).await?.motd().await)
+}
+
+/// Timeline of connection events for a session (connects, channels,
+/// fallbacks, dropped links, failed transfers), oldest first.
+#[tauri::command]
+pub async fn ssh_session_events(
+    session_mgr: State<'_, Arc<SshSessionManager>>,
+    session_id: String,
+) -> AppResult<Vec<SessionEvent>> {
+    Ok(session_mgr
+        .get_session(&session_id)
+        .await?
+        .events
+        .snapshot())
+}
+
+/// Remote `$HOME`, `$SHELL`, OS and helper binaries, cached per session.
+#[tauri::command]
+pub async fn ssh_remote_env(
+    session_mgr: State<'_, Arc<SshSessionManager>>,
+    session_id: String,
+) -> AppResult<RemoteEnv> {
+    let session = session_mgr.get_session(&session_id).await?;
+    session.remote_env().await.cloned()
+}
+
+/// Free space, free inodes and quota for the filesystem holding `path`.
+/// Pass the size and file count of a planned upload to be warned if it
+/// would not fit.
+#[tauri::command]
+pub async fn ssh_disk_usage(
+    session_mgr: State<'_, Arc<SshSessionManager>>,
+    session_id: String,
+    path: String,
+    upload_bytes: Option<u64>,
+    upload_files: Option<u64>,
+) -> AppResult<DiskUsage> {
+    log::debug!("[CMD] ssh_disk_usage called — path=\"{}\"", path);
+    let session = session_mgr.get_session(&session_id).await?;
+    let (bytes, files) = (upload_bytes.unwrap_or(0), upload_files.unwrap_or(0));
+    disk_usage::disk_usage(&session, &path, bytes, files).await
+}
+
+/// Turn SFTP protocol tracing on or off for a session. Each request is
+/// recorded with its reply and latency, with paths redacted, in a new file
+/// under the app data dir. Returns the trace file: the one started, or the
+/// one finished when turning tracing off.
+#[tauri::command]
+pub async fn ssh_set_tracing(
+    app: tauri::AppHandle,
+    session_mgr: State<'_, Arc<SshSessionManager>>,
+    session_id: String,
+    enabled: bool,
+) -> AppResult<Option<String>> {
+    log::info!(
+        "[CMD] ssh_set_tracing — session={} enabled={}",
+        session_id,
+        enabled
+    );
+    let session = session_mgr.get_session(&session_id).await?;
+    if !enabled {
+        let path = session.tracer.stop().await;
+        return Ok(path.map(|p| p.to_string_lossy().to_string()));
+    }
+    let dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| AppError::Io(format!("Failed to resolve app data dir: {e}")))?
+        .join("traces");
+    let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S");
+    let short_id: String = session_id.chars().take(8).collect();
+    let path = dir.join(format!("sftp-{stamp}-{short_id}.jsonl"));
+    session.tracer.start(&path)?;
+    Ok(Some(path.to_string_lossy().to_string()))
+}
+
+/// Operations currently running on a session, oldest first.
+#[tauri::command]
+pub async fn session_pending_ops(
+    session_mgr: State<'_, Arc<SshSessionManager>>,
+    session_id: String,
+) -> AppResult<Vec<PendingOp>> {
+    Ok(session_mgr.get_session(&session_id).await?.ops.pending())
+}
+
+/// Mark a session exclusive so background work (thumbnail prefetch) waits
+/// until the flag is cleared, leaving the connection to a critical transfer.
+#[tauri::command]
+pub async fn session_set_exclusive(
+    session_mgr: State<'_, Arc<SshSessionManager>>,
+    session_id: String,
+    exclusive: bool,
+) -> AppResult<()> {
+    log::info!(
+        "[CMD] session_set_exclusive — session={} exclusive={}",
+        session_id,
+        exclusive
+    );
+    session_mgr
+        .get_session(&session_id)
+        .await?
+        .ops
+        .set_exclusive(exclusive);
+    Ok(())
+}
+
+/// `sftp://` connection string and QR code for a saved server profile.
+/// Credentials are never included.
+#[tauri::command]
+pub async fn profile_share(
+    name: String,
+    host: String,
+    port: u16,
+    user: String,
+    path: Option<String>,
+) -> AppResult<ProfileShare> {
+    share::profile_share(&name, &host, port, &user, path.as_deref())
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn ssh_test_connection(
+    session_mgr: State<'_, Arc<SshSessionManager>>,
+    host: String,
+    port: u16,
+    user: String,
+    key_name: Option<String>,
+    passphrase: Option<String>,
+    password: Option<String>,
+    host_key_pin: Option<String>,
+    host_key_strict: Option<bool>,
+) -> AppResult<()> {
+    log::info!("[SSH] Testing connection to {}@{}:{}", user, host, port);
+    let start = std::time::Instant::now();
+    let pin = HostKeyPin {
+        key: host_key_pin,
+        strict: host_key_strict.unwrap_or(false),
+    };
+    let result = if let Some(pw) = password {
+        session_mgr
+            .test_connection_with_password(&host, port, &user, &pw, pin)
+            .await
+    } else if let Some(ref kn) = key_name {
+        session_mgr
+            .test_connection_with_key(&host, port, &user, kn, passphrase.as_deref(), pin)
+            .await
+    } else {
+        Err(AppError::Ssh(
+            "Either key_name or password must be provided".into(),
+        ))
+    };
+    match &result {
+        Ok(()) => log::info!(
+            "[SSH] Test connection succeeded in {:.2}ms",
+            start.elapsed().as_secs_f64() * 1000.0,
+        ),
+        Err(e) => log::error!(
+            "[SSH] Test connection failed after {:.2}ms — {}",
+            start.elapsed().as_secs_f64() * 1000.0,
+            e,
+        ),
+    }
+    result
+}
+
+#[tauri::command]
+pub async fn discover_lan_hosts(
+    timeout_ms: Option<u64>,
+    probe_subnet: Option<bool>,
+) -> AppResult<Vec<DiscoveredHost>> {
+    log::info!("[CMD] discover_lan_hosts — probe_subnet={:?}", probe_subnet);
+    discovery::discover_lan_hosts(
+        std::time::Duration::from_millis(timeout_ms.unwrap_or(3000)),
+        probe_subnet.unwrap_or(false),
+    )
+    .await
+}
+
+#[tauri::command]
+pub async fn ssh_disconnect(
+    session_mgr: State<'_, Arc<SshSessionManager>>,
+    session_id: String,
+) -> AppResult<()> {
+    log::info!("[SSH] Disconnecting session_id={}", session_id);
+    session_mgr.disconnect(&session_id).await
+}
+
+#[tauri::command]
+pub async fn ssh_temp_dir(
+    session_mgr: State<'_, Arc<SshSessionManager>>,
+    session_id: String,
+) -> AppResult<String> {
+    let session = session_mgr.get_session(&session_id).await?;
+    session.temp_dir().await.map(str::to_string)
+}
+
+#[tauri::command]
+pub async fn ssh_list_sessions(
+    session_mgr: State<'_, Arc<SshSessionManager>>,
+) -> AppResult<Vec<SessionInfo>> {
+    let sessions = session_mgr.list_sessions().await;
+    Ok(sessions
+        .into_iter()
+        .map(|(id, host, user)| SessionInfo { id, host, user })
+        .collect())
+}
+
+// ─── Window Commands ───────────────────────────────────────────────────
+
+/// Open another app window sharing all sessions, optionally showing
+/// `session_id` at `path`. Returns the new window's label.
+#[tauri::command]
+pub async fn open_window(
+    app: tauri::AppHandle,
+    windows: State<'_, Arc<WindowRegistry>>,
+    session_id: Option<String>,
+    path: Option<String>,
+) -> AppResult<String> {
+    windows.open(&app, session_id, path)
+}
+
+/// What every open window is showing.
+#[tauri::command]
+pub async fn list_windows(windows: State<'_, Arc<WindowRegistry>>) -> AppResult<Vec<WindowView>> {
+    Ok(windows.list())
+}
+
+/// The calling window's own view, e.g. the session it was opened with.
+#[tauri::command]
+pub async fn current_window_view(
+    window: tauri::Window,
+    windows: State<'_, Arc<WindowRegistry>>,
+) -> AppResult<WindowView> {
+    Ok(windows.get(window.label()))
+}
+
+/// Record the session and folder the calling window is showing; all
+/// windows receive a `windows-changed` event.
+#[tauri::command]
+pub async fn set_window_view(
+    app: tauri::AppHandle,
+    window: tauri::Window,
+    windows: State<'_, Arc<WindowRegistry>>,
+    session_id: Option<String>,
+    path: Option<String>,
+) -> AppResult<()> {
+    windows.set(
+        &app,
+        WindowView {
+            label: window.label().to_string(),
+            session_id,
+            path,
+        },
+    );
+    Ok(())
+}
+
+// ─── Shell Commands ────────────────────────────────────────────────────
+
+/// Emit shell events to the webview as `shell-event`.
+fn shell_emitter(app: tauri::AppHandle) -> impl Fn(ShellEvent) + Send + 'static {
+    move |event: ShellEvent| {
+        if let Err(e) = app.emit("shell-event", &event) {
+            log::warn!("[CMD] failed to emit shell-event: {}", e);
+        }
+    }
+}
+
+/// Open a PTY login shell in the user's home directory. Output arrives as
+/// `shell-event` events; returns the shell ID. A session can run several.
+#[tauri::command]
+pub async fn ssh_open_shell(
+    app: tauri::AppHandle,
+    session_mgr: State<'_, Arc<SshSessionManager>>,
+    session_id: String,
+    cols: Option<u32>,
+    rows: Option<u32>,
+) -> AppResult<String> {
+    log::debug!("[CMD] ssh_open_shell called — session={}", session_id);
+    let session = session_mgr.get_session(&session_id).await?;
+    let (cols, rows) = (cols.unwrap_or(80), rows.unwrap_or(24));
+    shell::open_shell(&session, None, cols, rows, shell_emitter(app)).await
+}
+
+/// Open a PTY shell already `cd`-ed into remote `path`. Output arrives as
+/// `shell-event` events; returns the shell ID.
+#[tauri::command]
+pub async fn ssh_open_shell_at(
+    app: tauri::AppHandle,
+    session_mgr: State<'_, Arc<SshSessionManager>>,
+    session_id: String,
+    path: String,
+    cols: Option<u32>,
+    rows: Option<u32>,
+) -> AppResult<String> {
+    log::debug!("[CMD] ssh_open_shell_at called — path=\"{}\"", path);
+    let session = session_mgr.get_session(&session_id).await?;
+    let (cols, rows) = (cols.unwrap_or(80), rows.unwrap_or(24));
+    shell::open_shell(&session, Some(&path), cols, rows, shell_emitter(app)).await
+}
+
+/// IDs of the shells open on a session.
+#[tauri::command]
+pub async fn ssh_list_shells(
+    session_mgr: State<'_, Arc<SshSessionManager>>,
+    session_id: String,
+) -> AppResult<Vec<String>> {
+    let session = session_mgr.get_session(&session_id).await?;
+    Ok(session.shells.ids())
+}
+
+/// Send keystrokes to an open shell.
+#[tauri::command]
+pub async fn ssh_shell_write(
+    session_mgr: State<'_, Arc<SshSessionManager>>,
+    session_id: String,
+    shell_id: String,
+    data: String,
+) -> AppResult<()> {
+    let session = session_mgr.get_session(&session_id).await?;
+    session
+        .shells
+        .send(&shell_id, ShellInput::Data(data.into_bytes()))
+}
+
+#[tauri::command]
+pub async fn ssh_shell_resize(
+    session_mgr: State<'_, Arc<SshSessionManager>>,
+    session_id: String,
+    shell_id: String,
+    cols: u32,
+    rows: u32,
+) -> AppResult<()> {
+    let session = session_mgr.get_session(&session_id).await?;
+    session
+        .shells
+        .send(&shell_id, ShellInput::Resize { cols, rows })
+}
+
+#[tauri::command]
+pub async fn ssh_shell_close(
+    session_mgr: State<'_, Arc<SshSessionManager>>,
+    session_id: String,
+    shell_id: String,
+) -> AppResult<()> {
+    log::debug!("[CMD] ssh_shell_close called — shell_id={}", shell_id);
+    let session = session_mgr.get_session(&session_id).await?;
+    session.shells.send(&shell_id, ShellInput::Close)
+}
+
+// ─── Tail Commands ─────────────────────────────────────────────────────
+
+/// Follow a remote file like `tail -f`: the last `lines` lines (default
+/// 100) and then appended lines arrive as `tail://data` events. Pass
+/// `tail_id` to know the ID before the first event; one is generated
+/// otherwise. Returns the tail ID for `sftp_tail_stop`.
+#[tauri::command]
+pub async fn sftp_tail_start(
+    app: tauri::AppHandle,
+    session_mgr: State<'_, Arc<SshSessionManager>>,
+    session_id: String,
+    path: String,
+    lines: Option<usize>,
+    tail_id: Option<String>,
+) -> AppResult<String> {
+    log::debug!("[CMD] sftp_tail_start called — path=\"{}\"", path);
+    let session = session_mgr.get_session(&session_id).await?;
+    let tail_id = tail_id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    let emit = move |event: TailEvent| {
+        if let Err(e) = app.emit("tail://data", &event) {
+            log::warn!("[CMD] failed to emit tail://data: {}", e);
+        }
+    };
+    tail::start_tail(&session, tail_id, &path, lines.unwrap_or(100), emit).await
+}
+
+/// Stop following a file. Returns `false` if it was no longer followed.
+#[tauri::command]
+pub async fn sftp_tail_stop(
+    session_mgr: State<'_, Arc<SshSessionManager>>,
+    session_id: String,
+    tail_id: String,
+) -> AppResult<bool> {
+    log::debug!("[CMD] sftp_tail_stop called — tail_id={}", tail_id);
+    let session = session_mgr.get_session(&session_id).await?;
+    Ok(session.tails.stop(&tail_id))
+}
+
+// ─── SFTP Commands ────────────────────────────────────────────────────
+
+/// Size limit for commands that load a whole file into memory,
+/// or `None` when the caller forces the transfer.
+async fn in_memory_limit(settings: &SettingsStore, force: Option<bool>) -> AppResult<Option<u64>> {
+    if force.unwrap_or(false) {
+        return Ok(None);
+    }
+    Ok(Some(settings.get().await?.in_memory_limit()))
+}
+
+/// One batch of a directory listing, emitted as `listing://partial` so the
+/// view can fill in while a slow server is still sending.
+#[derive(Clone, serde::Serialize)]
+struct ListingPartial<'a> {
+    session_id: &'a str,
+    view_id: Option<&'a str>,
+    path: &'a str,
+    entries: &'a [FileEntry],
+}
+
+/// List a directory. Each batch of entries is emitted as a
+/// `listing://partial` event as it arrives; with `on_progress`, the batches
+/// and periodic heartbeats are also sent on that channel. Entries matching
+/// the ignore patterns are left out unless `show_ignored` is set.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn sftp_list_dir(
+    app: tauri::AppHandle,
+    session_mgr: State<'_, Arc<SshSessionManager>>,
+    session_id: String,
+    path: String,
+    view_id: Option<String>,
+    generation: Option<u64>,
+    on_progress: Option<Channel<ListProgress>>,
+    show_ignored: Option<bool>,
+) -> AppResult<Vec<FileEntry>> {
+    log::debug!("[CMD] sftp_list_dir called — path=\"{}\"", path);
+    let start = std::time::Instant::now();
+
+    let session = session_mgr.get_session(&session_id).await?;
+    let session_lookup_ms = start.elapsed().as_secs_f64() * 1000.0;
+
+    // A listing with a newer generation means the view navigated: abort
+    // everything still running for the old one.
+    let show_ignored = show_ignored.unwrap_or(false);
+    let rules = ignore::rules_for(&session.host);
+    let listing = sftp_ops::list_dir_with_progress(&session, &path, |mut progress| {
+        if let ListProgress::Received { batch, .. } = &mut progress {
+            if !show_ignored {
+                rules.retain(batch);
+            }
+            let partial = ListingPartial {
+                session_id: &session_id,
+                view_id: view_id.as_deref(),
+                path: &path,
+                entries: batch,
+            };
+            if let Err(e) = app.emit("listing://partial", &partial) {
+                log::warn!("[CMD] failed to emit listing://partial: {}", e);
+            }
+        }
+        if let Some(channel) = &on_progress {
+            channel.send(progress).ok();
+        }
+    });
+    let listing = match (&view_id, generation) {
+        (Some(view_id), Some(generation)) => {
+            session.ops.advance_view(view_id, generation);
+            session.ops.run_for_view(view_id, generation, listing).await
+        }
+        _ => listing.await,
+    };
+    let result = listing.map(|mut entries| {
+        if !show_ignored {
+            rules.retain(&mut entries);
+        }
+        if let Ok(thumbs) = app_cache(&app, &cache::THUMBNAILS) {
+            sftp_ops::enrich_placeholder_colors(&mut entries, &thumbs);
+        }
+        entries
+    });
+    if let (Ok(entries), Ok(listings)) = (&result, app_cache(&app, &cache::LISTINGS)) {
+        snapshots::record(&listings, &session.host, &session.user, &path, entries).await;
+    }
+
+    log::info!(
+        "[CMD] sftp_list_dir \"{}\" — total_cmd: {:.2}ms | session_lookup: {:.2}ms",
+        path,
+        start.elapsed().as_secs_f64() * 1000.0,
+        session_lookup_ms,
+    );
+    result
+}
+
+#[tauri::command]
+pub async fn sftp_list_dir_diff(
+    session_mgr: State<'_, Arc<SshSessionManager>>,
+    session_id: String,
+    path: String,
+    previous_snapshot_hash: Option<String>,
+) -> AppResult<DirDiff> {
+    log::debug!("[CMD] sftp_list_dir_diff called — path=\"{}\"", path);
+    let start = std::time::Instant::now();
+    let session = session_mgr.get_session(&session_id).await?;
+    let result = sftp_ops::list_dir_diff(&session, &path, previous_snapshot_hash.as_deref()).await;
+    log::info!(
+        "[CMD] sftp_list_dir_diff \"{}\" — total_cmd: {:.2}ms",
+        path,
+        start.elapsed().as_secs_f64() * 1000.0,
+    );
+    result
+}
+
+/// Read mode bits and, where the server supports them, POSIX ACLs.
+#[tauri::command]
+pub async fn sftp_get_acl(
+    session_mgr: State<'_, Arc<SshSessionManager>>,
+    session_id: String,
+    path: String,
+) -> AppResult<FilePermissions> {
+    log::debug!("[CMD] sftp_get_acl called — path=\"{}\"", path);
+    let session = session_mgr.get_session(&session_id).await?;
+    acl::get_permissions(&session, &path).await
+}
+
+/// Read the crontab of `user`, or of the session user when `None`.
+#[tauri::command]
+pub async fn ssh_crontab_get(
+    session_mgr: State<'_, Arc<SshSessionManager>>,
+    session_id: String,
+    user: Option<String>,
+) -> AppResult<Crontab> {
+    log::debug!("[CMD] ssh_crontab_get called — user={:?}", user);
+    let session = session_mgr.get_session(&session_id).await?;
+    crontab::get_crontab(&session, user.as_deref()).await
+}
+
+/// Install a new crontab, backing up the previous one in the session temp dir.
+#[tauri::command]
+pub async fn ssh_crontab_set(
+    session_mgr: State<'_, Arc<SshSessionManager>>,
+    session_id: String,
+    user: Option<String>,
+    content: String,
+) -> AppResult<Crontab> {
+    log::debug!("[CMD] ssh_crontab_set called — user={:?}", user);
+    let session = session_mgr.get_session(&session_id).await?;
+    crontab::set_crontab(&session, user.as_deref(), &content).await
+}
+
+/// systemd service units on the server with their current state.
+#[tauri::command]
+pub async fn ssh_services_list(
+    session_mgr: State<'_, Arc<SshSessionManager>>,
+    session_id: String,
+) -> AppResult<Vec<ServiceUnit>> {
+    let session = session_mgr.get_session(&session_id).await?;
+    services::list_services(&session).await
+}
+
+/// Start, stop, restart, reload, enable, disable or query a service. With
+/// `sudo`, the password (if any) is sent on stdin, never on the command line.
+#[tauri::command]
+pub async fn ssh_service_action(
+    session_mgr: State<'_, Arc<SshSessionManager>>,
+    session_id: String,
+    unit: String,
+    action: ServiceAction,
+    sudo: Option<bool>,
+    sudo_password: Option<String>,
+) -> AppResult<ServiceActionResult> {
+    log::info!("[CMD] ssh_service_action — {:?} {}", action, unit);
+    let session = session_mgr.get_session(&session_id).await?;
+    let sudo = Sudo {
+        enabled: sudo.unwrap_or(false) || sudo_password.is_some(),
+        password: sudo_password,
+    };
+    services::service_action(&session, &unit, action, &sudo).await
+}
+
+/// Replace the ACL of a remote path; returns the resulting permissions.
+#[tauri::command]
+pub async fn sftp_set_acl(
+    session_mgr: State<'_, Arc<SshSessionManager>>,
+    session_id: String,
+    path: String,
+    entries: Vec<AclEntry>,
+) -> AppResult<FilePermissions> {
+    log::debug!(
+        "[CMD] sftp_set_acl called — path=\"{}\" entries={}",
+        path,
+        entries.len()
+    );
+    let session = session_mgr.get_session(&session_id).await?;
+    acl::set_acl(&session, &path, &entries).await
+}
+
+/// Suggest a collision-free name for `desired_name` inside remote `dir`.
+#[tauri::command]
+pub async fn sftp_suggest_name(
+    session_mgr: State<'_, Arc<SshSessionManager>>,
+    session_id: String,
+    dir: String,
+    desired_name: String,
+) -> AppResult<String> {
+    log::debug!(
+        "[CMD] sftp_suggest_name called — dir=\"{}\" name=\"{}\"",
+        dir,
+        desired_name,
+    );
+    let session = session_mgr.get_session(&session_id).await?;
+    sftp_ops::suggest_name(&session, &dir, &desired_name).await
+}
+
+/// Walk a remote tree and flag risky permissions and ownership.
+#[tauri::command]
+pub async fn sftp_permission_audit(
+    session_mgr: State<'_, Arc<SshSessionManager>>,
+    session_id: String,
+    root: String,
+) -> AppResult<AuditReport> {
+    log::debug!("[CMD] sftp_permission_audit called — root=\"{}\"", root);
+    let start = std::time::Instant::now();
+    let session = session_mgr.get_session(&session_id).await?;
+    let result = audit::permission_audit(&session, &root).await;
+    log::info!(
+        "[CMD] sftp_permission_audit \"{}\" — total_cmd: {:.2}ms",
+        root,
+        start.elapsed().as_secs_f64() * 1000.0,
+    );
+    result
+}
+
+/// File counts and sizes below `root`, grouped by category (images, video,
+/// code, archives…) and extension, for a storage breakdown chart.
+#[tauri::command]
+pub async fn sftp_type_stats(
+    session_mgr: State<'_, Arc<SshSessionManager>>,
+    session_id: String,
+    root: String,
+) -> AppResult<TypeStats> {
+    log::debug!("[CMD] sftp_type_stats called — root=\"{}\"", root);
+    let start = std::time::Instant::now();
+    let session = session_mgr.get_session(&session_id).await?;
+    let result = type_stats::type_stats(&session, &root).await;
+    log::info!(
+        "[CMD] sftp_type_stats \"{}\" — total_cmd: {:.2}ms",
+        root,
+        start.elapsed().as_secs_f64() * 1000.0,
+    );
+    result
+}
+
+/// Size and free space of the filesystem holding `path`, from the
+/// `statvfs@openssh.com` extension or `df`.
+#[tauri::command]
+pub async fn sftp_statvfs(
+    session_mgr: State<'_, Arc<SshSessionManager>>,
+    session_id: String,
+    path: String,
+) -> AppResult<FsStats> {
+    log::debug!("[CMD] sftp_statvfs called — path=\"{}\"", path);
+    let session = session_mgr.get_session(&session_id).await?;
+    sftp_ops::statvfs(&session, &path).await
+}
+
+/// Digest of a remote file computed on the server, with the `check-file`
+/// SFTP extension or `sha256sum` and friends. `algo` defaults to SHA-256.
+#[tauri::command]
+pub async fn sftp_checksum(
+    session_mgr: State<'_, Arc<SshSessionManager>>,
+    session_id: String,
+    path: String,
+    algo: Option<ChecksumAlgo>,
+) -> AppResult<RemoteChecksum> {
+    log::debug!("[CMD] sftp_checksum called — path=\"{}\"", path);
+    let session = session_mgr.get_session(&session_id).await?;
+    let algo = algo.unwrap_or(ChecksumAlgo::Sha256);
+    sftp_ops::checksum(&session, &path, algo).await
+}
+
+/// Progress payload for `sftp_dir_size` (`dir-size-progress`).
+#[derive(Clone, serde::Serialize)]
+struct DirSizeProgress {
+    operation_id: String,
+    #[serde(flatten)]
+    totals: DirSize,
+}
+
+/// Total size of everything below `path`. Running totals are emitted as
+/// `dir-size-progress` events under `operation_id` while the walk runs;
+/// cancel with `sftp_cancel_transfer(operation_id)`.
+#[tauri::command]
+pub async fn sftp_dir_size(
+    app: tauri::AppHandle,
+    session_mgr: State<'_, Arc<SshSessionManager>>,
+    session_id: String,
+    path: String,
+    operation_id: Option<String>,
+) -> AppResult<DirSize> {
+    log::debug!("[CMD] sftp_dir_size called — path=\"{}\"", path);
+    let start = std::time::Instant::now();
+    let operation_id = operation_id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    let session = session_mgr.get_session(&session_id).await?;
+    let work = sftp_ops::dir_size(&session, &path, |totals| {
+        let progress = DirSizeProgress {
+            operation_id: operation_id.clone(),
+            totals: totals.clone(),
+        };
+        if let Err(e) = app.emit("dir-size-progress", &progress) {
+            log::warn!("[CMD] failed to emit dir-size-progress: {}", e);
+        }
+    });
+    let result = session.ops.run_transfer(&operation_id, work).await;
+    log::info!(
+        "[CMD] sftp_dir_size \"{}\" — total_cmd: {:.2}ms",
+        path,
+        start.elapsed().as_secs_f64() * 1000.0,
+    );
+    result
+}
+
+/// Search below `root` for names matching `pattern` (a glob, or a regex
+/// with `options.kind`). Matches are sent through `on_match` as they are
+/// found; cancel with `sftp_cancel_transfer(search_id)`.
+#[tauri::command]
+pub async fn sftp_search(
+    session_mgr: State<'_, Arc<SshSessionManager>>,
+    session_id: String,
+    search_id: String,
+    root: String,
+    pattern: String,
+    options: Option<SearchOptions>,
+    on_match: Channel<SearchMatch>,
+) -> AppResult<SearchSummary> {
+    log::debug!(
+        "[CMD] sftp_search called — root=\"{}\" pattern=\"{}\"",
+        root,
+        pattern
+    );
+    let start = std::time::Instant::now();
+    let session = session_mgr.get_session(&session_id).await?;
+    let options = options.unwrap_or_default();
+    let send = |hit: SearchMatch| {
+        on_match.send(hit).ok();
+    };
+    let work = search::search(&session, &root, &pattern, &options, send);
+    let result = session.ops.run_transfer(&search_id, work).await;
+    log::info!(
+        "[CMD] sftp_search \"{}\" — total_cmd: {:.2}ms",
+        root,
+        start.elapsed().as_secs_f64() * 1000.0,
+    );
+    result
+}
+
+/// Line, word and byte counts and the longest line of a text file, to size
+/// up a large log before previewing it.
+#[tauri::command]
+pub async fn sftp_text_stats(
+    session_mgr: State<'_, Arc<SshSessionManager>>,
+    session_id: String,
+    path: String,
+) -> AppResult<TextStats> {
+    log::debug!("[CMD] sftp_text_stats called — path=\"{}\"", path);
+    let start = std::time::Instant::now();
+    let session = session_mgr.get_session(&session_id).await?;
+    let result = text_stats::text_stats(&session, &path).await;
+    log::info!(
+        "[CMD] sftp_text_stats \"{}\" — total_cmd: {:.2}ms",
+        path,
+        start.elapsed().as_secs_f64() * 1000.0,
+    );
+    result
+}
+
+/// Extract text and an embedded thumbnail from an office document
+/// (docx/xlsx/pptx/odt/ods/odp) without downloading the whole file.
+#[tauri::command]
+pub async fn sftp_preview_document(
+    session_mgr: State<'_, Arc<SshSessionManager>>,
+    session_id: String,
+    path: String,
+) -> AppResult<DocumentPreview> {
+    log::debug!("[CMD] sftp_preview_document called — path=\"{}\"", path);
+    let start = std::time::Instant::now();
+    let session = session_mgr.get_session(&session_id).await?;
+    let result = doc_preview::preview_document(&session, &path).await;
+    log::info!(
+        "[CMD] sftp_preview_document \"{}\" — total_cmd: {:.2}ms",
+        path,
+        start.elapsed().as_secs_f64() * 1000.0,
+    );
+    result
+}
+
+/// Previews larger than this are streamed over `on_chunk` when one is given.
+const PREVIEW_STREAM_THRESHOLD: usize = 512 * 1024;
+/// Size of each streamed preview chunk.
+const PREVIEW_CHUNK_BYTES: usize = 256 * 1024;
+
+/// Preview the start of a file, served from the preview cache while the
+/// remote mtime and size (from the listing, or a stat) are unchanged. With
+/// a `query` (opened from a search result) the hits in the text are
+/// returned alongside it.
+///
+/// Large previews are sent over the `on_chunk` channel in pieces rather
+/// than as one JSON string; the result then carries the chunk count and an
+/// empty `content`.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn sftp_read_file_preview(
+    app: tauri::AppHandle,
+    session_mgr: State<'_, Arc<SshSessionManager>>,
+    settings: State<'_, Arc<SettingsStore>>,
+    session_id: String,
+    path: String,
+    max_bytes: Option<usize>,
+    force: Option<bool>,
+    remote_mtime: Option<u64>,
+    remote_size: Option<u64>,
+    query: Option<String>,
+    case_sensitive: Option<bool>,
+    on_chunk: Option<Channel<PreviewChunk>>,
+) -> AppResult<FilePreview> {
+    log::debug!("[CMD] sftp_read_file_preview called — path=\"{}\"", path);
+    let start = std::time::Instant::now();
+    let limit = in_memory_limit(&settings, force).await?;
+    let previews = app_cache(&app, &cache::PREVIEWS)?;
+    let validator = Validator {
+        mtime: remote_mtime,
+        size: remote_size,
+    };
+    let session = session_mgr.get_session(&session_id).await?;
+    let max_bytes = max_bytes.unwrap_or(64 * 1024);
+    let mut result =
+        sftp_ops::cached_file_preview(&session, &path, max_bytes, &previews, validator, limit)
+            .await;
+    if let (Ok(preview), Some(query)) = (&mut result, query) {
+        if preview.is_text {
+            let case_sensitive = case_sensitive.unwrap_or(false);
+            preview.matches = search_hits::find_matches(&preview.content, &query, case_sensitive);
+        }
+    }
+    if let (Ok(preview), Some(channel)) = (&mut result, on_chunk) {
+        if preview.content.len() > PREVIEW_STREAM_THRESHOLD {
+            let content = std::mem::take(&mut preview.content);
+            let chunks = sftp_ops::chunk_text(&content, PREVIEW_CHUNK_BYTES);
+            preview.chunks = chunks.len();
+            for (index, data) in chunks.into_iter().enumerate() {
+                let chunk = PreviewChunk {
+                    index,
+                    data: data.to_string(),
+                };
+                channel
+                    .send(chunk)
+                    .map_err(|e| AppError::Other(format!("Failed to stream preview: {e}")))?;
+            }
+        }
+    }
+    log::info!(
+        "[CMD] sftp_read_file_preview \"{}\" — total_cmd: {:.2}ms",
+        path,
+        start.elapsed().as_secs_f64() * 1000.0,
+    );
+    result
+}
+
+/// Preview a Markdown file with its relative image links rewritten to
+/// locally cached copies.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn sftp_preview_markdown(
+    app: tauri::AppHandle,
+    session_mgr: State<'_, Arc<SshSessionManager>>,
+    settings: State<'_, Arc<SettingsStore>>,
+    session_id: String,
+    path: String,
+    max_bytes: Option<usize>,
+    force: Option<bool>,
+) -> AppResult<MarkdownPreview> {
+    log::debug!("[CMD] sftp_preview_markdown called — path=\"{}\"", path);
+    let start = std::time::Instant::now();
+    let limit = in_memory_limit(&settings, force).await?;
+    let images = app_cache(&app, &cache::IMAGES)?;
+    let session = session_mgr.get_session(&session_id).await?;
+    sftp_ops::check_size_limit(&session, &path, limit).await?;
+    let result =
+        markdown::preview_markdown(&session, &path, max_bytes.unwrap_or(64 * 1024), &images).await;
+    log::info!(
+        "[CMD] sftp_preview_markdown \"{}\" — total_cmd: {:.2}ms",
+        path,
+        start.elapsed().as_secs_f64() * 1000.0,
+    );
+    result
+}
+
+/// Parse a JSON/YAML file into pretty-printed text and a structural outline.
+#[tauri::command]
+pub async fn sftp_preview_structured(
+    session_mgr: State<'_, Arc<SshSessionManager>>,
+    session_id: String,
+    path: String,
+) -> AppResult<StructuredPreview> {
+    log::debug!("[CMD] sftp_preview_structured called — path=\"{}\"", path);
+    let start = std::time::Instant::now();
+    let session = session_mgr.get_session(&session_id).await?;
+    let result = structured::preview_structured(&session, &path).await;
+    log::info!(
+        "[CMD] sftp_preview_structured \"{}\" — total_cmd: {:.2}ms",
+        path,
+        start.elapsed().as_secs_f64() * 1000.0,
+    );
+    result
+}
+
+/// Split a remote file into `<file>.partNNN` pieces of `chunk_size` bytes.
+#[tauri::command]
+pub async fn sftp_split_file(
+    session_mgr: State<'_, Arc<SshSessionManager>>,
+    session_id: String,
+    path: String,
+    chunk_size: u64,
+) -> AppResult<SplitResult> {
+    log::debug!(
+        "[CMD] sftp_split_file called — path=\"{}\" chunk={}",
+        path,
+        chunk_size
+    );
+    let start = std::time::Instant::now();
+    let session = session_mgr.get_session(&session_id).await?;
+    let result = split::split_file(&session, &path, chunk_size).await;
+    log::info!(
+        "[CMD] sftp_split_file \"{}\" — total_cmd: {:.2}ms",
+        path,
+        start.elapsed().as_secs_f64() * 1000.0,
+    );
+    result
+}
+
+/// Concatenate remote `parts`, in order, into `dest`.
+#[tauri::command]
+pub async fn sftp_join_files(
+    session_mgr: State<'_, Arc<SshSessionManager>>,
+    session_id: String,
+    parts: Vec<String>,
+    dest: String,
+) -> AppResult<JoinResult> {
+    log::debug!(
+        "[CMD] sftp_join_files called — {} parts → \"{}\"",
+        parts.len(),
+        dest
+    );
+    let start = std::time::Instant::now();
+    let session = session_mgr.get_session(&session_id).await?;
+    let result = split::join_files(&session, &parts, &dest).await;
+    log::info!(
+        "[CMD] sftp_join_files \"{}\" — total_cmd: {:.2}ms",
+        dest,
+        start.elapsed().as_secs_f64() * 1000.0,
+    );
+    result
+}
+
+#[tauri::command]
+pub async fn sftp_download_file(
+    session_mgr: State<'_, Arc<SshSessionManager>>,
+    settings: State<'_, Arc<SettingsStore>>,
+    session_id: String,
+    path: String,
+    force: Option<bool>,
+) -> AppResult<Vec<u8>> {
+    log::debug!("[CMD] sftp_download_file called — path=\"{}\"", path);
+    let start = std::time::Instant::now();
+    let limit = in_memory_limit(&settings, force).await?;
+    let session = session_mgr.get_session(&session_id).await?;
+    sftp_ops::check_size_limit(&session, &path, limit).await?;
+    let result = sftp_ops::download_file(&session, &path).await;
+    log::info!(
+        "[CMD] sftp_download_file \"{}\" — total_cmd: {:.2}ms",
+        path,
+        start.elapsed().as_secs_f64() * 1000.0,
+    );
+    result
+}
+
+/// Platform default download directory, used when no setting is configured.
+fn platform_download_dir(app: &tauri::AppHandle) -> AppResult<PathBuf> {
+    if cfg!(target_os = "android") {
+        let public = PathBuf::from("/storage/emulated/0/Download");
+        if public.exists()
+            && std::fs::metadata(&public)
+                .map(|m| !m.permissions().readonly())
+                .unwrap_or(false)
+        {
+            Ok(public)
+        } else {
+            app.path()
+                .download_dir()
+                .or_else(|_| app.path().app_data_dir())
+                .map_err(|e| AppError::Sftp(format!("Cannot determine save directory: {e}")))
+        }
+    } else {
+        app.path()
+            .download_dir()
+            .or_else(|_| app.path().document_dir())
+            .or_else(|_| app.path().app_data_dir())
+            .map_err(|e| AppError::Sftp(format!("Cannot determine save directory: {e}")))
+    }
+}
+
+/// Resolve the directory a download should be saved to: the per-download
+/// override if given, else the configured setting, else the platform default.
+async fn resolve_download_dir(
+    app: &tauri::AppHandle,
+    settings: &SettingsStore,
+    override_dir: Option<String>,
+) -> AppResult<PathBuf> {
+    if let Some(dir) = override_dir {
+        return Ok(PathBuf::from(dir));
+    }
+    if let Some(dir) = settings.get().await?.download_dir {
+        return Ok(PathBuf::from(dir));
+    }
+    platform_download_dir(app)
+}
+
+/// Stream a remote file into `save_dir` (else the session's paired local
+/// directory, else the download directory), emitting `transfer-progress`
+/// events under `transfer_id`. Any `post_actions` run once the file is
+/// complete; their outcome arrives as a final `post_actions` event.
+/// `verify` adds the `checksum` action, comparing local and remote digests.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn sftp_save_file(
+    app: tauri::AppHandle,
+    session_mgr: State<'_, Arc<SshSessionManager>>,
+    settings: State<'_, Arc<SettingsStore>>,
+    queue: State<'_, Arc<DownloadQueue>>,
+    session_id: String,
+    remote_path: String,
+    file_name: String,
+    save_dir: Option<String>,
+    confirm: Option<bool>,
+    transfer_id: Option<String>,
+    post_actions: Option<Vec<PostAction>>,
+    verify: Option<bool>,
+) -> AppResult<String> {
+    let start = std::time::Instant::now();
+    let session = session_mgr.get_session(&session_id).await?;
+    let st = sftp_ops::stat(&session, &remote_path).await?;
+    if network::is_metered() {
+        network::check_download(st.size.unwrap_or(0), confirm.unwrap_or(false))?;
+    }
+
+    let save_dir = save_dir.or_else(|| session.local_dir());
+    let save_dir = resolve_download_dir(&app, &settings, save_dir).await?;
+
+    std::fs::create_dir_all(&save_dir)
+        .map_err(|e| AppError::Sftp(format!("Cannot create save directory: {e}")))?;
+
+    // A name whose `.part` file holds this same remote file is reused, so
+    // a retry after a failure continues where it stopped.
+    let file_name = sftp_ops::first_free_name(&file_name, |n| {
+        let path = save_dir.join(n);
+        path.exists()
+            || sftp_ops::partial_download(&path).is_some_and(|p| p.remote_path != remote_path)
+    });
+    let local_path = save_dir.join(&file_name);
+    let partial = sftp_ops::prepare_partial(&session, &remote_path, &local_path).await?;
+    let post_actions = with_checksum(post_actions.unwrap_or_default(), verify);
+
+    let local_str = local_path.to_string_lossy().to_string();
+    log::debug!(
+        "[CMD] sftp_save_file called — remote=\"{}\" local=\"{}\"",
+        remote_path,
+        local_str,
+    );
+
+    let transfer_id = transfer_id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    queue.begin(QueuedDownload {
+        id: transfer_id.clone(),
+        kind: QueuedKind::File,
+        host: session.host.clone(),
+        user: session.user.clone(),
+        remote_path: remote_path.clone(),
+        local_path: local_str.clone(),
+        bytes_done: partial.offset,
+        bytes_total: partial.size,
+        remote_mtime: partial.mtime,
+        started_at: chrono::Utc::now().to_rfc3339(),
+        post_actions: post_actions.clone(),
+    });
+    let sink = queue.journal_sink(&transfer_id, transfer_sink(app.clone()));
+    let result = transfer::save_file(&session, &transfer_id, &local_path, partial, sink).await;
+    queue.finish(&transfer_id);
+    result?;
+
+    if !post_actions.is_empty() {
+        let results = post_actions::run(&session, &remote_path, &local_path, &post_actions).await;
+        transfer_sink(app)(TransferEvent::PostActions {
+            transfer_id: transfer_id.clone(),
+            local_path: local_str.clone(),
+            results,
+        });
+    }
+
+    log::info!(
+        "[CMD] sftp_save_file \"{}\" -> \"{}\" — total_cmd: {:.2}ms",
+        remote_path,
+        local_str,
+        start.elapsed().as_secs_f64() * 1000.0,
+    );
+
+    Ok(local_str)
+}
+
+#[tauri::command]
+pub async fn sftp_create_dir(
+    session_mgr: State<'_, Arc<SshSessionManager>>,
+    settings: State<'_, Arc<SettingsStore>>,
+    session_id: String,
+    path: String,
+    mode: Option<u32>,
+) -> AppResult<()> {
+    log::debug!("[CMD] sftp_create_dir called — path=\"{}\"", path);
+    let start = std::time::Instant::now();
+    let mode = match mode {
+        Some(m) => Some(m),
+        None => settings.get().await?.create_dir_mode,
+    };
+    let session = session_mgr.get_session(&session_id).await?;
+    let result = sftp_ops::create_dir(&session, &path, mode).await;
+    log::info!(
+        "[CMD] sftp_create_dir \"{}\" — total_cmd: {:.2}ms",
+        path,
+        start.elapsed().as_secs_f64() * 1000.0,
+    );
+    result
+}
+
+/// Set the permission bits of a single file or directory.
+#[tauri::command]
+pub async fn sftp_set_permissions(
+    session_mgr: State<'_, Arc<SshSessionManager>>,
+    session_id: String,
+    path: String,
+    mode: u32,
+) -> AppResult<()> {
+    log::debug!(
+        "[CMD] sftp_set_permissions called — path=\"{}\" mode={:o}",
+        path,
+        mode
+    );
+    let session = session_mgr.get_session(&session_id).await?;
+    sftp_ops::set_permissions(&session, &path, mode).await
+}
+
+/// Change the owner and/or group of a single file or directory by numeric
+/// id; whichever is `None` is left as it is.
+#[tauri::command]
+pub async fn sftp_set_owner(
+    session_mgr: State<'_, Arc<SshSessionManager>>,
+    session_id: String,
+    path: String,
+    uid: Option<u32>,
+    gid: Option<u32>,
+) -> AppResult<()> {
+    log::debug!(
+        "[CMD] sftp_set_owner called — path=\"{}\" uid={:?} gid={:?}",
+        path,
+        uid,
+        gid
+    );
+    let session = session_mgr.get_session(&session_id).await?;
+    sftp_ops::set_owner(&session, &path, uid, gid).await
+}
+
+/// Create a symlink at `link_path` pointing to `target` (stored as given,
+/// so it may be relative to the link's directory).
+#[tauri::command]
+pub async fn sftp_create_symlink(
+    session_mgr: State<'_, Arc<SshSessionManager>>,
+    session_id: String,
+    target: String,
+    link_path: String,
+) -> AppResult<()> {
+    log::debug!(
+        "[CMD] sftp_create_symlink called — link=\"{}\" target=\"{}\"",
+        link_path,
+        target
+    );
+    let session = session_mgr.get_session(&session_id).await?;
+    sftp_ops::create_symlink(&session, &target, &link_path).await
+}
+
+/// Read where the symlink at `path` points, as stored on the server.
+#[tauri::command]
+pub async fn sftp_read_link(
+    session_mgr: State<'_, Arc<SshSessionManager>>,
+    session_id: String,
+    path: String,
+) -> AppResult<String> {
+    let session = session_mgr.get_session(&session_id).await?;
+    sftp_ops::read_link(&session, &path).await
+}
+
+/// Copy a file (or directory) in place as `"name (copy).ext"`, picking a
+/// free name. Returns the new path.
+#[tauri::command]
+pub async fn sftp_duplicate(
+    session_mgr: State<'_, Arc<SshSessionManager>>,
+    session_id: String,
+    path: String,
+) -> AppResult<String> {
+    log::debug!("[CMD] sftp_duplicate called — path=\"{}\"", path);
+    let session = session_mgr.get_session(&session_id).await?;
+    sftp_ops::duplicate(&session, &path).await
+}
+
+/// Rename a file or directory, or move it to another directory. Fails if
+/// `to` exists unless `overwrite` is set.
+///
+/// A move across filesystems on the server is done as copy, verify and
+/// delete, emitting `transfer-progress` events under `transfer_id`.
+#[tauri::command]
+pub async fn sftp_rename(
+    app: tauri::AppHandle,
+    session_mgr: State<'_, Arc<SshSessionManager>>,
+    session_id: String,
+    from: String,
+    to: String,
+    overwrite: Option<bool>,
+    transfer_id: Option<String>,
+) -> AppResult<()> {
+    log::debug!("[CMD] sftp_rename called — from=\"{}\" to=\"{}\"", from, to);
+    let session = session_mgr.get_session(&session_id).await?;
+    match sftp_ops::rename(&session, &from, &to, overwrite.unwrap_or(false)).await {
+        Err(AppError::CrossDevice(_)) => {
+            let transfer_id = transfer_id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+            transfer::move_by_copy(&session, &transfer_id, &from, &to, transfer_sink(app)).await
+        }
+        result => result,
+    }
+}
+
+/// Create a new file, empty or holding `initial_content` (e.g. a template).
+/// Never overwrites an existing path.
+#[tauri::command]
+pub async fn sftp_create_file(
+    session_mgr: State<'_, Arc<SshSessionManager>>,
+    settings: State<'_, Arc<SettingsStore>>,
+    session_id: String,
+    path: String,
+    initial_content: Option<String>,
+) -> AppResult<()> {
+    log::debug!("[CMD] sftp_create_file called — path=\"{}\"", path);
+    let mode = settings.get().await?.upload_file_mode;
+    let session = session_mgr.get_session(&session_id).await?;
+    let content = initial_content.unwrap_or_default();
+    sftp_ops::create_file(&session, &path, content.as_bytes(), mode).await
+}
+
+/// Upload `data` to `remote_path`. An interrupted upload continues instead
+/// of starting over: `offset` keeps that many bytes already on the server,
+/// and `resume` uses the size of the remote file as the offset. With
+/// `verify`, the server's SHA-256 of the result must match `data`. A large
+/// file on cellular waits for an unmetered network, announced by a
+/// `file_paused` event under `transfer_id`.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn sftp_upload_file(
+    app: tauri::AppHandle,
+    session_mgr: State<'_, Arc<SshSessionManager>>,
+    settings: State<'_, Arc<SettingsStore>>,
+    receipts: State<'_, Arc<ReceiptStore>>,
+    session_id: String,
+    remote_path: String,
+    data: Vec<u8>,
+    mode: Option<u32>,
+    offset: Option<u64>,
+    resume: Option<bool>,
+    verify: Option<bool>,
+    transfer_id: Option<String>,
+) -> AppResult<()> {
+    log::debug!("[CMD] sftp_upload_file called — path=\"{}\"", remote_path);
+    let start = std::time::Instant::now();
+    let current = settings.get().await?;
+    let mode = mode.or(current.upload_file_mode);
+    let session = session_mgr.get_session(&session_id).await?;
+    let offset = match offset {
+        Some(offset) => offset,
+        None if resume.unwrap_or(false) => {
+            sftp_ops::upload_resume_offset(&session, &remote_path, &data).await?
+        }
+        None => 0,
+    };
+    let transfer_id = transfer_id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    let sink = transfer_sink(app);
+    let wait = network::wait_for_transfer((data.len() as u64).saturating_sub(offset), || {
+        sink(TransferEvent::FilePaused {
+            transfer_id,
+            index: 0,
+            path: remote_path.clone(),
+        })
+    });
+    session.ops.until_closed(async { Ok(wait.await) }).await?;
+    let mut result =
+        sftp_ops::upload_file_at(&session, &remote_path, &data, offset, mode, |_| {}).await;
+    if result.is_ok() && verify.unwrap_or(false) {
+        result = verify_upload(&session, &remote_path, &data).await;
+    }
+    if result.is_ok() && current.upload_receipts.unwrap_or(false) {
+        receipts
+            .issue(&session, std::slice::from_ref(&remote_path))
+            .await;
+    }
+    log::info!(
+        "[CMD] sftp_upload_file \"{}\" — total_cmd: {:.2}ms",
+        remote_path,
+        start.elapsed().as_secs_f64() * 1000.0,
+    );
+    result
+}
+
+/// Check that the server's copy of `remote_path` hashes the same as `data`.
+async fn verify_upload(session: &Arc<SshSession>, remote_path: &str, data: &[u8]) -> AppResult<()> {
+    let local = post_actions::sha256_hex(data);
+    let remote = sftp_ops::checksum(session, remote_path, ChecksumAlgo::Sha256)
+        .await
+        .map_err(|e| AppError::Other(format!("Uploaded, but could not verify: {e}")))?;
+    if remote.digest != local {
+        return Err(AppError::Other(format!(
+            "Checksum mismatch after upload: local {local}, remote {}",
+            remote.digest
+        )));
+    }
+    log::info!(
+        "[TRANSFER] verified \"{}\" ({})",
+        remote_path,
+        remote.source
+    );
+    Ok(())
+}
+
+/// Event sink that forwards transfer events to the webview as `transfer-progress`.
+fn transfer_sink(app: tauri::AppHandle) -> transfer::EventSink {
+    Arc::new(move |event: TransferEvent| {
+        if let Err(e) = app.emit("transfer-progress", &event) {
+            log::warn!("[CMD] failed to emit transfer-progress: {}", e);
+        }
+    })
+}
+
+/// Upload several files concurrently, emitting `transfer-progress` events
+/// with per-file and aggregate progress. Transient failures are retried per
+/// the configured policy; the summary reports each file's final outcome.
+/// Items given by `local_path` are read from disk, relative paths from the
+/// session's paired local directory.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn sftp_upload_files(
+    app: tauri::AppHandle,
+    session_mgr: State<'_, Arc<SshSessionManager>>,
+    settings: State<'_, Arc<SettingsStore>>,
+    receipts: State<'_, Arc<ReceiptStore>>,
+    session_id: String,
+    files: Vec<UploadItem>,
+    concurrency: Option<usize>,
+    transfer_id: Option<String>,
+) -> AppResult<TransferSummary> {
+    log::debug!("[CMD] sftp_upload_files called — files={}", files.len());
+    let start = std::time::Instant::now();
+
+    let current = settings.get().await?;
+    let concurrency = concurrency
+        .or(current.upload_concurrency)
+        .unwrap_or(transfer::DEFAULT_UPLOAD_CONCURRENCY);
+    let transfer_id = transfer_id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+
+    let session = session_mgr.get_session(&session_id).await?;
+    let mut items = Vec::with_capacity(files.len());
+    for mut f in files {
+        if let Some(local) = f.local_path.take() {
+            f.data = tokio::fs::read(paired_path(&session, Some(local))?).await?;
+        }
+        f.mode = f.mode.or(current.upload_file_mode);
+        items.push(f);
+    }
+    let files = items;
+    let sink = transfer_sink(app);
+    let retry = current.retry_policy();
+    let mut result = transfer::upload_batch(
+        session.clone(),
+        transfer_id,
+        files,
+        concurrency,
+        retry,
+        sink,
+    )
+    .await;
+    if let Ok(summary) = &mut result {
+        if current.upload_receipts.unwrap_or(false) {
+            summary.receipts = receipts.issue(&session, &summary.uploaded()).await;
+        }
+    }
+
+    log::info!(
+        "[CMD] sftp_upload_files — total_cmd: {:.2}ms",
+        start.elapsed().as_secs_f64() * 1000.0,
+    );
+    result
+}
+
+/// Recursively download a remote directory into `local_dir` (by default the
+/// session's paired directory), skipping files that already match locally.
+/// With `verify` (or the `checksum` post-action), each file's SHA-256 must
+/// match the server's. Emits `transfer-progress` events, ending with a
+/// `post_actions` event when any were requested.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn sftp_download_dir(
+    app: tauri::AppHandle,
+    session_mgr: State<'_, Arc<SshSessionManager>>,
+    settings: State<'_, Arc<SettingsStore>>,
+    queue: State<'_, Arc<DownloadQueue>>,
+    session_id: String,
+    remote_dir: String,
+    local_dir: Option<String>,
+    skip_unchanged: Option<bool>,
+    quick_hash: Option<bool>,
+    transfer_id: Option<String>,
+    confirm: Option<bool>,
+    verify: Option<bool>,
+    post_actions: Option<Vec<PostAction>>,
+) -> AppResult<TransferSummary> {
+    log::debug!(
+        "[CMD] sftp_download_dir called — remote=\"{}\" local={:?}",
+        remote_dir,
+        local_dir,
+    );
+    let current = settings.get().await?;
+    let post_actions = with_checksum(post_actions.unwrap_or_default(), verify);
+    let opts = SyncOptions {
+        skip_unchanged: skip_unchanged.unwrap_or(true),
+        quick_hash: quick_hash.unwrap_or(false),
+        mode: None,
+        confirm_large: confirm.unwrap_or(false),
+        confirm_threshold: Some(current.dir_download_threshold()),
+        verify: post_actions.contains(&PostAction::Checksum),
+    };
+    let retry = current.retry_policy();
+    let transfer_id = transfer_id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    let session = session_mgr.get_session(&session_id).await?;
+    let local_dir = paired_path(&session, local_dir)?;
+    queue.begin(QueuedDownload {
+        id: transfer_id.clone(),
+        kind: QueuedKind::Dir,
+        host: session.host.clone(),
+        user: session.user.clone(),
+        remote_path: remote_dir.clone(),
+        local_path: local_dir.to_string_lossy().to_string(),
+        bytes_done: 0,
+        bytes_total: 0,
+        remote_mtime: None,
+        started_at: chrono::Utc::now().to_rfc3339(),
+        post_actions: post_actions.clone(),
+    });
+    let download = transfer::download_dir(
+        session.clone(),
+        transfer_id.clone(),
+        &remote_dir,
+        &local_dir,
+        opts,
+        retry,
+        queue.journal_sink(&transfer_id, transfer_sink(app.clone())),
+    );
+    let result = session.ops.run_transfer(&transfer_id, download).await;
+    queue.finish(&transfer_id);
+    let summary = result?;
+    dir_post_actions(
+        &app,
+        &session,
+        &transfer_id,
+        &remote_dir,
+        &local_dir,
+        &post_actions,
+        &summary,
+    )
+    .await;
+    Ok(summary)
+}
+
+/// `actions`, plus `checksum` when `verify` is set.
+fn with_checksum(mut actions: Vec<PostAction>, verify: Option<bool>) -> Vec<PostAction> {
+    if verify.unwrap_or(false) && !actions.contains(&PostAction::Checksum) {
+        actions.push(PostAction::Checksum);
+    }
+    actions
+}
+
+/// Run the post-actions of a finished directory download and report them
+/// as a `post_actions` event.
+async fn dir_post_actions(
+    app: &tauri::AppHandle,
+    session: &Arc<SshSession>,
+    transfer_id: &str,
+    remote_dir: &str,
+    local_dir: &std::path::Path,
+    actions: &[PostAction],
+    summary: &TransferSummary,
+) {
+    if actions.is_empty() {
+        return;
+    }
+    let results = post_actions::run_dir(
+        session,
+        remote_dir,
+        local_dir,
+        actions,
+        summary.failed.len(),
+    )
+    .await;
+    transfer_sink(app.clone())(TransferEvent::PostActions {
+        transfer_id: transfer_id.to_string(),
+        local_path: local_dir.to_string_lossy().to_string(),
+        results,
+    });
+}
+
+/// Downloads interrupted by the app exiting, optionally only those for the
+/// server behind `session_id`.
+#[tauri::command]
+pub async fn download_queue_list(
+    session_mgr: State<'_, Arc<SshSessionManager>>,
+    queue: State<'_, Arc<DownloadQueue>>,
+    session_id: Option<String>,
+) -> AppResult<Vec<QueuedDownload>> {
+    let session = match session_id {
+        Some(id) => Some(session_mgr.get_session(&id).await?),
+        None => None,
+    };
+    Ok(queue.list(session.as_ref().map(|s| (s.host.as_str(), s.user.as_str()))))
+}
+
+/// Resume an interrupted download on `session_id`, emitting
+/// `transfer-progress` events under its original id. A file continues from
+/// the bytes already saved unless it changed on the server; a directory is
+/// re-run with unchanged files skipped. The post-actions requested when it
+/// started run once it completes. Returns the local path.
+#[tauri::command]
+pub async fn download_queue_resume(
+    app: tauri::AppHandle,
+    session_mgr: State<'_, Arc<SshSessionManager>>,
+    settings: State<'_, Arc<SettingsStore>>,
+    queue: State<'_, Arc<DownloadQueue>>,
+    session_id: String,
+    id: String,
+) -> AppResult<String> {
+    let start = std::time::Instant::now();
+    let mut item = queue
+        .interrupted(&id)
+        .ok_or_else(|| AppError::Other(format!("No interrupted download {id}")))?;
+    let session = session_mgr.get_session(&session_id).await?;
+    if item.host != session.host || item.user != session.user {
+        return Err(AppError::Other(format!(
+            "Download {id} belongs to {}@{}",
+            item.user, item.host
+        )));
+    }
+    let local_path = PathBuf::from(&item.local_path);
+    let sink = queue.journal_sink(&id, transfer_sink(app.clone()));
+
+    let result = match item.kind {
+        QueuedKind::File => {
+            let partial =
+                sftp_ops::prepare_partial(&session, &item.remote_path, &local_path).await?;
+            item.bytes_done = partial.offset;
+            item.bytes_total = partial.size;
+            item.remote_mtime = partial.mtime;
+            queue.begin(item.clone());
+            let saved = transfer::save_file(&session, &id, &local_path, partial, sink).await;
+            if saved.is_ok() && !item.post_actions.is_empty() {
+                let results =
+                    post_actions::run(&session, &item.remote_path, &local_path, &item.post_actions)
+                        .await;
+                transfer_sink(app)(TransferEvent::PostActions {
+                    transfer_id: id.clone(),
+                    local_path: item.local_path.clone(),
+                    results,
+                });
+            }
+            saved.map(|_| ())
+        }
+        QueuedKind::Dir => {
+            queue.begin(item.clone());
+            let current = settings.get().await?;
+            let opts = SyncOptions {
+                skip_unchanged: true,
+                quick_hash: false,
+                mode: None,
+                confirm_large: true,
+                confirm_threshold: None,
+                verify: item.post_actions.contains(&PostAction::Checksum),
+            };
+            let download = transfer::download_dir(
+                session.clone(),
+                id.clone(),
+                &item.remote_path,
+                &local_path,
+                opts,
+                current.retry_policy(),
+                sink,
+            );
+            match session.ops.run_transfer(&id, download).await {
+                Ok(summary) => {
+                    dir_post_actions(
+                        &app,
+                        &session,
+                        &id,
+                        &item.remote_path,
+                        &local_path,
+                        &item.post_actions,
+                        &summary,
+                    )
+                    .await;
+                    Ok(())
+                }
+                Err(e) => Err(e),
+            }
+        }
+    };
+    queue.finish(&id);
+    result?;
+
+    log::info!(
+        "[CMD] download_queue_resume \"{}\" -> \"{}\" — total_cmd: {:.2}ms",
+        item.remote_path,
+        item.local_path,
+        start.elapsed().as_secs_f64() * 1000.0,
+    );
+    Ok(item.local_path)
+}
+
+/// Forget an interrupted download, removing the partial file it left.
+#[tauri::command]
+pub async fn download_queue_discard(
+    queue: State<'_, Arc<DownloadQueue>>,
+    id: String,
+) -> AppResult<bool> {
+    let Some(item) = queue.interrupted(&id) else {
+        return Ok(false);
+    };
+    queue.finish(&id);
+    if item.kind == QueuedKind::File {
+        sftp_ops::discard_partial(std::path::Path::new(&item.local_path));
+    }
+    log::info!("[CMD] download_queue_discard \"{}\"", item.remote_path);
+    Ok(true)
+}
+
+/// Cancel a running `sftp_save_file` or `sftp_download_dir` by its
+/// transfer id. Returns `false` if no such transfer is running.
+#[tauri::command]
+pub async fn sftp_cancel_transfer(
+    session_mgr: State<'_, Arc<SshSessionManager>>,
+    session_id: String,
+    transfer_id: String,
+) -> AppResult<bool> {
+    let session = session_mgr.get_session(&session_id).await?;
+    let cancelled = session.ops.cancel_transfer(&transfer_id);
+    log::info!(
+        "[CMD] sftp_cancel_transfer {} — found: {}",
+        transfer_id,
+        cancelled
+    );
+    Ok(cancelled)
+}
+
+/// Archive `remote_dir` with `tar cz` on the server, streaming it into the
+/// local `.tar.gz` at `local_tar_path`. Emits `transfer-progress` events
+/// with bytes and entries so far; cancel with
+/// `sftp_cancel_transfer(transfer_id)`.
+#[tauri::command]
+pub async fn backup_directory(
+    app: tauri::AppHandle,
+    session_mgr: State<'_, Arc<SshSessionManager>>,
+    session_id: String,
+    remote_dir: String,
+    local_tar_path: String,
+    transfer_id: Option<String>,
+) -> AppResult<BackupSummary> {
+    log::debug!(
+        "[CMD] backup_directory called — remote=\"{}\" local=\"{}\"",
+        remote_dir,
+        local_tar_path,
+    );
+    let local_path = PathBuf::from(&local_tar_path);
+    if let Some(parent) = local_path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| AppError::Io(format!("Cannot create {}: {e}", parent.display())))?;
+    }
+    let transfer_id = transfer_id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    let session = session_mgr.get_session(&session_id).await?;
+    let sink = transfer_sink(app);
+    transfer::backup_directory(&session, &transfer_id, &remote_dir, &local_path, sink).await
 }
 
+/// Compare a remote tree with a local backup of it, reporting missing,
+/// extra and mismatched files. Emits `transfer-progress` events.
 #[tauri::command]
-pub async fn ssh_disconnect(
+pub async fn verify_backup(
+    app: tauri::AppHandle,
     session_mgr: State<'_, Arc<SshSessionManager>>,
     session_id: String,
-) -> AppResult<()> {
-    log::info!("[SSH] Disconnecting session_id={}", session_id);
-    session_mgr.disconnect(&session_id).await
+    remote_root: String,
+    local_root: String,
+    hash: Option<bool>,
+    transfer_id: Option<String>,
+) -> AppResult<VerifyReport> {
+    log::debug!(
+        "[CMD] verify_backup called — remote=\"{}\" local=\"{}\"",
+        remote_root,
+        local_root,
+    );
+    let transfer_id = transfer_id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    let session = session_mgr.get_session(&session_id).await?;
+    transfer::verify_backup(
+        session,
+        transfer_id,
+        &remote_root,
+        std::path::Path::new(&local_root),
+        hash.unwrap_or(false),
+        transfer_sink(app),
+    )
+    .await
 }
 
+/// Recursively upload `local_dir` (by default the session's paired
+/// directory), skipping files that already match on the remote. With
+/// `verify`, each uploaded file's SHA-256 must match the server's. Emits
+/// `transfer-progress` events.
 #[tauri::command]
-pub async fn ssh_list_sessions(
+#[allow(clippy::too_many_arguments)]
+pub async fn sftp_upload_dir(
+    app: tauri::AppHandle,
     session_mgr: State<'_, Arc<SshSessionManager>>,
-) -> AppResult<Vec<SessionInfo>> {
-    let sessions = session_mgr.list_sessions().await;
-    Ok(sessions
-        .into_iter()
-        .map(|(id, host, user)| SessionInfo { id, host, user })
-        .collect())
+    settings: State<'_, Arc<SettingsStore>>,
+    receipts: State<'_, Arc<ReceiptStore>>,
+    session_id: String,
+    local_dir: Option<String>,
+    remote_dir: String,
+    skip_unchanged: Option<bool>,
+    quick_hash: Option<bool>,
+    transfer_id: Option<String>,
+    verify: Option<bool>,
+) -> AppResult<TransferSummary> {
+    log::debug!(
+        "[CMD] sftp_upload_dir called — local={:?} remote=\"{}\"",
+        local_dir,
+        remote_dir,
+    );
+    let current = settings.get().await?;
+    let opts = SyncOptions {
+        skip_unchanged: skip_unchanged.unwrap_or(true),
+        quick_hash: quick_hash.unwrap_or(false),
+        mode: current.upload_file_mode,
+        confirm_large: false,
+        confirm_threshold: None,
+        verify: verify.unwrap_or(false),
+    };
+    let transfer_id = transfer_id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    let session = session_mgr.get_session(&session_id).await?;
+    let local_dir = paired_path(&session, local_dir)?;
+    let mut summary = transfer::upload_dir(
+        session.clone(),
+        transfer_id,
+        &local_dir,
+        &remote_dir,
+        opts,
+        current.retry_policy(),
+        transfer_sink(app),
+    )
+    .await?;
+    if current.upload_receipts.unwrap_or(false) {
+        summary.receipts = receipts.issue(&session, &summary.uploaded()).await;
+    }
+    Ok(summary)
 }
 
-// ─── SFTP Commands ────────────────────────────────────────────────────
+/// Turn integrity receipts for uploads on or off.
+#[tauri::command]
+pub async fn set_upload_receipts(
+    settings: State<'_, Arc<SettingsStore>>,
+    enabled: bool,
+) -> AppResult<AppSettings> {
+    settings.set_upload_receipts(enabled).await
+}
 
+/// Upload receipts, most recent first, optionally only for `host`.
 #[tauri::command]
-pub async fn sftp_list_dir(
-    session_mgr: State<'_, Arc<SshSessionManager>>,
-    session_id: String,
-    path: String,
-) -> AppResult<Vec<FileEntry>> {
-    log::debug!("[CMD] sftp_list_dir called — path=\"{}\"", path);
-    let start = std::time::Instant::now();
+pub async fn list_upload_receipts(
+    receipts: State<'_, Arc<ReceiptStore>>,
+    host: Option<String>,
+    limit: Option<usize>,
+) -> AppResult<Vec<UploadReceipt>> {
+    Ok(receipts.list(host.as_deref(), limit.unwrap_or(200)))
+}
 
-    let session = session_mgr.get_session(&session_id).await?;
-    let session_lookup_ms = start.elapsed().as_secs_f64() * 1000.0;
+/// Check that a receipt was issued by this device and has not been edited.
+#[tauri::command]
+pub async fn verify_upload_receipt(
+    receipts: State<'_, Arc<ReceiptStore>>,
+    receipt: UploadReceipt,
+) -> AppResult<bool> {
+    Ok(receipts.verify(&receipt))
+}
 
-    let result = sftp_ops::list_dir(&session, &path).await;
+/// Progress payload for recursive chmod/chown (`tree-change-progress`).
+#[derive(Clone, serde::Serialize)]
+struct TreeChangeProgress {
+    operation_id: String,
+    done: usize,
+    total: usize,
+}
 
+async fn change_tree(
+    app: tauri::AppHandle,
+    session_mgr: &SshSessionManager,
+    session_id: &str,
+    path: &str,
+    change: TreeChange,
+    dry_run: bool,
+    operation_id: Option<String>,
+) -> AppResult<TreeChangeSummary> {
+    let start = std::time::Instant::now();
+    let operation_id = operation_id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    let session = session_mgr.get_session(session_id).await?;
+    let result = tree_ops::apply_recursive(&session, path, change, dry_run, |done, total| {
+        let progress = TreeChangeProgress {
+            operation_id: operation_id.clone(),
+            done,
+            total,
+        };
+        if let Err(e) = app.emit("tree-change-progress", &progress) {
+            log::warn!("[CMD] failed to emit tree-change-progress: {}", e);
+        }
+    })
+    .await;
     log::info!(
-        "[CMD] sftp_list_dir \"{}\" — total_cmd: {:.2}ms | session_lookup: {:.2}ms",
+        "[CMD] change_tree \"{}\" (dry_run={}) — total_cmd: {:.2}ms",
         path,
+        dry_run,
         start.elapsed().as_secs_f64() * 1000.0,
-        session_lookup_ms,
     );
     result
 }
 
+/// Recursively apply `file_mode` to files and `dir_mode` to directories.
+/// Call with `dry_run` first to get the number of entries that would change.
 #[tauri::command]
-pub async fn sftp_read_file_preview(
+#[allow(clippy::too_many_arguments)]
+pub async fn sftp_chmod_recursive(
+    app: tauri::AppHandle,
     session_mgr: State<'_, Arc<SshSessionManager>>,
     session_id: String,
     path: String,
-    max_bytes: Option<usize>,
-) -> AppResult<FilePreview> {
-    log::debug!("[CMD] sftp_read_file_preview called — path=\"{}\"", path);
-    let start = std::time::Instant::now();
-    let session = session_mgr.get_session(&session_id).await?;
-    let result = sftp_ops::read_file_preview(&session, &path, max_bytes.unwrap_or(64 * 1024)).await;
-    log::info!(
-        "[CMD] sftp_read_file_preview \"{}\" — total_cmd: {:.2}ms",
+    file_mode: Option<u32>,
+    dir_mode: Option<u32>,
+    dry_run: Option<bool>,
+    operation_id: Option<String>,
+) -> AppResult<TreeChangeSummary> {
+    log::debug!(
+        "[CMD] sftp_chmod_recursive called — path=\"{}\" file_mode={:?} dir_mode={:?}",
         path,
-        start.elapsed().as_secs_f64() * 1000.0,
+        file_mode.map(|m| format!("{m:o}")),
+        dir_mode.map(|m| format!("{m:o}")),
     );
-    result
+    settings::validate_mode(file_mode)?;
+    settings::validate_mode(dir_mode)?;
+    let change = TreeChange {
+        file_mode,
+        dir_mode,
+        ..Default::default()
+    };
+    let dry_run = dry_run.unwrap_or(false);
+    change_tree(
+        app,
+        &session_mgr,
+        &session_id,
+        &path,
+        change,
+        dry_run,
+        operation_id,
+    )
+    .await
 }
 
+/// Recursively change the owning user and/or group (numeric ids).
 #[tauri::command]
-pub async fn sftp_download_file(
+#[allow(clippy::too_many_arguments)]
+pub async fn sftp_chown_recursive(
+    app: tauri::AppHandle,
     session_mgr: State<'_, Arc<SshSessionManager>>,
     session_id: String,
     path: String,
-) -> AppResult<Vec<u8>> {
-    log::debug!("[CMD] sftp_download_file called — path=\"{}\"", path);
-    let start = std::time::Instant::now();
-    let session = session_mgr.get_session(&session_id).await?;
-    let result = sftp_ops::download_file(&session, &path).await;
-    log::info!(
-        "[CMD] sftp_download_file \"{}\" — total_cmd: {:.2}ms",
+    uid: Option<u32>,
+    gid: Option<u32>,
+    dry_run: Option<bool>,
+    operation_id: Option<String>,
+) -> AppResult<TreeChangeSummary> {
+    log::debug!(
+        "[CMD] sftp_chown_recursive called — path=\"{}\" uid={:?} gid={:?}",
         path,
-        start.elapsed().as_secs_f64() * 1000.0,
+        uid,
+        gid,
     );
-    result
+    let change = TreeChange {
+        uid,
+        gid,
+        ..Default::default()
+    };
+    let dry_run = dry_run.unwrap_or(false);
+    change_tree(
+        app,
+        &session_mgr,
+        &session_id,
+        &path,
+        change,
+        dry_run,
+        operation_id,
+    )
+    .await
+}
+
+/// Cache root, chosen once per run so entries stay where they were written.
+static CACHE_ROOT: std::sync::OnceLock<PathBuf> = std::sync::OnceLock::new();
+
+/// Open one of the app's disk caches, placing the cache root on the
+/// candidate location with the most free space.
+fn app_cache(app: &tauri::AppHandle, kind: &'static cache::CacheKind) -> AppResult<DiskCache> {
+    if let Some(root) = CACHE_ROOT.get() {
+        return kind.open(root);
+    }
+    let internal = app
+        .path()
+        .app_cache_dir()
+        .map_err(|e| AppError::Sftp(format!("Cannot determine cache dir: {e}")))?;
+    #[allow(unused_mut)]
+    let mut candidates = vec![internal.clone()];
+    #[cfg(target_os = "android")]
+    candidates.push(PathBuf::from(format!(
+        "/storage/emulated/0/Android/data/{}/cache",
+        app.config().identifier
+    )));
+
+    let root = cache::pick_root(&candidates).unwrap_or(internal);
+    log::info!("[CACHE] cache root: {}", root.display());
+    kind.open(CACHE_ROOT.get_or_init(|| root))
 }
 
 #[tauri::command]
-pub async fn sftp_save_file(
+#[allow(clippy::too_many_arguments)]
+pub async fn sftp_get_thumbnail(
     app: tauri::AppHandle,
     session_mgr: State<'_, Arc<SshSessionManager>>,
     session_id: String,
-    remote_path: String,
-    file_name: String,
+    path: String,
+    remote_mtime: Option<u64>,
+    remote_size: Option<u64>,
+    stale_ok: Option<bool>,
+    view_id: Option<String>,
+    generation: Option<u64>,
 ) -> AppResult<String> {
-    let start = std::time::Instant::now();
+    log::debug!("[CMD] sftp_get_thumbnail called — path=\"{}\"", path);
 
-    let save_dir = if cfg!(target_os = "android") {
-        let public = std::path::PathBuf::from("/storage/emulated/0/Download");
-        if public.exists()
-            && std::fs::metadata(&public)
-                .map(|m| !m.permissions().readonly())
-                .unwrap_or(false)
-        {
-            public
-        } else {
-            app.path()
-                .download_dir()
-                .or_else(|_| app.path().app_data_dir())
-                .map_err(|e| AppError::Sftp(format!("Cannot determine save directory: {e}")))?
-        }
-    } else {
-        app.path()
-            .download_dir()
-            .or_else(|_| app.path().document_dir())
-            .or_else(|_| app.path().app_data_dir())
-            .map_err(|e| AppError::Sftp(format!("Cannot determine save directory: {e}")))?
+    let thumbs = app_cache(&app, &cache::THUMBNAILS)?;
+    let validator = Validator {
+        mtime: remote_mtime,
+        size: remote_size,
     };
-
-    std::fs::create_dir_all(&save_dir)
-        .map_err(|e| AppError::Sftp(format!("Cannot create save directory: {e}")))?;
-
-    let mut local_path = save_dir.join(&file_name);
-    if local_path.exists() {
-        let stem = local_path
-            .file_stem()
-            .and_then(|s| s.to_str())
-            .unwrap_or(&file_name)
-            .to_string();
-        let ext = local_path
-            .extension()
-            .and_then(|e| e.to_str())
-            .unwrap_or("")
-            .to_string();
-        let mut counter = 1u32;
-        loop {
-            let new_name = if ext.is_empty() {
-                format!("{stem} ({counter})")
-            } else {
-                format!("{stem} ({counter}).{ext}")
-            };
-            local_path = save_dir.join(&new_name);
-            if !local_path.exists() {
-                break;
-            }
-            counter += 1;
+    let session = session_mgr.get_session(&session_id).await?;
+    let stale_ok = stale_ok.unwrap_or(false);
+    let work = sftp_ops::get_thumbnail(&session, &path, &thumbs, validator, stale_ok);
+    match (view_id, generation) {
+        (Some(view_id), Some(generation)) => {
+            session.ops.run_for_view(&view_id, generation, work).await
         }
+        _ => work.await,
     }
+}
 
-    let local_str = local_path.to_string_lossy().to_string();
-    log::debug!(
-        "[CMD] sftp_save_file called — remote=\"{}\" local=\"{}\"",
-        remote_path,
-        local_str,
-    );
-
+/// Abort all work tagged with generations of `view_id` older than
+/// `generation`, e.g. when the view is closed or navigates without listing.
+#[tauri::command]
+pub async fn session_cancel_view(
+    session_mgr: State<'_, Arc<SshSessionManager>>,
+    session_id: String,
+    view_id: String,
+    generation: u64,
+) -> AppResult<()> {
     let session = session_mgr.get_session(&session_id).await?;
-    sftp_ops::save_file(&session, &remote_path, &local_str).await?;
-
-    log::info!(
-        "[CMD] sftp_save_file \"{}\" -> \"{}\" — total_cmd: {:.2}ms",
-        remote_path,
-        local_str,
-        start.elapsed().as_secs_f64() * 1000.0,
-    );
-
-    Ok(local_str)
+    session.ops.advance_view(&view_id, generation);
+    Ok(())
 }
 
+/// Download a full image for the viewer and return its local path. Images
+/// over the cache bypass threshold are served without being cached unless
+/// `keep_offline` pins them.
 #[tauri::command]
-pub async fn sftp_create_dir(
+#[allow(clippy::too_many_arguments)]
+pub async fn sftp_cache_image(
+    app: tauri::AppHandle,
     session_mgr: State<'_, Arc<SshSessionManager>>,
+    settings: State<'_, Arc<SettingsStore>>,
     session_id: String,
     path: String,
-) -> AppResult<()> {
-    log::debug!("[CMD] sftp_create_dir called — path=\"{}\"", path);
+    remote_mtime: Option<u64>,
+    remote_size: Option<u64>,
+    prefetch: Option<bool>,
+    keep_offline: Option<bool>,
+) -> AppResult<String> {
+    log::debug!("[CMD] sftp_cache_image called — path=\"{}\"", path);
     let start = std::time::Instant::now();
+    if prefetch.unwrap_or(false) && memory::is_low_memory() {
+        return Err(AppError::Other(
+            "Prefetch is disabled in low-memory mode".into(),
+        ));
+    }
+    if prefetch.unwrap_or(false) && !network::prefetch_allowed() {
+        return Err(AppError::Other("Prefetch is disabled on cellular".into()));
+    }
+
+    let images = app_cache(&app, &cache::IMAGES)?;
+    let validator = Validator {
+        mtime: remote_mtime,
+        size: remote_size,
+    };
+    let bypass_over = if keep_offline.unwrap_or(false) {
+        None
+    } else {
+        Some(settings.get().await?.image_cache_bypass_threshold())
+    };
     let session = session_mgr.get_session(&session_id).await?;
-    let result = sftp_ops::create_dir(&session, &path).await;
+    let local_path =
+        sftp_ops::cache_image(&session, &path, &images, validator, bypass_over).await?;
+
     log::info!(
-        "[CMD] sftp_create_dir \"{}\" — total_cmd: {:.2}ms",
+        "[CMD] sftp_cache_image \"{}\" → \"{}\" — total_cmd: {:.2}ms",
         path,
+        local_path,
         start.elapsed().as_secs_f64() * 1000.0,
     );
-    result
+    Ok(local_path)
 }
 
+// ─── Offline Pins ──────────────────────────────────────────────────────
+
+/// Download a file into the offline store so it stays available while
+/// disconnected.
 #[tauri::command]
-pub async fn sftp_upload_file(
+pub async fn pin_offline(
     session_mgr: State<'_, Arc<SshSessionManager>>,
+    offline: State<'_, Arc<OfflineStore>>,
     session_id: String,
-    remote_path: String,
-    data: Vec<u8>,
-) -> AppResult<()> {
-    log::debug!("[CMD] sftp_upload_file called — path=\"{}\"", remote_path);
-    let start = std::time::Instant::now();
+    path: String,
+) -> AppResult<OfflinePin> {
     let session = session_mgr.get_session(&session_id).await?;
-    let result = sftp_ops::upload_file(&session, &remote_path, &data).await;
-    log::info!(
-        "[CMD] sftp_upload_file \"{}\" — total_cmd: {:.2}ms",
-        remote_path,
-        start.elapsed().as_secs_f64() * 1000.0,
-    );
-    result
+    offline.pin(&session, &path).await
 }
 
+/// Remove an offline pin and its local copy.
 #[tauri::command]
-pub async fn sftp_get_thumbnail(
-    app: tauri::AppHandle,
+pub async fn unpin_offline(offline: State<'_, Arc<OfflineStore>>, id: String) -> AppResult<bool> {
+    offline.unpin(&id)
+}
+
+/// Pinned files, optionally only those of `user@host` (on `port`) directly
+/// inside `dir`. Needs no session, so pins can be browsed offline.
+#[tauri::command]
+pub async fn pins_list(
+    offline: State<'_, Arc<OfflineStore>>,
+    host: Option<String>,
+    port: Option<u16>,
+    user: Option<String>,
+    dir: Option<String>,
+) -> AppResult<Vec<OfflinePin>> {
+    let server = host.as_deref().zip(user.as_deref());
+    Ok(offline.list(server, port, dir.as_deref()))
+}
+
+/// Preview a pinned file from its local copy.
+#[tauri::command]
+pub async fn pin_preview(
+    offline: State<'_, Arc<OfflineStore>>,
+    id: String,
+    max_bytes: Option<usize>,
+) -> AppResult<FilePreview> {
+    offline.preview(&id, max_bytes.unwrap_or(64 * 1024)).await
+}
+
+/// Re-download the session's pins whose remote copy has changed.
+#[tauri::command]
+pub async fn pins_sync(
     session_mgr: State<'_, Arc<SshSessionManager>>,
+    offline: State<'_, Arc<OfflineStore>>,
     session_id: String,
-    path: String,
-    max_bytes: Option<usize>,
-    remote_mtime: Option<u64>,
-) -> AppResult<String> {
-    log::debug!("[CMD] sftp_get_thumbnail called — path=\"{}\"", path);
+) -> AppResult<PinSyncSummary> {
+    let session = session_mgr.get_session(&session_id).await?;
+    Ok(offline.sync(&session).await)
+}
 
-    let cache_dir = app
-        .path()
-        .app_cache_dir()
-        .map_err(|e| AppError::Sftp(format!("Cannot determine cache dir: {e}")))?;
-    let thumb_cache_dir = cache_dir.join("thumbnails");
-    std::fs::create_dir_all(&thumb_cache_dir)
-        .map_err(|e| AppError::Sftp(format!("Cannot create thumbnail cache dir: {e}")))?;
+// ─── Offline Snapshots ─────────────────────────────────────────────────
 
-    let session = session_mgr.get_session(&session_id).await?;
-    sftp_ops::get_thumbnail(
-        &session,
-        &path,
-        max_bytes.unwrap_or(128 * 1024),
-        &thumb_cache_dir,
-        remote_mtime,
-    )
-    .await
+/// The listing of `path` on `user@host` as last seen while connected.
+/// Needs no session.
+#[tauri::command]
+pub async fn offline_list_dir(
+    app: tauri::AppHandle,
+    host: String,
+    user: String,
+    path: String,
+) -> AppResult<DirSnapshot> {
+    let listings = app_cache(&app, &cache::LISTINGS)?;
+    snapshots::list_dir(&listings, &host, &user, &path).await
 }
 
+/// Open a file on `user@host` (port 22 unless given) from its offline pin
+/// or cached copies. Needs no session.
 #[tauri::command]
-pub async fn sftp_cache_image(
+pub async fn offline_open_file(
+    app: tauri::AppHandle,
+    offline: State<'_, Arc<OfflineStore>>,
+    host: String,
+    port: Option<u16>,
+    user: String,
+    path: String,
+    max_bytes: Option<usize>,
+) -> AppResult<OfflineFile> {
+    let previews = app_cache(&app, &cache::PREVIEWS)?;
+    let max_bytes = max_bytes.unwrap_or(64 * 1024);
+    let port = port.unwrap_or(22);
+    snapshots::open_file(&offline, &previews, &host, port, &user, &path, max_bytes).await
+}
+
+/// Download every image in a remote folder into a local zip (or folder),
+/// optionally shrinking each to `max_dimension` pixels on its longer side.
+#[tauri::command]
+pub async fn export_gallery(
     app: tauri::AppHandle,
     session_mgr: State<'_, Arc<SshSessionManager>>,
     session_id: String,
-    path: String,
-    remote_mtime: Option<u64>,
-) -> AppResult<String> {
-    log::debug!("[CMD] sftp_cache_image called — path=\"{}\"", path);
+    dir: String,
+    dest: String,
+    as_zip: Option<bool>,
+    max_dimension: Option<u32>,
+) -> AppResult<GalleryExport> {
+    log::debug!(
+        "[CMD] export_gallery called — dir=\"{}\" dest=\"{}\"",
+        dir,
+        dest
+    );
     let start = std::time::Instant::now();
 
-    // Ensure the image cache directory exists.
-    let cache_dir = app
-        .path()
-        .app_cache_dir()
-        .map_err(|e| AppError::Sftp(format!("Cannot determine cache dir: {e}")))?;
-    let image_cache_dir = cache_dir.join("image_cache");
-    std::fs::create_dir_all(&image_cache_dir)
-        .map_err(|e| AppError::Sftp(format!("Cannot create image cache dir: {e}")))?;
-
+    let images = app_cache(&app, &cache::IMAGES)?;
     let session = session_mgr.get_session(&session_id).await?;
-    let local_path = sftp_ops::cache_image(&session, &path, &image_cache_dir, remote_mtime).await?;
+    let result = gallery::export_gallery(
+        &session,
+        &dir,
+        std::path::Path::new(&dest),
+        as_zip.unwrap_or(true),
+        max_dimension,
+        &images,
+    )
+    .await;
 
     log::info!(
-        "[CMD] sftp_cache_image \"{}\" → \"{}\" — total_cmd: {:.2}ms",
-        path,
-        local_path,
+        "[CMD] export_gallery \"{}\" — total_cmd: {:.2}ms",
+        dir,
         start.elapsed().as_secs_f64() * 1000.0,
     );
-    Ok(local_path)
+    result
 }
 
 #[tauri::command]
@@ -388,6 +3078,19 @@ pub async fn open_file_externally(path: String) -> AppResult<()> {
         .map_err(|e| AppError::Sftp(format!("Failed to open file externally: {e}")))
 }
 
+#[tauri::command]
+pub async fn list_open_with_apps(path: String) -> AppResult<Vec<OpenWithApp>> {
+    open_with::list_handlers(&path)
+}
+
+/// Open a local file with a specific application, or show the OS
+/// "open with" chooser when `app` is omitted.
+#[tauri::command]
+pub async fn open_file_with(path: String, app: Option<String>) -> AppResult<()> {
+    log::info!("[CMD] open_file_with — path=\"{}\" app={:?}", path, app);
+    open_with::open_with(&path, app.as_deref())
+}
+
 #[tauri::command]
 pub async fn sftp_delete_file(
     session_mgr: State<'_, Arc<SshSessionManager>>,
@@ -406,6 +3109,26 @@ pub async fn sftp_delete_file(
     result
 }
 
+/// Delete a directory and everything in it. Failures are reported per path
+/// in the summary rather than aborting the delete.
+#[tauri::command]
+pub async fn sftp_delete_recursive(
+    session_mgr: State<'_, Arc<SshSessionManager>>,
+    session_id: String,
+    path: String,
+) -> AppResult<DeleteSummary> {
+    log::debug!("[CMD] sftp_delete_recursive called — path=\"{}\"", path);
+    let start = std::time::Instant::now();
+    let session = session_mgr.get_session(&session_id).await?;
+    let result = tree_ops::delete_recursive(&session, &path).await;
+    log::info!(
+        "[CMD] sftp_delete_recursive \"{}\" — total_cmd: {:.2}ms",
+        path,
+        start.elapsed().as_secs_f64() * 1000.0,
+    );
+    result
+}
+
 // ─── Helper types ─────────────────────────────────────────────────────
 
 #[derive(serde::Serialize)]
@@ -0,0 +1,250 @@
+//! Credential providers: look up a host's password in a password manager
+//! at connect time instead of having it typed or pasted in.
+//!
+//! A profile opts in by naming a source. Providers shell out to the
+//! manager's own CLI, so nothing is linked in and the manager keeps its
+//! usual unlock prompts and policies. Profiles arrive from the webview, so
+//! only a fixed set of CLIs can be named and their arguments are validated;
+//! nothing is ever handed to a shell.
+
+use serde::{Deserialize, Serialize};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+use crate::errors::{AppError, AppResult};
+
+/// Attribute the Secret Service entries are filed under.
+const SECRET_SERVICE_NAME: &str = "oxidock";
+
+/// How long a manager CLI may run, unlock prompt included, before it is
+/// killed and the lookup fails.
+const LOOKUP_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// How often a running CLI is polled for exit.
+const LOOKUP_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+// ─── Data Structures ───────────────────────────────────────────────────
+
+/// Where a profile's password comes from (safe to send to JS).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum CredentialSource {
+    /// Freedesktop Secret Service (GNOME Keyring, KWallet) via `secret-tool`,
+    /// from an entry with `service=oxidock host=<host> user=<user>`.
+    SecretService,
+    /// macOS Keychain internet password for the host and account, via `security`.
+    Keychain,
+    /// `pass show <entry>`. `{host}`, `{port}` and `{user}` in the entry
+    /// name are replaced, e.g. `ssh/{user}@{host}`.
+    Pass { entry: String },
+    /// 1Password CLI `op read <reference>` with an `op://vault/item/field`
+    /// secret reference; the same placeholders are replaced.
+    OnePassword { reference: String },
+}
+
+/// The account a credential is wanted for.
+pub struct CredentialQuery<'a> {
+    pub host: &'a str,
+    pub port: u16,
+    pub user: &'a str,
+}
+
+/// Looks up passwords. `Ok(None)` means the manager has no entry.
+pub trait CredentialProvider: Send + Sync {
+    fn name(&self) -> &'static str;
+    fn lookup(&self, query: &CredentialQuery) -> AppResult<Option<String>>;
+}
+
+// ─── Providers ─────────────────────────────────────────────────────────
+
+/// Run `program` with `args` and return the first line of its output, or
+/// `None` if it exits unsuccessfully (which these CLIs do for "not found").
+/// The CLI is killed if it is still running after [`LOOKUP_TIMEOUT`].
+fn first_line_of(name: &str, program: &str, args: &[&str]) -> AppResult<Option<String>> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| AppError::Other(format!("{name}: cannot run {program}: {e}")))?;
+
+    let started = Instant::now();
+    loop {
+        match child.try_wait() {
+            Ok(Some(_)) => break,
+            Ok(None) if started.elapsed() >= LOOKUP_TIMEOUT => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(AppError::Timeout(format!(
+                    "{name} lookup did not finish within {}s",
+                    LOOKUP_TIMEOUT.as_secs()
+                )));
+            }
+            Ok(None) => std::thread::sleep(LOOKUP_POLL_INTERVAL),
+            Err(e) => return Err(AppError::Other(format!("{name}: {program} failed: {e}"))),
+        }
+    }
+
+    let output = child
+        .wait_with_output()
+        .map_err(|e| AppError::Other(format!("{name}: {program} failed: {e}")))?;
+    if !output.status.success() {
+        log::debug!(
+            "[AUTH] {} lookup failed: {}",
+            name,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+        return Ok(None);
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    Ok(stdout
+        .lines()
+        .next()
+        .filter(|l| !l.is_empty())
+        .map(str::to_string))
+}
+
+/// Fill `{host}`, `{port}` and `{user}` into an entry name or reference.
+fn expand(template: &str, query: &CredentialQuery) -> String {
+    template
+        .replace("{host}", query.host)
+        .replace("{port}", &query.port.to_string())
+        .replace("{user}", query.user)
+}
+
+/// Check an expanded entry name or reference before it becomes a CLI
+/// argument: it must not look like an option or climb out of the store.
+fn checked_arg(name: &str, value: String) -> AppResult<String> {
+    let allowed = |c: char| c.is_ascii_alphanumeric() || "._-@/:+ ".contains(c);
+    if value.is_empty()
+        || value.starts_with('-')
+        || !value.chars().all(allowed)
+        || value.split('/').any(|part| part == "..")
+    {
+        return Err(AppError::Other(format!(
+            "{name}: invalid entry name \"{value}\""
+        )));
+    }
+    Ok(value)
+}
+
+pub struct SecretService;
+
+impl CredentialProvider for SecretService {
+    fn name(&self) -> &'static str {
+        "Secret Service"
+    }
+
+    fn lookup(&self, query: &CredentialQuery) -> AppResult<Option<String>> {
+        let args = [
+            "lookup",
+            "service",
+            SECRET_SERVICE_NAME,
+            "host",
+            query.host,
+            "user",
+            query.user,
+        ];
+        first_line_of(self.name(), "secret-tool", &args)
+    }
+}
+
+pub struct Keychain;
+
+impl CredentialProvider for Keychain {
+    fn name(&self) -> &'static str {
+        "Keychain"
+    }
+
+    fn lookup(&self, query: &CredentialQuery) -> AppResult<Option<String>> {
+        let args = [
+            "find-internet-password",
+            "-s",
+            query.host,
+            "-a",
+            query.user,
+            "-w",
+        ];
+        first_line_of(self.name(), "security", &args)
+    }
+}
+
+pub struct Pass {
+    pub entry: String,
+}
+
+impl CredentialProvider for Pass {
+    fn name(&self) -> &'static str {
+        "pass"
+    }
+
+    fn lookup(&self, query: &CredentialQuery) -> AppResult<Option<String>> {
+        let entry = checked_arg(self.name(), expand(&self.entry, query))?;
+        first_line_of(self.name(), "pass", &["show", "--", &entry])
+    }
+}
+
+pub struct OnePassword {
+    pub reference: String,
+}
+
+impl CredentialProvider for OnePassword {
+    fn name(&self) -> &'static str {
+        "1Password"
+    }
+
+    fn lookup(&self, query: &CredentialQuery) -> AppResult<Option<String>> {
+        let reference = checked_arg(self.name(), expand(&self.reference, query))?;
+        if !reference.starts_with("op://") {
+            return Err(AppError::Other(
+                "1Password: the reference must be an op:// secret reference".into(),
+            ));
+        }
+        first_line_of(self.name(), "op", &["read", "--no-newline", &reference])
+    }
+}
+
+impl CredentialSource {
+    pub fn provider(&self) -> Box<dyn CredentialProvider> {
+        match self {
+            CredentialSource::SecretService => Box::new(SecretService),
+            CredentialSource::Keychain => Box::new(Keychain),
+            CredentialSource::Pass { entry } => Box::new(Pass {
+                entry: entry.clone(),
+            }),
+            CredentialSource::OnePassword { reference } => Box::new(OnePassword {
+                reference: reference.clone(),
+            }),
+        }
+    }
+}
+
+/// Ask `source` for the password of `user@host:port`. The CLIs may block
+/// on an unlock prompt, so this runs off the async runtime.
+pub async fn lookup_password(
+    source: &CredentialSource,
+    host: &str,
+    port: u16,
+    user: &str,
+) -> AppResult<Option<String>> {
+    let provider = source.provider();
+    let (host, user) = (host.to_string(), user.to_string());
+    let name = provider.name();
+    let found = tokio::task::spawn_blocking(move || {
+        let query = CredentialQuery {
+            host: &host,
+            port,
+            user: &user,
+        };
+        provider.lookup(&query)
+    })
+    .await
+    .map_err(|e| AppError::Other(format!("Credential lookup failed: {e}")))??;
+    log::info!(
+        "[AUTH] {} lookup — {}",
+        name,
+        if found.is_some() { "found" } else { "no entry" }
+    );
+    Ok(found)
+}
@@ -0,0 +1,145 @@
+//! Viewing and editing a user's crontab over exec. Before a new table is
+//! installed the previous one is copied into the session temp dir, so a bad
+//! edit can be undone.
+
+use serde::Serialize;
+use std::sync::Arc;
+
+use crate::errors::{AppError, AppResult};
+use crate::sftp_ops;
+use crate::ssh_manager::{shell_quote, SshSession};
+
+// ─── Data Structures ───────────────────────────────────────────────────
+
+/// A user's crontab (safe to send to JS).
+#[derive(Debug, Clone, Serialize)]
+pub struct Crontab {
+    /// `None` for the session's own user.
+    pub user: Option<String>,
+    /// Raw table text; empty when the user has no crontab.
+    pub content: String,
+    /// Whether the user has a crontab at all.
+    pub exists: bool,
+    /// Where the previous table was saved by the last `set_crontab`.
+    pub backup_path: Option<String>,
+}
+
+// ─── Helpers ───────────────────────────────────────────────────────────
+
+/// Longest user name accepted; matches the common `useradd` limit.
+const MAX_USER_NAME_LEN: usize = 32;
+
+/// Refuse `user` unless it is a POSIX portable user name (letters, digits,
+/// `.`, `_` and `-`, not starting with `-` or `.`, optionally ending in
+/// `$`), as it ends up in a command line and a backup file name.
+fn check_user_name(user: &str) -> AppResult<()> {
+    let name = user.strip_suffix('$').unwrap_or(user);
+    let valid = !name.is_empty()
+        && user.len() <= MAX_USER_NAME_LEN
+        && !name.starts_with(['-', '.'])
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'));
+    if !valid || user.contains("..") {
+        return Err(AppError::Other(format!("Invalid user name: {user:?}")));
+    }
+    Ok(())
+}
+
+/// `crontab` plus `-u <user>` when editing someone else's table.
+fn crontab_cmd(session: &SshSession, user: Option<&str>) -> AppResult<String> {
+    match user.filter(|u| *u != session.user) {
+        None => Ok("crontab".to_string()),
+        Some(u) => {
+            check_user_name(u)?;
+            Ok(format!("crontab -u {}", shell_quote(u)))
+        }
+    }
+}
+
+/// "no crontab for alice" — an empty table, not an error.
+fn is_no_crontab(stderr: &str) -> bool {
+    stderr.to_ascii_lowercase().contains("no crontab for")
+}
+
+// ─── Operations ────────────────────────────────────────────────────────
+
+/// Read the crontab of `user` (or the session user).
+pub async fn get_crontab(session: &Arc<SshSession>, user: Option<&str>) -> AppResult<Crontab> {
+    let output = session
+        .exec(&format!("{} -l", crontab_cmd(session, user)?))
+        .await?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let exists = match output.exit_status {
+        Some(0) => true,
+        _ if is_no_crontab(&stderr) => false,
+        status => {
+            return Err(AppError::Ssh(format!(
+                "crontab -l failed (exit {:?}): {}",
+                status,
+                stderr.trim()
+            )))
+        }
+    };
+    Ok(Crontab {
+        user: user.map(str::to_string),
+        content: String::from_utf8_lossy(&output.stdout).to_string(),
+        exists,
+        backup_path: None,
+    })
+}
+
+/// Install `content` as the crontab of `user` (or the session user). The
+/// previous table is saved in the session temp dir first; `crontab` itself
+/// validates the new one and leaves the old in place if it is rejected.
+pub async fn set_crontab(
+    session: &Arc<SshSession>,
+    user: Option<&str>,
+    content: &str,
+) -> AppResult<Crontab> {
+    let start = std::time::Instant::now();
+    let cmd = crontab_cmd(session, user)?;
+    // Part of the backup file names below.
+    let name = user.unwrap_or(&session.user);
+    check_user_name(name)?;
+    let previous = get_crontab(session, user).await?;
+
+    let temp = session.temp_dir().await?;
+    let stamp = chrono::Utc::now().format("%Y%m%dT%H%M%S");
+    let backup_path = if previous.exists {
+        let path = format!("{temp}/crontab.{name}.{stamp}.bak");
+        sftp_ops::upload_file(session, &path, previous.content.as_bytes(), Some(0o600)).await?;
+        Some(path)
+    } else {
+        None
+    };
+
+    // cron needs a trailing newline on the last entry.
+    let mut content = content.to_string();
+    if !content.is_empty() && !content.ends_with('\n') {
+        content.push('\n');
+    }
+    let new_path = format!("{temp}/crontab.{name}.{stamp}.new");
+    sftp_ops::upload_file(session, &new_path, content.as_bytes(), Some(0o600)).await?;
+    let output = session
+        .exec(&format!("{} {}", cmd, shell_quote(&new_path)))
+        .await?;
+    sftp_ops::delete_file(session, &new_path).await.ok();
+    if output.exit_status != Some(0) {
+        return Err(AppError::Ssh(format!(
+            "crontab rejected the new table: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    log::info!(
+        "[SSH] crontab for {} updated — {:.2}ms | bytes: {} | backup: {:?}",
+        name,
+        start.elapsed().as_secs_f64() * 1000.0,
+        content.len(),
+        backup_path,
+    );
+    let mut current = get_crontab(session, user).await?;
+    current.backup_path = backup_path;
+    Ok(current)
+}
@@ -0,0 +1,122 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+use crate::sftp_ops::FileEntry;
+
+/// Maximum number of directory snapshots retained per session.
+const MAX_SNAPSHOTS: usize = 64;
+
+// ─── Data Structures ───────────────────────────────────────────────────
+
+/// Result of a diff-aware directory listing.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum DirDiff {
+    /// Listing is identical to the caller's snapshot.
+    Unchanged { hash: String },
+    /// Entries changed relative to the caller's snapshot.
+    Changed {
+        hash: String,
+        added: Vec<FileEntry>,
+        /// Paths of entries no longer present.
+        removed: Vec<String>,
+        modified: Vec<FileEntry>,
+    },
+    /// The caller's snapshot is unknown — full listing included.
+    Full {
+        hash: String,
+        entries: Vec<FileEntry>,
+    },
+}
+
+struct Snapshot {
+    hash: String,
+    entries: Vec<FileEntry>,
+}
+
+/// Last listing seen per directory, used as the base for diffs.
+#[derive(Default)]
+pub struct SnapshotCache {
+    by_path: std::sync::Mutex<HashMap<String, Snapshot>>,
+}
+
+// ─── Diffing ───────────────────────────────────────────────────────────
+
+/// Hash a listing by the fields that matter for display.
+pub fn snapshot_hash(entries: &[FileEntry]) -> String {
+    let mut keyed: Vec<_> = entries
+        .iter()
+        .map(|e| (&e.path, e.is_dir, e.size, &e.modified))
+        .collect();
+    keyed.sort();
+
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    keyed.hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+fn entry_changed(old: &FileEntry, new: &FileEntry) -> bool {
+    old.size != new.size || old.modified != new.modified || old.is_dir != new.is_dir
+}
+
+impl SnapshotCache {
+    /// Record `entries` as the latest listing of `path` and diff it against
+    /// the snapshot the caller last saw.
+    pub fn diff(
+        &self,
+        path: &str,
+        previous_hash: Option<&str>,
+        entries: Vec<FileEntry>,
+    ) -> DirDiff {
+        let hash = snapshot_hash(&entries);
+        let mut by_path = self.by_path.lock().unwrap();
+
+        let result = match (previous_hash, by_path.get(path)) {
+            (Some(prev), _) if prev == hash => DirDiff::Unchanged { hash: hash.clone() },
+            (Some(prev), Some(old)) if old.hash == prev => {
+                let old_by_path: HashMap<&str, &FileEntry> =
+                    old.entries.iter().map(|e| (e.path.as_str(), e)).collect();
+                let new_paths: std::collections::HashSet<&str> =
+                    entries.iter().map(|e| e.path.as_str()).collect();
+
+                let mut added = Vec::new();
+                let mut modified = Vec::new();
+                for e in &entries {
+                    match old_by_path.get(e.path.as_str()) {
+                        None => added.push(e.clone()),
+                        Some(o) if entry_changed(o, e) => modified.push(e.clone()),
+                        Some(_) => {}
+                    }
+                }
+                let removed = old
+                    .entries
+                    .iter()
+                    .filter(|e| !new_paths.contains(e.path.as_str()))
+                    .map(|e| e.path.clone())
+                    .collect();
+
+                DirDiff::Changed {
+                    hash: hash.clone(),
+                    added,
+                    removed,
+                    modified,
+                }
+            }
+            _ => DirDiff::Full {
+                hash: hash.clone(),
+                entries: entries.clone(),
+            },
+        };
+
+        if by_path.len() >= MAX_SNAPSHOTS && !by_path.contains_key(path) {
+            // Arbitrary eviction is fine: a miss only costs a full listing.
+            if let Some(k) = by_path.keys().next().cloned() {
+                by_path.remove(&k);
+            }
+        }
+        by_path.insert(path.to_string(), Snapshot { hash, entries });
+
+        result
+    }
+}
@@ -0,0 +1,161 @@
+use serde::Serialize;
+use std::collections::HashSet;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket};
+use std::time::Duration;
+
+use mdns_sd::{ServiceDaemon, ServiceEvent};
+
+use crate::errors::{AppError, AppResult};
+
+/// mDNS service types advertised by SSH servers (Avahi, Synology, macOS…).
+const SSH_SERVICE_TYPES: &[&str] = &["_ssh._tcp.local.", "_sftp-ssh._tcp.local."];
+
+/// Per-host connect timeout used by the subnet port probe.
+const PROBE_TIMEOUT: Duration = Duration::from_millis(400);
+
+// ─── Data Structures ───────────────────────────────────────────────────
+
+/// How a LAN host was found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiscoverySource {
+    Mdns,
+    Probe,
+}
+
+/// A host found on the local network (safe to send to JS).
+#[derive(Debug, Clone, Serialize)]
+pub struct DiscoveredHost {
+    /// Advertised service instance name, if any.
+    pub name: Option<String>,
+    pub host: String,
+    pub port: u16,
+    pub source: DiscoverySource,
+}
+
+// ─── Discovery ─────────────────────────────────────────────────────────
+
+/// Browse mDNS for SSH services and optionally probe the local /24 subnet
+/// for an open port 22. Results are de-duplicated by `host:port`, with
+/// mDNS entries taking precedence.
+pub async fn discover_lan_hosts(
+    timeout: Duration,
+    probe_subnet: bool,
+) -> AppResult<Vec<DiscoveredHost>> {
+    let start = std::time::Instant::now();
+
+    let (mdns_hosts, probe_hosts) = if probe_subnet {
+        tokio::join!(browse_mdns(timeout), probe_local_subnet(22))
+    } else {
+        (browse_mdns(timeout).await, Ok(Vec::new()))
+    };
+
+    let mut seen = HashSet::new();
+    let mut hosts = Vec::new();
+    for h in mdns_hosts?.into_iter().chain(probe_hosts?) {
+        if seen.insert((h.host.clone(), h.port)) {
+            hosts.push(h);
+        }
+    }
+
+    log::info!(
+        "[DISCOVERY] found {} hosts in {:.2}ms",
+        hosts.len(),
+        start.elapsed().as_secs_f64() * 1000.0,
+    );
+    Ok(hosts)
+}
+
+/// Collect resolved SSH services from mDNS until `timeout` elapses.
+async fn browse_mdns(timeout: Duration) -> AppResult<Vec<DiscoveredHost>> {
+    let daemon = ServiceDaemon::new()
+        .map_err(|e| AppError::Other(format!("Failed to start mDNS daemon: {e}")))?;
+
+    let mut receivers = Vec::new();
+    for service in SSH_SERVICE_TYPES {
+        let rx = daemon
+            .browse(service)
+            .map_err(|e| AppError::Other(format!("Failed to browse {service}: {e}")))?;
+        receivers.push(rx);
+    }
+
+    let deadline = tokio::time::Instant::now() + timeout;
+    let mut hosts = Vec::new();
+    for rx in receivers {
+        loop {
+            let event = match tokio::time::timeout_at(deadline, rx.recv_async()).await {
+                Ok(Ok(event)) => event,
+                // Timed out or channel closed.
+                _ => break,
+            };
+            if let ServiceEvent::ServiceResolved(info) = event {
+                let name = info.get_fullname().split('.').next().map(str::to_string);
+                for addr in info.get_addresses() {
+                    hosts.push(DiscoveredHost {
+                        name: name.clone(),
+                        host: addr.to_string(),
+                        port: info.get_port(),
+                        source: DiscoverySource::Mdns,
+                    });
+                }
+            }
+        }
+    }
+
+    if let Err(e) = daemon.shutdown() {
+        log::warn!("[DISCOVERY] mDNS daemon shutdown failed: {}", e);
+    }
+    Ok(hosts)
+}
+
+/// Find the primary local IPv4 address without sending any packets.
+fn local_ipv4() -> Option<Ipv4Addr> {
+    let socket = UdpSocket::bind("0.0.0.0:0").ok()?;
+    socket.connect("192.0.2.1:80").ok()?;
+    match socket.local_addr().ok()?.ip() {
+        IpAddr::V4(ip) if !ip.is_loopback() => Some(ip),
+        _ => None,
+    }
+}
+
+/// Attempt a TCP connect to `port` on every address in the local /24.
+async fn probe_local_subnet(port: u16) -> AppResult<Vec<DiscoveredHost>> {
+    let Some(local) = local_ipv4() else {
+        log::warn!("[DISCOVERY] no local IPv4 address — skipping subnet probe");
+        return Ok(Vec::new());
+    };
+    let [a, b, c, _] = local.octets();
+
+    let mut tasks = tokio::task::JoinSet::new();
+    for d in 1..=254u8 {
+        let ip = Ipv4Addr::new(a, b, c, d);
+        if ip == local {
+            continue;
+        }
+        tasks.spawn(async move {
+            let addr = SocketAddr::new(IpAddr::V4(ip), port);
+            match tokio::time::timeout(PROBE_TIMEOUT, tokio::net::TcpStream::connect(addr)).await {
+                Ok(Ok(_)) => Some(ip),
+                _ => None,
+            }
+        });
+    }
+
+    let mut found = Vec::new();
+    while let Some(res) = tasks.join_next().await {
+        if let Ok(Some(ip)) = res {
+            found.push(ip);
+        }
+    }
+    found.sort();
+
+    Ok(found
+        .into_iter()
+        .map(|ip| DiscoveredHost {
+            name: None,
+            host: ip.to_string(),
+            port,
+            source: DiscoverySource::Probe,
+        })
+        .collect())
+}
@@ -0,0 +1,216 @@
+//! Free space, free inodes and the user's quota on the filesystem holding a
+//! remote path, read over exec with `df` and `quota`. Warnings are raised
+//! before an upload would run out of any of them, since servers only
+//! report a generic "write error" once it does.
+
+use serde::Serialize;
+use std::sync::Arc;
+
+use crate::errors::{AppError, AppResult};
+use crate::ssh_manager::{shell_quote, SshSession};
+
+/// Warn once free space or free inodes drop below this share of the total.
+const LOW_FREE_PERCENT: u64 = 5;
+/// Warn once quota usage reaches this share of the limit.
+const NEAR_QUOTA_PERCENT: u64 = 90;
+
+// ─── Data Structures ───────────────────────────────────────────────────
+
+/// The user's quota on one filesystem. Limits of 0 mean none is set.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct UserQuota {
+    pub filesystem: String,
+    pub used_bytes: u64,
+    pub soft_limit_bytes: u64,
+    pub hard_limit_bytes: u64,
+    pub used_files: u64,
+    pub soft_limit_files: u64,
+    pub hard_limit_files: u64,
+}
+
+/// Something that may make writes fail.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SpaceWarning {
+    /// Free space is low, or smaller than the planned upload.
+    LowSpace { free_bytes: u64 },
+    /// Free inodes are low, or fewer than the files to be created.
+    LowInodes { free_inodes: u64 },
+    /// Usage is at or near the quota's byte limit.
+    QuotaBytes { used_bytes: u64, limit_bytes: u64 },
+    /// Usage is at or near the quota's file limit.
+    QuotaFiles { used_files: u64, limit_files: u64 },
+}
+
+/// Space on the filesystem holding `path` (safe to send to JS).
+#[derive(Debug, Clone, Serialize)]
+pub struct DiskUsage {
+    pub path: String,
+    pub filesystem: String,
+    pub mount_point: String,
+    pub total_bytes: u64,
+    pub free_bytes: u64,
+    /// `None` where the filesystem has no fixed inode count (btrfs, ZFS).
+    pub total_inodes: Option<u64>,
+    pub free_inodes: Option<u64>,
+    /// `None` if no quota applies or `quota` is not installed.
+    pub quota: Option<UserQuota>,
+    pub warnings: Vec<SpaceWarning>,
+}
+
+// ─── Parsing ───────────────────────────────────────────────────────────
+
+/// Fields of the data line of `df -P` output: filesystem, three numbers,
+/// the use percentage and the mount point (which may contain spaces).
+pub(crate) fn parse_df(output: &str) -> Option<(String, [u64; 3], String)> {
+    let line = output.lines().nth(1)?;
+    let mut fields = line.split_whitespace();
+    let filesystem = fields.next()?.to_string();
+    let mut numbers = [0u64; 3];
+    for n in &mut numbers {
+        *n = fields.next()?.parse().ok()?;
+    }
+    fields.next()?;
+    let mount_point = fields.collect::<Vec<_>>().join(" ");
+    Some((filesystem, numbers, mount_point))
+}
+
+/// Parse `quota -u -w -p` output: per filesystem, blocks (KiB), soft and
+/// hard limits and grace, then the same for files. Usage over a limit is
+/// marked with a trailing `*`.
+fn parse_quota(output: &str) -> Vec<UserQuota> {
+    let number = |s: &str| s.trim_end_matches('*').parse::<u64>().ok();
+    output
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() < 9 {
+                return None;
+            }
+            Some(UserQuota {
+                filesystem: fields[0].to_string(),
+                used_bytes: number(fields[1])? * 1024,
+                soft_limit_bytes: number(fields[2])? * 1024,
+                hard_limit_bytes: number(fields[3])? * 1024,
+                used_files: number(fields[5])?,
+                soft_limit_files: number(fields[6])?,
+                hard_limit_files: number(fields[7])?,
+            })
+        })
+        .collect()
+}
+
+/// The tighter of two limits, ignoring unset (0) ones.
+fn effective_limit(soft: u64, hard: u64) -> Option<u64> {
+    [soft, hard].into_iter().filter(|&l| l > 0).min()
+}
+
+fn near(used: u64, limit: u64, adding: u64) -> bool {
+    used + adding >= limit || used * 100 >= limit * NEAR_QUOTA_PERCENT
+}
+
+fn low(free: u64, total: u64, needed: u64) -> bool {
+    free < needed || free * 100 < total * LOW_FREE_PERCENT
+}
+
+impl DiskUsage {
+    /// Warnings for writing `bytes` in `files` new files here.
+    fn warnings_for(&self, bytes: u64, files: u64) -> Vec<SpaceWarning> {
+        let mut warnings = Vec::new();
+        if low(self.free_bytes, self.total_bytes, bytes) {
+            warnings.push(SpaceWarning::LowSpace {
+                free_bytes: self.free_bytes,
+            });
+        }
+        if let (Some(total), Some(free)) = (self.total_inodes, self.free_inodes) {
+            if low(free, total, files) {
+                warnings.push(SpaceWarning::LowInodes { free_inodes: free });
+            }
+        }
+        if let Some(q) = &self.quota {
+            if let Some(limit) = effective_limit(q.soft_limit_bytes, q.hard_limit_bytes) {
+                if near(q.used_bytes, limit, bytes) {
+                    warnings.push(SpaceWarning::QuotaBytes {
+                        used_bytes: q.used_bytes,
+                        limit_bytes: limit,
+                    });
+                }
+            }
+            if let Some(limit) = effective_limit(q.soft_limit_files, q.hard_limit_files) {
+                if near(q.used_files, limit, files) {
+                    warnings.push(SpaceWarning::QuotaFiles {
+                        used_files: q.used_files,
+                        limit_files: limit,
+                    });
+                }
+            }
+        }
+        warnings
+    }
+}
+
+// ─── Usage ─────────────────────────────────────────────────────────────
+
+/// Read space, inodes and quota for the filesystem holding `path`, with
+/// warnings for an upload of `upload_bytes` in `upload_files` files.
+pub async fn disk_usage(
+    session: &Arc<SshSession>,
+    path: &str,
+    upload_bytes: u64,
+    upload_files: u64,
+) -> AppResult<DiskUsage> {
+    let start = std::time::Instant::now();
+    let quoted = shell_quote(path);
+    let df_bytes = format!("df -Pk -- {quoted}");
+    let df_inodes = format!("df -Pi -- {quoted}");
+    let (bytes, inodes, quota) = tokio::join!(
+        session.exec(&df_bytes),
+        session.exec(&df_inodes),
+        session.exec("quota -u -w -p 2>/dev/null"),
+    );
+
+    let bytes = bytes?;
+    let stdout = String::from_utf8_lossy(&bytes.stdout);
+    let (filesystem, [total_kib, _, free_kib], mount_point) =
+        parse_df(&stdout).ok_or_else(|| {
+            let stderr = String::from_utf8_lossy(&bytes.stderr);
+            AppError::Ssh(format!("Failed to read free space: {}", stderr.trim()))
+        })?;
+
+    // Inode and quota figures are best-effort: not every server has them.
+    let inodes = inodes
+        .ok()
+        .and_then(|out| parse_df(&String::from_utf8_lossy(&out.stdout)))
+        .map(|(_, [total, _, free], _)| (total, free))
+        .filter(|&(total, _)| total > 0);
+    // `quota` names the device or, on some systems, the mount point. Another
+    // filesystem's quota says nothing about this one, so none is reported
+    // unless a line matches.
+    let quota = quota.ok().and_then(|out| {
+        parse_quota(&String::from_utf8_lossy(&out.stdout))
+            .into_iter()
+            .find(|q| q.filesystem == filesystem || q.filesystem == mount_point)
+    });
+
+    let mut usage = DiskUsage {
+        path: path.to_string(),
+        filesystem,
+        mount_point,
+        total_bytes: total_kib * 1024,
+        free_bytes: free_kib * 1024,
+        total_inodes: inodes.map(|(total, _)| total),
+        free_inodes: inodes.map(|(_, free)| free),
+        quota,
+        warnings: Vec::new(),
+    };
+    usage.warnings = usage.warnings_for(upload_bytes, upload_files);
+
+    log::info!(
+        "[PERF] disk_usage \"{}\" — {:.2}ms | free: {} bytes | warnings: {}",
+        path,
+        start.elapsed().as_secs_f64() * 1000.0,
+        usage.free_bytes,
+        usage.warnings.len(),
+    );
+    Ok(usage)
+}
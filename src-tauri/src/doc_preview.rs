@@ -0,0 +1,298 @@
+//! Text and thumbnail previews for Office Open XML and OpenDocument files.
+//!
+//! These formats are zip containers, so only the central directory and the
+//! few entries we need are fetched with ranged reads — never the whole file.
+
+use serde::Serialize;
+use std::io::Read;
+use std::sync::Arc;
+
+use crate::errors::{AppError, AppResult};
+use crate::file_types::{self, FileCategory};
+use crate::sftp_ops;
+use crate::ssh_manager::SshSession;
+
+/// End-of-central-directory record plus the largest possible zip comment.
+const EOCD_SEARCH_BYTES: u64 = 22 + 65_535;
+/// Refuse central directories or entries larger than this.
+const MAX_ENTRY_BYTES: u64 = 16 * 1024 * 1024;
+/// Cap on extracted text returned to the UI.
+const MAX_PREVIEW_CHARS: usize = 64 * 1024;
+
+// ─── Data Structures ───────────────────────────────────────────────────
+
+/// Preview of an office document (safe to send to JS).
+#[derive(Debug, Clone, Serialize)]
+pub struct DocumentPreview {
+    /// Container format, e.g. "docx", "odt".
+    pub kind: String,
+    pub text: String,
+    pub truncated: bool,
+    /// Base64 embedded thumbnail, if the document carries one.
+    pub thumbnail: Option<String>,
+    pub thumbnail_mime: Option<String>,
+}
+
+struct ZipEntry {
+    name: String,
+    method: u16,
+    compressed_size: u64,
+    uncompressed_size: u64,
+    local_offset: u64,
+}
+
+/// Which zip entries hold a format's text, and which tags end a paragraph.
+struct Layout {
+    text_entries: fn(&str) -> bool,
+    paragraph_ends: &'static [&'static str],
+    thumbnails: &'static [&'static str],
+}
+
+const OOXML_THUMBS: &[&str] = &["docProps/thumbnail.jpeg", "docProps/thumbnail.png"];
+const ODF_THUMBS: &[&str] = &["Thumbnails/thumbnail.png"];
+const ODF_PARAS: &[&str] = &["</text:p>", "</text:h>"];
+
+fn layout_for(ext: &str) -> Option<Layout> {
+    let layout = match ext {
+        "docx" => Layout {
+            text_entries: |n| n == "word/document.xml",
+            paragraph_ends: &["</w:p>"],
+            thumbnails: OOXML_THUMBS,
+        },
+        "xlsx" => Layout {
+            text_entries: |n| n == "xl/sharedStrings.xml",
+            paragraph_ends: &["</si>"],
+            thumbnails: OOXML_THUMBS,
+        },
+        "pptx" => Layout {
+            text_entries: |n| n.starts_with("ppt/slides/slide") && n.ends_with(".xml"),
+            paragraph_ends: &["</a:p>"],
+            thumbnails: OOXML_THUMBS,
+        },
+        "odt" | "ods" | "odp" => Layout {
+            text_entries: |n| n == "content.xml",
+            paragraph_ends: ODF_PARAS,
+            thumbnails: ODF_THUMBS,
+        },
+        _ => return None,
+    };
+    Some(layout)
+}
+
+// ─── Zip Container ─────────────────────────────────────────────────────
+
+fn u16_at(b: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([b[at], b[at + 1]])
+}
+
+fn u32_at(b: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([b[at], b[at + 1], b[at + 2], b[at + 3]])
+}
+
+fn bad_zip(msg: &str) -> AppError {
+    AppError::Other(format!("Not a readable document container: {msg}"))
+}
+
+/// Read the central directory using ranged reads from the end of the file.
+async fn read_central_directory(
+    session: &Arc<SshSession>,
+    path: &str,
+    size: u64,
+) -> AppResult<Vec<ZipEntry>> {
+    let tail_len = size.min(EOCD_SEARCH_BYTES);
+    let tail_start = size - tail_len;
+    let tail = sftp_ops::read_range(session, path, tail_start, tail_len).await?;
+
+    let eocd = (0..tail.len().saturating_sub(21))
+        .rev()
+        .find(|&i| u32_at(&tail, i) == 0x0605_4b50)
+        .ok_or_else(|| bad_zip("end of central directory not found"))?;
+    let count = u16_at(&tail, eocd + 10) as usize;
+    let cd_size = u32_at(&tail, eocd + 12) as u64;
+    let cd_offset = u32_at(&tail, eocd + 16) as u64;
+    if cd_offset == 0xFFFF_FFFF || cd_size > MAX_ENTRY_BYTES {
+        return Err(bad_zip("zip64 or oversized central directory"));
+    }
+
+    let cd = if cd_offset >= tail_start {
+        let at = (cd_offset - tail_start) as usize;
+        tail.get(at..at + cd_size as usize)
+            .ok_or_else(|| bad_zip("central directory out of range"))?
+            .to_vec()
+    } else {
+        sftp_ops::read_range(session, path, cd_offset, cd_size).await?
+    };
+
+    let mut entries = Vec::with_capacity(count);
+    let mut at = 0;
+    while at + 46 <= cd.len() && u32_at(&cd, at) == 0x0201_4b50 {
+        let name_len = u16_at(&cd, at + 28) as usize;
+        let extra_len = u16_at(&cd, at + 30) as usize;
+        let comment_len = u16_at(&cd, at + 32) as usize;
+        let Some(name) = cd.get(at + 46..at + 46 + name_len) else {
+            break;
+        };
+        entries.push(ZipEntry {
+            name: String::from_utf8_lossy(name).to_string(),
+            method: u16_at(&cd, at + 10),
+            compressed_size: u32_at(&cd, at + 20) as u64,
+            uncompressed_size: u32_at(&cd, at + 24) as u64,
+            local_offset: u32_at(&cd, at + 42) as u64,
+        });
+        at += 46 + name_len + extra_len + comment_len;
+    }
+    Ok(entries)
+}
+
+/// Fetch and decompress one entry.
+async fn read_entry(session: &Arc<SshSession>, path: &str, entry: &ZipEntry) -> AppResult<Vec<u8>> {
+    if entry.compressed_size > MAX_ENTRY_BYTES || entry.uncompressed_size > MAX_ENTRY_BYTES {
+        return Err(bad_zip(&format!("{} is too large to preview", entry.name)));
+    }
+    let header = sftp_ops::read_range(session, path, entry.local_offset, 30).await?;
+    if header.len() < 30 || u32_at(&header, 0) != 0x0403_4b50 {
+        return Err(bad_zip("bad local file header"));
+    }
+    let data_start =
+        entry.local_offset + 30 + u16_at(&header, 26) as u64 + u16_at(&header, 28) as u64;
+    let raw = sftp_ops::read_range(session, path, data_start, entry.compressed_size).await?;
+
+    match entry.method {
+        0 => Ok(raw),
+        8 => {
+            let mut out = Vec::with_capacity(entry.uncompressed_size as usize);
+            flate2::read::DeflateDecoder::new(raw.as_slice())
+                .take(MAX_ENTRY_BYTES)
+                .read_to_end(&mut out)
+                .map_err(|e| bad_zip(&format!("failed to inflate {}: {e}", entry.name)))?;
+            Ok(out)
+        }
+        m => Err(bad_zip(&format!("unsupported compression method {m}"))),
+    }
+}
+
+// ─── Text Extraction ───────────────────────────────────────────────────
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Strip XML tags, keeping character data and turning paragraph-end tags
+/// (and tabs/line breaks) into whitespace.
+fn xml_text(xml: &str, paragraph_ends: &[&str]) -> String {
+    let mut out = String::new();
+    let mut rest = xml;
+    while let Some(open) = rest.find('<') {
+        out.push_str(&unescape(&rest[..open]));
+        let Some(close) = rest[open..].find('>') else {
+            break;
+        };
+        let tag = &rest[open..open + close + 1];
+        if paragraph_ends.contains(&tag) {
+            out.push('\n');
+        } else if tag.starts_with("<w:tab") || tag.starts_with("<text:tab") {
+            out.push('\t');
+        } else if tag.starts_with("<w:br") || tag.starts_with("<text:line-break") {
+            out.push('\n');
+        }
+        rest = &rest[open + close + 1..];
+    }
+    out
+}
+
+/// Slide number for ordering `ppt/slides/slideN.xml`.
+fn slide_number(name: &str) -> u32 {
+    name.trim_start_matches("ppt/slides/slide")
+        .trim_end_matches(".xml")
+        .parse()
+        .unwrap_or(u32::MAX)
+}
+
+// ─── Preview ───────────────────────────────────────────────────────────
+
+/// Extract plain text and any embedded thumbnail from a docx/xlsx/pptx or
+/// odt/ods/odp file.
+pub async fn preview_document(session: &Arc<SshSession>, path: &str) -> AppResult<DocumentPreview> {
+    let start = std::time::Instant::now();
+    let ext = file_types::extension(path).unwrap_or_default();
+    let layout = layout_for(&ext)
+        .filter(|_| file_types::classify(path) == FileCategory::Documents)
+        .ok_or_else(|| AppError::Other(format!("No document preview for .{ext} files")))?;
+
+    let size = sftp_ops::file_size(session, path)
+        .await?
+        .ok_or_else(|| AppError::Sftp("Could not determine file size".into()))?;
+    let entries = read_central_directory(session, path, size).await?;
+
+    let mut text_entries: Vec<&ZipEntry> = entries
+        .iter()
+        .filter(|e| (layout.text_entries)(&e.name))
+        .collect();
+    text_entries.sort_by_key(|e| slide_number(&e.name));
+
+    let mut text = String::new();
+    for entry in text_entries {
+        if text.len() >= MAX_PREVIEW_CHARS {
+            break;
+        }
+        let xml = read_entry(session, path, entry).await?;
+        text.push_str(&xml_text(
+            &String::from_utf8_lossy(&xml),
+            layout.paragraph_ends,
+        ));
+        text.push('\n');
+    }
+    let truncated = text.len() > MAX_PREVIEW_CHARS;
+    if truncated {
+        let mut cut = MAX_PREVIEW_CHARS;
+        while !text.is_char_boundary(cut) {
+            cut -= 1;
+        }
+        text.truncate(cut);
+    }
+
+    let mut thumbnail = None;
+    let mut thumbnail_mime = None;
+    if let Some(entry) = layout
+        .thumbnails
+        .iter()
+        .find_map(|t| entries.iter().find(|e| e.name == *t))
+    {
+        match read_entry(session, path, entry).await {
+            Ok(data) => {
+                thumbnail_mime = Some(
+                    if entry.name.ends_with(".png") {
+                        "image/png"
+                    } else {
+                        "image/jpeg"
+                    }
+                    .to_string(),
+                );
+                thumbnail = Some(base64::Engine::encode(
+                    &base64::engine::general_purpose::STANDARD,
+                    &data,
+                ));
+            }
+            Err(e) => log::warn!("[SFTP] document thumbnail unreadable: {}", e),
+        }
+    }
+
+    log::info!(
+        "[PERF] preview_document \"{}\" — {:.2}ms | entries: {} | chars: {}",
+        path,
+        start.elapsed().as_secs_f64() * 1000.0,
+        entries.len(),
+        text.len(),
+    );
+    Ok(DocumentPreview {
+        kind: ext,
+        text: text.trim().to_string(),
+        truncated,
+        thumbnail,
+        thumbnail_mime,
+    })
+}
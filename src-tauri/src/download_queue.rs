@@ -0,0 +1,188 @@
+//! Downloads that were still running when the app last exited.
+//!
+//! `sftp_save_file` and `sftp_download_dir` journal each transfer to a JSON
+//! file when it starts, record its byte offset as it runs and drop it once
+//! it finishes, fails or is cancelled. Anything left on the next launch was
+//! cut short by the app going away and can be resumed: a single file from
+//! the bytes already on disk, a directory by re-running it with unchanged
+//! files skipped.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::errors::{AppError, AppResult};
+use crate::post_actions::PostAction;
+use crate::transfer::{EventSink, TransferEvent};
+
+/// Offsets are written to disk at most this often while a transfer runs.
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+// ─── Data Structures ───────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QueuedKind {
+    File,
+    Dir,
+}
+
+/// One journaled download (safe to send to JS).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueuedDownload {
+    /// The transfer id its progress events were sent under.
+    pub id: String,
+    pub kind: QueuedKind,
+    pub host: String,
+    pub user: String,
+    pub remote_path: String,
+    pub local_path: String,
+    /// Last recorded offset; may trail the bytes actually on disk.
+    pub bytes_done: u64,
+    pub bytes_total: u64,
+    /// Remote mtime when the file download started. A resume starts over
+    /// if the file has changed since.
+    #[serde(default)]
+    pub remote_mtime: Option<u32>,
+    /// RFC 3339 timestamp.
+    pub started_at: String,
+    /// Run once the download completes, including after a resume.
+    #[serde(default)]
+    pub post_actions: Vec<PostAction>,
+}
+
+/// Journal of running and interrupted downloads, backed by a JSON file.
+pub struct DownloadQueue {
+    path: PathBuf,
+    items: Mutex<Vec<QueuedDownload>>,
+    /// Ids owned by a transfer in this process; the rest are interrupted.
+    running: Mutex<HashSet<String>>,
+    last_flush: Mutex<Instant>,
+}
+
+// ─── Store ─────────────────────────────────────────────────────────────
+
+impl DownloadQueue {
+    /// Open the journal at `path`; entries already in it are interrupted.
+    pub fn new(path: PathBuf) -> Self {
+        let items: Vec<QueuedDownload> = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|text| serde_json::from_str(&text).ok())
+            .unwrap_or_default();
+        if !items.is_empty() {
+            log::info!(
+                "[TRANSFER] {} interrupted download(s) in queue",
+                items.len()
+            );
+        }
+        Self {
+            path,
+            items: Mutex::new(items),
+            running: Mutex::new(HashSet::new()),
+            last_flush: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Write the journal via a temporary file so a crash never leaves it
+    /// half-written.
+    fn flush(&self, items: &[QueuedDownload]) {
+        let result = serde_json::to_vec(items)
+            .map_err(|e| AppError::Other(format!("Failed to encode download queue: {e}")))
+            .and_then(|data| {
+                let tmp = self.path.with_extension("tmp");
+                std::fs::write(&tmp, data)
+                    .and_then(|_| std::fs::rename(&tmp, &self.path))
+                    .map_err(|e| AppError::Io(format!("Failed to write download queue: {e}")))
+            });
+        if let Err(e) = result {
+            log::warn!("[TRANSFER] {}", e);
+        }
+        *self.last_flush.lock().unwrap() = Instant::now();
+    }
+
+    /// Journal a download that is about to start, replacing any entry with
+    /// the same id.
+    pub fn begin(&self, item: QueuedDownload) {
+        self.running.lock().unwrap().insert(item.id.clone());
+        let mut items = self.items.lock().unwrap();
+        items.retain(|i| i.id != item.id);
+        items.push(item);
+        self.flush(&items);
+    }
+
+    /// Record how far download `id` has got. Written to disk at most once
+    /// per `FLUSH_INTERVAL`.
+    pub fn progress(&self, id: &str, bytes_done: u64, bytes_total: u64) {
+        let mut items = self.items.lock().unwrap();
+        let Some(item) = items.iter_mut().find(|i| i.id == id) else {
+            return;
+        };
+        item.bytes_done = bytes_done;
+        item.bytes_total = bytes_total.max(item.bytes_total);
+        if self.last_flush.lock().unwrap().elapsed() >= FLUSH_INTERVAL {
+            self.flush(&items);
+        }
+    }
+
+    /// Drop download `id` from the journal, returning its entry.
+    pub fn finish(&self, id: &str) -> Option<QueuedDownload> {
+        self.running.lock().unwrap().remove(id);
+        let mut items = self.items.lock().unwrap();
+        let index = items.iter().position(|i| i.id == id)?;
+        let item = items.remove(index);
+        self.flush(&items);
+        Some(item)
+    }
+
+    /// An interrupted download by id; `None` if unknown or running.
+    pub fn interrupted(&self, id: &str) -> Option<QueuedDownload> {
+        if self.running.lock().unwrap().contains(id) {
+            return None;
+        }
+        self.items
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|i| i.id == id)
+            .cloned()
+    }
+
+    /// Interrupted downloads, oldest first, optionally only those for one
+    /// `(host, user)`.
+    pub fn list(&self, server: Option<(&str, &str)>) -> Vec<QueuedDownload> {
+        let running = self.running.lock().unwrap();
+        self.items
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|i| !running.contains(&i.id))
+            .filter(|i| server.is_none_or(|(h, u)| i.host == h && i.user == u))
+            .cloned()
+            .collect()
+    }
+
+    /// Forward events to `sink`, recording the offset of download `id` from
+    /// its `Download` and `Aggregate` events on the way.
+    pub fn journal_sink(self: &Arc<Self>, id: &str, sink: EventSink) -> EventSink {
+        let queue = self.clone();
+        let id = id.to_string();
+        Arc::new(move |event: TransferEvent| {
+            match &event {
+                TransferEvent::Download {
+                    bytes_done,
+                    bytes_total,
+                    ..
+                }
+                | TransferEvent::Aggregate {
+                    bytes_done,
+                    bytes_total,
+                    ..
+                } => queue.progress(&id, *bytes_done, *bytes_total),
+                _ => {}
+            }
+            sink(event)
+        })
+    }
+}
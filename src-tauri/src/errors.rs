@@ -9,15 +9,78 @@ pub enum AppError {
     #[error("SFTP error: {0}")]
     Sftp(String),
 
+    #[error("SFTP subsystem unavailable: {0}")]
+    SftpUnavailable(String),
+
+    #[error(
+        "File too large: {size} bytes exceeds the {limit}-byte in-memory limit. \
+         Save it to disk with sftp_save_file instead, or pass force=true."
+    )]
+    TooLarge { size: u64, limit: u64 },
+
+    #[error(
+        "Large download: {size} bytes exceeds the {limit}-byte confirmation threshold. \
+         Pass confirm=true to download anyway."
+    )]
+    ConfirmDownload { size: u64, limit: u64 },
+
     #[error("Key storage error: {0}")]
     KeyStore(String),
 
+    #[error("Vault is locked — re-authenticate to unlock")]
+    VaultLocked,
+
     #[error("Session not found: {0}")]
     SessionNotFound(String),
 
     #[error("IO error: {0}")]
     Io(String),
 
+    #[error("Settings error: {0}")]
+    Settings(String),
+
+    /// The server requires a new password before the login can complete.
+    #[error("Password change required: {0}")]
+    PasswordChangeRequired(String),
+
+    /// The server's key differs from the key pinned in the profile.
+    #[error("Host key mismatch: expected {expected}, server presented {presented}")]
+    HostKeyMismatch { expected: String, presented: String },
+
+    /// A strict profile refused the server's key; no override is offered.
+    #[error("Host key rejected (strict): {0}")]
+    HostKeyRejected(String),
+
+    /// The server refused the credentials. The fields say what it would
+    /// still accept and what is likely wrong.
+    #[error(
+        "Authentication rejected by server{}",
+        auth_detail(.methods_remaining, .hints)
+    )]
+    AuthRejected {
+        /// Methods the server still offers, e.g. `publickey`, `password`.
+        methods_remaining: Vec<String>,
+        /// Whether a key was offered to a server that accepts keys, i.e.
+        /// the key itself was checked and refused.
+        key_attempted: bool,
+        /// The server accepted this step but wants another method as well.
+        partial_success: bool,
+        hints: Vec<String>,
+    },
+
+    #[error("Account expired: {0}")]
+    AccountExpired(String),
+
+    /// A rename would cross filesystems on the server; the caller copies instead.
+    #[error("Cannot rename across filesystems: {0}")]
+    CrossDevice(String),
+
+    #[error("Timed out: {0}")]
+    Timeout(String),
+
+    #[error("Cancelled: {0}")]
+    Cancelled(String),
+
     #[error("Unsupported key type: {0}")]
     UnsupportedKeyType(String),
 
@@ -25,12 +88,47 @@ pub enum AppError {
     Other(String),
 }
 
+/// Suffix for [`AppError::AuthRejected`]: the methods on offer and hints.
+fn auth_detail(methods_remaining: &[String], hints: &[String]) -> String {
+    let mut detail = if methods_remaining.is_empty() {
+        " (no other methods offered)".to_string()
+    } else {
+        format!(" (server allows: {})", methods_remaining.join(", "))
+    };
+    for hint in hints {
+        detail.push_str(". ");
+        detail.push_str(hint);
+    }
+    detail
+}
+
+/// Errors reach JS as their message, except a refused login, which is an
+/// object (`kind: "auth_rejected"`) so the UI can act on its fields.
 impl Serialize for AppError {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: serde::Serializer,
     {
-        serializer.serialize_str(&self.to_string())
+        use serde::ser::SerializeStruct;
+
+        match self {
+            AppError::AuthRejected {
+                methods_remaining,
+                key_attempted,
+                partial_success,
+                hints,
+            } => {
+                let mut s = serializer.serialize_struct("AuthRejected", 6)?;
+                s.serialize_field("kind", "auth_rejected")?;
+                s.serialize_field("message", &self.to_string())?;
+                s.serialize_field("methods_remaining", methods_remaining)?;
+                s.serialize_field("key_attempted", key_attempted)?;
+                s.serialize_field("partial_success", partial_success)?;
+                s.serialize_field("hints", hints)?;
+                s.end()
+            }
+            _ => serializer.serialize_str(&self.to_string()),
+        }
     }
 }
 
@@ -0,0 +1,68 @@
+//! Per-session timeline of significant connection events, kept in a bounded
+//! ring buffer so failures can be explained after the fact.
+
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+/// Events kept per session; older ones are dropped first.
+const MAX_EVENTS: usize = 500;
+
+// ─── Data Structures ───────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionEventKind {
+    Connected,
+    /// An SFTP, exec or PTY channel was opened.
+    ChannelOpened,
+    /// A remote operation exceeded its timeout.
+    Timeout,
+    /// The server refused SFTP; falling back to SCP.
+    SftpFallback,
+    /// Keepalives went unanswered or the connection dropped.
+    ConnectionLost,
+    /// The server closed the connection.
+    Disconnected,
+    /// A dropped connection was re-dialed under the same session id.
+    Reconnected,
+    /// Some files in a transfer failed permanently.
+    TransferFailed,
+}
+
+/// One timeline entry (safe to send to JS).
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionEvent {
+    /// RFC 3339 timestamp.
+    pub at: String,
+    pub kind: SessionEventKind,
+    pub detail: String,
+}
+
+/// Bounded event log shared by a session and its connection handler.
+#[derive(Default)]
+pub struct EventLog {
+    events: Mutex<VecDeque<SessionEvent>>,
+}
+
+// ─── Recording ─────────────────────────────────────────────────────────
+
+impl EventLog {
+    pub fn record(&self, kind: SessionEventKind, detail: impl Into<String>) {
+        let event = SessionEvent {
+            at: chrono::Utc::now().to_rfc3339(),
+            kind,
+            detail: detail.into(),
+        };
+        let mut events = self.events.lock().unwrap();
+        if events.len() == MAX_EVENTS {
+            events.pop_front();
+        }
+        events.push_back(event);
+    }
+
+    /// All recorded events, oldest first.
+    pub fn snapshot(&self) -> Vec<SessionEvent> {
+        self.events.lock().unwrap().iter().cloned().collect()
+    }
+}
@@ -0,0 +1,124 @@
+//! Central registry of file types. A file's category decides how it is
+//! previewed and whether it gets a thumbnail (images and fonts do).
+//!
+//! Built-in categories go by the last extension. User overrides from the
+//! settings map a whole suffix (`"log.1"`, `"insp"`) to a category and take
+//! precedence, longest suffix first.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+const IMAGE_EXTENSIONS: &[&str] = &[
+    "png", "jpg", "jpeg", "gif", "bmp", "webp", "avif", "heic", "svg",
+];
+const VIDEO_EXTENSIONS: &[&str] = &["mp4", "mkv", "mov", "avi", "webm", "m4v", "wmv", "flv"];
+const AUDIO_EXTENSIONS: &[&str] = &["mp3", "flac", "wav", "ogg", "m4a", "aac", "opus"];
+const ARCHIVE_EXTENSIONS: &[&str] = &[
+    "zip", "tar", "gz", "tgz", "bz2", "xz", "zst", "7z", "rar", "deb", "rpm", "iso",
+];
+const DOCUMENT_EXTENSIONS: &[&str] = &[
+    "pdf", "doc", "docx", "xls", "xlsx", "ppt", "pptx", "odt", "ods", "odp",
+];
+const FONT_EXTENSIONS: &[&str] = &["ttf", "otf", "ttc", "woff", "woff2"];
+const TEXT_EXTENSIONS: &[&str] = &["txt", "md", "csv", "log", "ini", "conf", "cfg", "env"];
+const CODE_EXTENSIONS: &[&str] = &[
+    "rs", "py", "js", "ts", "tsx", "jsx", "go", "c", "h", "cpp", "hpp", "java", "kt", "rb", "php",
+    "sh", "swift", "cs", "html", "css", "scss", "json", "yaml", "yml", "toml", "xml", "sql", "lua",
+];
+
+/// User overrides as `(suffix, category)`, longest suffix first.
+static OVERRIDES: RwLock<Vec<(String, FileCategory)>> = RwLock::new(Vec::new());
+
+// ─── Data Structures ───────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileCategory {
+    Images,
+    Video,
+    Audio,
+    Documents,
+    Text,
+    Code,
+    Archives,
+    Fonts,
+    #[default]
+    Other,
+}
+
+// ─── Classification ────────────────────────────────────────────────────
+
+/// Lower-cased suffix without leading dots, as stored in the settings.
+pub fn normalize_suffix(suffix: &str) -> String {
+    suffix.trim().trim_start_matches('.').to_lowercase()
+}
+
+/// Category of a built-in extension (lower-cased, without the dot).
+fn builtin(ext: &str) -> FileCategory {
+    let table: [(&[&str], FileCategory); 8] = [
+        (IMAGE_EXTENSIONS, FileCategory::Images),
+        (VIDEO_EXTENSIONS, FileCategory::Video),
+        (AUDIO_EXTENSIONS, FileCategory::Audio),
+        (DOCUMENT_EXTENSIONS, FileCategory::Documents),
+        (TEXT_EXTENSIONS, FileCategory::Text),
+        (CODE_EXTENSIONS, FileCategory::Code),
+        (ARCHIVE_EXTENSIONS, FileCategory::Archives),
+        (FONT_EXTENSIONS, FileCategory::Fonts),
+    ];
+    table
+        .iter()
+        .find(|(exts, _)| exts.contains(&ext))
+        .map_or(FileCategory::Other, |&(_, category)| category)
+}
+
+/// Lower-cased last extension of `name`, without the dot. `None` for a
+/// name without one, including hidden files such as `.bashrc`.
+pub fn extension(name: &str) -> Option<String> {
+    let name = name.rsplit('/').next().unwrap_or(name);
+    match name.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() && !ext.is_empty() => Some(ext.to_lowercase()),
+        _ => None,
+    }
+}
+
+/// Category of the file `name`.
+pub fn classify(name: &str) -> FileCategory {
+    let name = name.to_lowercase();
+    let overrides = OVERRIDES.read().unwrap();
+    let matched = overrides.iter().find(|(suffix, _)| {
+        // The suffix must follow a dot and leave a non-empty stem.
+        name.len() > suffix.len() + 1
+            && name.ends_with(suffix.as_str())
+            && name[..name.len() - suffix.len()].ends_with('.')
+    });
+    if let Some(&(_, category)) = matched {
+        return category;
+    }
+    match name.rsplit_once('.') {
+        // A leading dot marks a hidden file, not an extension.
+        Some((stem, ext)) if !stem.is_empty() => builtin(ext),
+        _ => FileCategory::Other,
+    }
+}
+
+/// Returns true if `name` is classified as an image.
+pub fn is_image(name: &str) -> bool {
+    classify(name) == FileCategory::Images
+}
+
+/// Returns true if `name` is classified as a font.
+pub fn is_font(name: &str) -> bool {
+    classify(name) == FileCategory::Fonts
+}
+
+/// Install the user overrides from the settings.
+pub fn apply(overrides: &HashMap<String, FileCategory>) {
+    let mut list: Vec<(String, FileCategory)> = overrides
+        .iter()
+        .map(|(suffix, &category)| (normalize_suffix(suffix), category))
+        .filter(|(suffix, _)| !suffix.is_empty())
+        .collect();
+    list.sort_by(|a, b| b.0.len().cmp(&a.0.len()).then_with(|| a.0.cmp(&b.0)));
+    *OVERRIDES.write().unwrap() = list;
+}
@@ -0,0 +1,122 @@
+//! Font previews: a sample string rasterised with the font itself, served
+//! through the same WebP thumbnail pipeline as images. WOFF and WOFF2 are
+//! unpacked to plain sfnt data first.
+
+use ab_glyph::{point, Font, FontVec, PxScale, ScaleFont};
+use image::{Rgba, RgbaImage};
+
+use crate::errors::{AppError, AppResult};
+
+/// Side of the square preview, matching image thumbnails.
+const PREVIEW_SIZE: u32 = 256;
+/// Blank border around the sample text.
+const PADDING: f32 = 14.0;
+/// Lines of sample text with their preferred pixel size. Lines too wide
+/// for the preview are scaled down to fit.
+const SAMPLE_LINES: &[(&str, f32)] = &[("Aa", 112.0), ("Bb Cc Gg Qq", 40.0), ("0123456789", 30.0)];
+/// Gap between sample lines, as a share of the line's size.
+const LINE_GAP: f32 = 0.12;
+
+const BACKGROUND: Rgba<u8> = Rgba([250, 250, 250, 255]);
+const INK: [u8; 3] = [24, 24, 27];
+
+/// Parse a TTF, OTF, TTC (first face), WOFF or WOFF2 file.
+fn load_font(buf: &[u8]) -> AppResult<FontVec> {
+    let sfnt = match buf.get(..4) {
+        Some(b"wOFF") => wuff::decompress_woff1(buf)
+            .map_err(|e| AppError::Other(format!("WOFF decode failed: {e:?}")))?,
+        Some(b"wOF2") => wuff::decompress_woff2(buf)
+            .map_err(|e| AppError::Other(format!("WOFF2 decode failed: {e:?}")))?,
+        _ => buf.to_vec(),
+    };
+    FontVec::try_from_vec_and_index(sfnt, 0)
+        .map_err(|e| AppError::Other(format!("Font parse failed: {e}")))
+}
+
+/// Width of `text` set at `scale`, kerning included.
+fn line_width<F: Font>(font: &F, scale: PxScale, text: &str) -> f32 {
+    let scaled = font.as_scaled(scale);
+    let mut width = 0.0;
+    let mut prev = None;
+    for c in text.chars() {
+        let id = scaled.glyph_id(c);
+        if let Some(prev) = prev {
+            width += scaled.kern(prev, id);
+        }
+        width += scaled.h_advance(id);
+        prev = Some(id);
+    }
+    width
+}
+
+/// Draw `text` with its left end at `x` and baseline at `baseline`.
+fn draw_line<F: Font>(
+    img: &mut RgbaImage,
+    font: &F,
+    scale: PxScale,
+    text: &str,
+    x: f32,
+    baseline: f32,
+) {
+    let scaled = font.as_scaled(scale);
+    let mut caret = x;
+    let mut prev = None;
+    for c in text.chars() {
+        let id = scaled.glyph_id(c);
+        if let Some(prev) = prev {
+            caret += scaled.kern(prev, id);
+        }
+        let glyph = id.with_scale_and_position(scale, point(caret, baseline));
+        caret += scaled.h_advance(id);
+        prev = Some(id);
+
+        let Some(outline) = font.outline_glyph(glyph) else {
+            continue;
+        };
+        let bounds = outline.px_bounds();
+        outline.draw(|gx, gy, coverage| {
+            let px = bounds.min.x as i32 + gx as i32;
+            let py = bounds.min.y as i32 + gy as i32;
+            if px < 0 || py < 0 || px >= img.width() as i32 || py >= img.height() as i32 {
+                return;
+            }
+            let pixel = img.get_pixel_mut(px as u32, py as u32);
+            let a = coverage.clamp(0.0, 1.0);
+            for (channel, ink) in pixel.0.iter_mut().zip(INK) {
+                *channel = (*channel as f32 * (1.0 - a) + ink as f32 * a).round() as u8;
+            }
+        });
+    }
+}
+
+/// Render the sample text in the font file `buf` and encode it as a WebP
+/// preview of `PREVIEW_SIZE` pixels square.
+pub fn webp_thumbnail(buf: &[u8]) -> AppResult<Vec<u8>> {
+    let font = load_font(buf)?;
+    let mut img = RgbaImage::from_pixel(PREVIEW_SIZE, PREVIEW_SIZE, BACKGROUND);
+    let avail = PREVIEW_SIZE as f32 - 2.0 * PADDING;
+
+    let mut top = PADDING;
+    for &(text, size) in SAMPLE_LINES {
+        let width = line_width(&font, PxScale::from(size), text);
+        let size = if width > avail {
+            size * avail / width
+        } else {
+            size
+        };
+        let scale = PxScale::from(size);
+        let scaled = font.as_scaled(scale);
+        let baseline = top + scaled.ascent();
+        if baseline - scaled.descent() > PREVIEW_SIZE as f32 - PADDING {
+            break;
+        }
+        draw_line(&mut img, &font, scale, text, PADDING, baseline);
+        top = baseline - scaled.descent() + size * LINE_GAP;
+    }
+
+    let mut webp_buf = std::io::Cursor::new(Vec::new());
+    image::DynamicImage::ImageRgba8(img)
+        .write_to(&mut webp_buf, image::ImageFormat::WebP)
+        .map_err(|e| AppError::Other(format!("WebP encoding failed: {e}")))?;
+    Ok(webp_buf.into_inner())
+}
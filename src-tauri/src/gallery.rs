@@ -0,0 +1,166 @@
+use serde::Serialize;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::cache::{DiskCache, Validator};
+use crate::errors::{AppError, AppResult};
+use crate::sftp_ops;
+use crate::ssh_manager::SshSession;
+use crate::tree_ops::FailedPath;
+
+// ─── Data Structures ───────────────────────────────────────────────────
+
+/// Outcome of a gallery export.
+#[derive(Debug, Clone, Serialize)]
+pub struct GalleryExport {
+    /// The written zip file or folder.
+    pub dest: String,
+    pub exported: usize,
+    pub failed: Vec<FailedPath>,
+}
+
+/// Where exported images go.
+enum Sink {
+    Zip(zip::ZipWriter<std::fs::File>),
+    Folder(PathBuf),
+}
+
+// ─── Export ────────────────────────────────────────────────────────────
+
+/// Shrink `data` so neither side exceeds `max_dim`, re-encoding in the same
+/// format. Returns `None` for formats the `image` crate cannot round-trip
+/// (HEIC, SVG…) or images already small enough — the original is kept.
+fn resize_image(name: &str, data: &[u8], max_dim: u32) -> Option<Vec<u8>> {
+    let format = image::ImageFormat::from_path(name).ok()?;
+    let img = image::load_from_memory_with_format(data, format).ok()?;
+    if img.width() <= max_dim && img.height() <= max_dim {
+        return None;
+    }
+    let resized = img.resize(max_dim, max_dim, image::imageops::FilterType::Lanczos3);
+    let mut out = std::io::Cursor::new(Vec::new());
+    resized.write_to(&mut out, format).ok()?;
+    Some(out.into_inner())
+}
+
+/// Reduce a server-supplied name to a single file name so it cannot climb
+/// out of the export folder or zip root.
+fn entry_name(name: &str) -> AppResult<&str> {
+    match Path::new(name).file_name().and_then(|n| n.to_str()) {
+        Some(file) if file == name && file != ".." => Ok(file),
+        _ => Err(AppError::Other(format!(
+            "Refusing unsafe file name \"{name}\""
+        ))),
+    }
+}
+
+impl Sink {
+    fn add(&mut self, name: &str, data: &[u8]) -> AppResult<()> {
+        let name = entry_name(name)?;
+        match self {
+            Sink::Zip(zip) => {
+                // Images are already compressed; storing avoids wasted CPU.
+                let options = zip::write::SimpleFileOptions::default()
+                    .compression_method(zip::CompressionMethod::Stored);
+                zip.start_file(name, options)
+                    .map_err(|e| AppError::Io(format!("Failed to add {name} to zip: {e}")))?;
+                zip.write_all(data)
+                    .map_err(|e| AppError::Io(format!("Failed to write {name} to zip: {e}")))
+            }
+            Sink::Folder(dir) => std::fs::write(dir.join(name), data)
+                .map_err(|e| AppError::Io(format!("Failed to write {name}: {e}"))),
+        }
+    }
+}
+
+/// Download every image in remote `dir` (reusing fresh copies from the
+/// image cache), optionally shrink them to `max_dimension`, and write them
+/// into a zip at `dest` or, if `as_zip` is false, into the folder `dest`.
+pub async fn export_gallery(
+    session: &Arc<SshSession>,
+    dir: &str,
+    dest: &Path,
+    as_zip: bool,
+    max_dimension: Option<u32>,
+    images_cache: &DiskCache,
+) -> AppResult<GalleryExport> {
+    let start = std::time::Instant::now();
+    let images: Vec<_> = sftp_ops::list_dir(session, dir)
+        .await?
+        .into_iter()
+        .filter(|e| e.is_image && !e.is_dir)
+        .collect();
+
+    let mut failed = Vec::new();
+    let mut cached = Vec::with_capacity(images.len());
+    for entry in &images {
+        let validator = Validator {
+            mtime: sftp_ops::entry_mtime(entry).map(u64::from),
+            size: Some(entry.size),
+        };
+        match sftp_ops::cache_image(session, &entry.path, images_cache, validator, None).await {
+            Ok(local) => {
+                let name = entry.path.rsplit('/').next().unwrap_or(&entry.name);
+                cached.push((name.to_string(), entry.path.clone(), PathBuf::from(local)));
+            }
+            Err(e) => failed.push(FailedPath {
+                path: entry.path.clone(),
+                error: e.to_string(),
+            }),
+        }
+    }
+
+    let dest_str = dest.to_string_lossy().to_string();
+    let dest = dest.to_path_buf();
+    let (exported, write_failures) = tokio::task::spawn_blocking(move || {
+        let mut sink = if as_zip {
+            if let Some(parent) = dest.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            Sink::Zip(zip::ZipWriter::new(std::fs::File::create(&dest)?))
+        } else {
+            std::fs::create_dir_all(&dest)?;
+            Sink::Folder(dest.clone())
+        };
+
+        let mut exported = 0;
+        let mut failed = Vec::new();
+        for (name, remote, local) in cached {
+            let result = std::fs::read(&local)
+                .map_err(AppError::from)
+                .and_then(|data| {
+                    let resized = max_dimension.and_then(|max| resize_image(&name, &data, max));
+                    sink.add(&name, resized.as_deref().unwrap_or(&data))
+                });
+            match result {
+                Ok(()) => exported += 1,
+                Err(e) => failed.push(FailedPath {
+                    path: remote,
+                    error: e.to_string(),
+                }),
+            }
+        }
+
+        if let Sink::Zip(zip) = sink {
+            zip.finish()
+                .map_err(|e| AppError::Io(format!("Failed to finalize zip: {e}")))?;
+        }
+        Ok::<_, AppError>((exported, failed))
+    })
+    .await
+    .map_err(|e| AppError::Other(format!("Gallery export task panicked: {e}")))??;
+    failed.extend(write_failures);
+
+    log::info!(
+        "[PERF] export_gallery \"{}\" — {:.2}ms | exported: {} | failed: {}",
+        dir,
+        start.elapsed().as_secs_f64() * 1000.0,
+        exported,
+        failed.len(),
+    );
+    Ok(GalleryExport {
+        dest: dest_str,
+        exported,
+        failed,
+    })
+}
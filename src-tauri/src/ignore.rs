@@ -0,0 +1,99 @@
+//! Ignore patterns: names like `.DS_Store`, `node_modules` or `*.tmp` that
+//! are left out of listings, searches and directory transfers.
+//!
+//! Patterns are shell-style globs matched against the entry's name. The
+//! settings hold a global list plus extra patterns per host; both apply.
+
+use regex::Regex;
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use crate::errors::{AppError, AppResult};
+use crate::search::glob_to_regex;
+use crate::sftp_ops::FileEntry;
+
+/// Compiled global patterns and per-host patterns (keyed by lower-cased host).
+static RULES: RwLock<(Vec<Regex>, Vec<(String, Vec<Regex>)>)> =
+    RwLock::new((Vec::new(), Vec::new()));
+
+// ─── Data Structures ───────────────────────────────────────────────────
+
+/// The patterns in effect for one host.
+#[derive(Debug, Clone, Default)]
+pub struct IgnoreRules {
+    patterns: Vec<Regex>,
+}
+
+impl IgnoreRules {
+    /// Returns true if the entry called `name` is ignored.
+    pub fn matches(&self, name: &str) -> bool {
+        self.patterns.iter().any(|p| p.is_match(name))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+
+    /// Drop ignored entries from a listing.
+    pub fn retain(&self, entries: &mut Vec<FileEntry>) {
+        if !self.is_empty() {
+            entries.retain(|e| !self.matches(&e.name));
+        }
+    }
+}
+
+// ─── Patterns ──────────────────────────────────────────────────────────
+
+/// Trim and check user patterns, dropping blank ones. Patterns match a
+/// single name, so they may not contain `/`.
+pub fn normalize(patterns: Vec<String>) -> AppResult<Vec<String>> {
+    let mut out = Vec::with_capacity(patterns.len());
+    for pattern in patterns {
+        let pattern = pattern.trim();
+        if pattern.is_empty() {
+            continue;
+        }
+        if pattern.contains('/') {
+            return Err(AppError::Settings(format!(
+                "Ignore pattern \"{pattern}\" must match a name, not a path"
+            )));
+        }
+        if !out.iter().any(|p| p == pattern) {
+            out.push(pattern.to_string());
+        }
+    }
+    Ok(out)
+}
+
+fn compile(patterns: &[String]) -> Vec<Regex> {
+    patterns
+        .iter()
+        .filter_map(|p| match Regex::new(&glob_to_regex(p)) {
+            Ok(re) => Some(re),
+            Err(e) => {
+                log::warn!("[SFTP] ignoring invalid ignore pattern \"{}\": {}", p, e);
+                None
+            }
+        })
+        .collect()
+}
+
+/// Install the global and per-host patterns from the settings.
+pub fn apply(global: &[String], per_host: &HashMap<String, Vec<String>>) {
+    let hosts = per_host
+        .iter()
+        .map(|(host, patterns)| (host.to_ascii_lowercase(), compile(patterns)))
+        .collect();
+    *RULES.write().unwrap() = (compile(global), hosts);
+}
+
+/// The patterns that apply on `host`.
+pub fn rules_for(host: &str) -> IgnoreRules {
+    let rules = RULES.read().unwrap();
+    let host = host.to_ascii_lowercase();
+    let mut patterns = rules.0.clone();
+    if let Some((_, extra)) = rules.1.iter().find(|(h, _)| *h == host) {
+        patterns.extend(extra.iter().cloned());
+    }
+    IgnoreRules { patterns }
+}
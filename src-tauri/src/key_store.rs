@@ -2,10 +2,13 @@ use base64::Engine;
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::sync::Mutex;
 
 use crate::errors::{AppError, AppResult};
+use crate::vault_backend::{UnopenedVault, VaultBackend, VaultBackendKind};
 
 // ─── Supported Key Types ───────────────────────────────────────────────
 
@@ -103,9 +106,9 @@ pub struct KeyInfo {
     pub created_at: String,
 }
 
-/// Internal key record stored on disk.
+/// Internal key record kept by the vault backend.
 #[derive(Debug, Clone, Serialize, Deserialize)]
-struct KeyRecord {
+pub(crate) struct KeyRecord {
     name: String,
     #[serde(default = "default_key_type")]
     key_type: KeyType,
@@ -116,21 +119,101 @@ struct KeyRecord {
 
 // ─── Key Store ─────────────────────────────────────────────────────────
 
-/// Manages SSH keys stored as an encrypted JSON file.
-/// Uses Stronghold-style storage via a simple JSON vault on disk.
+/// Lock state of the vault. While locked, key material cannot be retrieved
+/// until the frontend re-authenticates (biometric/passphrase) and unlocks.
+struct VaultState {
+    unlocked: bool,
+    last_activity: Instant,
+    auto_lock_after: Option<Duration>,
+}
+
+/// Manages stored SSH keys. Persistence is delegated to a `VaultBackend`
+/// (a JSON file by default, or a Stronghold snapshot).
 pub struct KeyStore {
-    vault_path: PathBuf,
+    backend: std::sync::Mutex<Arc<dyn VaultBackend>>,
     lock: Mutex<()>,
+    state: std::sync::Mutex<VaultState>,
 }
 
 impl KeyStore {
-    pub fn new(vault_path: PathBuf) -> Self {
+    pub fn new(backend: Arc<dyn VaultBackend>) -> Self {
         Self {
-            vault_path,
+            backend: std::sync::Mutex::new(backend),
             lock: Mutex::new(()),
+            state: std::sync::Mutex::new(VaultState {
+                unlocked: true,
+                last_activity: Instant::now(),
+                auto_lock_after: None,
+            }),
+        }
+    }
+
+    /// Lock the vault. Subsequent key retrievals fail until `unlock`.
+    pub fn lock_vault(&self) {
+        let mut state = self.state.lock().unwrap();
+        if state.unlocked {
+            log::info!("[KEYS] vault locked");
+        }
+        state.unlocked = false;
+        self.close_stronghold();
+    }
+
+    /// Drop an open Stronghold handle so its key material leaves memory;
+    /// unlocking opens the snapshot again with the password.
+    fn close_stronghold(&self) {
+        let mut backend = self.backend.lock().unwrap();
+        if backend.kind() == VaultBackendKind::Stronghold {
+            *backend = Arc::new(UnopenedVault);
         }
     }
 
+    /// Unlock the vault after the caller has re-authenticated the user.
+    pub fn unlock_vault(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.unlocked = true;
+        state.last_activity = Instant::now();
+        log::info!("[KEYS] vault unlocked");
+    }
+
+    /// Whether the vault is currently locked (applying any pending auto-lock).
+    pub fn is_locked(&self) -> bool {
+        self.lock_if_idle();
+        !self.state.lock().unwrap().unlocked
+    }
+
+    /// Configure the inactivity timeout. `None` disables auto-lock.
+    pub fn set_auto_lock(&self, after: Option<Duration>) {
+        let mut state = self.state.lock().unwrap();
+        state.auto_lock_after = after;
+        state.last_activity = Instant::now();
+    }
+
+    /// Lock the vault if it has been idle longer than the auto-lock timeout.
+    /// Returns true if this call locked it.
+    pub fn lock_if_idle(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+        match state.auto_lock_after {
+            Some(after) if state.unlocked && state.last_activity.elapsed() >= after => {
+                state.unlocked = false;
+                self.close_stronghold();
+                log::info!("[KEYS] vault auto-locked after {}s idle", after.as_secs());
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Fail with `VaultLocked` if locked; otherwise record activity.
+    fn ensure_unlocked(&self) -> AppResult<()> {
+        self.lock_if_idle();
+        let mut state = self.state.lock().unwrap();
+        if !state.unlocked {
+            return Err(AppError::VaultLocked);
+        }
+        state.last_activity = Instant::now();
+        Ok(())
+    }
+
     /// Compute a simple fingerprint from a PEM key string.
     fn compute_fingerprint(pem: &str) -> String {
         use std::collections::hash_map::DefaultHasher;
@@ -141,29 +224,52 @@ impl KeyStore {
         format!("FP:{:016x}", hash)
     }
 
-    /// Load the index of all stored keys from disk.
+    fn backend(&self) -> Arc<dyn VaultBackend> {
+        self.backend.lock().unwrap().clone()
+    }
+
+    /// Which backend currently holds the keys.
+    pub fn backend_kind(&self) -> VaultBackendKind {
+        self.backend().kind()
+    }
+
+    /// Load the index of all stored keys from the backend.
     fn load_index_sync(&self) -> AppResult<HashMap<String, KeyRecord>> {
-        if !self.vault_path.exists() {
-            return Ok(HashMap::new());
-        }
-        let data = std::fs::read_to_string(&self.vault_path)
-            .map_err(|e| AppError::KeyStore(format!("Failed to read vault: {e}")))?;
-        if data.trim().is_empty() {
-            return Ok(HashMap::new());
-        }
-        serde_json::from_str(&data)
-            .map_err(|e| AppError::KeyStore(format!("Failed to parse vault: {e}")))
+        self.backend().load()
     }
 
-    /// Save the index of all stored keys to disk.
+    /// Save the index of all stored keys to the backend.
     fn save_index_sync(&self, index: &HashMap<String, KeyRecord>) -> AppResult<()> {
-        let data = serde_json::to_string_pretty(index)
-            .map_err(|e| AppError::KeyStore(format!("Failed to serialize vault: {e}")))?;
-        if let Some(parent) = self.vault_path.parent() {
-            std::fs::create_dir_all(parent).ok();
-        }
-        std::fs::write(&self.vault_path, data)
-            .map_err(|e| AppError::KeyStore(format!("Failed to write vault: {e}")))
+        self.backend().save(index)
+    }
+
+    /// Replace the backend without copying keys, e.g. once a Stronghold
+    /// vault selected in settings has been opened with its password.
+    pub async fn set_backend(&self, backend: Arc<dyn VaultBackend>) {
+        let _guard = self.lock.lock().await;
+        *self.backend.lock().unwrap() = backend;
+    }
+
+    /// Copy every key from the current backend into `to`, then switch to it.
+    /// The old backend's files are left alone: the caller removes a JSON
+    /// vault only after the new backend has been recorded in settings, so a
+    /// failure in between never leaves settings pointing at a deleted vault.
+    /// Returns the number of keys migrated.
+    pub async fn migrate_to(&self, to: Arc<dyn VaultBackend>) -> AppResult<usize> {
+        let _guard = self.lock.lock().await;
+        let from = self.backend();
+        let mut index = from.load()?;
+        // Keys already in the target win over same-named ones being migrated.
+        index.extend(to.load()?);
+        to.save(&index)?;
+        log::info!(
+            "[KEYS] migrated {} key(s) from {:?} to {:?} vault",
+            index.len(),
+            from.kind(),
+            to.kind(),
+        );
+        *self.backend.lock().unwrap() = to;
+        Ok(index.len())
     }
 
     /// Store a new SSH key. The key type is auto-detected from PEM content.
@@ -226,6 +332,7 @@ impl KeyStore {
     /// Retrieve the raw PEM key for Rust-only use (SSH authentication).
     /// This MUST NOT be exposed to JS.
     pub async fn retrieve_key_pem(&self, name: &str) -> AppResult<String> {
+        self.ensure_unlocked()?;
         let _guard = self.lock.lock().await;
         let index = self.load_index_sync()?;
         let record = index
@@ -239,4 +346,126 @@ impl KeyStore {
         String::from_utf8(pem_bytes)
             .map_err(|e| AppError::KeyStore(format!("Invalid UTF-8 in key: {e}")))
     }
+
+    /// Export a stored key to `dest_path` in OpenSSH format, encrypted under
+    /// `passphrase`. `current_passphrase` unlocks keys stored encrypted.
+    pub async fn export_key(
+        &self,
+        name: &str,
+        dest_path: &Path,
+        passphrase: &str,
+        current_passphrase: Option<&str>,
+    ) -> AppResult<()> {
+        use russh::keys::ssh_key::rand_core::OsRng;
+        use russh::keys::ssh_key::LineEnding;
+
+        if passphrase.is_empty() {
+            return Err(AppError::KeyStore(
+                "An export passphrase is required".into(),
+            ));
+        }
+
+        let pem = self.retrieve_key_pem(name).await?;
+        let key = russh::keys::decode_secret_key(&pem, current_passphrase)
+            .map_err(|e| AppError::KeyStore(format!("Failed to decode key: {e}")))?;
+
+        let encrypted = key
+            .encrypt(&mut OsRng, passphrase)
+            .map_err(|e| AppError::KeyStore(format!("Failed to encrypt key: {e}")))?;
+        let openssh = encrypted
+            .to_openssh(LineEnding::LF)
+            .map_err(|e| AppError::KeyStore(format!("Failed to encode key: {e}")))?;
+
+        write_private_file(dest_path, openssh.as_bytes())
+            .map_err(|e| AppError::KeyStore(format!("Failed to write key file: {e}")))?;
+
+        log::info!(
+            "[KEYS] exported key \"{}\" to {}",
+            name,
+            dest_path.display()
+        );
+        Ok(())
+    }
+
+    /// Load a stored key into the running ssh-agent (`$SSH_AUTH_SOCK`).
+    /// `lifetime_secs` makes the agent forget it after that long; `confirm`
+    /// makes the agent ask before each use.
+    pub async fn add_to_agent(
+        &self,
+        name: &str,
+        passphrase: Option<&str>,
+        lifetime_secs: Option<u32>,
+        confirm: bool,
+    ) -> AppResult<()> {
+        let pem = self.retrieve_key_pem(name).await?;
+        let key = russh::keys::decode_secret_key(&pem, passphrase)
+            .map_err(|e| AppError::KeyStore(format!("Failed to decode key: {e}")))?;
+        add_identity_to_agent(&key, lifetime_secs, confirm).await?;
+        log::info!(
+            "[KEYS] added key \"{}\" to ssh-agent (lifetime={:?}, confirm={})",
+            name,
+            lifetime_secs,
+            confirm,
+        );
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+async fn add_identity_to_agent(
+    key: &russh::keys::PrivateKey,
+    lifetime_secs: Option<u32>,
+    confirm: bool,
+) -> AppResult<()> {
+    use russh::keys::agent::client::AgentClient;
+    use russh::keys::agent::Constraint;
+
+    let mut agent = AgentClient::connect_env()
+        .await
+        .map_err(|e| AppError::KeyStore(format!("Cannot reach ssh-agent: {e}")))?;
+    let mut constraints = Vec::new();
+    if let Some(seconds) = lifetime_secs {
+        constraints.push(Constraint::KeyLifetime { seconds });
+    }
+    if confirm {
+        constraints.push(Constraint::Confirm);
+    }
+    agent
+        .add_identity(key, &constraints)
+        .await
+        .map_err(|e| AppError::KeyStore(format!("ssh-agent refused the key: {e}")))
+}
+
+#[cfg(not(unix))]
+async fn add_identity_to_agent(
+    _key: &russh::keys::PrivateKey,
+    _lifetime_secs: Option<u32>,
+    _confirm: bool,
+) -> AppResult<()> {
+    Err(AppError::KeyStore(
+        "ssh-agent is only supported on Unix systems".into(),
+    ))
+}
+
+/// Write `data` to a file readable only by the owner (0600 on Unix).
+/// The open mode only applies to a new file, so an existing one is
+/// narrowed to 0600 before its old contents are dropped.
+pub(crate) fn write_private_file(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    use std::io::Write;
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(false);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(path)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+    }
+    file.set_len(0)?;
+    file.write_all(data)
 }
@@ -0,0 +1,310 @@
+//! Host-key store in OpenSSH `known_hosts` format.
+//!
+//! Host fields are matched the way the OpenSSH client matches them:
+//! comma-separated patterns with `*`/`?` wildcards and `!` negation, hashed
+//! entries (`|1|salt|hmac`), and port-qualified names (`[host]:2222`) for
+//! servers not on port 22. Newly learned hosts are written hashed.
+//!
+//! A profile can also pin the exact key it expects, which takes precedence
+//! over the store.
+
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::errors::{AppError, AppResult};
+
+type HmacSha1 = Hmac<sha1::Sha1>;
+
+// ─── Data Structures ───────────────────────────────────────────────────
+
+/// Result of checking a server's key against the store.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HostKeyStatus {
+    /// A matching entry has this exact key.
+    Known,
+    /// No entry matches the host.
+    Unknown,
+    /// The host is known with a different key of the same type.
+    Changed,
+    /// The key is marked `@revoked`.
+    Revoked,
+}
+
+/// One parsed `known_hosts` line.
+struct Entry {
+    marker: Option<String>,
+    hosts: String,
+    /// Key type and base64 blob, e.g. ("ssh-ed25519", "AAAA…").
+    key_type: String,
+    key_b64: String,
+}
+
+/// A profile's expected server key.
+#[derive(Debug, Clone, Default)]
+pub struct HostKeyPin {
+    /// `"ssh-ed25519 AAAA…"` (a trailing comment is ignored) or a
+    /// `SHA256:…` fingerprint.
+    pub key: Option<String>,
+    /// Refuse any other key without an override, and never trust an
+    /// unpinned, unknown host on first use.
+    pub strict: bool,
+}
+
+/// A server's host key (safe to send to JS).
+#[derive(Debug, Clone, Serialize)]
+pub struct HostKeyInfo {
+    /// OpenSSH form, `"<type> <base64>"`, suitable for pinning.
+    pub key: String,
+    /// e.g. "SHA256:…".
+    pub fingerprint: String,
+}
+
+/// Known hosts, backed by a file.
+pub struct KnownHosts {
+    path: PathBuf,
+    entries: Mutex<Vec<Entry>>,
+}
+
+// ─── Matching ──────────────────────────────────────────────────────────
+
+/// The name OpenSSH looks up: bare host on port 22, `[host]:port` otherwise.
+pub fn host_key_name(host: &str, port: u16) -> String {
+    if port == 22 {
+        host.to_ascii_lowercase()
+    } else {
+        format!("[{}]:{}", host.to_ascii_lowercase(), port)
+    }
+}
+
+/// Glob match supporting `*` and `?`, case-insensitive.
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let p: Vec<char> = pattern.to_ascii_lowercase().chars().collect();
+    let t: Vec<char> = text.to_ascii_lowercase().chars().collect();
+    let (mut pi, mut ti) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while ti < t.len() {
+        if pi < p.len() && (p[pi] == '?' || p[pi] == t[ti]) {
+            pi += 1;
+            ti += 1;
+        } else if pi < p.len() && p[pi] == '*' {
+            star = Some((pi, ti));
+            pi += 1;
+        } else if let Some((sp, st)) = star {
+            pi = sp + 1;
+            ti = st + 1;
+            star = Some((sp, st + 1));
+        } else {
+            return false;
+        }
+    }
+    p[pi..].iter().all(|&c| c == '*')
+}
+
+/// Whether a hashed `|1|salt|hash` field matches `name`.
+fn hashed_match(field: &str, name: &str) -> bool {
+    let b64 = base64::engine::general_purpose::STANDARD;
+    let mut parts = field.strip_prefix("|1|").unwrap_or_default().split('|');
+    let (Some(salt), Some(hash)) = (parts.next(), parts.next()) else {
+        return false;
+    };
+    let (Ok(salt), Ok(hash)) = (b64.decode(salt), b64.decode(hash)) else {
+        return false;
+    };
+    let Ok(mut mac) = HmacSha1::new_from_slice(&salt) else {
+        return false;
+    };
+    mac.update(name.as_bytes());
+    mac.verify_slice(&hash).is_ok()
+}
+
+/// Match a host field against `name`. A negated pattern that matches
+/// rejects the whole line, as in OpenSSH.
+fn hosts_match(hosts: &str, name: &str) -> bool {
+    if hosts.starts_with("|1|") {
+        return hashed_match(hosts, name);
+    }
+    let mut matched = false;
+    for pattern in hosts.split(',') {
+        match pattern.strip_prefix('!') {
+            Some(negated) if glob_match(negated, name) => return false,
+            Some(_) => {}
+            None => matched |= glob_match(pattern, name),
+        }
+    }
+    matched
+}
+
+/// A hashed host field for `name` with a fresh random salt.
+fn hash_host(name: &str) -> String {
+    use russh::keys::ssh_key::rand_core::{OsRng, RngCore};
+
+    let b64 = base64::engine::general_purpose::STANDARD;
+    let mut salt = [0u8; 20];
+    OsRng.fill_bytes(&mut salt);
+    let mut mac = HmacSha1::new_from_slice(&salt).expect("HMAC accepts any key length");
+    mac.update(name.as_bytes());
+    format!(
+        "|1|{}|{}",
+        b64.encode(salt),
+        b64.encode(mac.finalize().into_bytes())
+    )
+}
+
+// ─── Pinning ───────────────────────────────────────────────────────────
+
+impl HostKeyInfo {
+    pub fn of(key: &russh::keys::PublicKey) -> Option<Self> {
+        let (key_type, key_b64) = key_fields(key)?;
+        Some(Self {
+            key: format!("{key_type} {key_b64}"),
+            fingerprint: key.fingerprint(russh::keys::HashAlg::Sha256).to_string(),
+        })
+    }
+}
+
+impl HostKeyPin {
+    /// The pinned key, if one is set.
+    pub fn pinned(&self) -> Option<&str> {
+        self.key.as_deref().map(str::trim).filter(|k| !k.is_empty())
+    }
+
+    /// Whether `presented` is the pinned key; `None` when nothing is pinned.
+    pub fn matches(&self, presented: &HostKeyInfo) -> Option<bool> {
+        let pinned = self.pinned()?;
+        if pinned.starts_with("SHA256:") {
+            return Some(pinned == presented.fingerprint);
+        }
+        let mut fields = pinned.split_whitespace();
+        Some(fields.next().zip(fields.next()) == presented.key.split_once(' '))
+    }
+}
+
+// ─── Store ─────────────────────────────────────────────────────────────
+
+fn parse_line(line: &str) -> Option<Entry> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+    let mut fields = line.split_whitespace();
+    let mut first = fields.next()?;
+    let marker = if first.starts_with('@') {
+        let marker = first.to_string();
+        first = fields.next()?;
+        Some(marker)
+    } else {
+        None
+    };
+    Some(Entry {
+        marker,
+        hosts: first.to_string(),
+        key_type: fields.next()?.to_string(),
+        key_b64: fields.next()?.to_string(),
+    })
+}
+
+/// Key type and base64 blob of a server key.
+fn key_fields(key: &russh::keys::PublicKey) -> Option<(String, String)> {
+    let openssh = key.to_openssh().ok()?;
+    let mut fields = openssh.split_whitespace();
+    Some((fields.next()?.to_string(), fields.next()?.to_string()))
+}
+
+impl KnownHosts {
+    /// Load the store from `path`; a missing file is an empty store.
+    pub fn new(path: PathBuf) -> Self {
+        let entries = std::fs::read_to_string(&path)
+            .map(|text| text.lines().filter_map(parse_line).collect())
+            .unwrap_or_default();
+        Self {
+            path,
+            entries: Mutex::new(entries),
+        }
+    }
+
+    /// Check `key` for `host`:`port`.
+    pub fn check(&self, host: &str, port: u16, key: &russh::keys::PublicKey) -> HostKeyStatus {
+        let Some((key_type, key_b64)) = key_fields(key) else {
+            return HostKeyStatus::Unknown;
+        };
+        let name = host_key_name(host, port);
+        let entries = self.entries.lock().unwrap();
+
+        let same_key = |e: &Entry| e.key_type == key_type && e.key_b64 == key_b64;
+        if entries
+            .iter()
+            .any(|e| e.marker.as_deref() == Some("@revoked") && same_key(e))
+        {
+            return HostKeyStatus::Revoked;
+        }
+
+        // As in OpenSSH, only an entry of the same key type can conflict;
+        // a host known under other key types is simply unknown for this one.
+        let mut host_seen = false;
+        for entry in entries.iter().filter(|e| e.marker.is_none()) {
+            if entry.key_type == key_type && hosts_match(&entry.hosts, &name) {
+                if entry.key_b64 == key_b64 {
+                    return HostKeyStatus::Known;
+                }
+                host_seen = true;
+            }
+        }
+        if host_seen {
+            HostKeyStatus::Changed
+        } else {
+            HostKeyStatus::Unknown
+        }
+    }
+
+    /// Record `key` for `host`:`port` as a hashed entry.
+    pub fn learn(&self, host: &str, port: u16, key: &russh::keys::PublicKey) -> AppResult<()> {
+        let (key_type, key_b64) =
+            key_fields(key).ok_or_else(|| AppError::Ssh("Cannot encode server host key".into()))?;
+        let entry = Entry {
+            marker: None,
+            hosts: hash_host(&host_key_name(host, port)),
+            key_type,
+            key_b64,
+        };
+        self.append(vec![entry])?;
+        log::info!("[SSH] learned host key for {}:{}", host, port);
+        Ok(())
+    }
+
+    /// Import entries from OpenSSH `known_hosts` text, keeping their host
+    /// fields (plain, wildcard or hashed) as they are. Returns the count.
+    pub fn import(&self, text: &str) -> AppResult<usize> {
+        let entries: Vec<Entry> = text.lines().filter_map(parse_line).collect();
+        let count = entries.len();
+        self.append(entries)?;
+        log::info!("[SSH] imported {} known-hosts entries", count);
+        Ok(count)
+    }
+
+    fn append(&self, new: Vec<Entry>) -> AppResult<()> {
+        use std::io::Write;
+
+        let mut text = String::new();
+        for e in &new {
+            if let Some(marker) = &e.marker {
+                text.push_str(marker);
+                text.push(' ');
+            }
+            text.push_str(&format!("{} {} {}\n", e.hosts, e.key_type, e.key_b64));
+        }
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent).ok();
+        }
+        std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .and_then(|mut f| f.write_all(text.as_bytes()))
+            .map_err(|e| AppError::Io(format!("Failed to write known_hosts: {e}")))?;
+        self.entries.lock().unwrap().extend(new);
+        Ok(())
+    }
+}
@@ -1,14 +1,69 @@
+mod acl;
+mod audit;
+mod cache;
 mod commands;
+mod credentials;
+mod crontab;
+mod dir_diff;
+mod discovery;
+mod disk_usage;
+mod doc_preview;
+mod download_queue;
 mod errors;
+mod events;
+mod file_types;
+mod font_thumbnail;
+mod gallery;
+mod ignore;
 mod key_store;
+mod known_hosts;
+mod markdown;
+mod memory;
+mod network;
+mod offline;
+mod op_tracker;
+mod open_with;
+mod post_actions;
+mod profiles;
+mod raw_sftp;
+mod receipts;
+mod scp;
+mod search;
+mod search_hits;
+mod services;
+mod settings;
 mod sftp_ops;
+mod sftp_trace;
+mod share;
+mod shell;
+mod snapshots;
+mod split;
 mod ssh_manager;
+mod structured;
+mod tail;
+mod text_stats;
+mod thumbnail;
+mod timeouts;
+mod transfer;
+mod tree_ops;
+mod type_stats;
+mod vault_backend;
+mod windows;
 
 use std::sync::Arc;
-use tauri::Manager;
+use std::time::Duration;
+use tauri::{Emitter, Manager};
 
+use download_queue::DownloadQueue;
 use key_store::KeyStore;
+use known_hosts::KnownHosts;
+use offline::OfflineStore;
+use profiles::ProfileStore;
+use receipts::ReceiptStore;
+use settings::SettingsStore;
 use ssh_manager::SshSessionManager;
+use vault_backend::{JsonVault, UnopenedVault, VaultBackend, VaultBackendKind, VaultPaths};
+use windows::WindowRegistry;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
@@ -30,12 +85,74 @@ pub fn run() {
                 .expect("Failed to get app data dir");
             std::fs::create_dir_all(&app_dir).ok();
 
-            let vault_path = app_dir.join("ssh_keys.json");
-            let key_store = Arc::new(KeyStore::new(vault_path));
-            let session_mgr = Arc::new(SshSessionManager::new(key_store.clone()));
+            let settings = Arc::new(SettingsStore::new(app_dir.join("settings.json")));
+            let initial = tauri::async_runtime::block_on(settings.get()).unwrap_or_default();
+
+            // A Stronghold vault stays unusable until the user enters its
+            // password via `vault_open_stronghold`.
+            let vault_paths = Arc::new(VaultPaths::new(&app_dir));
+            let backend: Arc<dyn VaultBackend> = match initial.vault_backend.unwrap_or_default() {
+                VaultBackendKind::Json => Arc::new(JsonVault::new(vault_paths.json.clone())),
+                VaultBackendKind::Stronghold => Arc::new(UnopenedVault),
+            };
+            let key_store = Arc::new(KeyStore::new(backend));
+            let known_hosts = Arc::new(KnownHosts::new(app_dir.join("known_hosts")));
+            let session_mgr = Arc::new(SshSessionManager::new(key_store.clone(), known_hosts));
+            let handle = app.handle().clone();
+            session_mgr.set_reconnect_listener(move |session_id| {
+                handle.emit("session://reconnected", session_id).ok();
+            });
+
+            let download_queue = Arc::new(DownloadQueue::new(app_dir.join("download_queue.json")));
+            let receipts = Arc::new(ReceiptStore::new(
+                app_dir.join("upload_receipts.jsonl"),
+                app_dir.join("receipt_key"),
+            )?);
+
+            key_store.set_auto_lock(initial.vault_auto_lock_secs.map(Duration::from_secs));
+            memory::apply(initial.low_memory_mode);
+            timeouts::apply(initial.metadata_timeout_secs, initial.transfer_timeout_secs);
+            network::apply(&initial.network_policy);
+            file_types::apply(&initial.file_type_overrides);
+            ignore::apply(&initial.ignore_patterns, &initial.host_ignore_patterns);
+
+            // Periodically apply the vault auto-lock so the UI is notified
+            // even when no key is being accessed.
+            let handle = app.handle().clone();
+            let ks = key_store.clone();
+            tauri::async_runtime::spawn(async move {
+                let mut ticker = tokio::time::interval(Duration::from_secs(15));
+                loop {
+                    ticker.tick().await;
+                    if ks.lock_if_idle() {
+                        handle.emit("vault-locked", ()).ok();
+                    }
+                }
+            });
+
+            // Drop sessions whose connection died so their channels are
+            // freed, and tell the UI which ones went away.
+            let handle = app.handle().clone();
+            let mgr = session_mgr.clone();
+            tauri::async_runtime::spawn(async move {
+                let mut ticker = tokio::time::interval(Duration::from_secs(60));
+                loop {
+                    ticker.tick().await;
+                    for stale in mgr.sweep_stale(Duration::from_secs(120)).await {
+                        handle.emit("session-expired", stale).ok();
+                    }
+                }
+            });
 
             app.manage(key_store);
+            app.manage(vault_paths);
             app.manage(session_mgr);
+            app.manage(settings);
+            app.manage(Arc::new(ProfileStore::new(app_dir.join("profiles.json"))));
+            app.manage(receipts);
+            app.manage(download_queue);
+            app.manage(Arc::new(OfflineStore::new(app_dir.join("offline"))));
+            app.manage(Arc::new(WindowRegistry::default()));
 
             #[cfg(mobile)]
             app.handle().plugin(tauri_plugin_biometric::init())?;
@@ -46,26 +163,150 @@ pub fn run() {
 
             Ok(())
         })
+        .on_window_event(|window, event| {
+            if let tauri::WindowEvent::Destroyed = event {
+                let app = window.app_handle();
+                app.state::<Arc<WindowRegistry>>()
+                    .remove(app, window.label());
+            }
+        })
         .invoke_handler(tauri::generate_handler![
+            commands::open_window,
+            commands::list_windows,
+            commands::current_window_view,
+            commands::set_window_view,
             commands::store_key,
             commands::list_keys,
             commands::delete_key,
             commands::get_key,
+            commands::export_key,
+            commands::agent_add_key,
+            commands::known_hosts_import,
+            commands::vault_lock,
+            commands::vault_unlock,
+            commands::vault_set_passphrase,
+            commands::vault_is_locked,
+            commands::vault_set_auto_lock,
+            commands::vault_backend,
+            commands::vault_open_stronghold,
+            commands::vault_set_backend,
             commands::list_supported_key_types,
+            commands::get_settings,
+            commands::set_download_dir,
+            commands::set_default_modes,
+            commands::set_upload_concurrency,
+            commands::set_host_limits,
+            commands::set_timeouts,
+            commands::set_keepalive,
+            commands::set_file_type_overrides,
+            commands::set_ignore_patterns,
+            commands::set_network_type,
+            commands::network_status,
+            commands::set_network_policy,
+            commands::set_low_memory_mode,
+            commands::set_upload_receipts,
+            commands::list_upload_receipts,
+            commands::verify_upload_receipt,
+            commands::memory_status,
+            commands::set_max_in_memory_bytes,
+            commands::set_confirm_dir_download_bytes,
+            commands::set_image_cache_bypass_bytes,
+            commands::set_transfer_retry,
+            commands::get_default_download_dir,
             commands::ssh_connect,
+            commands::ssh_connect_interactive,
+            commands::ssh_auth_respond,
+            commands::abort_connect,
+            commands::profile_list,
+            commands::profile_save,
+            commands::profile_delete,
+            commands::profile_connect,
             commands::ssh_test_connection,
+            commands::profile_share,
+            commands::ssh_startup_output,
+            commands::ssh_local_dir,
+            commands::ssh_set_local_dir,
+            commands::ssh_exec,
+            commands::ssh_open_shell,
+            commands::ssh_open_shell_at,
+            commands::ssh_shell_write,
+            commands::ssh_shell_resize,
+            commands::ssh_shell_close,
+            commands::ssh_list_shells,
+            commands::sftp_tail_start,
+            commands::sftp_tail_stop,
+            commands::ssh_remote_env,
+            commands::ssh_disk_usage,
+            commands::ssh_set_tracing,
+            commands::session_pending_ops,
+            commands::session_set_exclusive,
+            commands::session_cancel_view,
             commands::ssh_disconnect,
+            commands::ssh_session_events,
+            commands::ssh_session_motd,
+            commands::ssh_session_host_key,
+            commands::discover_lan_hosts,
             commands::ssh_list_sessions,
+            commands::ssh_temp_dir,
             commands::sftp_list_dir,
+            commands::sftp_list_dir_diff,
+            commands::sftp_permission_audit,
+            commands::sftp_type_stats,
+            commands::sftp_dir_size,
+            commands::sftp_statvfs,
+            commands::sftp_checksum,
+            commands::sftp_search,
+            commands::sftp_text_stats,
+            commands::sftp_suggest_name,
+            commands::sftp_get_acl,
+            commands::sftp_set_acl,
+            commands::ssh_crontab_get,
+            commands::ssh_crontab_set,
+            commands::ssh_services_list,
+            commands::ssh_service_action,
+            commands::sftp_set_permissions,
+            commands::sftp_set_owner,
+            commands::sftp_chmod_recursive,
+            commands::sftp_chown_recursive,
             commands::sftp_read_file_preview,
+            commands::sftp_preview_document,
+            commands::sftp_preview_markdown,
+            commands::sftp_preview_structured,
+            commands::sftp_split_file,
+            commands::sftp_join_files,
             commands::sftp_download_file,
             commands::sftp_save_file,
             commands::sftp_create_dir,
+            commands::sftp_create_symlink,
+            commands::sftp_read_link,
+            commands::sftp_create_file,
+            commands::sftp_duplicate,
+            commands::sftp_rename,
             commands::sftp_upload_file,
+            commands::sftp_upload_files,
+            commands::sftp_download_dir,
+            commands::sftp_cancel_transfer,
+            commands::download_queue_list,
+            commands::download_queue_resume,
+            commands::download_queue_discard,
+            commands::sftp_upload_dir,
+            commands::verify_backup,
+            commands::backup_directory,
             commands::sftp_get_thumbnail,
             commands::sftp_cache_image,
+            commands::pin_offline,
+            commands::unpin_offline,
+            commands::pins_list,
+            commands::pin_preview,
+            commands::pins_sync,
+            commands::offline_list_dir,
+            commands::offline_open_file,
+            commands::export_gallery,
             commands::open_file_externally,
+            commands::list_open_with_apps,
+            commands::open_file_with,
             commands::sftp_delete_file,
+            commands::sftp_delete_recursive,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,270 @@
+//! Markdown preview with relative image links resolved through the image
+//! cache, so remote documentation renders with its pictures.
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::cache::{DiskCache, Validator};
+use crate::errors::AppResult;
+use crate::file_types;
+use crate::sftp_ops::{self, FilePreview};
+use crate::ssh_manager::SshSession;
+
+/// Cap on distinct images fetched for one document.
+const MAX_ASSETS: usize = 64;
+
+// ─── Data Structures ───────────────────────────────────────────────────
+
+/// One image link that was rewritten to a cached local copy.
+#[derive(Debug, Clone, Serialize)]
+pub struct ResolvedAsset {
+    /// The link as written in the document.
+    pub link: String,
+    pub remote_path: String,
+    pub local_path: String,
+}
+
+/// Markdown preview with image links pointing at the local cache.
+#[derive(Debug, Clone, Serialize)]
+pub struct MarkdownPreview {
+    #[serde(flatten)]
+    pub preview: FilePreview,
+    pub assets: Vec<ResolvedAsset>,
+    /// Links that looked local but could not be fetched.
+    pub unresolved: Vec<String>,
+}
+
+// ─── Link Handling ─────────────────────────────────────────────────────
+
+/// Byte ranges of image link targets: `![alt](target "title")` and
+/// `<img src="target">`.
+fn image_links(md: &str) -> Vec<(usize, usize)> {
+    let mut links = Vec::new();
+
+    let mut from = 0;
+    while let Some(pos) = md[from..].find("![") {
+        let at = from + pos;
+        from = at + 2;
+        let Some(close) = md[at..].find("](") else {
+            break;
+        };
+        let start = at + close + 2;
+        let Some(len) = md[start..].find(')') else {
+            break;
+        };
+        // Drop an optional title and the `<...>` form.
+        let target = &md[start..start + len];
+        let trimmed = target.trim_start();
+        let lead = target.len() - trimmed.len();
+        let url_len = if trimmed.starts_with('<') {
+            trimmed.find('>').map_or(trimmed.len(), |i| i + 1)
+        } else {
+            trimmed.find(char::is_whitespace).unwrap_or(trimmed.len())
+        };
+        let (s, e) = (start + lead, start + lead + url_len);
+        let (s, e) = if md[s..e].starts_with('<') && md[s..e].ends_with('>') {
+            (s + 1, e - 1)
+        } else {
+            (s, e)
+        };
+        if s < e {
+            links.push((s, e));
+        }
+        from = start + len;
+    }
+
+    let lower = md.to_ascii_lowercase();
+    let mut from = 0;
+    while let Some(pos) = lower[from..].find("<img") {
+        let at = from + pos;
+        from = at + 4;
+        let Some(tag_len) = lower[at..].find('>') else {
+            break;
+        };
+        let tag = &lower[at..at + tag_len];
+        let Some(src) = tag.find("src=") else {
+            continue;
+        };
+        let value = at + src + 4;
+        let (s, e) = match md.as_bytes().get(value) {
+            Some(&q @ (b'"' | b'\'')) => {
+                let s = value + 1;
+                match md[s..].find(q as char) {
+                    Some(len) => (s, s + len),
+                    None => continue,
+                }
+            }
+            _ => {
+                let len = md[value..at + tag_len]
+                    .find(|c: char| c.is_whitespace() || c == '/')
+                    .unwrap_or(at + tag_len - value);
+                (value, value + len)
+            }
+        };
+        if s < e {
+            links.push((s, e));
+        }
+    }
+
+    links.sort_unstable();
+    links
+}
+
+/// Resolve a relative link against the directory of the Markdown file.
+/// Returns `None` for URLs, anchors and data URIs, which are left alone.
+fn resolve_link(md_path: &str, link: &str) -> Option<String> {
+    if link.starts_with('#') || link.starts_with("//") {
+        return None;
+    }
+    let scheme_end = link.find(|c: char| c == ':' || c == '/');
+    if let Some(i) = scheme_end {
+        if link.as_bytes()[i] == b':' {
+            return None;
+        }
+    }
+    let link = link.split(['?', '#']).next().unwrap_or(link);
+    let link = percent_decode(link);
+
+    let base = if link.starts_with('/') {
+        ""
+    } else {
+        md_path.rsplit_once('/').map_or("", |(dir, _)| dir)
+    };
+    let mut parts: Vec<&str> = Vec::new();
+    for part in base.split('/').chain(link.split('/')) {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            p => parts.push(p),
+        }
+    }
+    Some(format!("/{}", parts.join("/")))
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            if let Some(b) = s
+                .get(i + 1..i + 3)
+                .and_then(|h| u8::from_str_radix(h, 16).ok())
+            {
+                out.push(b);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).to_string()
+}
+
+/// URL under which the webview's asset protocol serves `local_path`
+/// (the same form as `convertFileSrc` on the JS side).
+fn asset_url(local_path: &str) -> String {
+    let mut encoded = String::with_capacity(local_path.len());
+    for b in local_path.bytes() {
+        if b.is_ascii_alphanumeric() || b"-_.!~*'()".contains(&b) {
+            encoded.push(b as char);
+        } else {
+            encoded.push_str(&format!("%{b:02X}"));
+        }
+    }
+    if cfg!(any(windows, target_os = "android")) {
+        format!("http://asset.localhost/{encoded}")
+    } else {
+        format!("asset://localhost/{encoded}")
+    }
+}
+
+// ─── Preview ───────────────────────────────────────────────────────────
+
+/// Read a Markdown file and rewrite its relative image links to locally
+/// cached copies fetched through the image cache.
+pub async fn preview_markdown(
+    session: &Arc<SshSession>,
+    path: &str,
+    max_bytes: usize,
+    images_cache: &DiskCache,
+) -> AppResult<MarkdownPreview> {
+    let start = std::time::Instant::now();
+    let mut preview = sftp_ops::read_file_preview(session, path, max_bytes).await?;
+    if !preview.is_text {
+        return Ok(MarkdownPreview {
+            preview,
+            assets: Vec::new(),
+            unresolved: Vec::new(),
+        });
+    }
+
+    let mut assets = Vec::new();
+    let mut unresolved = Vec::new();
+    // remote path → asset URL, so repeated images are fetched once.
+    let mut fetched: HashMap<String, Option<String>> = HashMap::new();
+    let mut rewritten = String::with_capacity(preview.content.len());
+    let mut last = 0;
+
+    for (s, e) in image_links(&preview.content) {
+        if s < last {
+            continue;
+        }
+        let link = &preview.content[s..e];
+        let Some(remote) = resolve_link(path, link) else {
+            continue;
+        };
+        if !file_types::is_image(&remote) {
+            continue;
+        }
+        if !fetched.contains_key(&remote) {
+            let url = if fetched.len() >= MAX_ASSETS {
+                None
+            } else {
+                let validator = Validator::default();
+                match sftp_ops::cache_image(session, &remote, images_cache, validator, None).await {
+                    Ok(local) => {
+                        assets.push(ResolvedAsset {
+                            link: link.to_string(),
+                            remote_path: remote.clone(),
+                            local_path: local.clone(),
+                        });
+                        Some(asset_url(&local))
+                    }
+                    Err(e) => {
+                        log::warn!("[SFTP] markdown asset \"{}\" unavailable: {}", remote, e);
+                        None
+                    }
+                }
+            };
+            if url.is_none() {
+                unresolved.push(link.to_string());
+            }
+            fetched.insert(remote.clone(), url);
+        }
+        if let Some(Some(url)) = fetched.get(&remote) {
+            rewritten.push_str(&preview.content[last..s]);
+            rewritten.push_str(url);
+            last = e;
+        }
+    }
+    rewritten.push_str(&preview.content[last..]);
+    preview.content = rewritten;
+
+    log::info!(
+        "[PERF] preview_markdown \"{}\" — {:.2}ms | assets: {} | unresolved: {}",
+        path,
+        start.elapsed().as_secs_f64() * 1000.0,
+        assets.len(),
+        unresolved.len(),
+    );
+    Ok(MarkdownPreview {
+        preview,
+        assets,
+        unresolved,
+    })
+}
@@ -0,0 +1,69 @@
+//! Low-memory mode for constrained devices.
+//!
+//! A process-wide flag read by the caches, the transfer code and the
+//! thumbnail pipeline. It is set from the `low_memory_mode` setting, or —
+//! when that is unset — from the detected device RAM on mobile.
+
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// On mobile, auto mode turns low-memory mode on below this much RAM.
+const AUTO_THRESHOLD_BYTES: u64 = 3 * 1024 * 1024 * 1024;
+
+/// Most files in flight at once while in low-memory mode.
+pub const LOW_MEMORY_MAX_CONCURRENCY: usize = 2;
+
+static LOW_MEMORY: AtomicBool = AtomicBool::new(false);
+
+/// Current low-memory state (safe to send to JS).
+#[derive(Debug, Clone, Serialize)]
+pub struct MemoryStatus {
+    pub low_memory: bool,
+    /// `true` when the state came from RAM detection rather than the setting.
+    pub auto: bool,
+    pub total_ram_bytes: Option<u64>,
+}
+
+/// Whether low-memory mode is on.
+pub fn is_low_memory() -> bool {
+    LOW_MEMORY.load(Ordering::Relaxed)
+}
+
+/// Pick `low` in low-memory mode, `normal` otherwise.
+pub fn pick<T>(normal: T, low: T) -> T {
+    if is_low_memory() {
+        low
+    } else {
+        normal
+    }
+}
+
+/// Total physical memory, from `/proc/meminfo` where available.
+pub fn total_ram_bytes() -> Option<u64> {
+    let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+    let line = meminfo.lines().find(|l| l.starts_with("MemTotal:"))?;
+    let kb: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kb * 1024)
+}
+
+/// Apply the `low_memory_mode` setting; `None` means detect on mobile and
+/// stay off on desktop.
+pub fn apply(setting: Option<bool>) -> MemoryStatus {
+    let total_ram_bytes = total_ram_bytes();
+    let low_memory = setting.unwrap_or_else(|| {
+        cfg!(mobile) && total_ram_bytes.is_some_and(|ram| ram < AUTO_THRESHOLD_BYTES)
+    });
+    if LOW_MEMORY.swap(low_memory, Ordering::Relaxed) != low_memory {
+        log::info!(
+            "[CACHE] low-memory mode {} (setting={:?}, ram={:?})",
+            if low_memory { "on" } else { "off" },
+            setting,
+            total_ram_bytes,
+        );
+    }
+    MemoryStatus {
+        low_memory,
+        auto: setting.is_none(),
+        total_ram_bytes,
+    }
+}
@@ -0,0 +1,155 @@
+//! Network-type-aware transfer policies.
+//!
+//! The webview reports whether the device is on Wi-Fi, ethernet or metered
+//! cellular (from the Network Information API); the transfer code asks here
+//! before each file. On cellular, large files wait until the device is back
+//! on an unmetered network, image prefetch is refused, and downloads over
+//! the confirmation threshold need the caller to confirm.
+
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicU8, Ordering};
+use tokio::sync::Notify;
+
+use crate::errors::{AppError, AppResult};
+use crate::settings::NetworkPolicy;
+
+pub const DEFAULT_LARGE_FILE_BYTES: u64 = 100 * 1024 * 1024;
+pub const DEFAULT_CONFIRM_DOWNLOAD_BYTES: u64 = 2 * 1024 * 1024 * 1024;
+
+static NETWORK: AtomicU8 = AtomicU8::new(NetworkType::Unknown as u8);
+static CHANGED: Notify = Notify::const_new();
+
+static PAUSE_LARGE_ON_CELLULAR: AtomicBool = AtomicBool::new(true);
+static LARGE_FILE_BYTES: AtomicU64 = AtomicU64::new(DEFAULT_LARGE_FILE_BYTES);
+static PREFETCH_ON_CELLULAR: AtomicBool = AtomicBool::new(false);
+static CONFIRM_DOWNLOAD_BYTES: AtomicU64 = AtomicU64::new(DEFAULT_CONFIRM_DOWNLOAD_BYTES);
+
+// ─── Data Structures ───────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[repr(u8)]
+pub enum NetworkType {
+    Unknown,
+    Wifi,
+    Ethernet,
+    Cellular,
+}
+
+impl NetworkType {
+    fn from_u8(value: u8) -> Self {
+        match value {
+            1 => Self::Wifi,
+            2 => Self::Ethernet,
+            3 => Self::Cellular,
+            _ => Self::Unknown,
+        }
+    }
+}
+
+/// Current network and the policy in effect (safe to send to JS).
+#[derive(Debug, Clone, Serialize)]
+pub struct NetworkStatus {
+    pub network: NetworkType,
+    pub metered: bool,
+    pub pause_large_on_cellular: bool,
+    pub large_file_bytes: u64,
+    pub prefetch_on_cellular: bool,
+    pub confirm_download_bytes: u64,
+}
+
+// ─── State ─────────────────────────────────────────────────────────────
+
+pub fn current() -> NetworkType {
+    NetworkType::from_u8(NETWORK.load(Ordering::Relaxed))
+}
+
+/// Whether the device is on a metered (cellular) connection.
+pub fn is_metered() -> bool {
+    current() == NetworkType::Cellular
+}
+
+/// Record the network type reported by the webview, waking paused transfers.
+pub fn set_current(network: NetworkType) {
+    if NETWORK.swap(network as u8, Ordering::Relaxed) != network as u8 {
+        log::info!("[TRANSFER] network changed to {:?}", network);
+        CHANGED.notify_waiters();
+    }
+}
+
+/// Apply the `network_policy` setting; unset fields restore defaults.
+pub fn apply(policy: &NetworkPolicy) {
+    PAUSE_LARGE_ON_CELLULAR.store(
+        policy.pause_large_on_cellular.unwrap_or(true),
+        Ordering::Relaxed,
+    );
+    LARGE_FILE_BYTES.store(
+        policy.large_file_bytes.unwrap_or(DEFAULT_LARGE_FILE_BYTES),
+        Ordering::Relaxed,
+    );
+    PREFETCH_ON_CELLULAR.store(
+        policy.prefetch_on_cellular.unwrap_or(false),
+        Ordering::Relaxed,
+    );
+    CONFIRM_DOWNLOAD_BYTES.store(
+        policy
+            .confirm_download_bytes
+            .unwrap_or(DEFAULT_CONFIRM_DOWNLOAD_BYTES),
+        Ordering::Relaxed,
+    );
+    // A relaxed policy may release paused transfers.
+    CHANGED.notify_waiters();
+}
+
+pub fn status() -> NetworkStatus {
+    NetworkStatus {
+        network: current(),
+        metered: is_metered(),
+        pause_large_on_cellular: PAUSE_LARGE_ON_CELLULAR.load(Ordering::Relaxed),
+        large_file_bytes: LARGE_FILE_BYTES.load(Ordering::Relaxed),
+        prefetch_on_cellular: PREFETCH_ON_CELLULAR.load(Ordering::Relaxed),
+        confirm_download_bytes: CONFIRM_DOWNLOAD_BYTES.load(Ordering::Relaxed),
+    }
+}
+
+// ─── Policies ──────────────────────────────────────────────────────────
+
+/// Whether a file of `size` bytes must wait for an unmetered network.
+fn must_pause(size: u64) -> bool {
+    is_metered()
+        && PAUSE_LARGE_ON_CELLULAR.load(Ordering::Relaxed)
+        && size >= LARGE_FILE_BYTES.load(Ordering::Relaxed)
+}
+
+/// Wait until a file of `size` bytes may be transferred. `on_pause` is
+/// called once if the transfer has to wait.
+pub async fn wait_for_transfer(size: u64, on_pause: impl FnOnce()) {
+    if !must_pause(size) {
+        return;
+    }
+    on_pause();
+    log::info!("[TRANSFER] pausing {}-byte file until off cellular", size);
+    loop {
+        let changed = CHANGED.notified();
+        if !must_pause(size) {
+            break;
+        }
+        changed.await;
+    }
+    log::info!("[TRANSFER] resuming {}-byte file", size);
+}
+
+/// Whether background image prefetch may run on the current network.
+pub fn prefetch_allowed() -> bool {
+    !is_metered() || PREFETCH_ON_CELLULAR.load(Ordering::Relaxed)
+}
+
+/// Fail with `ConfirmDownload` for a download of `size` bytes on cellular
+/// above the confirmation threshold, unless the user has `confirmed`.
+pub fn check_download(size: u64, confirmed: bool) -> AppResult<()> {
+    let limit = CONFIRM_DOWNLOAD_BYTES.load(Ordering::Relaxed);
+    if confirmed || !is_metered() || size < limit {
+        return Ok(());
+    }
+    Err(AppError::ConfirmDownload { size, limit })
+}
@@ -0,0 +1,288 @@
+//! Remote files pinned for offline use.
+//!
+//! A pinned file is downloaded into `offline/` under the app data dir,
+//! which is never evicted (unlike the caches), and recorded in an index so
+//! it can still be listed and previewed with no session. `pins_sync`
+//! re-downloads pins whose remote size or mtime has changed.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use crate::errors::{AppError, AppResult};
+use crate::sftp_ops::{self, FilePreview};
+use crate::ssh_manager::SshSession;
+use crate::tree_ops::FailedPath;
+
+// ─── Data Structures ───────────────────────────────────────────────────
+
+/// One pinned file (safe to send to JS).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OfflinePin {
+    /// Derived from host, port, user and path, so pinning again replaces it.
+    pub id: String,
+    pub host: String,
+    /// Pins made before ports were recorded are taken to be on port 22.
+    #[serde(default = "default_port")]
+    pub port: u16,
+    pub user: String,
+    pub remote_path: String,
+    pub local_path: String,
+    pub size: u64,
+    /// Remote mtime of the pinned copy.
+    pub mtime: Option<u32>,
+    /// RFC 3339 timestamps.
+    pub pinned_at: String,
+    pub synced_at: String,
+}
+
+/// Outcome of `pins_sync` (safe to send to JS).
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PinSyncSummary {
+    /// Remote paths re-downloaded because they changed.
+    pub refreshed: Vec<String>,
+    pub unchanged: usize,
+    pub failed: Vec<FailedPath>,
+}
+
+/// Pinned files and their index, kept in `dir`.
+pub struct OfflineStore {
+    dir: PathBuf,
+    pins: Mutex<Vec<OfflinePin>>,
+}
+
+fn default_port() -> u16 {
+    22
+}
+
+fn pin_id(host: &str, port: u16, user: &str, path: &str) -> String {
+    let digest = Sha256::digest(format!("{user}@{host}:{port}:{path}").as_bytes());
+    digest[..16].iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// File name for a pin: its id plus the remote extension, so the local
+/// copy still opens with the right app.
+fn local_name(id: &str, remote_path: &str) -> String {
+    let name = remote_path.rsplit('/').next().unwrap_or_default();
+    match name.rsplit_once('.') {
+        Some((stem, ext))
+            if !stem.is_empty()
+                && ext.len() <= 10
+                && ext.chars().all(|c| c.is_ascii_alphanumeric()) =>
+        {
+            format!("{id}.{ext}")
+        }
+        _ => id.to_string(),
+    }
+}
+
+// ─── Store ─────────────────────────────────────────────────────────────
+
+impl OfflineStore {
+    /// Open the offline store in `dir`, creating it if needed.
+    pub fn new(dir: PathBuf) -> Self {
+        std::fs::create_dir_all(&dir).ok();
+        let mut pins: Vec<OfflinePin> = std::fs::read_to_string(dir.join("pins.json"))
+            .ok()
+            .and_then(|text| serde_json::from_str(&text).ok())
+            .unwrap_or_default();
+        // Ids from before the port was part of them.
+        for pin in &mut pins {
+            pin.id = pin_id(&pin.host, pin.port, &pin.user, &pin.remote_path);
+        }
+        Self {
+            dir,
+            pins: Mutex::new(pins),
+        }
+    }
+
+    fn persist(&self, pins: &[OfflinePin]) -> AppResult<()> {
+        let data = serde_json::to_vec_pretty(pins)
+            .map_err(|e| AppError::Other(format!("Failed to encode pins: {e}")))?;
+        std::fs::write(self.dir.join("pins.json"), data)
+            .map_err(|e| AppError::Io(format!("Failed to write pins: {e}")))
+    }
+
+    /// Record `pin`, replacing any pin with the same id.
+    fn upsert(&self, pin: OfflinePin) -> AppResult<()> {
+        let mut pins = self.pins.lock().unwrap();
+        pins.retain(|p| p.id != pin.id);
+        pins.push(pin);
+        self.persist(&pins)
+    }
+
+    /// Download `path` into the store. The previous copy, if any, is kept
+    /// until the new one has been fully written.
+    async fn fetch(
+        &self,
+        session: &Arc<SshSession>,
+        path: &str,
+        pinned_at: Option<String>,
+    ) -> AppResult<OfflinePin> {
+        let st = sftp_ops::stat(session, path).await?;
+        if st.is_dir {
+            return Err(AppError::Sftp(format!("{path} is a directory")));
+        }
+        let id = pin_id(&session.host, session.port(), &session.user, path);
+        let local = self.dir.join(local_name(&id, path));
+        let partial = local.with_extension("part");
+        let size = match sftp_ops::download_to_path(session, path, &partial, |_| {}).await {
+            Ok(size) => size,
+            Err(e) => {
+                tokio::fs::remove_file(&partial).await.ok();
+                return Err(e);
+            }
+        };
+        tokio::fs::rename(&partial, &local)
+            .await
+            .map_err(|e| AppError::Io(format!("Failed to store pinned file: {e}")))?;
+
+        let now = chrono::Utc::now().to_rfc3339();
+        Ok(OfflinePin {
+            id,
+            host: session.host.clone(),
+            port: session.port(),
+            user: session.user.clone(),
+            remote_path: path.to_string(),
+            local_path: local.to_string_lossy().to_string(),
+            size,
+            mtime: st.mtime,
+            pinned_at: pinned_at.unwrap_or_else(|| now.clone()),
+            synced_at: now,
+        })
+    }
+
+    /// Download `path` and keep it available offline.
+    pub async fn pin(&self, session: &Arc<SshSession>, path: &str) -> AppResult<OfflinePin> {
+        let start = std::time::Instant::now();
+        let pin = self.fetch(session, path, None).await?;
+        self.upsert(pin.clone())?;
+        log::info!(
+            "[SFTP] pin_offline \"{}\" — {:.2}ms | size: {} bytes",
+            path,
+            start.elapsed().as_secs_f64() * 1000.0,
+            pin.size,
+        );
+        Ok(pin)
+    }
+
+    /// Remove pin `id` and its local copy. Returns `false` if not pinned.
+    pub fn unpin(&self, id: &str) -> AppResult<bool> {
+        let mut pins = self.pins.lock().unwrap();
+        let Some(index) = pins.iter().position(|p| p.id == id) else {
+            return Ok(false);
+        };
+        let pin = pins.remove(index);
+        std::fs::remove_file(&pin.local_path).ok();
+        self.persist(&pins)?;
+        Ok(true)
+    }
+
+    /// Pins, optionally only those for one `(host, user)` (and `port`) and
+    /// directly inside `dir`, sorted by remote path.
+    pub fn list(
+        &self,
+        server: Option<(&str, &str)>,
+        port: Option<u16>,
+        dir: Option<&str>,
+    ) -> Vec<OfflinePin> {
+        let dir = dir.map(|d| match d.trim_end_matches('/') {
+            "" => "/",
+            d => d,
+        });
+        let mut pins: Vec<OfflinePin> = self
+            .pins
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|p| server.is_none_or(|(h, u)| p.host == h && p.user == u))
+            .filter(|p| port.is_none_or(|port| p.port == port))
+            .filter(|p| dir.is_none_or(|d| sftp_ops::parent_dir(&p.remote_path) == d))
+            .cloned()
+            .collect();
+        pins.sort_by(|a, b| a.remote_path.cmp(&b.remote_path));
+        pins
+    }
+
+    /// The pin for `path` on `user@host:port`, if it is pinned.
+    pub fn find(&self, host: &str, port: u16, user: &str, path: &str) -> Option<OfflinePin> {
+        let id = pin_id(host, port, user, path);
+        self.pins
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|p| p.id == id)
+            .cloned()
+    }
+
+    /// Preview pin `id` from its local copy, reading at most `max_bytes`;
+    /// needs no session.
+    pub async fn preview(&self, id: &str, max_bytes: usize) -> AppResult<FilePreview> {
+        use tokio::io::AsyncReadExt;
+
+        let local_path = self
+            .pins
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|p| p.id == id)
+            .map(|p| p.local_path.clone())
+            .ok_or_else(|| AppError::Other(format!("No offline pin {id}")))?;
+        let read_err = |e: std::io::Error| AppError::Io(format!("Failed to read pinned file: {e}"));
+        let file = tokio::fs::File::open(&local_path).await.map_err(read_err)?;
+        let total_size = file.metadata().await.map_err(read_err)?.len();
+        // One byte past the limit tells `preview_from_bytes` to truncate.
+        let mut data = Vec::new();
+        file.take(max_bytes as u64 + 1)
+            .read_to_end(&mut data)
+            .await
+            .map_err(read_err)?;
+        let mut preview = sftp_ops::preview_from_bytes(&data, max_bytes);
+        preview.total_size = total_size;
+        Ok(preview)
+    }
+
+    /// Re-download this server's pins whose remote size or mtime changed.
+    pub async fn sync(&self, session: &Arc<SshSession>) -> PinSyncSummary {
+        let start = std::time::Instant::now();
+        let mut summary = PinSyncSummary::default();
+        let server = Some((session.host.as_str(), session.user.as_str()));
+        for pin in self.list(server, Some(session.port()), None) {
+            let fresh = match sftp_ops::stat(session, &pin.remote_path).await {
+                Ok(st) => st.mtime == pin.mtime && st.size == Some(pin.size),
+                Err(e) => {
+                    summary.failed.push(FailedPath {
+                        path: pin.remote_path,
+                        error: e.to_string(),
+                    });
+                    continue;
+                }
+            };
+            if fresh {
+                summary.unchanged += 1;
+                continue;
+            }
+            let fetched = self
+                .fetch(session, &pin.remote_path, Some(pin.pinned_at))
+                .await;
+            match fetched.and_then(|p| self.upsert(p)) {
+                Ok(()) => summary.refreshed.push(pin.remote_path),
+                Err(e) => summary.failed.push(FailedPath {
+                    path: pin.remote_path,
+                    error: e.to_string(),
+                }),
+            }
+        }
+        log::info!(
+            "[PERF] pins_sync {}@{} — {:.2}ms | refreshed: {} | unchanged: {} | failed: {}",
+            session.user,
+            session.host,
+            start.elapsed().as_secs_f64() * 1000.0,
+            summary.refreshed.len(),
+            summary.unchanged,
+            summary.failed.len(),
+        );
+        summary
+    }
+}
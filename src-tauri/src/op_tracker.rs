@@ -0,0 +1,254 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
+use tokio::sync::watch;
+
+use crate::errors::{AppError, AppResult};
+
+// ─── Data Structures ───────────────────────────────────────────────────
+
+/// A running remote operation (safe to send to JS).
+#[derive(Debug, Clone, Serialize)]
+pub struct PendingOp {
+    pub id: u64,
+    /// Operation type, e.g. "list_dir", "upload", "thumbnail".
+    pub kind: &'static str,
+    pub path: String,
+    pub elapsed_ms: f64,
+}
+
+struct RunningOp {
+    kind: &'static str,
+    path: String,
+    started: Instant,
+}
+
+/// Tracks in-flight operations on one session and its "exclusive" flag,
+/// which holds back background work (thumbnail prefetch) while set.
+///
+/// It also keeps a generation counter per frontend view. Work tagged with a
+/// view's generation is aborted as soon as that view moves to a newer one
+/// (e.g. the user navigated to another directory).
+///
+/// Long transfers register under their transfer id so the user can cancel
+/// them individually. Closing the tracker (on disconnect) aborts all of the
+/// above, along with background tasks spawned through it.
+pub struct OpTracker {
+    next_id: AtomicU64,
+    running: Mutex<HashMap<u64, RunningOp>>,
+    exclusive: watch::Sender<bool>,
+    views: Mutex<HashMap<String, watch::Sender<u64>>>,
+    transfers: Mutex<HashMap<String, watch::Sender<bool>>>,
+    closed: watch::Sender<bool>,
+}
+
+/// Removes its operation from the tracker when dropped.
+pub struct OpGuard<'a> {
+    tracker: &'a OpTracker,
+    id: u64,
+}
+
+// ─── Tracking ──────────────────────────────────────────────────────────
+
+impl Default for OpTracker {
+    fn default() -> Self {
+        Self {
+            next_id: AtomicU64::new(1),
+            running: Mutex::new(HashMap::new()),
+            exclusive: watch::Sender::new(false),
+            views: Mutex::new(HashMap::new()),
+            transfers: Mutex::new(HashMap::new()),
+            closed: watch::Sender::new(false),
+        }
+    }
+}
+
+impl OpTracker {
+    /// Register an operation until the returned guard is dropped.
+    pub fn track(&self, kind: &'static str, path: &str) -> OpGuard<'_> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.running.lock().unwrap().insert(
+            id,
+            RunningOp {
+                kind,
+                path: path.to_string(),
+                started: Instant::now(),
+            },
+        );
+        OpGuard { tracker: self, id }
+    }
+
+    /// Snapshot of running operations, oldest first.
+    pub fn pending(&self) -> Vec<PendingOp> {
+        let running = self.running.lock().unwrap();
+        let mut ops: Vec<PendingOp> = running
+            .iter()
+            .map(|(&id, op)| PendingOp {
+                id,
+                kind: op.kind,
+                path: op.path.clone(),
+                elapsed_ms: op.started.elapsed().as_secs_f64() * 1000.0,
+            })
+            .collect();
+        ops.sort_by_key(|op| op.id);
+        ops
+    }
+
+    pub fn set_exclusive(&self, exclusive: bool) {
+        self.exclusive.send_replace(exclusive);
+    }
+
+    pub fn is_exclusive(&self) -> bool {
+        *self.exclusive.borrow()
+    }
+
+    /// Wait until the session is not in exclusive mode. Called by
+    /// background work before it touches the connection.
+    pub async fn wait_background(&self) {
+        let mut rx = self.exclusive.subscribe();
+        // The sender lives as long as `self`, so this cannot fail.
+        let _ = rx.wait_for(|exclusive| !*exclusive).await;
+    }
+}
+
+// ─── View Generations ──────────────────────────────────────────────────
+
+impl OpTracker {
+    fn with_view<R>(&self, view_id: &str, f: impl FnOnce(&watch::Sender<u64>) -> R) -> R {
+        let mut views = self.views.lock().unwrap();
+        let view = views
+            .entry(view_id.to_string())
+            .or_insert_with(|| watch::Sender::new(0));
+        f(view)
+    }
+
+    /// Move `view_id` to `generation` if it is newer, aborting work tagged
+    /// with any older generation.
+    pub fn advance_view(&self, view_id: &str, generation: u64) {
+        let advanced = self.with_view(view_id, |view| {
+            view.send_if_modified(|current| {
+                let newer = generation > *current;
+                if newer {
+                    *current = generation;
+                }
+                newer
+            })
+        });
+        if advanced {
+            log::debug!(
+                "[SFTP] view {} advanced to generation {}",
+                view_id,
+                generation
+            );
+        }
+    }
+
+    /// Run `work` unless or until `view_id` moves past `generation`, in
+    /// which case it is dropped and `AppError::Cancelled` returned.
+    pub async fn run_for_view<T>(
+        &self,
+        view_id: &str,
+        generation: u64,
+        work: impl Future<Output = AppResult<T>>,
+    ) -> AppResult<T> {
+        let mut rx = self.with_view(view_id, |view| view.subscribe());
+        let cancelled = || AppError::Cancelled(format!("view {view_id} moved on"));
+        if *rx.borrow() > generation {
+            return Err(cancelled());
+        }
+        let work = self.until_closed(work);
+        tokio::select! {
+            result = work => result,
+            _ = rx.wait_for(|current| *current > generation) => Err(cancelled()),
+        }
+    }
+}
+
+// ─── Cancellable Transfers ─────────────────────────────────────────────
+
+impl OpTracker {
+    /// Run `work` under `transfer_id` until it finishes or
+    /// [`cancel_transfer`](Self::cancel_transfer) is called for that id, in
+    /// which case it is dropped and `AppError::Cancelled` returned.
+    pub async fn run_transfer<T>(
+        &self,
+        transfer_id: &str,
+        work: impl Future<Output = AppResult<T>>,
+    ) -> AppResult<T> {
+        let (tx, mut rx) = watch::channel(false);
+        self.transfers
+            .lock()
+            .unwrap()
+            .insert(transfer_id.to_string(), tx);
+        let result = tokio::select! {
+            result = work => result,
+            _ = rx.wait_for(|cancelled| *cancelled) => {
+                Err(AppError::Cancelled(format!("transfer {transfer_id} cancelled")))
+            }
+        };
+        self.transfers.lock().unwrap().remove(transfer_id);
+        result
+    }
+
+    /// Cancel a running transfer. Returns `false` if none has that id.
+    pub fn cancel_transfer(&self, transfer_id: &str) -> bool {
+        match self.transfers.lock().unwrap().get(transfer_id) {
+            Some(tx) => {
+                tx.send_replace(true);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+// ─── Session Shutdown ──────────────────────────────────────────────────
+
+impl OpTracker {
+    /// Abort everything running on the session. Called on disconnect.
+    pub fn close(&self) {
+        let pending = self.pending().len();
+        if !self.closed.send_replace(true) && pending > 0 {
+            log::info!("[SSH] aborting {} in-flight operation(s)", pending);
+        }
+    }
+
+    pub fn is_closed(&self) -> bool {
+        *self.closed.borrow()
+    }
+
+    /// Run `work` until it finishes or the session is closed, in which case
+    /// it is dropped and `AppError::Cancelled` returned.
+    pub async fn until_closed<T>(&self, work: impl Future<Output = AppResult<T>>) -> AppResult<T> {
+        let disconnected = || AppError::Cancelled("session disconnected".to_string());
+        let mut rx = self.closed.subscribe();
+        if *rx.borrow() {
+            return Err(disconnected());
+        }
+        tokio::select! {
+            result = work => result,
+            _ = rx.wait_for(|closed| *closed) => Err(disconnected()),
+        }
+    }
+
+    /// Spawn background work that is aborted when the session is closed, so
+    /// it stops holding the session once the user disconnects.
+    pub fn spawn(&self, work: impl Future<Output = ()> + Send + 'static) {
+        let mut rx = self.closed.subscribe();
+        tokio::spawn(async move {
+            tokio::select! {
+                () = work => {}
+                _ = rx.wait_for(|closed| *closed) => {}
+            }
+        });
+    }
+}
+
+impl Drop for OpGuard<'_> {
+    fn drop(&mut self) {
+        self.tracker.running.lock().unwrap().remove(&self.id);
+    }
+}
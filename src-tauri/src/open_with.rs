@@ -0,0 +1,112 @@
+//! Platform-specific "open with" support for downloaded files.
+
+use serde::Serialize;
+
+use crate::errors::{AppError, AppResult};
+
+/// An application that can handle a file (safe to send to JS).
+#[derive(Debug, Clone, Serialize)]
+pub struct OpenWithApp {
+    /// Identifier passed back to `open_file_with` (desktop file id, app path…).
+    pub id: String,
+    pub name: String,
+}
+
+/// Enumerate applications registered for the file's type.
+///
+/// Only Linux (via `gio`) supports enumeration; other platforms return an
+/// empty list and rely on the system chooser instead.
+pub fn list_handlers(path: &str) -> AppResult<Vec<OpenWithApp>> {
+    #[cfg(target_os = "linux")]
+    {
+        linux::list_handlers(path)
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = path;
+        Ok(Vec::new())
+    }
+}
+
+/// Open `path` with `app` if given, otherwise show the OS chooser where the
+/// platform has one (falling back to the default association).
+pub fn open_with(path: &str, app: Option<&str>) -> AppResult<()> {
+    if let Some(app) = app {
+        #[cfg(target_os = "linux")]
+        if app.ends_with(".desktop") {
+            return linux::launch_desktop_entry(app, path);
+        }
+        return tauri_plugin_opener::open_path(path, Some(app))
+            .map_err(|e| AppError::Other(format!("Failed to open file with {app}: {e}")));
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        std::process::Command::new("rundll32.exe")
+            .arg("shell32.dll,OpenAs_RunDLL")
+            .arg(path)
+            .spawn()
+            .map_err(|e| AppError::Other(format!("Failed to show Open With dialog: {e}")))?;
+        Ok(())
+    }
+    #[cfg(not(target_os = "windows"))]
+    {
+        // Android's opener already routes through an intent chooser; elsewhere
+        // there is no system chooser, so use the default association.
+        tauri_plugin_opener::open_path(path, None::<&str>)
+            .map_err(|e| AppError::Other(format!("Failed to open file externally: {e}")))
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use super::OpenWithApp;
+    use crate::errors::{AppError, AppResult};
+    use std::process::Command;
+
+    fn run(cmd: &mut Command) -> AppResult<String> {
+        let out = cmd
+            .output()
+            .map_err(|e| AppError::Other(format!("Failed to run {cmd:?}: {e}")))?;
+        Ok(String::from_utf8_lossy(&out.stdout).to_string())
+    }
+
+    pub fn list_handlers(path: &str) -> AppResult<Vec<OpenWithApp>> {
+        let mime = run(Command::new("xdg-mime").args(["query", "filetype", path]))?;
+        let mime = mime.trim();
+        if mime.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        // `gio mime <type>` lists "Registered applications:" then tab-indented ids.
+        let listing = run(Command::new("gio").args(["mime", mime]))?;
+        let mut apps = Vec::new();
+        let mut in_registered = false;
+        for line in listing.lines() {
+            if line.starts_with("Registered applications") {
+                in_registered = true;
+                continue;
+            }
+            if !line.starts_with('\t') {
+                in_registered = false;
+                continue;
+            }
+            let id = line.trim();
+            if in_registered && !apps.iter().any(|a: &OpenWithApp| a.id == id) {
+                apps.push(OpenWithApp {
+                    id: id.to_string(),
+                    name: id.trim_end_matches(".desktop").to_string(),
+                });
+            }
+        }
+        Ok(apps)
+    }
+
+    pub fn launch_desktop_entry(desktop_id: &str, path: &str) -> AppResult<()> {
+        Command::new("gtk-launch")
+            .args([desktop_id, path])
+            .spawn()
+            .map_err(|e| AppError::Other(format!("Failed to launch {desktop_id}: {e}")))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,208 @@
+//! Actions run on a downloaded file or directory once its transfer completes.
+//!
+//! The caller picks them per transfer. They run in the order given, except
+//! that deleting the remote source always runs last. A failed action does
+//! not stop the ones after it, but the remote copy is only deleted once the
+//! local file is confirmed complete (and, when a checksum was requested,
+//! matching).
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::errors::{AppError, AppResult};
+use crate::sftp_ops::{self, ChecksumAlgo};
+use crate::ssh_manager::SshSession;
+use crate::tree_ops;
+
+// ─── Data Structures ───────────────────────────────────────────────────
+
+/// Something to do with a finished download.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PostAction {
+    /// Open the file with its default application.
+    Open,
+    /// Show the file in the system file manager.
+    Reveal,
+    /// Hash the local copy and compare it with the server's.
+    Checksum,
+    /// Delete the remote source, turning the download into a move.
+    DeleteRemote,
+}
+
+/// Outcome of one post-download action (safe to send to JS).
+#[derive(Debug, Clone, Serialize)]
+pub struct PostActionResult {
+    pub action: PostAction,
+    pub error: Option<String>,
+    /// Hex SHA-256 of the local copy, for `Checksum`.
+    pub sha256: Option<String>,
+}
+
+// ─── Actions ───────────────────────────────────────────────────────────
+
+fn hex_digest(hasher: Sha256) -> String {
+    hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+/// Hex SHA-256 of a local file.
+pub(crate) async fn local_sha256(path: &Path) -> AppResult<String> {
+    let path = path.to_path_buf();
+    tokio::task::spawn_blocking(move || -> AppResult<String> {
+        let mut file = std::fs::File::open(&path)?;
+        let mut hasher = Sha256::new();
+        std::io::copy(&mut file, &mut hasher)?;
+        Ok(hex_digest(hasher))
+    })
+    .await
+    .map_err(|e| AppError::Other(format!("Checksum task failed: {e}")))?
+}
+
+/// Hex SHA-256 of bytes held in memory.
+pub(crate) fn sha256_hex(data: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(data);
+    hex_digest(hasher)
+}
+
+/// Compare the local copy's hash with the server's (`check-file` or
+/// `sha256sum`). A server that can't hash files fails the check: the copy
+/// was asked to be verified and could not be.
+async fn checksum(session: &Arc<SshSession>, remote: &str, local: &Path) -> AppResult<String> {
+    let local_hash = local_sha256(local).await?;
+    let remote_hash = sftp_ops::checksum(session, remote, ChecksumAlgo::Sha256)
+        .await
+        .map_err(|e| AppError::Other(format!("Downloaded, but could not verify: {e}")))?;
+    if remote_hash.digest != local_hash {
+        return Err(AppError::Other(format!(
+            "Checksum mismatch: local {local_hash}, remote {}",
+            remote_hash.digest
+        )));
+    }
+    Ok(local_hash)
+}
+
+/// Delete `remote` only if the local copy has the same size.
+async fn delete_remote(session: &Arc<SshSession>, remote: &str, local: &Path) -> AppResult<()> {
+    let remote_size = sftp_ops::stat(session, remote).await?.size;
+    let local_size = tokio::fs::metadata(local).await?.len();
+    if remote_size != Some(local_size) {
+        return Err(AppError::Other(format!(
+            "Kept {remote}: local copy is {local_size} bytes, remote is {}",
+            remote_size.map_or("unknown".into(), |s| format!("{s} bytes")),
+        )));
+    }
+    sftp_ops::delete_file(session, remote).await
+}
+
+/// `actions` in the order they run: as given, with one delete at the end.
+fn ordered(actions: &[PostAction]) -> impl Iterator<Item = PostAction> {
+    let (deletes, others): (Vec<PostAction>, Vec<PostAction>) = actions
+        .iter()
+        .partition(|&&a| a == PostAction::DeleteRemote);
+    others.into_iter().chain(deletes.into_iter().take(1))
+}
+
+fn open(local: &Path) -> AppResult<()> {
+    tauri_plugin_opener::open_path(local, None::<&str>)
+        .map_err(|e| AppError::Other(format!("Failed to open file: {e}")))
+}
+
+fn reveal(local: &Path) -> AppResult<()> {
+    tauri_plugin_opener::reveal_item_in_dir(local)
+        .map_err(|e| AppError::Other(format!("Failed to reveal file: {e}")))
+}
+
+fn result(
+    action: PostAction,
+    remote: &str,
+    outcome: AppResult<()>,
+    sha256: Option<String>,
+) -> PostActionResult {
+    if let Err(e) = &outcome {
+        log::warn!(
+            "[TRANSFER] post-download {:?} on \"{}\" failed: {}",
+            action,
+            remote,
+            e
+        );
+    }
+    PostActionResult {
+        action,
+        error: outcome.err().map(|e| e.to_string()),
+        sha256,
+    }
+}
+
+/// Run `actions` on a completed download of `remote` to `local`.
+pub async fn run(
+    session: &Arc<SshSession>,
+    remote: &str,
+    local: &Path,
+    actions: &[PostAction],
+) -> Vec<PostActionResult> {
+    let mut results = Vec::with_capacity(actions.len());
+    let mut checksum_failed = false;
+    for action in ordered(actions) {
+        let mut sha256 = None;
+        let outcome = match action {
+            PostAction::Open => open(local),
+            PostAction::Reveal => reveal(local),
+            PostAction::Checksum => checksum(session, remote, local).await.map(|hash| {
+                sha256 = Some(hash);
+            }),
+            PostAction::DeleteRemote if checksum_failed => Err(AppError::Other(format!(
+                "Kept {remote}: checksum did not match"
+            ))),
+            PostAction::DeleteRemote => delete_remote(session, remote, local).await,
+        };
+        if action == PostAction::Checksum && outcome.is_err() {
+            checksum_failed = true;
+        }
+        results.push(result(action, remote, outcome, sha256));
+    }
+    results
+}
+
+/// Run `actions` on a completed download of directory `remote` to `local`,
+/// in which `failed` files did not arrive. `Checksum` is done per file by
+/// the transfer itself (the caller turns on verification), so here it only
+/// reports whether every file passed. Open and reveal act on the local
+/// directory; the remote tree is deleted only when nothing failed.
+pub async fn run_dir(
+    session: &Arc<SshSession>,
+    remote: &str,
+    local: &Path,
+    actions: &[PostAction],
+    failed: usize,
+) -> Vec<PostActionResult> {
+    let mut results = Vec::with_capacity(actions.len());
+    for action in ordered(actions) {
+        let outcome = match action {
+            PostAction::Open => open(local),
+            PostAction::Reveal => reveal(local),
+            PostAction::Checksum | PostAction::DeleteRemote if failed > 0 => Err(AppError::Other(
+                format!("{failed} file(s) under {remote} failed to download or verify"),
+            )),
+            PostAction::Checksum => Ok(()),
+            PostAction::DeleteRemote => match tree_ops::delete_recursive(session, remote).await {
+                Ok(summary) => match summary.failed.first() {
+                    Some(item) => Err(AppError::Other(format!(
+                        "Could not remove {}: {}",
+                        item.path, item.error
+                    ))),
+                    None => Ok(()),
+                },
+                Err(e) => Err(e),
+            },
+        };
+        results.push(result(action, remote, outcome, None));
+    }
+    results
+}
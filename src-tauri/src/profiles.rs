@@ -0,0 +1,182 @@
+//! Saved connection profiles, so a server can be reconnected to without
+//! retyping its details.
+//!
+//! Profiles live in a JSON file in the app data dir. They never hold
+//! secrets: a password or key passphrase is supplied on each connect (or
+//! looked up in a password manager named by the profile), and keys are
+//! referenced by their name in the key store.
+
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use tokio::sync::Mutex;
+
+use crate::credentials::CredentialSource;
+use crate::errors::{AppError, AppResult};
+
+// ─── Data Structures ───────────────────────────────────────────────────
+
+/// How a profile authenticates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthMethod {
+    /// A key from the key store, named by `key_name`.
+    Key,
+    /// A password given at connect time.
+    Password,
+    /// Keyboard-interactive prompts answered at connect time.
+    Interactive,
+}
+
+/// One saved connection (safe to send to JS).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Profile {
+    /// Assigned on first save; pass it back to update the profile.
+    #[serde(default)]
+    pub id: String,
+    /// Display name; defaults to `user@host`.
+    #[serde(default)]
+    pub name: String,
+    pub host: String,
+    #[serde(default = "default_port")]
+    pub port: u16,
+    pub user: String,
+    pub auth_method: AuthMethod,
+    #[serde(default)]
+    pub key_name: Option<String>,
+    /// Directory opened after connecting.
+    #[serde(default)]
+    pub start_path: Option<String>,
+    /// Local directory paired with `start_path`: opened beside it, and the
+    /// default target for downloads on this connection.
+    #[serde(default)]
+    pub local_path: Option<String>,
+    /// Commands run over exec right after connecting; see
+    /// `ssh_startup_output`.
+    #[serde(default)]
+    pub startup_commands: Vec<String>,
+    #[serde(default)]
+    pub host_key_pin: Option<String>,
+    #[serde(default)]
+    pub host_key_strict: bool,
+    /// Password manager to ask when no password is given. `None` opts out.
+    #[serde(default)]
+    pub credential_source: Option<CredentialSource>,
+}
+
+/// Result of `profile_connect` (safe to send to JS).
+#[derive(Debug, Clone, Serialize)]
+pub struct ProfileConnection {
+    pub session_id: String,
+    pub start_path: Option<String>,
+    pub local_path: Option<String>,
+}
+
+fn default_port() -> u16 {
+    22
+}
+
+/// Profile store backed by a JSON file.
+pub struct ProfileStore {
+    path: PathBuf,
+    lock: Mutex<()>,
+}
+
+// ─── Store ─────────────────────────────────────────────────────────────
+
+impl ProfileStore {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// Load profiles from disk; a missing or empty file means none.
+    fn load_sync(&self) -> AppResult<Vec<Profile>> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        let data = std::fs::read_to_string(&self.path)
+            .map_err(|e| AppError::Io(format!("Failed to read profiles: {e}")))?;
+        if data.trim().is_empty() {
+            return Ok(Vec::new());
+        }
+        serde_json::from_str(&data)
+            .map_err(|e| AppError::Other(format!("Failed to parse profiles: {e}")))
+    }
+
+    fn save_sync(&self, profiles: &[Profile]) -> AppResult<()> {
+        let data = serde_json::to_string_pretty(profiles)
+            .map_err(|e| AppError::Other(format!("Failed to serialize profiles: {e}")))?;
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent).ok();
+        }
+        std::fs::write(&self.path, data)
+            .map_err(|e| AppError::Io(format!("Failed to write profiles: {e}")))
+    }
+
+    /// All profiles, sorted by display name.
+    pub async fn list(&self) -> AppResult<Vec<Profile>> {
+        let _guard = self.lock.lock().await;
+        let mut profiles = self.load_sync()?;
+        profiles.sort_by_key(|p| p.name.to_lowercase());
+        Ok(profiles)
+    }
+
+    /// The profile with `id`.
+    pub async fn get(&self, id: &str) -> AppResult<Profile> {
+        let _guard = self.lock.lock().await;
+        self.load_sync()?
+            .into_iter()
+            .find(|p| p.id == id)
+            .ok_or_else(|| AppError::Other(format!("No saved profile {id}")))
+    }
+
+    /// Add `profile`, or replace the one with the same id. Returns it with
+    /// its id and name filled in.
+    pub async fn save(&self, mut profile: Profile) -> AppResult<Profile> {
+        if profile.host.trim().is_empty() || profile.user.trim().is_empty() {
+            return Err(AppError::Other("A profile needs a host and a user".into()));
+        }
+        if profile.auth_method == AuthMethod::Key && profile.key_name.is_none() {
+            return Err(AppError::Other(
+                "Key authentication needs a key name".into(),
+            ));
+        }
+        if let Some(local) = &profile.local_path {
+            if !std::path::Path::new(local).is_absolute() {
+                return Err(AppError::Other(format!(
+                    "Local directory \"{local}\" must be an absolute path"
+                )));
+            }
+        }
+        if profile.id.is_empty() {
+            profile.id = uuid::Uuid::new_v4().to_string();
+        }
+        if profile.name.trim().is_empty() {
+            profile.name = format!("{}@{}", profile.user, profile.host);
+        }
+
+        let _guard = self.lock.lock().await;
+        let mut profiles = self.load_sync()?;
+        match profiles.iter_mut().find(|p| p.id == profile.id) {
+            Some(existing) => *existing = profile.clone(),
+            None => profiles.push(profile.clone()),
+        }
+        self.save_sync(&profiles)?;
+        Ok(profile)
+    }
+
+    /// Remove the profile with `id`. Returns `false` if there was none.
+    pub async fn delete(&self, id: &str) -> AppResult<bool> {
+        let _guard = self.lock.lock().await;
+        let mut profiles = self.load_sync()?;
+        let before = profiles.len();
+        profiles.retain(|p| p.id != id);
+        if profiles.len() == before {
+            return Ok(false);
+        }
+        self.save_sync(&profiles)?;
+        Ok(true)
+    }
+}
@@ -0,0 +1,545 @@
+//! Byte-exact paths for names that are not valid UTF-8.
+//!
+//! The SFTP client decodes every name lossily and only sends UTF-8 paths,
+//! so such a file can be listed but not addressed through it. Listings
+//! give these entries an opaque path (`"\0raw:"` and the base64 of the
+//! exact bytes; a real path never contains NUL), and stat, download,
+//! rename and delete send it byte for byte: over a short-lived SFTP
+//! channel of their own, or `printf`-built shell words on the SCP fallback.
+
+use base64::Engine;
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::errors::{AppError, AppResult};
+use crate::sftp_ops::{self, FileEntry};
+use crate::ssh_manager::{shell_quote, SshSession};
+use crate::timeouts;
+
+/// Marks an opaque path; the base64 of the exact bytes follows.
+const RAW_PREFIX: &str = "\0raw:";
+/// Largest reply accepted from the server.
+const MAX_PACKET_BYTES: usize = 4 * 1024 * 1024;
+/// Rename that replaces an existing target, as POSIX `rename(2)` does.
+pub(crate) const POSIX_RENAME: &str = "posix-rename@openssh.com";
+/// Bytes asked for per read; every server accepts at least this much.
+const READ_CHUNK_BYTES: u32 = 32 * 1024;
+
+const SSH_FXP_INIT: u8 = 1;
+const SSH_FXP_VERSION: u8 = 2;
+const SSH_FXP_OPEN: u8 = 3;
+const SSH_FXP_CLOSE: u8 = 4;
+const SSH_FXP_READ: u8 = 5;
+const SSH_FXP_LSTAT: u8 = 7;
+const SSH_FXP_OPENDIR: u8 = 11;
+const SSH_FXP_READDIR: u8 = 12;
+const SSH_FXP_REMOVE: u8 = 13;
+const SSH_FXP_RMDIR: u8 = 15;
+const SSH_FXP_STAT: u8 = 17;
+const SSH_FXP_RENAME: u8 = 18;
+const SSH_FXP_EXTENDED: u8 = 200;
+const SSH_FXP_STATUS: u8 = 101;
+const SSH_FXP_HANDLE: u8 = 102;
+const SSH_FXP_DATA: u8 = 103;
+const SSH_FXP_NAME: u8 = 104;
+const SSH_FXP_ATTRS: u8 = 105;
+
+const SSH_FX_OK: u32 = 0;
+const SSH_FX_EOF: u32 = 1;
+const SSH_FX_OP_UNSUPPORTED: u32 = 8;
+const SSH_FXF_READ: u32 = 0x1;
+
+const ATTR_SIZE: u32 = 0x1;
+const ATTR_UIDGID: u32 = 0x2;
+const ATTR_PERMISSIONS: u32 = 0x4;
+const ATTR_ACMODTIME: u32 = 0x8;
+const ATTR_EXTENDED: u32 = 0x8000_0000;
+
+// ─── Opaque Paths ──────────────────────────────────────────────────────
+
+/// The opaque path for exact `bytes`.
+pub fn encode_path(bytes: &[u8]) -> String {
+    format!(
+        "{RAW_PREFIX}{}",
+        base64::engine::general_purpose::STANDARD.encode(bytes)
+    )
+}
+
+/// The exact bytes of an opaque path, or `None` for a plain path.
+pub fn decode_path(path: &str) -> Option<Vec<u8>> {
+    let encoded = path.strip_prefix(RAW_PREFIX)?;
+    base64::engine::general_purpose::STANDARD
+        .decode(encoded)
+        .ok()
+}
+
+pub fn is_raw(path: &str) -> bool {
+    path.starts_with(RAW_PREFIX)
+}
+
+/// The exact bytes `path` names, opaque or plain.
+pub fn path_bytes(path: &str) -> Vec<u8> {
+    decode_path(path).unwrap_or_else(|| path.as_bytes().to_vec())
+}
+
+/// `path` for display and logs, decoded lossily when opaque.
+pub fn lossy_path(path: &str) -> String {
+    match decode_path(path) {
+        Some(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+        None => path.to_string(),
+    }
+}
+
+/// The directory holding `path`, opaque when `path` is and its parent is
+/// not valid UTF-8 either.
+pub fn parent_path(path: &str) -> String {
+    let Some(bytes) = decode_path(path) else {
+        return sftp_ops::parent_dir(path).to_string();
+    };
+    let parent = match bytes.iter().rposition(|&b| b == b'/') {
+        Some(0) | None => return "/".to_string(),
+        Some(i) => &bytes[..i],
+    };
+    match std::str::from_utf8(parent) {
+        Ok(parent) => parent.to_string(),
+        Err(_) => encode_path(parent),
+    }
+}
+
+/// Give `entry`, listed in the plain directory `dir`, the opaque path of
+/// its exact `name` when that is not valid UTF-8.
+pub(crate) fn mark_raw(entry: &mut FileEntry, dir: &str, name: &[u8]) {
+    if std::str::from_utf8(name).is_ok() {
+        return;
+    }
+    let mut path = dir.as_bytes().to_vec();
+    if !dir.ends_with('/') {
+        path.push(b'/');
+    }
+    path.extend_from_slice(name);
+    entry.path = encode_path(&path);
+    entry.raw_name = Some(base64::engine::general_purpose::STANDARD.encode(name));
+}
+
+/// Exact names of one directory, read once the first lossy entry turns up.
+#[derive(Default)]
+pub(crate) struct RawNames {
+    names: Option<Vec<Vec<u8>>>,
+}
+
+impl RawNames {
+    /// Give each entry of `entries`, listed in `dir` over SFTP, whose name
+    /// was decoded lossily the opaque path of the name it came from. Names
+    /// that can't be matched keep their lossy path.
+    pub(crate) async fn mark(
+        &mut self,
+        session: &Arc<SshSession>,
+        dir: &str,
+        entries: &mut [FileEntry],
+    ) {
+        for entry in entries {
+            let lossy = entry.path.rsplit('/').next().unwrap_or_default();
+            if !lossy.contains(char::REPLACEMENT_CHARACTER) {
+                continue;
+            }
+            if self.names.is_none() {
+                let names = match read_dir_names(session, dir).await {
+                    Ok(names) => names,
+                    Err(e) => {
+                        log::warn!("[SFTP] raw names of {} unavailable: {}", dir, e);
+                        Vec::new()
+                    }
+                };
+                self.names = Some(
+                    names
+                        .into_iter()
+                        .filter(|n| std::str::from_utf8(n).is_err())
+                        .collect(),
+                );
+            }
+            let names = self.names.as_mut().expect("raw names just read");
+            if let Some(i) = names
+                .iter()
+                .position(|n| String::from_utf8_lossy(n) == lossy)
+            {
+                let name = names.swap_remove(i);
+                mark_raw(entry, dir, &name);
+            }
+        }
+    }
+}
+
+/// `path` as one shell word: quoted when plain, rebuilt with `printf`
+/// octal escapes when opaque.
+pub(crate) fn shell_word(path: &str) -> AppResult<String> {
+    let Some(bytes) = decode_path(path) else {
+        return Ok(shell_quote(path));
+    };
+    // Command substitution drops trailing newlines.
+    if bytes.ends_with(b"\n") {
+        return Err(AppError::Sftp(
+            "Cannot address a name ending in a newline over exec".into(),
+        ));
+    }
+    let escaped: String = bytes.iter().map(|b| format!("\\{b:03o}")).collect();
+    Ok(format!("\"$(printf '{escaped}')\""))
+}
+
+// ─── Raw Channel ───────────────────────────────────────────────────────
+
+/// The attributes of a stat reply that callers use.
+#[derive(Debug, Default)]
+pub struct RawAttrs {
+    pub size: Option<u64>,
+    pub permissions: Option<u32>,
+    pub mtime: Option<u32>,
+}
+
+impl RawAttrs {
+    pub fn is_dir(&self) -> bool {
+        self.permissions.is_some_and(|p| p & 0o170000 == 0o040000)
+    }
+}
+
+/// Reads SFTP wire types from a reply body.
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, n: usize) -> AppResult<&'a [u8]> {
+        if self.0.len() < n {
+            return Err(AppError::Sftp("Malformed SFTP reply".into()));
+        }
+        let (head, rest) = self.0.split_at(n);
+        self.0 = rest;
+        Ok(head)
+    }
+
+    fn u32(&mut self) -> AppResult<u32> {
+        Ok(u32::from_be_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> AppResult<u64> {
+        Ok(u64::from_be_bytes(self.bytes(8)?.try_into().unwrap()))
+    }
+
+    fn string(&mut self) -> AppResult<&'a [u8]> {
+        let len = self.u32()? as usize;
+        self.bytes(len)
+    }
+
+    fn attrs(&mut self) -> AppResult<RawAttrs> {
+        let flags = self.u32()?;
+        let mut attrs = RawAttrs::default();
+        if flags & ATTR_SIZE != 0 {
+            attrs.size = Some(self.u64()?);
+        }
+        if flags & ATTR_UIDGID != 0 {
+            self.bytes(8)?;
+        }
+        if flags & ATTR_PERMISSIONS != 0 {
+            attrs.permissions = Some(self.u32()?);
+        }
+        if flags & ATTR_ACMODTIME != 0 {
+            self.u32()?;
+            attrs.mtime = Some(self.u32()?);
+        }
+        if flags & ATTR_EXTENDED != 0 {
+            for _ in 0..self.u32()? {
+                self.string()?;
+                self.string()?;
+            }
+        }
+        Ok(attrs)
+    }
+}
+
+fn put_string(buf: &mut Vec<u8>, s: &[u8]) {
+    buf.extend_from_slice(&(s.len() as u32).to_be_bytes());
+    buf.extend_from_slice(s);
+}
+
+/// An SFTP channel spoken to directly, one request at a time.
+struct RawChannel {
+    stream: russh::ChannelStream<russh::client::Msg>,
+    next_id: u32,
+    _slot: Option<tokio::sync::OwnedSemaphorePermit>,
+}
+
+impl RawChannel {
+    async fn open(session: &SshSession) -> AppResult<Self> {
+        let slot = session.channel_slot().await?;
+        let channel = session.open_sftp_channel().await?;
+        let mut raw = Self {
+            stream: channel.into_stream(),
+            next_id: 0,
+            _slot: slot,
+        };
+        let mut init = vec![SSH_FXP_INIT];
+        init.extend_from_slice(&3u32.to_be_bytes());
+        raw.write_packet(&init).await?;
+        let version = raw.read_packet().await?;
+        if version.first() != Some(&SSH_FXP_VERSION) {
+            return Err(AppError::Sftp("Unexpected reply to SFTP init".into()));
+        }
+        Ok(raw)
+    }
+
+    async fn write_packet(&mut self, body: &[u8]) -> AppResult<()> {
+        let io_err = |e: std::io::Error| AppError::Sftp(format!("SFTP write failed: {e}"));
+        self.stream
+            .write_all(&(body.len() as u32).to_be_bytes())
+            .await
+            .map_err(io_err)?;
+        self.stream.write_all(body).await.map_err(io_err)?;
+        self.stream.flush().await.map_err(io_err)
+    }
+
+    async fn read_packet(&mut self) -> AppResult<Vec<u8>> {
+        let io_err = |e: std::io::Error| AppError::Sftp(format!("SFTP read failed: {e}"));
+        let len = self.stream.read_u32().await.map_err(io_err)? as usize;
+        if len == 0 || len > MAX_PACKET_BYTES {
+            return Err(AppError::Sftp(format!("SFTP reply of {len} bytes refused")));
+        }
+        let mut body = vec![0u8; len];
+        self.stream.read_exact(&mut body).await.map_err(io_err)?;
+        Ok(body)
+    }
+
+    /// Send request `kind` with `payload` and return the reply type and
+    /// the body after its id.
+    async fn request(&mut self, kind: u8, payload: &[u8]) -> AppResult<(u8, Vec<u8>)> {
+        self.next_id = self.next_id.wrapping_add(1);
+        let id = self.next_id;
+        let mut body = Vec::with_capacity(payload.len() + 5);
+        body.push(kind);
+        body.extend_from_slice(&id.to_be_bytes());
+        body.extend_from_slice(payload);
+        self.write_packet(&body).await?;
+        let reply = self.read_packet().await?;
+        let mut r = Reader(&reply[1..]);
+        if r.u32()? != id {
+            return Err(AppError::Sftp("SFTP reply for another request".into()));
+        }
+        Ok((reply[0], r.0.to_vec()))
+    }
+
+    /// A request answered by a status: `Ok` for success, the status as an
+    /// error otherwise.
+    async fn request_status(&mut self, kind: u8, payload: &[u8], what: &str) -> AppResult<()> {
+        let (reply, body) = self.request(kind, payload).await?;
+        match status(reply, &body, what)? {
+            SSH_FX_OK => Ok(()),
+            _ => Err(AppError::Sftp(format!(
+                "Failed to {what}: unexpected status"
+            ))),
+        }
+    }
+
+    async fn handle(&mut self, kind: u8, payload: &[u8], what: &str) -> AppResult<Vec<u8>> {
+        let (reply, body) = self.request(kind, payload).await?;
+        if reply != SSH_FXP_HANDLE {
+            status(reply, &body, what)?;
+            return Err(AppError::Sftp(format!("Failed to {what}: no handle")));
+        }
+        Ok(Reader(&body).string()?.to_vec())
+    }
+
+    async fn close(&mut self, handle: &[u8]) {
+        let mut payload = Vec::new();
+        put_string(&mut payload, handle);
+        self.request(SSH_FXP_CLOSE, &payload).await.ok();
+    }
+}
+
+/// The code of a status reply; any code but OK and EOF is an error.
+fn status(reply: u8, body: &[u8], what: &str) -> AppResult<u32> {
+    if reply != SSH_FXP_STATUS {
+        return Err(AppError::Sftp(format!(
+            "Failed to {what}: unexpected reply {reply}"
+        )));
+    }
+    let mut r = Reader(body);
+    let code = r.u32()?;
+    if code == SSH_FX_OK || code == SSH_FX_EOF {
+        return Ok(code);
+    }
+    let message = r
+        .string()
+        .map(|m| String::from_utf8_lossy(m).into_owned())
+        .unwrap_or_default();
+    Err(AppError::Sftp(format!(
+        "Failed to {what}: {message} ({code})"
+    )))
+}
+
+fn string_payload(path: &[u8]) -> Vec<u8> {
+    let mut payload = Vec::new();
+    put_string(&mut payload, path);
+    payload
+}
+
+// ─── Operations ────────────────────────────────────────────────────────
+
+/// The exact names in the plain directory `dir`, `.` and `..` left out.
+pub(crate) async fn read_dir_names(
+    session: &Arc<SshSession>,
+    dir: &str,
+) -> AppResult<Vec<Vec<u8>>> {
+    let list = async {
+        let mut raw = RawChannel::open(session).await?;
+        let handle = raw
+            .handle(
+                SSH_FXP_OPENDIR,
+                &string_payload(dir.as_bytes()),
+                "read directory",
+            )
+            .await?;
+        let mut names = Vec::new();
+        let listed = loop {
+            let (reply, body) = match raw.request(SSH_FXP_READDIR, &string_payload(&handle)).await {
+                Ok(reply) => reply,
+                Err(e) => break Err(e),
+            };
+            if reply != SSH_FXP_NAME {
+                break status(reply, &body, "read directory").map(|_| ());
+            }
+            let mut r = Reader(&body);
+            let parsed: AppResult<()> = (|| {
+                for _ in 0..r.u32()? {
+                    let name = r.string()?.to_vec();
+                    r.string()?;
+                    r.attrs()?;
+                    if name != b"." && name != b".." {
+                        names.push(name);
+                    }
+                }
+                Ok(())
+            })();
+            if let Err(e) = parsed {
+                break Err(e);
+            }
+        };
+        raw.close(&handle).await;
+        listed.map(|()| names)
+    };
+    session
+        .timed(timeouts::metadata(), "raw list_dir", list)
+        .await
+}
+
+/// Stat `path`, following symlinks unless `lstat` is set.
+pub(crate) async fn stat(
+    session: &Arc<SshSession>,
+    path: &[u8],
+    lstat: bool,
+) -> AppResult<RawAttrs> {
+    let kind = if lstat { SSH_FXP_LSTAT } else { SSH_FXP_STAT };
+    let stat = async {
+        let mut raw = RawChannel::open(session).await?;
+        let (reply, body) = raw.request(kind, &string_payload(path)).await?;
+        if reply != SSH_FXP_ATTRS {
+            status(reply, &body, "stat file")?;
+            return Err(AppError::Sftp("Failed to stat file: no attributes".into()));
+        }
+        Reader(&body).attrs()
+    };
+    session.timed(timeouts::metadata(), "raw stat", stat).await
+}
+
+/// Rename `from` to `to`, replacing `to` if it exists: with the
+/// `posix-rename@openssh.com` extension, or a plain SFTP rename (which
+/// refuses to replace) where the server lacks it.
+pub(crate) async fn rename(session: &Arc<SshSession>, from: &[u8], to: &[u8]) -> AppResult<()> {
+    let rename = async {
+        let mut raw = RawChannel::open(session).await?;
+        let mut payload = string_payload(POSIX_RENAME.as_bytes());
+        put_string(&mut payload, from);
+        put_string(&mut payload, to);
+        let (reply, body) = raw.request(SSH_FXP_EXTENDED, &payload).await?;
+        let unsupported = reply == SSH_FXP_STATUS
+            && Reader(&body)
+                .u32()
+                .is_ok_and(|code| code == SSH_FX_OP_UNSUPPORTED);
+        if !unsupported {
+            return status(reply, &body, "rename").map(|_| ());
+        }
+        let mut payload = string_payload(from);
+        put_string(&mut payload, to);
+        raw.request_status(SSH_FXP_RENAME, &payload, "rename").await
+    };
+    session
+        .timed(timeouts::metadata(), "raw rename", rename)
+        .await
+}
+
+/// Delete the file, or the empty directory, at `path`.
+pub(crate) async fn remove(session: &Arc<SshSession>, path: &[u8], is_dir: bool) -> AppResult<()> {
+    let (kind, what) = if is_dir {
+        (SSH_FXP_RMDIR, "delete directory")
+    } else {
+        (SSH_FXP_REMOVE, "delete file")
+    };
+    let remove = async {
+        let mut raw = RawChannel::open(session).await?;
+        raw.request_status(kind, &string_payload(path), what).await
+    };
+    session
+        .timed(timeouts::metadata(), "raw delete", remove)
+        .await
+}
+
+/// Copy the file at `path` from `offset` on into `out`, calling
+/// `on_progress` with `offset` plus the bytes written. Returns that total.
+pub(crate) async fn read_file<W, F>(
+    session: &Arc<SshSession>,
+    path: &[u8],
+    offset: u64,
+    out: &mut W,
+    on_progress: F,
+) -> AppResult<u64>
+where
+    W: AsyncWrite + Unpin,
+    F: Fn(u64),
+{
+    let open = async {
+        let mut raw = RawChannel::open(session).await?;
+        let mut payload = string_payload(path);
+        payload.extend_from_slice(&SSH_FXF_READ.to_be_bytes());
+        payload.extend_from_slice(&0u32.to_be_bytes());
+        let handle = raw.handle(SSH_FXP_OPEN, &payload, "open file").await?;
+        Ok::<_, AppError>((raw, handle))
+    };
+    let (mut raw, handle) = session
+        .timed(timeouts::metadata(), "raw open", open)
+        .await?;
+
+    let mut total = offset;
+    let copied = loop {
+        let mut payload = string_payload(&handle);
+        payload.extend_from_slice(&total.to_be_bytes());
+        payload.extend_from_slice(&READ_CHUNK_BYTES.to_be_bytes());
+        let read = raw.request(SSH_FXP_READ, &payload);
+        let (reply, body) = match session.timed(timeouts::transfer(), "raw read", read).await {
+            Ok(reply) => reply,
+            Err(e) => break Err(e),
+        };
+        if reply != SSH_FXP_DATA {
+            break status(reply, &body, "download file").map(|_| ());
+        }
+        let data = match Reader(&body).string() {
+            Ok(data) => data,
+            Err(e) => break Err(e),
+        };
+        if let Err(e) = out.write_all(data).await {
+            break Err(AppError::Io(format!("Failed to write local file: {e}")));
+        }
+        total += data.len() as u64;
+        on_progress(total);
+    };
+    raw.close(&handle).await;
+    copied?;
+    out.flush()
+        .await
+        .map_err(|e| AppError::Io(format!("Failed to write local file: {e}")))?;
+    Ok(total)
+}
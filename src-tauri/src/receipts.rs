@@ -0,0 +1,243 @@
+//! Upload integrity receipts.
+//!
+//! After an upload the remote file is stat'ed and, where `sha256sum` can be
+//! run over exec, hashed. The result is stored as a receipt in an
+//! append-only history and signed with an HMAC key that never leaves this
+//! device, so a receipt can later be shown to be unaltered.
+
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use crate::errors::{AppError, AppResult};
+use crate::key_store;
+use crate::sftp_ops;
+use crate::ssh_manager::{shell_quote, SshSession, BULK_EXEC_TIMEOUT};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Receipts kept in memory and on disk; older ones are dropped first.
+const MAX_RECEIPTS: usize = 10_000;
+
+// ─── Data Structures ───────────────────────────────────────────────────
+
+/// What was delivered to the server for one upload (safe to send to JS).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UploadReceipt {
+    pub id: String,
+    pub host: String,
+    pub user: String,
+    pub remote_path: String,
+    /// Size reported by the server after the upload.
+    pub size: Option<u64>,
+    pub mtime: Option<u32>,
+    /// Hex SHA-256 computed on the server; `None` when exec is unavailable.
+    pub sha256: Option<String>,
+    /// RFC 3339 timestamp.
+    pub at: String,
+    /// Hex HMAC-SHA256 over the fields above, keyed per device.
+    pub signature: String,
+}
+
+/// Receipt history backed by a JSON-lines file.
+pub struct ReceiptStore {
+    path: PathBuf,
+    key: Vec<u8>,
+    receipts: Mutex<Vec<UploadReceipt>>,
+}
+
+// ─── Signing ───────────────────────────────────────────────────────────
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn from_hex(text: &str) -> Option<Vec<u8>> {
+    if text.len() % 2 != 0 {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// MAC over every field except the signature, one per line.
+fn mac_for(key: &[u8], r: &UploadReceipt) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts any key length");
+    let fields = [
+        r.id.clone(),
+        r.host.clone(),
+        r.user.clone(),
+        r.remote_path.clone(),
+        r.size.map(|s| s.to_string()).unwrap_or_default(),
+        r.mtime.map(|m| m.to_string()).unwrap_or_default(),
+        r.sha256.clone().unwrap_or_default(),
+        r.at.clone(),
+    ];
+    for field in &fields {
+        mac.update(field.as_bytes());
+        mac.update(b"\n");
+    }
+    mac
+}
+
+/// Load the device signing key from `path`, creating it on first use.
+fn load_or_create_key(path: &PathBuf) -> AppResult<Vec<u8>> {
+    use russh::keys::ssh_key::rand_core::{OsRng, RngCore};
+
+    if let Some(key) = std::fs::read_to_string(path)
+        .ok()
+        .and_then(|t| from_hex(t.trim()))
+    {
+        if !key.is_empty() {
+            return Ok(key);
+        }
+    }
+    let mut key = vec![0u8; 32];
+    OsRng.fill_bytes(&mut key);
+    // Start from a fresh file so an unreadable leftover can't keep looser
+    // permissions than the owner-only ones given at creation.
+    std::fs::remove_file(path).ok();
+    key_store::write_private_file(path, to_hex(&key).as_bytes())
+        .map_err(|e| AppError::Io(format!("Failed to write receipt key: {e}")))?;
+    Ok(key)
+}
+
+// ─── Store ─────────────────────────────────────────────────────────────
+
+impl ReceiptStore {
+    /// Open the history at `path`, signing with the key in `key_path`.
+    pub fn new(path: PathBuf, key_path: PathBuf) -> AppResult<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).ok();
+        }
+        let key = load_or_create_key(&key_path)?;
+        let receipts = std::fs::read_to_string(&path)
+            .map(|text| {
+                text.lines()
+                    .filter_map(|l| serde_json::from_str(l).ok())
+                    .collect()
+            })
+            .unwrap_or_default();
+        Ok(Self {
+            path,
+            key,
+            receipts: Mutex::new(receipts),
+        })
+    }
+
+    /// Sign `receipt` and append it to the history.
+    fn record(&self, mut receipt: UploadReceipt) -> AppResult<UploadReceipt> {
+        use std::io::Write;
+
+        receipt.signature = to_hex(&mac_for(&self.key, &receipt).finalize().into_bytes());
+        let line = serde_json::to_string(&receipt)
+            .map_err(|e| AppError::Other(format!("Failed to encode receipt: {e}")))?;
+
+        let mut receipts = self.receipts.lock().unwrap();
+        receipts.push(receipt.clone());
+        if receipts.len() > MAX_RECEIPTS {
+            let excess = receipts.len() - MAX_RECEIPTS;
+            receipts.drain(..excess);
+            // Rewrite the file so it stays bounded too.
+            let text: String = receipts
+                .iter()
+                .filter_map(|r| serde_json::to_string(r).ok())
+                .map(|l| l + "\n")
+                .collect();
+            std::fs::write(&self.path, text)
+        } else {
+            std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)
+                .and_then(|mut f| writeln!(f, "{line}"))
+        }
+        .map_err(|e| AppError::Io(format!("Failed to write receipt: {e}")))?;
+        Ok(receipt)
+    }
+
+    /// Most recent receipts first, optionally only those for `host`.
+    pub fn list(&self, host: Option<&str>, limit: usize) -> Vec<UploadReceipt> {
+        self.receipts
+            .lock()
+            .unwrap()
+            .iter()
+            .rev()
+            .filter(|r| host.is_none_or(|h| r.host == h))
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+
+    /// Whether `receipt` was signed by this device and is unaltered.
+    pub fn verify(&self, receipt: &UploadReceipt) -> bool {
+        from_hex(&receipt.signature)
+            .is_some_and(|sig| mac_for(&self.key, receipt).verify_slice(&sig).is_ok())
+    }
+
+    /// Stat and hash each uploaded path, recording a receipt for each.
+    /// Failures are logged and skipped; they never fail the upload itself.
+    pub async fn issue(&self, session: &Arc<SshSession>, paths: &[String]) -> Vec<UploadReceipt> {
+        let start = std::time::Instant::now();
+        let mut issued = Vec::with_capacity(paths.len());
+        for path in paths {
+            match receipt_for(session, path)
+                .await
+                .and_then(|r| self.record(r))
+            {
+                Ok(r) => issued.push(r),
+                Err(e) => log::warn!("[SFTP] no receipt for \"{}\": {}", path, e),
+            }
+        }
+        log::info!(
+            "[PERF] issue receipts — {:.2}ms | files: {} | issued: {}",
+            start.elapsed().as_secs_f64() * 1000.0,
+            paths.len(),
+            issued.len(),
+        );
+        issued
+    }
+}
+
+// ─── Remote Facts ──────────────────────────────────────────────────────
+
+/// Hash `path` on the server with `sha256sum`, if it can be run.
+pub(crate) async fn remote_sha256(session: &Arc<SshSession>, path: &str) -> Option<String> {
+    let output = session
+        .exec_within(
+            &format!("sha256sum -b -- {}", shell_quote(path)),
+            BULK_EXEC_TIMEOUT,
+        )
+        .await
+        .ok()?;
+    if output.exit_status != Some(0) {
+        return None;
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let hash = stdout.split_whitespace().next()?;
+    (hash.len() == 64 && hash.chars().all(|c| c.is_ascii_hexdigit()))
+        .then(|| hash.to_ascii_lowercase())
+}
+
+/// Unsigned receipt for `path` as it is on the server now.
+async fn receipt_for(session: &Arc<SshSession>, path: &str) -> AppResult<UploadReceipt> {
+    let st = sftp_ops::stat(session, path).await?;
+    if st.is_dir {
+        return Err(AppError::Other(format!("{path} is a directory")));
+    }
+    Ok(UploadReceipt {
+        id: uuid::Uuid::new_v4().to_string(),
+        host: session.host.clone(),
+        user: session.user.clone(),
+        remote_path: path.to_string(),
+        size: st.size,
+        mtime: st.mtime,
+        sha256: remote_sha256(session, path).await,
+        at: chrono::Utc::now().to_rfc3339(),
+        signature: String::new(),
+    })
+}
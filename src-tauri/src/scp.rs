@@ -0,0 +1,317 @@
+//! SCP / shell fallback for servers that disable the SFTP subsystem.
+//!
+//! Transfers speak the classic `scp -f` / `scp -t` protocol over an exec
+//! channel; directory listings are parsed from `ls -lan` output.
+
+use std::sync::Arc;
+use tokio::io::{
+    AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader,
+};
+
+use crate::errors::{AppError, AppResult};
+use crate::raw_sftp;
+use crate::sftp_ops::{self, FileEntry};
+use crate::ssh_manager::{shell_quote, SshSession};
+use crate::timeouts;
+
+/// Bytes requested per read while receiving a file.
+const READ_CHUNK_SIZE: usize = 256 * 1024;
+
+/// Read a single SCP acknowledgement byte. `0` is OK; `1`/`2` are followed
+/// by an error message line.
+async fn read_ack<R>(reader: &mut R) -> AppResult<()>
+where
+    R: AsyncBufRead + Unpin,
+{
+    let code = reader
+        .read_u8()
+        .await
+        .map_err(|e| AppError::Sftp(format!("SCP: failed to read response: {e}")))?;
+    if code == 0 {
+        return Ok(());
+    }
+    let mut msg = String::new();
+    reader.read_line(&mut msg).await.ok();
+    Err(AppError::Sftp(format!("SCP: {}", msg.trim())))
+}
+
+/// Download a remote file into memory using `scp -f`; see [`download_to`].
+pub async fn download(session: &Arc<SshSession>, path: &str) -> AppResult<Vec<u8>> {
+    let mut data = Vec::new();
+    download_to(session, path, &mut data, |_| {}).await?;
+    Ok(data)
+}
+
+/// Download a remote file using `scp -f`, writing it to `out` chunk by
+/// chunk and calling `on_progress` with the bytes written so far. Each read
+/// is limited by [`timeouts::transfer`], so a large file is fine while data
+/// flows. Returns the size received.
+pub async fn download_to<W, F>(
+    session: &Arc<SshSession>,
+    path: &str,
+    out: &mut W,
+    on_progress: F,
+) -> AppResult<u64>
+where
+    W: AsyncWrite + Unpin,
+    F: Fn(u64),
+{
+    let start = std::time::Instant::now();
+    let _slot = session.channel_slot().await?;
+    let channel = session
+        .open_exec_channel(&format!("scp -f {}", raw_sftp::shell_word(path)?))
+        .await?;
+    let stream = channel.into_stream();
+    let (reader, mut writer) = tokio::io::split(stream);
+    let mut reader = BufReader::new(reader);
+
+    let io_err = |e: std::io::Error| AppError::Sftp(format!("SCP transfer failed: {e}"));
+    let write_err = |e: std::io::Error| AppError::Io(format!("Failed to write local file: {e}"));
+    let limit = timeouts::transfer();
+
+    writer.write_all(&[0]).await.map_err(io_err)?;
+
+    // Header: "C<mode> <size> <name>\n", possibly preceded by "T..." timestamps.
+    let mut header = String::new();
+    loop {
+        header.clear();
+        let read_header = async {
+            let first = reader.read_u8().await.map_err(io_err)?;
+            reader.read_line(&mut header).await.map_err(io_err)?;
+            Ok(first)
+        };
+        let first = session.timed(limit, "scp header", read_header).await?;
+        match first {
+            1 | 2 => return Err(AppError::Sftp(format!("SCP: {}", header.trim()))),
+            b'C' => break,
+            b'T' => writer.write_all(&[0]).await.map_err(io_err)?,
+            b'D' => return Err(AppError::Sftp("SCP: path is a directory".into())),
+            _ => return Err(AppError::Sftp(format!("SCP: unexpected header {header:?}"))),
+        }
+    }
+
+    // Only bounds the reads; nothing is sized from it up front.
+    let size: u64 = header
+        .split_whitespace()
+        .nth(1)
+        .and_then(|s| s.parse().ok())
+        .ok_or_else(|| AppError::Sftp(format!("SCP: malformed header {header:?}")))?;
+
+    writer.write_all(&[0]).await.map_err(io_err)?;
+
+    let mut buf = vec![0u8; READ_CHUNK_SIZE];
+    let mut received = 0u64;
+    while received < size {
+        let want = (size - received).min(buf.len() as u64) as usize;
+        let read = async { reader.read(&mut buf[..want]).await.map_err(io_err) };
+        let n = session.timed(limit, "download", read).await?;
+        if n == 0 {
+            return Err(AppError::Sftp("SCP: connection closed mid-transfer".into()));
+        }
+        out.write_all(&buf[..n]).await.map_err(write_err)?;
+        received += n as u64;
+        on_progress(received);
+    }
+    out.flush().await.map_err(write_err)?;
+
+    session
+        .timed(limit, "scp ack", read_ack(&mut reader))
+        .await?;
+    writer.write_all(&[0]).await.map_err(io_err)?;
+    writer.shutdown().await.ok();
+
+    log::info!(
+        "[PERF] scp download \"{}\" — {:.2}ms | size: {} bytes",
+        raw_sftp::lossy_path(path),
+        start.elapsed().as_secs_f64() * 1000.0,
+        received,
+    );
+    Ok(received)
+}
+
+/// Upload file data to a remote path using `scp -t`. The `mode` is sent in
+/// the `C` header; the remote umask may still apply when creating new files.
+pub async fn upload(
+    session: &Arc<SshSession>,
+    remote_path: &str,
+    data: &[u8],
+    mode: u32,
+) -> AppResult<()> {
+    let start = std::time::Instant::now();
+    let _slot = session.channel_slot().await?;
+    let channel = session
+        .open_exec_channel(&format!("scp -t {}", shell_quote(remote_path)))
+        .await?;
+    let stream = channel.into_stream();
+    let (reader, mut writer) = tokio::io::split(stream);
+    let mut reader = BufReader::new(reader);
+
+    let io_err = |e: std::io::Error| AppError::Sftp(format!("SCP transfer failed: {e}"));
+
+    let name = remote_path.rsplit('/').next().unwrap_or(remote_path);
+
+    read_ack(&mut reader).await?;
+    writer
+        .write_all(format!("C{:04o} {} {}\n", mode & 0o7777, data.len(), name).as_bytes())
+        .await
+        .map_err(io_err)?;
+    read_ack(&mut reader).await?;
+    writer.write_all(data).await.map_err(io_err)?;
+    writer.write_all(&[0]).await.map_err(io_err)?;
+    read_ack(&mut reader).await?;
+    writer.shutdown().await.ok();
+
+    log::info!(
+        "[PERF] scp upload \"{}\" — {:.2}ms | size: {} bytes",
+        remote_path,
+        start.elapsed().as_secs_f64() * 1000.0,
+        data.len(),
+    );
+    Ok(())
+}
+
+/// Split off the first `n` whitespace-separated fields of `line`, returning
+/// them along with the (untrimmed-inside) remainder.
+fn split_fields(line: &str, n: usize) -> Option<(Vec<&str>, &str)> {
+    let mut fields = Vec::with_capacity(n);
+    let mut rest = line;
+    for _ in 0..n {
+        rest = rest.trim_start();
+        let end = rest.find(char::is_whitespace)?;
+        fields.push(&rest[..end]);
+        rest = &rest[end..];
+    }
+    Some((fields, rest.strip_prefix(' ').unwrap_or(rest)))
+}
+
+/// Permission bits from an `ls -l` mode string such as `drwxr-sr-t`.
+fn parse_mode(perms: &str) -> Option<u32> {
+    let bits = perms.as_bytes().get(1..10)?;
+    let mut mode = 0;
+    for (i, &c) in bits.iter().enumerate() {
+        let bit = 1 << (8 - i);
+        match c {
+            b'r' | b'w' | b'x' => mode |= bit,
+            b'-' => {}
+            // setuid/setgid/sticky, lower-case when execute is also set.
+            b's' | b'S' | b't' | b'T' => {
+                if c.is_ascii_lowercase() {
+                    mode |= bit;
+                }
+                mode |= 0o4000 >> (i / 3);
+            }
+            _ => return None,
+        }
+    }
+    Some(mode)
+}
+
+/// Plain `ls -l` month abbreviations, in order (`LC_ALL=C`).
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// Epoch seconds from a plain `ls -l` date: `Mon DD HH:MM` for recent
+/// files (this year, or last if that date is still ahead), `Mon DD YYYY`
+/// otherwise. Read as UTC, as the server's zone is unknown.
+fn parse_ls_date(month: &str, day: &str, time_or_year: &str) -> Option<u32> {
+    use chrono::Datelike;
+
+    let month = MONTHS.iter().position(|&m| m == month)? as u32 + 1;
+    let day: u32 = day.parse().ok()?;
+    let now = chrono::Utc::now();
+    let (year, hour, minute) = match time_or_year.split_once(':') {
+        Some((h, m)) => {
+            let (h, m) = (h.parse().ok()?, m.parse().ok()?);
+            let this_year = chrono::NaiveDate::from_ymd_opt(now.year(), month, day)?;
+            // A day of slack for servers ahead of this clock.
+            if this_year > now.date_naive() + chrono::Days::new(1) {
+                (now.year() - 1, h, m)
+            } else {
+                (now.year(), h, m)
+            }
+        }
+        None => (time_or_year.parse().ok()?, 0, 0),
+    };
+    let at = chrono::NaiveDate::from_ymd_opt(year, month, day)?.and_hms_opt(hour, minute, 0)?;
+    u32::try_from(at.and_utc().timestamp()).ok()
+}
+
+/// Whether `ls` failed because it does not know an option, as BusyBox and
+/// BSD `ls` do with `--time-style`.
+fn unknown_option(stderr: &[u8]) -> bool {
+    String::from_utf8_lossy(stderr).contains("option")
+}
+
+/// List a directory by parsing `ls -lan` output: with epoch timestamps from
+/// GNU `--time-style`, or the plain date columns where `ls` lacks it.
+pub async fn list_dir(session: &Arc<SshSession>, path: &str) -> AppResult<Vec<FileEntry>> {
+    let dir = shell_quote(path);
+    let mut epoch = true;
+    let mut output = session
+        .exec(&format!("LC_ALL=C ls -lan --time-style=+%s -- {dir}"))
+        .await?;
+    if output.exit_status.unwrap_or(0) != 0 && unknown_option(&output.stderr) {
+        log::info!("[SSH] ls has no --time-style — parsing plain ls -lan");
+        epoch = false;
+        output = session.exec(&format!("LC_ALL=C ls -lan -- {dir}")).await?;
+    }
+    if output.exit_status.unwrap_or(0) != 0 {
+        return Err(AppError::Sftp(format!(
+            "Failed to read directory: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    // perms, links, uid, gid, size, then the epoch or month, day and
+    // time-or-year.
+    let date_fields = if epoch { 1 } else { 3 };
+
+    let mut files = Vec::new();
+    for raw_line in output.stdout.split(|&b| b == b'\n') {
+        // Decode per line so one badly-encoded name doesn't affect the rest.
+        let line = String::from_utf8_lossy(raw_line);
+        let Some((fields, name)) = split_fields(&line, 5 + date_fields) else {
+            continue;
+        };
+        // The fields before the name are ASCII, so it starts at the same
+        // offset in the raw bytes; keep those when decoding was lossy.
+        let raw_name = std::str::from_utf8(raw_line).is_err().then(|| {
+            let raw = &raw_line[line.len() - name.len()..];
+            let end = if fields[0].starts_with('l') {
+                raw.windows(4)
+                    .position(|w| w == b" -> ")
+                    .unwrap_or(raw.len())
+            } else {
+                raw.len()
+            };
+            &raw[..end]
+        });
+        let perms = fields[0];
+        let is_dir = perms.starts_with('d');
+        let is_symlink = perms.starts_with('l');
+        let (name, target) = match name.split_once(" -> ") {
+            Some((name, target)) if is_symlink => (name, Some(target)),
+            _ => (name, None),
+        };
+        if name.is_empty() || name == "." || name == ".." {
+            continue;
+        }
+        let size = fields[4].parse().unwrap_or(0);
+        let mtime = if epoch {
+            fields[5].parse().ok()
+        } else {
+            parse_ls_date(fields[5], fields[6], fields[7])
+        };
+        let mut entry = sftp_ops::make_entry(path, name, is_dir, size, mtime);
+        entry.is_symlink = is_symlink;
+        entry.link_target = target.map(str::to_string);
+        entry.mode = parse_mode(perms);
+        entry.uid = fields[2].parse().ok();
+        entry.gid = fields[3].parse().ok();
+        if let Some(raw_name) = raw_name {
+            raw_sftp::mark_raw(&mut entry, path, raw_name);
+        }
+        files.push(entry);
+    }
+    Ok(files)
+}
@@ -0,0 +1,174 @@
+//! Remote file search by name. Walks the tree below a root with the usual
+//! concurrent [`sftp_ops::walk`], bounded in depth and entries, and streams
+//! each match to the caller as soon as its directory is listed.
+
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::errors::{AppError, AppResult};
+use crate::ignore::{self, IgnoreRules};
+use crate::sftp_ops::{self, FileEntry, WalkControl, WalkErrorPolicy, WalkOptions};
+use crate::ssh_manager::SshSession;
+
+/// Default deepest level searched; children of the root are depth 1.
+const DEFAULT_MAX_DEPTH: usize = 16;
+/// Default number of matches before the search stops.
+const DEFAULT_MAX_RESULTS: usize = 1000;
+/// Stop after visiting this many entries so huge trees stay responsive.
+const MAX_SEARCH_ENTRIES: usize = 500_000;
+
+// ─── Data Structures ───────────────────────────────────────────────────
+
+/// How the search pattern is read.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PatternKind {
+    /// Shell-style: `*` and `?` wildcards, matched against the whole name.
+    #[default]
+    Glob,
+    /// Regular expression, matching anywhere in the name unless anchored.
+    Regex,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct SearchOptions {
+    pub kind: PatternKind,
+    pub case_sensitive: bool,
+    /// `None` uses the default depth.
+    pub max_depth: Option<usize>,
+    /// `None` uses the default result limit.
+    pub max_results: Option<usize>,
+    /// Match directory names too, not just files.
+    pub include_dirs: bool,
+    /// Descend into dot-directories (`.git`, `.cache`…).
+    pub include_hidden: bool,
+    /// Search entries matching the ignore patterns too.
+    pub include_ignored: bool,
+}
+
+/// A match, streamed as it is found (safe to send to JS).
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchMatch {
+    pub entry: FileEntry,
+    /// Path relative to the search root.
+    pub rel: String,
+    pub depth: usize,
+}
+
+/// How a finished search went (safe to send to JS).
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchSummary {
+    pub root: String,
+    pub matches: usize,
+    pub scanned: usize,
+    pub dirs_listed: usize,
+    /// Directories that could not be listed.
+    pub unreadable: usize,
+    /// True if the result or entry limit ended the search early.
+    pub truncated: bool,
+}
+
+// ─── Matching ──────────────────────────────────────────────────────────
+
+/// Regex for a glob: `*` is any run of characters, `?` any one.
+pub(crate) fn glob_to_regex(glob: &str) -> String {
+    let mut out = String::with_capacity(glob.len() + 8);
+    out.push('^');
+    for c in glob.chars() {
+        match c {
+            '*' => out.push_str(".*"),
+            '?' => out.push('.'),
+            c => out.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    out.push('$');
+    out
+}
+
+fn compile(pattern: &str, opts: &SearchOptions) -> AppResult<Regex> {
+    let source = match opts.kind {
+        PatternKind::Glob => glob_to_regex(pattern),
+        PatternKind::Regex => pattern.to_string(),
+    };
+    RegexBuilder::new(&source)
+        .case_insensitive(!opts.case_sensitive)
+        .build()
+        .map_err(|e| AppError::Other(format!("Invalid search pattern: {e}")))
+}
+
+// ─── Search ────────────────────────────────────────────────────────────
+
+/// Search below `root` for names matching `pattern`, calling `on_match`
+/// for each hit. Dropping the future (e.g. under
+/// `OpTracker::run_transfer`) cancels the listings still in flight.
+pub async fn search<F>(
+    session: &Arc<SshSession>,
+    root: &str,
+    pattern: &str,
+    opts: &SearchOptions,
+    mut on_match: F,
+) -> AppResult<SearchSummary>
+where
+    F: FnMut(SearchMatch),
+{
+    let start = std::time::Instant::now();
+    let matcher = compile(pattern, opts)?;
+    let max_results = opts.max_results.unwrap_or(DEFAULT_MAX_RESULTS).max(1);
+    let walk_opts = WalkOptions {
+        max_depth: Some(opts.max_depth.unwrap_or(DEFAULT_MAX_DEPTH)),
+        on_error: WalkErrorPolicy::Skip,
+        ignore: if opts.include_ignored {
+            IgnoreRules::default()
+        } else {
+            ignore::rules_for(&session.host)
+        },
+        ..Default::default()
+    };
+
+    let mut matches = 0usize;
+    let mut scanned = 0usize;
+    let walk = sftp_ops::walk(session, root, &walk_opts, |item| {
+        scanned += 1;
+        if scanned > MAX_SEARCH_ENTRIES {
+            return WalkControl::Stop;
+        }
+        let is_dir = item.entry.is_dir;
+        let skip = is_dir && item.entry.name.starts_with('.') && !opts.include_hidden;
+        if (!is_dir || opts.include_dirs) && matcher.is_match(&item.entry.name) {
+            matches += 1;
+            on_match(SearchMatch {
+                entry: item.entry,
+                rel: item.rel,
+                depth: item.depth,
+            });
+            if matches >= max_results {
+                return WalkControl::Stop;
+            }
+        }
+        if skip {
+            WalkControl::SkipDir
+        } else {
+            WalkControl::Continue
+        }
+    })
+    .await?;
+
+    log::info!(
+        "[PERF] search \"{}\" in \"{}\" — {:.2}ms | scanned: {} | matches: {}",
+        pattern,
+        root,
+        start.elapsed().as_secs_f64() * 1000.0,
+        scanned.min(MAX_SEARCH_ENTRIES),
+        matches,
+    );
+    Ok(SearchSummary {
+        root: root.to_string(),
+        matches,
+        scanned: scanned.min(MAX_SEARCH_ENTRIES),
+        dirs_listed: walk.dirs_listed,
+        unreadable: walk.errors.len(),
+        truncated: walk.stopped,
+    })
+}
@@ -0,0 +1,74 @@
+//! Locating search terms in preview text, so a preview opened from a search
+//! result can scroll to and highlight each hit without rescanning in JS.
+
+use serde::{Deserialize, Serialize};
+
+/// Stop after this many hits; a preview is at most a few hundred KB.
+const MAX_MATCHES: usize = 1000;
+
+/// One occurrence of the query (safe to send to JS).
+///
+/// Offsets are UTF-16 code units into the preview content, matching JS
+/// string indexing.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PreviewMatch {
+    /// 1-based line number.
+    pub line: usize,
+    /// Offset of the hit within its line.
+    pub column: usize,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Fold one char for case-insensitive comparison, keeping a 1:1 mapping so
+/// offsets stay valid.
+fn fold(c: char, case_sensitive: bool) -> char {
+    if case_sensitive {
+        c
+    } else {
+        c.to_lowercase().next().unwrap_or(c)
+    }
+}
+
+/// Find non-overlapping occurrences of `query` in `content`, line by line.
+pub fn find_matches(content: &str, query: &str, case_sensitive: bool) -> Vec<PreviewMatch> {
+    let needle: Vec<char> = query.chars().map(|c| fold(c, case_sensitive)).collect();
+    let mut matches = Vec::new();
+    if needle.is_empty() {
+        return matches;
+    }
+
+    let mut line_start = 0;
+    for (i, line) in content.split('\n').enumerate() {
+        let chars: Vec<char> = line.chars().map(|c| fold(c, case_sensitive)).collect();
+        // UTF-16 offset of each char within the line.
+        let mut offsets = Vec::with_capacity(chars.len() + 1);
+        let mut offset = 0;
+        for c in line.chars() {
+            offsets.push(offset);
+            offset += c.len_utf16();
+        }
+        offsets.push(offset);
+
+        let mut at = 0;
+        while at + needle.len() <= chars.len() {
+            if chars[at..at + needle.len()] != needle[..] {
+                at += 1;
+                continue;
+            }
+            matches.push(PreviewMatch {
+                line: i + 1,
+                column: offsets[at],
+                start: line_start + offsets[at],
+                end: line_start + offsets[at + needle.len()],
+            });
+            if matches.len() == MAX_MATCHES {
+                return matches;
+            }
+            at += needle.len();
+        }
+        // +1 for the '\n' separator.
+        line_start += offset + 1;
+    }
+    matches
+}
@@ -0,0 +1,214 @@
+//! systemd service listing and control over exec, optionally through sudo,
+//! so a service can be restarted right after editing its config.
+
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::errors::{AppError, AppResult};
+use crate::ssh_manager::{shell_quote, ExecOutput, SshSession};
+
+// ─── Data Structures ───────────────────────────────────────────────────
+
+/// One row of `systemctl list-units` (safe to send to JS).
+#[derive(Debug, Clone, Serialize)]
+pub struct ServiceUnit {
+    pub unit: String,
+    /// e.g. "loaded", "not-found".
+    pub load: String,
+    /// e.g. "active", "inactive", "failed".
+    pub active: String,
+    /// e.g. "running", "exited", "dead".
+    pub sub: String,
+    pub description: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ServiceAction {
+    Start,
+    Stop,
+    Restart,
+    Reload,
+    Enable,
+    Disable,
+    Status,
+}
+
+impl ServiceAction {
+    fn verb(self) -> &'static str {
+        match self {
+            Self::Start => "start",
+            Self::Stop => "stop",
+            Self::Restart => "restart",
+            Self::Reload => "reload",
+            Self::Enable => "enable",
+            Self::Disable => "disable",
+            Self::Status => "status",
+        }
+    }
+}
+
+/// How to elevate a `systemctl` call.
+#[derive(Debug, Clone, Default)]
+pub struct Sudo {
+    pub enabled: bool,
+    /// Sent on stdin; without it sudo must not need a password.
+    pub password: Option<String>,
+}
+
+/// Outcome of a service action (safe to send to JS).
+#[derive(Debug, Clone, Serialize)]
+pub struct ServiceActionResult {
+    pub unit: String,
+    pub action: ServiceAction,
+    pub exit_status: Option<u32>,
+    /// Combined stdout and stderr.
+    pub output: String,
+    /// State after the action, when the unit is listed.
+    pub state: Option<ServiceUnit>,
+}
+
+// ─── Parsing ───────────────────────────────────────────────────────────
+
+/// Split the next whitespace-delimited column off `rest`.
+fn next_field(rest: &mut &str) -> Option<String> {
+    let text = rest.trim_start();
+    let end = text.find(char::is_whitespace).unwrap_or(text.len());
+    let (field, tail) = text.split_at(end);
+    *rest = tail;
+    (!field.is_empty()).then(|| field.to_string())
+}
+
+/// Parse `systemctl list-units --plain --no-legend` output. Columns are
+/// space-padded; the description is everything after the fourth.
+fn parse_units(text: &str) -> Vec<ServiceUnit> {
+    text.lines()
+        .filter_map(|line| {
+            // Failed units may still carry a status bullet.
+            let mut rest = line.trim().trim_start_matches(['●', '*']);
+            let unit = next_field(&mut rest)?;
+            let load = next_field(&mut rest)?;
+            let active = next_field(&mut rest)?;
+            let sub = next_field(&mut rest)?;
+            let description = rest.trim().to_string();
+            Some(ServiceUnit {
+                unit,
+                load,
+                active,
+                sub,
+                description,
+            })
+        })
+        .collect()
+}
+
+/// Unit names are passed to the shell; allow only systemd's character set.
+fn validate_unit(unit: &str) -> AppResult<()> {
+    let ok = !unit.is_empty()
+        && !unit.starts_with('-')
+        && unit
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || ":_.@-\\".contains(c));
+    if ok {
+        Ok(())
+    } else {
+        Err(AppError::Other(format!("Invalid unit name: {unit}")))
+    }
+}
+
+// ─── Operations ────────────────────────────────────────────────────────
+
+/// Run `systemctl <args>`, through sudo when requested.
+async fn systemctl(session: &Arc<SshSession>, args: &str, sudo: &Sudo) -> AppResult<ExecOutput> {
+    if !sudo.enabled {
+        return session.exec(&format!("systemctl {args}")).await;
+    }
+    let output = match &sudo.password {
+        Some(pw) => {
+            let command = format!("sudo -S -p '' -- systemctl {args}");
+            session
+                .exec_with_input(&command, format!("{pw}\n").as_bytes())
+                .await?
+        }
+        None => {
+            session
+                .exec(&format!("sudo -n -- systemctl {args}"))
+                .await?
+        }
+    };
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if output.exit_status != Some(0)
+        && (stderr.contains("a password is required") || stderr.contains("incorrect password"))
+    {
+        return Err(AppError::Ssh(format!("sudo refused: {}", stderr.trim())));
+    }
+    Ok(output)
+}
+
+/// List all service units with their load and activity state.
+pub async fn list_services(session: &Arc<SshSession>) -> AppResult<Vec<ServiceUnit>> {
+    let start = std::time::Instant::now();
+    let output = systemctl(
+        session,
+        "list-units --type=service --all --plain --no-legend --no-pager",
+        &Sudo::default(),
+    )
+    .await?;
+    if output.exit_status != Some(0) {
+        return Err(AppError::Ssh(format!(
+            "systemctl is unavailable: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    let units = parse_units(&String::from_utf8_lossy(&output.stdout));
+    log::info!(
+        "[PERF] list_services — {:.2}ms | units: {}",
+        start.elapsed().as_secs_f64() * 1000.0,
+        units.len(),
+    );
+    Ok(units)
+}
+
+/// Apply `action` to `unit` and report its state afterwards. A non-zero
+/// exit is reported in the result rather than as an error, since
+/// `status` exits non-zero for stopped units.
+pub async fn service_action(
+    session: &Arc<SshSession>,
+    unit: &str,
+    action: ServiceAction,
+    sudo: &Sudo,
+) -> AppResult<ServiceActionResult> {
+    validate_unit(unit)?;
+    let args = format!("{} --no-pager -- {}", action.verb(), shell_quote(unit));
+    let output = systemctl(session, &args, sudo).await?;
+    log::info!(
+        "[SSH] systemctl {} {} — exit: {:?} (sudo={})",
+        action.verb(),
+        unit,
+        output.exit_status,
+        sudo.enabled,
+    );
+
+    let state_args = format!(
+        "list-units --all --plain --no-legend --no-pager -- {}",
+        shell_quote(unit)
+    );
+    let state = systemctl(session, &state_args, &Sudo::default())
+        .await
+        .ok()
+        .and_then(|out| {
+            parse_units(&String::from_utf8_lossy(&out.stdout))
+                .into_iter()
+                .next()
+        });
+
+    let mut text = String::from_utf8_lossy(&output.stdout).to_string();
+    text.push_str(&String::from_utf8_lossy(&output.stderr));
+    Ok(ServiceActionResult {
+        unit: unit.to_string(),
+        action,
+        exit_status: output.exit_status,
+        output: text,
+        state,
+    })
+}
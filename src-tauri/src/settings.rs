@@ -0,0 +1,472 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tokio::sync::Mutex;
+
+use crate::errors::{AppError, AppResult};
+use crate::file_types::{self, FileCategory};
+use crate::ignore;
+use crate::transfer::RetryPolicy;
+use crate::vault_backend::VaultBackendKind;
+
+/// Default cap on files pulled fully into memory for preview/download.
+pub const DEFAULT_MAX_IN_MEMORY_BYTES: u64 = 50 * 1024 * 1024;
+
+/// Default size above which a recursive download must be confirmed.
+pub const DEFAULT_CONFIRM_DIR_DOWNLOAD_BYTES: u64 = 10 * 1024 * 1024 * 1024;
+
+/// Default size above which full images are served without being cached.
+pub const DEFAULT_IMAGE_CACHE_BYPASS_BYTES: u64 = 50 * 1024 * 1024;
+
+// ─── Data Structures ───────────────────────────────────────────────────
+
+/// Limits for servers (often embedded or NAS devices) that misbehave with
+/// several channels or parallel reads. `None` fields use the defaults.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HostLimits {
+    /// Channels open at once, including the pooled SFTP channel.
+    #[serde(default)]
+    pub max_channels: Option<usize>,
+    /// Files (and directory listings) transferred in parallel.
+    #[serde(default)]
+    pub max_parallel_transfers: Option<usize>,
+    /// Bytes per SFTP read or write request.
+    #[serde(default)]
+    pub chunk_size: Option<usize>,
+}
+
+/// Transfer behaviour on metered (cellular) networks. `None` fields use
+/// the defaults in `network`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NetworkPolicy {
+    /// Hold files of at least `large_file_bytes` until off cellular.
+    #[serde(default)]
+    pub pause_large_on_cellular: Option<bool>,
+    #[serde(default)]
+    pub large_file_bytes: Option<u64>,
+    /// Allow background image prefetch on cellular.
+    #[serde(default)]
+    pub prefetch_on_cellular: Option<bool>,
+    /// Downloads at least this large need confirming on cellular.
+    #[serde(default)]
+    pub confirm_download_bytes: Option<u64>,
+}
+
+/// Keepalives sent on idle connections, like OpenSSH's
+/// `ServerAliveInterval` and `ServerAliveCountMax`, so NAT and firewall
+/// timeouts do not drop them. `None` fields use the defaults.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Keepalive {
+    /// Seconds between keepalive requests; 0 disables them.
+    #[serde(default)]
+    pub interval_secs: Option<u64>,
+    /// Unanswered keepalives before the connection is considered lost.
+    #[serde(default)]
+    pub max_missed: Option<usize>,
+}
+
+impl Keepalive {
+    /// Fields set here, falling back to `other` for the rest.
+    pub fn or(self, other: Keepalive) -> Keepalive {
+        Keepalive {
+            interval_secs: self.interval_secs.or(other.interval_secs),
+            max_missed: self.max_missed.or(other.max_missed),
+        }
+    }
+}
+
+/// User-configurable application settings persisted on disk.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AppSettings {
+    /// Directory downloads are saved to. `None` means the platform default.
+    #[serde(default)]
+    pub download_dir: Option<String>,
+    /// Permission bits applied to uploaded files. `None` keeps the server umask.
+    #[serde(default)]
+    pub upload_file_mode: Option<u32>,
+    /// Permission bits applied to created directories. `None` keeps the server umask.
+    #[serde(default)]
+    pub create_dir_mode: Option<u32>,
+    /// Number of files uploaded concurrently in a batch. `None` uses the default.
+    #[serde(default)]
+    pub upload_concurrency: Option<usize>,
+    /// Largest file previewed or downloaded into memory without `force`.
+    #[serde(default)]
+    pub max_in_memory_bytes: Option<u64>,
+    /// Recursive downloads larger than this need confirming. `None` uses the default.
+    #[serde(default)]
+    pub confirm_dir_download_bytes: Option<u64>,
+    /// Images larger than this are not kept in the image cache unless pinned.
+    /// `None` uses the default.
+    #[serde(default)]
+    pub image_cache_bypass_bytes: Option<u64>,
+    /// Lock the key vault after this many seconds of inactivity. `None` disables.
+    #[serde(default)]
+    pub vault_auto_lock_secs: Option<u64>,
+    /// Where stored keys are kept. `None` uses the JSON vault.
+    #[serde(default)]
+    pub vault_backend: Option<VaultBackendKind>,
+    /// Argon2 verifier of the passphrase that unlocks the JSON vault. The
+    /// vault cannot be locked until one is set.
+    #[serde(default)]
+    pub vault_passphrase_verifier: Option<String>,
+    /// Total tries per file in a transfer, including the first. `None` uses the default.
+    #[serde(default)]
+    pub transfer_retry_attempts: Option<u32>,
+    /// Delay before the first transfer retry, doubled for each subsequent one.
+    #[serde(default)]
+    pub transfer_retry_backoff_ms: Option<u64>,
+    /// Force low-memory mode on or off. `None` detects it from device RAM on mobile.
+    #[serde(default)]
+    pub low_memory_mode: Option<bool>,
+    /// Record a signed integrity receipt (size and SHA-256) after each upload.
+    #[serde(default)]
+    pub upload_receipts: Option<bool>,
+    /// Time limit for stat, listing and other metadata requests. `None` uses the default.
+    #[serde(default)]
+    pub metadata_timeout_secs: Option<u64>,
+    /// Time limit per transfer chunk. `None` uses the default.
+    #[serde(default)]
+    pub transfer_timeout_secs: Option<u64>,
+    /// Per-host limits, keyed by lower-cased host name.
+    #[serde(default)]
+    pub host_limits: HashMap<String, HostLimits>,
+    /// Transfer policy on cellular networks.
+    #[serde(default)]
+    pub network_policy: NetworkPolicy,
+    /// Keepalives for new connections, unless overridden per connection.
+    #[serde(default)]
+    pub keepalive: Keepalive,
+    /// File-type overrides: suffix without the leading dot (`"log.1"`) to category.
+    #[serde(default)]
+    pub file_type_overrides: HashMap<String, FileCategory>,
+    /// Names hidden from listings, searches and directory transfers (`"*.tmp"`).
+    #[serde(default)]
+    pub ignore_patterns: Vec<String>,
+    /// Extra ignore patterns per host, keyed by lower-cased host name.
+    #[serde(default)]
+    pub host_ignore_patterns: HashMap<String, Vec<String>>,
+}
+
+impl AppSettings {
+    /// Effective in-memory size limit.
+    pub fn in_memory_limit(&self) -> u64 {
+        self.max_in_memory_bytes
+            .unwrap_or(DEFAULT_MAX_IN_MEMORY_BYTES)
+    }
+
+    /// Effective confirmation threshold for recursive downloads.
+    pub fn dir_download_threshold(&self) -> u64 {
+        self.confirm_dir_download_bytes
+            .unwrap_or(DEFAULT_CONFIRM_DIR_DOWNLOAD_BYTES)
+    }
+
+    /// Effective size above which full images bypass the image cache.
+    pub fn image_cache_bypass_threshold(&self) -> u64 {
+        self.image_cache_bypass_bytes
+            .unwrap_or(DEFAULT_IMAGE_CACHE_BYPASS_BYTES)
+    }
+
+    /// Limits configured for `host`, or all defaults.
+    pub fn limits_for(&self, host: &str) -> HostLimits {
+        self.host_limits
+            .get(&host.to_ascii_lowercase())
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Effective per-file retry policy for transfers.
+    pub fn retry_policy(&self) -> RetryPolicy {
+        let default = RetryPolicy::default();
+        RetryPolicy {
+            attempts: self.transfer_retry_attempts.unwrap_or(default.attempts),
+            backoff: self
+                .transfer_retry_backoff_ms
+                .map(std::time::Duration::from_millis)
+                .unwrap_or(default.backoff),
+        }
+    }
+}
+
+// ─── Settings Store ────────────────────────────────────────────────────
+
+/// Manages application settings stored as a JSON file.
+pub struct SettingsStore {
+    settings_path: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl SettingsStore {
+    pub fn new(settings_path: PathBuf) -> Self {
+        Self {
+            settings_path,
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// Load settings from disk, falling back to defaults if absent.
+    fn load_sync(&self) -> AppResult<AppSettings> {
+        if !self.settings_path.exists() {
+            return Ok(AppSettings::default());
+        }
+        let data = std::fs::read_to_string(&self.settings_path)
+            .map_err(|e| AppError::Settings(format!("Failed to read settings: {e}")))?;
+        if data.trim().is_empty() {
+            return Ok(AppSettings::default());
+        }
+        serde_json::from_str(&data)
+            .map_err(|e| AppError::Settings(format!("Failed to parse settings: {e}")))
+    }
+
+    /// Save settings to disk.
+    fn save_sync(&self, settings: &AppSettings) -> AppResult<()> {
+        let data = serde_json::to_string_pretty(settings)
+            .map_err(|e| AppError::Settings(format!("Failed to serialize settings: {e}")))?;
+        if let Some(parent) = self.settings_path.parent() {
+            std::fs::create_dir_all(parent).ok();
+        }
+        std::fs::write(&self.settings_path, data)
+            .map_err(|e| AppError::Settings(format!("Failed to write settings: {e}")))
+    }
+
+    /// Get the current settings.
+    pub async fn get(&self) -> AppResult<AppSettings> {
+        let _guard = self.lock.lock().await;
+        self.load_sync()
+    }
+
+    /// Apply a mutation to the stored settings and persist the result.
+    pub async fn update<F>(&self, f: F) -> AppResult<AppSettings>
+    where
+        F: FnOnce(&mut AppSettings),
+    {
+        let _guard = self.lock.lock().await;
+        let mut settings = self.load_sync()?;
+        f(&mut settings);
+        self.save_sync(&settings)?;
+        Ok(settings)
+    }
+
+    /// Set (or clear) the default download directory.
+    /// The directory is created if needed and must be writable.
+    pub async fn set_download_dir(&self, dir: Option<String>) -> AppResult<AppSettings> {
+        if let Some(ref d) = dir {
+            ensure_writable_dir(Path::new(d))?;
+        }
+        self.update(|s| s.download_dir = dir).await
+    }
+
+    /// Set (or clear) the default permission modes for uploads and mkdir.
+    pub async fn set_default_modes(
+        &self,
+        file_mode: Option<u32>,
+        dir_mode: Option<u32>,
+    ) -> AppResult<AppSettings> {
+        validate_mode(file_mode)?;
+        validate_mode(dir_mode)?;
+        self.update(|s| {
+            s.upload_file_mode = file_mode;
+            s.create_dir_mode = dir_mode;
+        })
+        .await
+    }
+
+    /// Set (or clear) the number of concurrent uploads per batch.
+    pub async fn set_upload_concurrency(
+        &self,
+        concurrency: Option<usize>,
+    ) -> AppResult<AppSettings> {
+        if concurrency == Some(0) {
+            return Err(AppError::Settings(
+                "Upload concurrency must be at least 1".into(),
+            ));
+        }
+        self.update(|s| s.upload_concurrency = concurrency).await
+    }
+
+    /// Set (or clear) the vault inactivity auto-lock timeout.
+    pub async fn set_vault_auto_lock_secs(&self, secs: Option<u64>) -> AppResult<AppSettings> {
+        self.update(|s| s.vault_auto_lock_secs = secs).await
+    }
+
+    /// Record which backend holds the key vault.
+    pub async fn set_vault_backend(&self, kind: VaultBackendKind) -> AppResult<AppSettings> {
+        self.update(|s| s.vault_backend = Some(kind)).await
+    }
+
+    /// Record the verifier of the JSON vault's unlock passphrase.
+    pub async fn set_vault_passphrase_verifier(&self, verifier: String) -> AppResult<AppSettings> {
+        self.update(|s| s.vault_passphrase_verifier = Some(verifier))
+            .await
+    }
+
+    /// Set (or clear) the in-memory size limit for preview and download.
+    pub async fn set_max_in_memory_bytes(&self, limit: Option<u64>) -> AppResult<AppSettings> {
+        self.update(|s| s.max_in_memory_bytes = limit).await
+    }
+
+    /// Set (or clear) the recursive download confirmation threshold.
+    pub async fn set_confirm_dir_download_bytes(
+        &self,
+        limit: Option<u64>,
+    ) -> AppResult<AppSettings> {
+        self.update(|s| s.confirm_dir_download_bytes = limit).await
+    }
+
+    /// Set (or clear) the size above which images bypass the image cache.
+    pub async fn set_image_cache_bypass_bytes(&self, limit: Option<u64>) -> AppResult<AppSettings> {
+        self.update(|s| s.image_cache_bypass_bytes = limit).await
+    }
+
+    /// Set (or clear) the transfer retry attempts and initial backoff.
+    pub async fn set_transfer_retry(
+        &self,
+        attempts: Option<u32>,
+        backoff_ms: Option<u64>,
+    ) -> AppResult<AppSettings> {
+        if attempts == Some(0) {
+            return Err(AppError::Settings(
+                "Retry attempts must be at least 1".into(),
+            ));
+        }
+        self.update(|s| {
+            s.transfer_retry_attempts = attempts;
+            s.transfer_retry_backoff_ms = backoff_ms;
+        })
+        .await
+    }
+
+    /// Set (or clear, for auto-detection) low-memory mode.
+    pub async fn set_low_memory_mode(&self, enabled: Option<bool>) -> AppResult<AppSettings> {
+        self.update(|s| s.low_memory_mode = enabled).await
+    }
+
+    /// Set (or clear, with `None`) the limits for `host`. They apply to
+    /// sessions opened afterwards.
+    pub async fn set_host_limits(
+        &self,
+        host: &str,
+        limits: Option<HostLimits>,
+    ) -> AppResult<AppSettings> {
+        if let Some(l) = &limits {
+            if [l.max_channels, l.max_parallel_transfers, l.chunk_size].contains(&Some(0)) {
+                return Err(AppError::Settings("Host limits must be at least 1".into()));
+            }
+        }
+        let host = host.to_ascii_lowercase();
+        self.update(|s| match limits {
+            Some(l) if l != HostLimits::default() => {
+                s.host_limits.insert(host, l);
+            }
+            _ => {
+                s.host_limits.remove(&host);
+            }
+        })
+        .await
+    }
+
+    /// Set the default keepalives. They apply to sessions opened afterwards.
+    pub async fn set_keepalive(&self, keepalive: Keepalive) -> AppResult<AppSettings> {
+        if keepalive.max_missed == Some(0) {
+            return Err(AppError::Settings(
+                "Keepalive max missed must be at least 1".into(),
+            ));
+        }
+        self.update(|s| s.keepalive = keepalive).await
+    }
+
+    /// Set (or clear) the metadata and transfer timeouts.
+    pub async fn set_timeouts(
+        &self,
+        metadata_secs: Option<u64>,
+        transfer_secs: Option<u64>,
+    ) -> AppResult<AppSettings> {
+        if metadata_secs == Some(0) || transfer_secs == Some(0) {
+            return Err(AppError::Settings(
+                "Timeouts must be at least 1 second".into(),
+            ));
+        }
+        self.update(|s| {
+            s.metadata_timeout_secs = metadata_secs;
+            s.transfer_timeout_secs = transfer_secs;
+        })
+        .await
+    }
+
+    /// Replace the cellular transfer policy.
+    pub async fn set_network_policy(&self, policy: NetworkPolicy) -> AppResult<AppSettings> {
+        if policy.large_file_bytes == Some(0) {
+            return Err(AppError::Settings(
+                "Large-file threshold must be above 0".into(),
+            ));
+        }
+        self.update(|s| s.network_policy = policy).await
+    }
+
+    /// Replace the file-type overrides. Suffixes are stored lower-cased
+    /// without leading dots.
+    pub async fn set_file_type_overrides(
+        &self,
+        overrides: HashMap<String, FileCategory>,
+    ) -> AppResult<AppSettings> {
+        let mut normalized = HashMap::with_capacity(overrides.len());
+        for (suffix, category) in overrides {
+            let key = file_types::normalize_suffix(&suffix);
+            if key.is_empty() {
+                return Err(AppError::Settings(format!(
+                    "Invalid file-type suffix \"{suffix}\""
+                )));
+            }
+            normalized.insert(key, category);
+        }
+        self.update(|s| s.file_type_overrides = normalized).await
+    }
+
+    /// Replace the global ignore patterns, or those for `host`. An empty
+    /// list for a host removes its entry.
+    pub async fn set_ignore_patterns(
+        &self,
+        host: Option<&str>,
+        patterns: Vec<String>,
+    ) -> AppResult<AppSettings> {
+        let patterns = ignore::normalize(patterns)?;
+        let host = host.map(str::to_ascii_lowercase);
+        self.update(|s| match host {
+            None => s.ignore_patterns = patterns,
+            Some(host) if patterns.is_empty() => {
+                s.host_ignore_patterns.remove(&host);
+            }
+            Some(host) => {
+                s.host_ignore_patterns.insert(host, patterns);
+            }
+        })
+        .await
+    }
+
+    /// Turn upload integrity receipts on or off.
+    pub async fn set_upload_receipts(&self, enabled: bool) -> AppResult<AppSettings> {
+        self.update(|s| s.upload_receipts = Some(enabled)).await
+    }
+}
+
+/// Reject anything outside the permission-bit range.
+pub(crate) fn validate_mode(mode: Option<u32>) -> AppResult<()> {
+    match mode {
+        Some(m) if m > 0o7777 => Err(AppError::Settings(format!(
+            "Invalid permission mode {m:o} (must be at most 7777)"
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// Create `dir` if missing and verify that files can be written into it.
+pub fn ensure_writable_dir(dir: &Path) -> AppResult<()> {
+    std::fs::create_dir_all(dir)
+        .map_err(|e| AppError::Settings(format!("Cannot create directory: {e}")))?;
+
+    let probe = dir.join(format!(".oxidock_write_test_{}", uuid::Uuid::new_v4()));
+    std::fs::write(&probe, b"")
+        .map_err(|e| AppError::Settings(format!("Directory is not writable: {e}")))?;
+    std::fs::remove_file(&probe).ok();
+    Ok(())
+}
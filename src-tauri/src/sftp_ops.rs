@@ -1,151 +1,1761 @@
-use serde::Serialize;
-use std::sync::atomic::{AtomicBool, Ordering};
+use russh_sftp::client::SftpSession;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
+use std::task::Poll;
 
 use tokio::io::AsyncWriteExt;
+use unicode_normalization::UnicodeNormalization;
 
+use crate::cache::{self, DiskCache, EntryMeta, Lookup, Validator};
+use crate::dir_diff::DirDiff;
+use crate::disk_usage;
 use crate::errors::{AppError, AppResult};
-use crate::ssh_manager::SshSession;
+use crate::file_types::{self, FileCategory};
+use crate::font_thumbnail;
+use crate::ignore::{self, IgnoreRules};
+use crate::memory;
+use crate::raw_sftp;
+use crate::scp;
+use crate::search_hits::PreviewMatch;
+use crate::ssh_manager::{shell_quote, SshSession, Transport, BULK_EXEC_TIMEOUT};
+use crate::thumbnail;
+use crate::timeouts;
+
+/// Most bytes of an image read to build a thumbnail.
+const THUMB_MAX_READ_BYTES: u64 = 10 * 1024 * 1024;
+/// `THUMB_MAX_READ_BYTES` in low-memory mode.
+const THUMB_MAX_READ_BYTES_LOW: u64 = 4 * 1024 * 1024;
+/// First read size for JPEG thumbnails, doubled until the prefix decodes.
+const THUMB_PARTIAL_START_BYTES: u64 = 256 * 1024;
+/// Largest font file rendered to a preview.
+const FONT_MAX_READ_BYTES: u64 = 8 * 1024 * 1024;
+/// Bytes at the end of a partial upload compared with the local data
+/// before the upload continues after them.
+const RESUME_CHECK_BYTES: u64 = 64 * 1024;
+/// Least time between progress reports from [`dir_size`].
+const DIR_SIZE_PROGRESS_INTERVAL: std::time::Duration = std::time::Duration::from_millis(250);
+/// Extension of cached thumbnails.
+const THUMB_EXT: &str = "webp";
+/// Extension of cached previews.
+pub(crate) const PREVIEW_EXT: &str = "json";
 
-static THUMB_EVICTION_RUNNING: AtomicBool = AtomicBool::new(false);
-static IMAGE_EVICTION_RUNNING: AtomicBool = AtomicBool::new(false);
+/// A file entry returned to the frontend.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileEntry {
+    /// NFC-normalized name for display. May differ from the on-disk name.
+    pub name: String,
+    /// Exact path as reported by the server. Treat as opaque and pass it
+    /// back unchanged for subsequent operations.
+    pub path: String,
+    /// The on-disk name was not valid UTF-8 or contained control characters.
+    pub invalid_name: bool,
+    /// Exact on-disk name bytes (base64), when they are not valid UTF-8.
+    /// `path` is then an opaque form that stat, download, rename and
+    /// delete accept; see `raw_sftp`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw_name: Option<String>,
+    /// For a symlink, whether its target is a directory.
+    pub is_dir: bool,
+    /// For a symlink, the size of its target if it resolves.
+    pub size: u64,
+    pub modified: Option<String>,
+    #[serde(default)]
+    pub is_symlink: bool,
+    /// Target of a symlink as stored on the server (possibly relative).
+    #[serde(default)]
+    pub link_target: Option<String>,
+    /// Registry category; always `Other` for directories.
+    #[serde(default)]
+    pub category: FileCategory,
+    pub is_image: bool,
+    /// Average colour of the cached thumbnail ("#rrggbb"), for placeholders.
+    pub placeholder_color: Option<String>,
+    /// Permission bits only (`0o7777` mask), if the server reported them.
+    #[serde(default)]
+    pub mode: Option<u32>,
+    #[serde(default)]
+    pub uid: Option<u32>,
+    #[serde(default)]
+    pub gid: Option<u32>,
+}
 
-/// 50 MB cap for the thumbnail disk cache.
-const THUMB_CACHE_MAX_BYTES: u64 = 50 * 1024 * 1024;
-/// 200 MB cap for the full-image disk cache.
-const IMAGE_CACHE_MAX_BYTES: u64 = 200 * 1024 * 1024;
+/// Normalize a raw file name for display: NFC composition, with control
+/// characters replaced. Returns the display name and whether the raw name
+/// was invalid (lossily decoded or containing control characters).
+pub fn display_name(raw: &str) -> (String, bool) {
+    let mut invalid = raw.contains(char::REPLACEMENT_CHARACTER);
+    let name = raw
+        .nfc()
+        .map(|c| {
+            if c.is_control() {
+                invalid = true;
+                char::REPLACEMENT_CHARACTER
+            } else {
+                c
+            }
+        })
+        .collect();
+    (name, invalid)
+}
 
-/// Evict oldest files from a cache directory until total size is under `max_bytes`.
-/// Sorts by modification time (oldest first) as an LRU proxy.
-fn evict_cache_lru(cache_dir: &std::path::Path, max_bytes: u64) {
-    let rd = match std::fs::read_dir(cache_dir) {
-        Ok(rd) => rd,
-        Err(_) => return,
+/// Return `name`, or the first free `"stem (N).ext"` variant of it for which
+/// `taken` is false.
+pub fn first_free_name(name: &str, taken: impl Fn(&str) -> bool) -> String {
+    if !taken(name) {
+        return name.to_string();
+    }
+    // A leading dot starts a hidden name, not an extension (".bashrc").
+    let (stem, ext) = match name.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() => (stem, Some(ext)),
+        _ => (name, None),
     };
+    (1u32..)
+        .map(|n| match ext {
+            Some(ext) => format!("{stem} ({n}).{ext}"),
+            None => format!("{stem} ({n})"),
+        })
+        .find(|candidate| !taken(candidate))
+        .expect("unbounded counter always finds a free name")
+}
 
-    let mut files: Vec<(std::path::PathBuf, u64, u64)> = Vec::new();
-    let mut total_size: u64 = 0;
+/// Suggest a name for `desired` in remote directory `dir` that does not
+/// collide with an existing entry.
+pub async fn suggest_name(
+    session: &Arc<SshSession>,
+    dir: &str,
+    desired: &str,
+) -> AppResult<String> {
+    let existing: std::collections::HashSet<String> = list_dir(session, dir)
+        .await?
+        .into_iter()
+        .map(|e| e.path.rsplit('/').next().unwrap_or(&e.name).to_string())
+        .collect();
+    Ok(first_free_name(desired, |n| existing.contains(n)))
+}
 
-    for entry in rd.filter_map(|e| e.ok()) {
-        let Ok(meta) = entry.metadata() else {
-            continue;
-        };
-        if !meta.is_file() {
-            continue;
-        }
-        let size = meta.len();
-        let mtime = meta
-            .modified()
-            .ok()
-            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
-            .map(|d| d.as_secs())
-            .unwrap_or(0);
-        total_size += size;
-        files.push((entry.path(), size, mtime));
+/// `"stem (copy).ext"` for `name`, keeping hidden names whole.
+fn copy_name(name: &str) -> String {
+    match name.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() => format!("{stem} (copy).{ext}"),
+        _ => format!("{name} (copy)"),
     }
+}
 
-    if total_size <= max_bytes {
-        return;
+/// Copy `src` to `dest` with `cp -pR` on the server, without any data
+/// passing through this side. Fails if exec or `cp` does.
+pub async fn copy_on_server(session: &Arc<SshSession>, src: &str, dest: &str) -> AppResult<()> {
+    let command = format!("cp -pR -- {} {}", shell_quote(src), shell_quote(dest));
+    let output = session.exec_within(&command, BULK_EXEC_TIMEOUT).await?;
+    if output.exit_status != Some(0) {
+        return Err(AppError::Sftp(format!(
+            "cp exited with {:?}: {}",
+            output.exit_status,
+            String::from_utf8_lossy(&output.stderr).trim(),
+        )));
     }
+    Ok(())
+}
 
-    files.sort_by_key(|&(_, _, mtime)| mtime);
+/// Copy `src` to `dest` on the server. Uses `cp -p` (directories too) when
+/// exec works; otherwise streams a regular file over SFTP.
+pub async fn copy_file(session: &Arc<SshSession>, src: &str, dest: &str) -> AppResult<()> {
+    let _op = session.ops.track("copy", dest);
+    match copy_on_server(session, src, dest).await {
+        Ok(()) => return Ok(()),
+        Err(e) => log::warn!("[SFTP] {} — copying over SFTP", e),
+    }
 
-    let to_free = total_size - max_bytes;
-    let mut freed: u64 = 0;
-    let mut evicted = 0u32;
+    let Transport::Sftp(sftp) = session.transport().await? else {
+        return Err(AppError::Sftp("Copying needs either exec or SFTP".into()));
+    };
+    if stat(session, src).await?.is_dir {
+        return Err(AppError::Sftp("Copying a directory needs exec (cp)".into()));
+    }
+    let mut reader = sftp
+        .open(src)
+        .await
+        .map_err(|e| AppError::Sftp(format!("Failed to open {src}: {e}")))?;
+    let mut writer = sftp
+        .create(dest)
+        .await
+        .map_err(|e| AppError::Sftp(format!("Failed to create {dest}: {e}")))?;
+    let copy = async {
+        tokio::io::copy(&mut reader, &mut writer)
+            .await
+            .map_err(|e| AppError::Sftp(format!("Failed to write {dest}: {e}")))?;
+        writer
+            .shutdown()
+            .await
+            .map_err(|e| AppError::Sftp(format!("Failed to close {dest}: {e}")))
+    };
+    session.timed(timeouts::transfer(), "copy", copy).await?;
+    if let Ok(meta) = sftp.metadata(src).await {
+        if let Some(mode) = meta.permissions {
+            set_mode(session, dest, mode & 0o7777).await.ok();
+        }
+    }
+    Ok(())
+}
+
+/// Stream regular file `src` to `dest` over SFTP, reporting bytes copied
+/// so far, and give `dest` the source's permission bits. Returns the size.
+/// A symlink is not followed: `dest` becomes a link to the same target and
+/// 0 bytes are reported.
+pub async fn copy_streamed<F: Fn(u64)>(
+    session: &Arc<SshSession>,
+    src: &str,
+    dest: &str,
+    on_progress: F,
+) -> AppResult<u64> {
+    use tokio::io::AsyncReadExt;
 
-    for (path, size, _) in &files {
-        if freed >= to_free {
+    let _op = session.ops.track("copy", dest);
+    if is_symlink(session, src).await? {
+        let target = read_link(session, src).await?;
+        create_symlink(session, &target, dest).await?;
+        return Ok(0);
+    }
+    let sftp = session.sftp().await?;
+    let mut reader = sftp
+        .open(src)
+        .await
+        .map_err(|e| AppError::Sftp(format!("Failed to open {src}: {e}")))?;
+    let mut writer = sftp
+        .create(dest)
+        .await
+        .map_err(|e| AppError::Sftp(format!("Failed to create {dest}: {e}")))?;
+    let write_err = |e: std::io::Error| AppError::Sftp(format!("Failed to write {dest}: {e}"));
+
+    let chunk = memory::pick(DOWNLOAD_CHUNK_SIZE, LOW_MEMORY_CHUNK_SIZE);
+    let mut buf = vec![0u8; session.chunk_size(chunk)];
+    let mut total = 0u64;
+    loop {
+        let read = async {
+            reader
+                .read(&mut buf)
+                .await
+                .map_err(|e| AppError::Sftp(format!("Failed to read {src}: {e}")))
+        };
+        let n = session.timed(timeouts::transfer(), "copy", read).await?;
+        if n == 0 {
             break;
         }
-        if std::fs::remove_file(path).is_ok() {
-            freed += size;
-            evicted += 1;
+        let write = async { writer.write_all(&buf[..n]).await.map_err(write_err) };
+        session.timed(timeouts::transfer(), "copy", write).await?;
+        total += n as u64;
+        on_progress(total);
+    }
+    writer.shutdown().await.map_err(write_err)?;
+    if let Ok(meta) = sftp.metadata(src).await {
+        if let Some(mode) = meta.permissions {
+            set_mode(session, dest, mode & 0o7777).await.ok();
         }
     }
+    Ok(total)
+}
 
+/// Directory part of `path`, with `/` for top-level entries.
+pub(crate) fn parent_dir(path: &str) -> &str {
+    match path.rsplit_once('/') {
+        Some(("", _)) | None => "/",
+        Some((parent, _)) => parent,
+    }
+}
+
+/// Copy `path` next to itself as `"name (copy).ext"` (or the first free
+/// variant of that name). Returns the new path.
+pub async fn duplicate(session: &Arc<SshSession>, path: &str) -> AppResult<String> {
+    let start = std::time::Instant::now();
+    let trimmed = path.trim_end_matches('/');
+    let (dir, name) = match trimmed.rsplit_once('/') {
+        Some(("", name)) => ("/", name),
+        Some((dir, name)) => (dir, name),
+        None => (".", trimmed),
+    };
+    if name.is_empty() {
+        return Err(AppError::Other(
+            "Cannot duplicate the root directory".into(),
+        ));
+    }
+    let new_name = suggest_name(session, dir, &copy_name(name)).await?;
+    let dest = if dir.ends_with('/') {
+        format!("{dir}{new_name}")
+    } else {
+        format!("{dir}/{new_name}")
+    };
+    copy_file(session, trimmed, &dest).await?;
     log::info!(
-        "[CACHE] eviction: removed {} files, freed {:.1} MB (was {:.1} MB, cap {:.1} MB)",
-        evicted,
-        freed as f64 / (1024.0 * 1024.0),
-        total_size as f64 / (1024.0 * 1024.0),
-        max_bytes as f64 / (1024.0 * 1024.0),
+        "[PERF] duplicate \"{}\" → \"{}\" — {:.2}ms",
+        path,
+        dest,
+        start.elapsed().as_secs_f64() * 1000.0,
     );
+    Ok(dest)
 }
 
-/// A file entry returned to the frontend.
+impl FileEntry {
+    /// Whether `path` is a plain path naming this entry exactly, so it is
+    /// safe to build on. An opaque path only works with the operations in
+    /// `raw_sftp`, and a lossy one could name a different file or none.
+    pub fn addressable(&self) -> bool {
+        !raw_sftp::is_raw(&self.path) && !self.path.contains(char::REPLACEMENT_CHARACTER)
+    }
+}
+
+/// Build a `FileEntry` for the raw on-disk `name` inside directory `dir`.
+/// The path keeps the raw name so it stays addressable on the server.
+pub(crate) fn make_entry(
+    dir: &str,
+    raw_name: &str,
+    is_dir: bool,
+    size: u64,
+    mtime: Option<u32>,
+) -> FileEntry {
+    let path = if dir.ends_with('/') {
+        format!("{dir}{raw_name}")
+    } else {
+        format!("{dir}/{raw_name}")
+    };
+    let modified = mtime.map(|t| {
+        chrono::DateTime::from_timestamp(t as i64, 0)
+            .map(|dt| dt.to_rfc3339())
+            .unwrap_or_default()
+    });
+    let (name, invalid_name) = display_name(raw_name);
+    let category = if is_dir {
+        FileCategory::Other
+    } else {
+        file_types::classify(&name)
+    };
+    FileEntry {
+        name,
+        path,
+        invalid_name,
+        raw_name: None,
+        is_dir,
+        size,
+        modified,
+        is_symlink: false,
+        link_target: None,
+        category,
+        is_image: category == FileCategory::Images,
+        placeholder_color: None,
+        mode: None,
+        uid: None,
+        gid: None,
+    }
+}
+
+/// Basic attributes of a remote path.
+#[derive(Debug, Clone, Copy)]
+pub struct RemoteStat {
+    pub size: Option<u64>,
+    pub mtime: Option<u32>,
+    pub is_dir: bool,
+}
+
+/// Stat a remote path (following symlinks), via SFTP or `stat` on the SCP fallback.
+pub async fn stat(session: &Arc<SshSession>, path: &str) -> AppResult<RemoteStat> {
+    session
+        .timed(timeouts::metadata(), "stat", stat_untimed(session, path))
+        .await
+}
+
+async fn stat_untimed(session: &Arc<SshSession>, path: &str) -> AppResult<RemoteStat> {
+    match session.transport().await? {
+        Transport::Sftp(_) if raw_sftp::is_raw(path) => {
+            let attrs = raw_sftp::stat(session, &raw_sftp::path_bytes(path), false).await?;
+            Ok(RemoteStat {
+                size: attrs.size,
+                mtime: attrs.mtime,
+                is_dir: attrs.is_dir(),
+            })
+        }
+        Transport::Sftp(sftp) => {
+            let meta = sftp
+                .metadata(path)
+                .await
+                .map_err(|e| AppError::Sftp(format!("Failed to stat file: {e}")))?;
+            Ok(RemoteStat {
+                size: meta.size,
+                mtime: meta.mtime,
+                is_dir: meta.is_dir(),
+            })
+        }
+        Transport::Scp => {
+            let output = session
+                .exec(&format!(
+                    "stat -L -c '%s %Y %F' -- {}",
+                    raw_sftp::shell_word(path)?
+                ))
+                .await?;
+            if output.exit_status.unwrap_or(0) != 0 {
+                return Err(AppError::Sftp(format!(
+                    "Failed to stat file: {}",
+                    String::from_utf8_lossy(&output.stderr).trim()
+                )));
+            }
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            let mut fields = stdout.trim().splitn(3, ' ');
+            Ok(RemoteStat {
+                size: fields.next().and_then(|s| s.parse().ok()),
+                mtime: fields.next().and_then(|s| s.parse().ok()),
+                is_dir: fields.next() == Some("directory"),
+            })
+        }
+    }
+}
+
+/// Size and free space of the filesystem holding a path (safe to send to JS).
 #[derive(Debug, Clone, Serialize)]
-pub struct FileEntry {
-    pub name: String,
+pub struct FsStats {
+    pub path: String,
+    pub total_bytes: u64,
+    /// Free space, including blocks reserved for root.
+    pub free_bytes: u64,
+    /// Free space an unprivileged user can write to.
+    pub available_bytes: u64,
+    /// `None` when read with `df`, or where the filesystem has no fixed
+    /// inode count.
+    pub total_inodes: Option<u64>,
+    pub free_inodes: Option<u64>,
+    /// `"statvfs"` (the `statvfs@openssh.com` extension) or `"df"`.
+    pub source: &'static str,
+}
+
+/// Free space on the filesystem holding `path`. Uses the
+/// `statvfs@openssh.com` extension where the server has it, else `df`
+/// over exec.
+pub async fn statvfs(session: &Arc<SshSession>, path: &str) -> AppResult<FsStats> {
+    session
+        .timed(
+            timeouts::metadata(),
+            "statvfs",
+            statvfs_untimed(session, path),
+        )
+        .await
+}
+
+async fn statvfs_untimed(session: &Arc<SshSession>, path: &str) -> AppResult<FsStats> {
+    if let Transport::Sftp(sftp) = session.transport().await? {
+        match sftp.fs_info(path).await {
+            Ok(Some(st)) => {
+                // Block counts are in fragment units; some servers leave it 0.
+                let unit = if st.fragment_size > 0 {
+                    st.fragment_size
+                } else {
+                    st.block_size
+                };
+                let inodes = (st.inodes > 0).then_some((st.inodes, st.inodes_free));
+                return Ok(FsStats {
+                    path: path.to_string(),
+                    total_bytes: st.blocks * unit,
+                    free_bytes: st.blocks_free * unit,
+                    available_bytes: st.blocks_avail * unit,
+                    total_inodes: inodes.map(|(total, _)| total),
+                    free_inodes: inodes.map(|(_, free)| free),
+                    source: "statvfs",
+                });
+            }
+            Ok(None) => log::debug!("[SFTP] server has no statvfs extension, using df"),
+            Err(e) => log::debug!("[SFTP] statvfs \"{}\" failed, using df: {}", path, e),
+        }
+    }
+
+    let output = session
+        .exec(&format!("df -Pk -- {}", shell_quote(path)))
+        .await?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let (_, [total_kib, used_kib, avail_kib], _) =
+        disk_usage::parse_df(&stdout).ok_or_else(|| {
+            AppError::Ssh(format!(
+                "Failed to read free space: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ))
+        })?;
+    Ok(FsStats {
+        path: path.to_string(),
+        total_bytes: total_kib * 1024,
+        free_bytes: total_kib.saturating_sub(used_kib) * 1024,
+        available_bytes: avail_kib * 1024,
+        total_inodes: None,
+        free_inodes: None,
+        source: "df",
+    })
+}
+
+/// Hash algorithms for [`checksum`], named as in the `check-file` extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChecksumAlgo {
+    Md5,
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+impl ChecksumAlgo {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Md5 => "md5",
+            Self::Sha1 => "sha1",
+            Self::Sha256 => "sha256",
+            Self::Sha512 => "sha512",
+        }
+    }
+
+    /// Length of the digest in bytes.
+    fn digest_len(self) -> usize {
+        match self {
+            Self::Md5 => 16,
+            Self::Sha1 => 20,
+            Self::Sha256 => 32,
+            Self::Sha512 => 64,
+        }
+    }
+}
+
+/// Digest of a remote file, computed on the server (safe to send to JS).
+#[derive(Debug, Clone, Serialize)]
+pub struct RemoteChecksum {
+    pub path: String,
+    pub algo: ChecksumAlgo,
+    /// Lower-case hex.
+    pub digest: String,
+    /// `"check-file"` (the SFTP extension) or `"exec"` (e.g. `sha256sum`).
+    pub source: &'static str,
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Ask for the whole-file digest with the `check-file-name` extension.
+/// `None` if the server doesn't support it (or not with `algo`).
+async fn check_file(session: &Arc<SshSession>, path: &str, algo: ChecksumAlgo) -> Option<String> {
+    fn put_string(buf: &mut Vec<u8>, s: &[u8]) {
+        buf.extend_from_slice(&(s.len() as u32).to_be_bytes());
+        buf.extend_from_slice(s);
+    }
+    fn take_string<'a>(buf: &mut &'a [u8]) -> Option<&'a [u8]> {
+        let len = u32::from_be_bytes(buf.get(..4)?.try_into().ok()?) as usize;
+        let s = buf.get(4..4 + len)?;
+        *buf = &buf[4 + len..];
+        Some(s)
+    }
+
+    let raw = session.listing_sftp().await.ok()?;
+    let mut request = Vec::new();
+    put_string(&mut request, path.as_bytes());
+    put_string(&mut request, algo.name().as_bytes());
+    request.extend_from_slice(&0u64.to_be_bytes()); // start offset
+    request.extend_from_slice(&0u64.to_be_bytes()); // length: to the end
+    request.extend_from_slice(&0u32.to_be_bytes()); // block size: one hash
+    let reply = match raw.extended("check-file-name", request).await {
+        Ok(russh_sftp::protocol::Packet::ExtendedReply(reply)) => reply.data,
+        Ok(_) => return None,
+        Err(e) => {
+            log::debug!("[SFTP] check-file \"{}\" unsupported: {}", path, e);
+            return None;
+        }
+    };
+
+    // Reply: ["check-file"], algorithm used, then the digest bytes.
+    let mut data = reply.as_slice();
+    let mut used = take_string(&mut data)?;
+    if used == b"check-file" {
+        used = take_string(&mut data)?;
+    }
+    (used == algo.name().as_bytes() && data.len() == algo.digest_len()).then(|| hex(data))
+}
+
+/// Digest of the remote file at `path`, computed on the server: with the
+/// `check-file` SFTP extension where available, else with `sha256sum` (or
+/// the matching tool for `algo`) over exec.
+pub async fn checksum(
+    session: &Arc<SshSession>,
+    path: &str,
+    algo: ChecksumAlgo,
+) -> AppResult<RemoteChecksum> {
+    let start = std::time::Instant::now();
+    let _op = session.ops.track("checksum", path);
+    let result = |digest, source| RemoteChecksum {
+        path: path.to_string(),
+        algo,
+        digest,
+        source,
+    };
+    if let Transport::Sftp(_) = session.transport().await? {
+        let check = session.timed(timeouts::transfer(), "checksum", async {
+            Ok(check_file(session, path, algo).await)
+        });
+        if let Some(digest) = check.await? {
+            return Ok(result(digest, "check-file"));
+        }
+    }
+
+    let command = format!("{}sum -b -- {}", algo.name(), shell_quote(path));
+    let output = session.exec_within(&command, BULK_EXEC_TIMEOUT).await?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let digest = stdout
+        .split_whitespace()
+        .next()
+        .filter(|d| d.len() == algo.digest_len() * 2 && d.chars().all(|c| c.is_ascii_hexdigit()))
+        .filter(|_| output.exit_status == Some(0))
+        .ok_or_else(|| {
+            AppError::Ssh(format!(
+                "Failed to checksum {path}: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ))
+        })?;
+    log::info!(
+        "[PERF] checksum \"{}\" ({}) — {:.2}ms",
+        path,
+        algo.name(),
+        start.elapsed().as_secs_f64() * 1000.0,
+    );
+    Ok(result(digest.to_ascii_lowercase(), "exec"))
+}
+
+/// Resolve at most this many symlinks per listing; the rest stay unresolved.
+const MAX_RESOLVED_LINKS: usize = 512;
+/// Symlink lookups in flight at once, within the host's parallel limit.
+const RESOLVE_LINK_CONCURRENCY: usize = 16;
+
+/// Whether SFTP permission bits describe a symlink.
+pub(crate) fn is_link_mode(permissions: Option<u32>) -> bool {
+    permissions.unwrap_or(0) & 0o170000 == 0o120000
+}
+
+/// Whether `path` itself is a symlink (lstat, not following it).
+pub async fn is_symlink(session: &Arc<SshSession>, path: &str) -> AppResult<bool> {
+    let check = async {
+        match session.transport().await? {
+            Transport::Sftp(sftp) => sftp
+                .symlink_metadata(path)
+                .await
+                .map(|meta| is_link_mode(meta.permissions))
+                .map_err(|e| AppError::Sftp(format!("Failed to stat file: {e}"))),
+            Transport::Scp => {
+                let output = session
+                    .exec(&format!("test -L {}", shell_quote(path)))
+                    .await?;
+                Ok(output.exit_status == Some(0))
+            }
+        }
+    };
+    session.timed(timeouts::metadata(), "lstat", check).await
+}
+
+/// Read the target of the symlink at `path`, as stored on the server.
+pub async fn read_link(session: &Arc<SshSession>, path: &str) -> AppResult<String> {
+    let read = async {
+        match session.transport().await? {
+            Transport::Sftp(sftp) => sftp
+                .read_link(path)
+                .await
+                .map_err(|e| AppError::Sftp(format!("Failed to read link: {e}"))),
+            Transport::Scp => {
+                let output = session
+                    .exec(&format!("readlink -- {}", shell_quote(path)))
+                    .await?;
+                if output.exit_status.unwrap_or(0) != 0 {
+                    return Err(AppError::Sftp(format!(
+                        "Failed to read link: {}",
+                        String::from_utf8_lossy(&output.stderr).trim()
+                    )));
+                }
+                let stdout = String::from_utf8_lossy(&output.stdout);
+                Ok(stdout.trim_end_matches('\n').to_string())
+            }
+        }
+    };
+    session.timed(timeouts::metadata(), "read_link", read).await
+}
+
+/// Fill in the targets of the symlinks in a listing, and whether each
+/// points at a directory. Broken links are left as files.
+async fn resolve_symlinks(session: &Arc<SshSession>, files: &mut [FileEntry]) {
+    let mut lookups = tokio::task::JoinSet::new();
+    let slots = Arc::new(tokio::sync::Semaphore::new(
+        session.max_parallel(RESOLVE_LINK_CONCURRENCY),
+    ));
+    let links = files.iter().enumerate().filter(|(_, f)| f.is_symlink);
+    for (index, file) in links.take(MAX_RESOLVED_LINKS) {
+        let session = session.clone();
+        let path = file.path.clone();
+        let known_target = file.link_target.clone();
+        let slots = slots.clone();
+        lookups.spawn(async move {
+            let _slot = slots.acquire_owned().await.ok();
+            let target = match known_target {
+                Some(target) => Some(target),
+                None => read_link(&session, &path).await.ok(),
+            };
+            (index, target, stat(&session, &path).await.ok())
+        });
+    }
+    while let Some(joined) = lookups.join_next().await {
+        let Ok((index, target, resolved)) = joined else {
+            continue;
+        };
+        let file = &mut files[index];
+        file.link_target = target;
+        if let Some(resolved) = resolved {
+            file.is_dir = resolved.is_dir;
+            file.size = resolved.size.unwrap_or(0);
+            if resolved.is_dir {
+                file.category = FileCategory::Other;
+                file.is_image = false;
+            }
+        }
+    }
+}
+
+/// Create a symlink at `link_path` pointing to `target`. The target is
+/// stored as given, so relative targets resolve from the link's directory.
+pub async fn create_symlink(
+    session: &Arc<SshSession>,
+    target: &str,
+    link_path: &str,
+) -> AppResult<()> {
+    let _op = session.ops.track("create_symlink", link_path);
+    let create = async {
+        match session.transport().await? {
+            // OpenSSH reads SSH_FXP_SYMLINK's two paths in reverse order
+            // from the draft, and nearly every server follows it: pass the
+            // target where the link path is expected.
+            Transport::Sftp(sftp) => sftp
+                .symlink(target, link_path)
+                .await
+                .map_err(|e| AppError::Sftp(format!("Failed to create symlink: {e}"))),
+            Transport::Scp => {
+                let command = format!(
+                    "ln -s -- {} {}",
+                    shell_quote(target),
+                    shell_quote(link_path)
+                );
+                let output = session.exec(&command).await?;
+                if output.exit_status.unwrap_or(0) != 0 {
+                    return Err(AppError::Sftp(format!(
+                        "Failed to create symlink: {}",
+                        String::from_utf8_lossy(&output.stderr).trim()
+                    )));
+                }
+                Ok(())
+            }
+        }
+    };
+    session
+        .timed(timeouts::metadata(), "create_symlink", create)
+        .await?;
+    log::info!("[SFTP] symlink \"{}\" -> \"{}\" created", link_path, target);
+    Ok(())
+}
+
+/// Stat a remote file's size. Returns `None` if the size could not be determined.
+pub async fn file_size(session: &Arc<SshSession>, path: &str) -> AppResult<Option<u64>> {
+    Ok(stat(session, path).await?.size)
+}
+
+/// Modification time of a listed entry as a Unix timestamp.
+pub fn entry_mtime(entry: &FileEntry) -> Option<u32> {
+    let modified = entry.modified.as_deref()?;
+    let ts = chrono::DateTime::parse_from_rfc3339(modified)
+        .ok()?
+        .timestamp();
+    u32::try_from(ts).ok()
+}
+
+/// A node found by [`walk_tree`], with the ownership and mode details a
+/// plain listing omits.
+#[derive(Debug, Clone)]
+pub struct TreeNode {
     pub path: String,
     pub is_dir: bool,
-    pub size: u64,
-    pub modified: Option<String>,
-    pub is_image: bool,
+    /// Permission bits only (`0o7777` mask).
+    pub mode: u32,
+    pub uid: Option<u32>,
+    pub gid: Option<u32>,
 }
 
-/// Returns true if the file extension is a supported image format.
-pub fn is_image_ext(name: &str) -> bool {
-    let ext = name.rsplit('.').next().unwrap_or("").to_lowercase();
-    matches!(
-        ext.as_str(),
-        "png" | "jpg" | "jpeg" | "gif" | "bmp" | "webp" | "avif" | "heic" | "svg"
-    )
+/// The root of a [`walk_tree`], from lstat so a symlinked root is handled
+/// as the link itself.
+async fn tree_root(session: &Arc<SshSession>, root: &str) -> AppResult<TreeNode> {
+    let lstat = async {
+        match session.transport().await? {
+            Transport::Sftp(sftp) => {
+                let meta = sftp
+                    .symlink_metadata(root)
+                    .await
+                    .map_err(|e| AppError::Sftp(format!("Failed to stat file: {e}")))?;
+                Ok(TreeNode {
+                    path: root.to_string(),
+                    is_dir: meta.is_dir(),
+                    mode: meta.permissions.unwrap_or(0) & 0o7777,
+                    uid: meta.uid,
+                    gid: meta.gid,
+                })
+            }
+            Transport::Scp => {
+                let output = session
+                    .exec(&format!("stat -c '%a %u %g %F' -- {}", shell_quote(root)))
+                    .await?;
+                if output.exit_status.unwrap_or(0) != 0 {
+                    return Err(AppError::Sftp(format!(
+                        "Failed to stat file: {}",
+                        String::from_utf8_lossy(&output.stderr).trim()
+                    )));
+                }
+                let stdout = String::from_utf8_lossy(&output.stdout);
+                let mut fields = stdout.trim().splitn(4, ' ');
+                Ok(TreeNode {
+                    path: root.to_string(),
+                    mode: fields
+                        .next()
+                        .and_then(|m| u32::from_str_radix(m, 8).ok())
+                        .unwrap_or(0),
+                    uid: fields.next().and_then(|u| u.parse().ok()),
+                    gid: fields.next().and_then(|g| g.parse().ok()),
+                    is_dir: fields.next() == Some("directory"),
+                })
+            }
+        }
+    };
+    session.timed(timeouts::metadata(), "stat", lstat).await
 }
 
-/// List directory contents via SFTP.
-pub async fn list_dir(session: &Arc<SshSession>, path: &str) -> AppResult<Vec<FileEntry>> {
-    let total_start = std::time::Instant::now();
+/// Walk `root` without following symlinks, returning the root itself first.
+/// Stops after `limit` nodes; the flag reports whether the walk was cut short.
+pub async fn walk_tree(
+    session: &Arc<SshSession>,
+    root: &str,
+    limit: usize,
+) -> AppResult<(Vec<TreeNode>, bool)> {
+    let start = std::time::Instant::now();
+    let root_node = tree_root(session, root).await?;
+    let descend = root_node.is_dir;
+    let mut nodes = vec![root_node];
+    let mut truncated = false;
+    if descend {
+        walk(session, root, &WalkOptions::default(), |item| {
+            if nodes.len() >= limit {
+                truncated = true;
+                return WalkControl::Stop;
+            }
+            // A link is a node of its own, never the directory it points at.
+            let entry = item.entry;
+            nodes.push(TreeNode {
+                is_dir: entry.is_dir && !entry.is_symlink,
+                mode: entry.mode.unwrap_or(0),
+                uid: entry.uid,
+                gid: entry.gid,
+                path: entry.path,
+            });
+            WalkControl::Continue
+        })
+        .await?;
+    }
 
-    let sftp_acquire_start = std::time::Instant::now();
-    let sftp = session.sftp().await?;
-    let sftp_acquire_ms = sftp_acquire_start.elapsed().as_secs_f64() * 1000.0;
+    log::info!(
+        "[PERF] walk_tree \"{}\" — {:.2}ms | nodes: {} | truncated: {}",
+        root,
+        start.elapsed().as_secs_f64() * 1000.0,
+        nodes.len(),
+        truncated,
+    );
+    Ok((nodes, truncated))
+}
 
-    let readdir_start = std::time::Instant::now();
-    let entries = sftp
-        .read_dir(path)
+/// Directory listings in flight at once during a [`walk`].
+pub const DEFAULT_WALK_CONCURRENCY: usize = 8;
+
+/// What a [`walk`] does after visiting an entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WalkControl {
+    Continue,
+    /// Don't descend into this directory (same as `Continue` for files).
+    SkipDir,
+    /// End the walk; listings still in flight are abandoned.
+    Stop,
+}
+
+/// How a [`walk`] treats a directory it cannot list.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WalkErrorPolicy {
+    /// Fail the whole walk with the listing error.
+    #[default]
+    Abort,
+    /// Record the error in [`WalkStats::errors`] and carry on.
+    Skip,
+}
+
+#[derive(Debug, Clone)]
+pub struct WalkOptions {
+    /// Directory listings in flight at once.
+    pub concurrency: usize,
+    /// Deepest level visited; children of the root are depth 1. `None` is unlimited.
+    pub max_depth: Option<usize>,
+    pub on_error: WalkErrorPolicy,
+    /// Entries left out entirely: neither visited nor descended into.
+    pub ignore: IgnoreRules,
+}
+
+impl Default for WalkOptions {
+    fn default() -> Self {
+        Self {
+            concurrency: memory::pick(DEFAULT_WALK_CONCURRENCY, memory::LOW_MEMORY_MAX_CONCURRENCY),
+            max_depth: None,
+            on_error: WalkErrorPolicy::Abort,
+            ignore: IgnoreRules::default(),
+        }
+    }
+}
+
+/// An entry handed to a [`walk`] visitor.
+#[derive(Debug, Clone)]
+pub struct WalkEntry {
+    pub entry: FileEntry,
+    /// Path relative to the walk root, built from raw on-disk names.
+    pub rel: String,
+    pub depth: usize,
+}
+
+/// What a [`walk`] covered.
+#[derive(Debug, Clone, Default)]
+pub struct WalkStats {
+    pub dirs_listed: usize,
+    pub entries: usize,
+    /// Directories skipped under [`WalkErrorPolicy::Skip`], and entries
+    /// whose names cannot be addressed, with the error.
+    pub errors: Vec<(String, String)>,
+    /// The visitor returned [`WalkControl::Stop`].
+    pub stopped: bool,
+}
+
+/// Walk the tree below `root`, listing up to `opts.concurrency` directories
+/// at once and calling `visit` for every entry (never for `root` itself).
+///
+/// Entries arrive in listing-completion order, not sorted. Dropping the
+/// returned future (e.g. under `OpTracker::run_for_view`) cancels the
+/// listings still in flight, as does returning [`WalkControl::Stop`].
+pub async fn walk<F>(
+    session: &Arc<SshSession>,
+    root: &str,
+    opts: &WalkOptions,
+    mut visit: F,
+) -> AppResult<WalkStats>
+where
+    F: FnMut(WalkEntry) -> WalkControl,
+{
+    let start = std::time::Instant::now();
+    let _op = session.ops.track("walk", root);
+    let concurrency = session.max_parallel(opts.concurrency.max(1));
+    let mut stats = WalkStats::default();
+    let mut queue = vec![(root.to_string(), String::new(), 0usize)];
+    let mut listings = tokio::task::JoinSet::new();
+
+    'walk: loop {
+        while listings.len() < concurrency {
+            let Some((dir, rel, depth)) = queue.pop() else {
+                break;
+            };
+            let session = session.clone();
+            listings.spawn(async move {
+                let result = list_dir(&session, &dir).await;
+                (dir, rel, depth, result)
+            });
+        }
+        let Some(joined) = listings.join_next().await else {
+            break;
+        };
+        let (dir, rel_dir, depth, result) =
+            joined.map_err(|e| AppError::Other(format!("Walk task panicked: {e}")))?;
+        stats.dirs_listed += 1;
+
+        let entries = match result {
+            Ok(entries) => entries,
+            Err(e) if opts.on_error == WalkErrorPolicy::Skip => {
+                log::warn!("[SFTP] walk skipped \"{}\": {}", dir, e);
+                stats.errors.push((dir, e.to_string()));
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+
+        let depth = depth + 1;
+        for entry in entries {
+            if opts.ignore.matches(&entry.name) {
+                continue;
+            }
+            if !entry.addressable() {
+                let path = raw_sftp::lossy_path(&entry.path);
+                log::warn!("[SFTP] walk skipped \"{}\": name is not valid UTF-8", path);
+                let error = "name is not valid UTF-8 and cannot be walked".to_string();
+                stats.errors.push((path, error));
+                continue;
+            }
+            let raw_name = entry.path.rsplit('/').next().unwrap_or(&entry.name);
+            let rel = if rel_dir.is_empty() {
+                raw_name.to_string()
+            } else {
+                format!("{rel_dir}/{raw_name}")
+            };
+            // Symlinked directories are reported but not entered, so links
+            // back up the tree cannot loop.
+            let descend =
+                entry.is_dir && !entry.is_symlink && opts.max_depth.is_none_or(|max| depth < max);
+            let next = descend.then(|| (entry.path.clone(), rel.clone(), depth));
+            stats.entries += 1;
+            match visit(WalkEntry { entry, rel, depth }) {
+                WalkControl::Continue => queue.extend(next),
+                WalkControl::SkipDir => {}
+                WalkControl::Stop => {
+                    stats.stopped = true;
+                    break 'walk;
+                }
+            }
+        }
+    }
+
+    log::info!(
+        "[PERF] walk \"{}\" — {:.2}ms | dirs: {} | entries: {} | errors: {} | stopped: {}",
+        root,
+        start.elapsed().as_secs_f64() * 1000.0,
+        stats.dirs_listed,
+        stats.entries,
+        stats.errors.len(),
+        stats.stopped,
+    );
+    Ok(stats)
+}
+
+/// Running totals of a [`dir_size`] walk (safe to send to JS).
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DirSize {
+    pub bytes: u64,
+    pub files: u64,
+    pub dirs: u64,
+    /// Directories that could not be listed and are left out of the totals.
+    pub unreadable: usize,
+}
+
+/// Sum the sizes of all files below `root`. Symlinks count as themselves
+/// and are not followed. `on_progress` gets the totals so far every
+/// `DIR_SIZE_PROGRESS_INTERVAL` while the walk runs.
+pub async fn dir_size<F>(
+    session: &Arc<SshSession>,
+    root: &str,
+    mut on_progress: F,
+) -> AppResult<DirSize>
+where
+    F: FnMut(&DirSize),
+{
+    let opts = WalkOptions {
+        on_error: WalkErrorPolicy::Skip,
+        ..Default::default()
+    };
+    let mut totals = DirSize::default();
+    let mut last_report = std::time::Instant::now();
+    let stats = walk(session, root, &opts, |item| {
+        if item.entry.is_symlink {
+            // A link takes its own (lstat) size, the length of its target
+            // path, not the size of what it points to.
+            totals.files += 1;
+            totals.bytes += item
+                .entry
+                .link_target
+                .as_ref()
+                .map_or(0, |t| t.len() as u64);
+        } else if item.entry.is_dir {
+            totals.dirs += 1;
+        } else {
+            totals.files += 1;
+            totals.bytes += item.entry.size;
+        }
+        if last_report.elapsed() >= DIR_SIZE_PROGRESS_INTERVAL {
+            last_report = std::time::Instant::now();
+            on_progress(&totals);
+        }
+        WalkControl::Continue
+    })
+    .await?;
+    totals.unreadable = stats.errors.len();
+    Ok(totals)
+}
+
+/// Read up to `len` bytes starting at `offset` of a remote file.
+pub async fn read_range(
+    session: &Arc<SshSession>,
+    path: &str,
+    offset: u64,
+    len: u64,
+) -> AppResult<Vec<u8>> {
+    use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+    match session.transport().await? {
+        Transport::Sftp(sftp) => {
+            let mut file = sftp
+                .open(path)
+                .await
+                .map_err(|e| AppError::Sftp(format!("Failed to open file: {e}")))?;
+            file.seek(std::io::SeekFrom::Start(offset))
+                .await
+                .map_err(|e| AppError::Sftp(format!("Failed to seek file: {e}")))?;
+            let mut buf = Vec::new();
+            file.take(len)
+                .read_to_end(&mut buf)
+                .await
+                .map_err(|e| AppError::Sftp(format!("Failed to read file: {e}")))?;
+            Ok(buf)
+        }
+        Transport::Scp => {
+            let output = session
+                .exec(&format!(
+                    "tail -c +{} -- {} | head -c {}",
+                    offset + 1,
+                    shell_quote(path),
+                    len
+                ))
+                .await?;
+            Ok(output.stdout)
+        }
+    }
+}
+
+/// Read at most the first `max_bytes` of a remote file.
+pub async fn read_head(
+    session: &Arc<SshSession>,
+    path: &str,
+    max_bytes: u64,
+) -> AppResult<Vec<u8>> {
+    use tokio::io::AsyncReadExt;
+
+    match session.transport().await? {
+        Transport::Sftp(sftp) => {
+            let file = sftp
+                .open(path)
+                .await
+                .map_err(|e| AppError::Sftp(format!("Failed to open file: {e}")))?;
+            let mut buf = Vec::new();
+            file.take(max_bytes)
+                .read_to_end(&mut buf)
+                .await
+                .map_err(|e| AppError::Sftp(format!("Failed to read file: {e}")))?;
+            Ok(buf)
+        }
+        Transport::Scp => {
+            let output = session
+                .exec(&format!("head -c {} -- {}", max_bytes, shell_quote(path)))
+                .await?;
+            Ok(output.stdout)
+        }
+    }
+}
+
+/// Set a remote path's access and modification times.
+pub async fn set_mtime(session: &Arc<SshSession>, path: &str, mtime: u32) -> AppResult<()> {
+    match session.transport().await? {
+        Transport::Sftp(sftp) => {
+            let attrs = russh_sftp::protocol::FileAttributes {
+                atime: Some(mtime),
+                mtime: Some(mtime),
+                ..Default::default()
+            };
+            let set = async {
+                sftp.set_metadata(path, attrs)
+                    .await
+                    .map_err(|e| AppError::Sftp(format!("Failed to set mtime: {e}")))
+            };
+            session.timed(timeouts::metadata(), "set mtime", set).await
+        }
+        Transport::Scp => {
+            session
+                .exec(&format!("touch -d @{} -- {}", mtime, shell_quote(path)))
+                .await?;
+            Ok(())
+        }
+    }
+}
+
+/// Set the permission bits of `path` (masked to `0o7777`).
+pub async fn set_permissions(session: &Arc<SshSession>, path: &str, mode: u32) -> AppResult<()> {
+    let mode = mode & 0o7777;
+    let attrs = russh_sftp::protocol::FileAttributes {
+        permissions: Some(mode),
+        ..Default::default()
+    };
+    set_attributes(session, path, attrs, &format!("chmod {mode:o}")).await
+}
+
+/// Change the owner and/or group of `path` by numeric id.
+pub async fn set_owner(
+    session: &Arc<SshSession>,
+    path: &str,
+    uid: Option<u32>,
+    gid: Option<u32>,
+) -> AppResult<()> {
+    let owner = match (uid, gid) {
+        (Some(u), Some(g)) => format!("{u}:{g}"),
+        (Some(u), None) => u.to_string(),
+        (None, Some(g)) => format!(":{g}"),
+        (None, None) => return Ok(()),
+    };
+    // SFTP sets uid and gid together, so a missing one is filled in from
+    // the current owner rather than sent as 0 (root).
+    let partial = uid.is_none() || gid.is_none();
+    let (uid, gid) = if let (true, Transport::Sftp(sftp)) = (partial, session.transport().await?) {
+        let lookup = async {
+            sftp.metadata(path)
+                .await
+                .map_err(|e| AppError::Sftp(format!("Failed to stat {path}: {e}")))
+        };
+        let current = session.timed(timeouts::metadata(), "stat", lookup).await?;
+        match (uid.or(current.uid), gid.or(current.gid)) {
+            (Some(u), Some(g)) => (Some(u), Some(g)),
+            _ => {
+                return Err(AppError::Sftp(format!(
+                "Cannot change only the owner or group of {path}: the server did not report both"
+            )))
+            }
+        }
+    } else {
+        (uid, gid)
+    };
+    let attrs = russh_sftp::protocol::FileAttributes {
+        uid,
+        gid,
+        ..Default::default()
+    };
+    set_attributes(session, path, attrs, &format!("chown {owner}")).await
+}
+
+/// Apply `attrs` with SFTP setstat, or run `command` on the path on the
+/// SCP fallback.
+async fn set_attributes(
+    session: &Arc<SshSession>,
+    path: &str,
+    attrs: russh_sftp::protocol::FileAttributes,
+    command: &str,
+) -> AppResult<()> {
+    match session.transport().await? {
+        Transport::Sftp(sftp) => {
+            let set = async {
+                sftp.set_metadata(path, attrs)
+                    .await
+                    .map_err(|e| AppError::Sftp(format!("Failed to set attributes: {e}")))
+            };
+            session
+                .timed(timeouts::metadata(), "set attributes", set)
+                .await
+        }
+        Transport::Scp => {
+            let output = session
+                .exec(&format!("{command} -- {}", shell_quote(path)))
+                .await?;
+            if output.exit_status.unwrap_or(0) != 0 {
+                return Err(AppError::Sftp(format!(
+                    "Failed to set attributes: {}",
+                    String::from_utf8_lossy(&output.stderr).trim()
+                )));
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Chunk size for streaming downloads to disk.
+const DOWNLOAD_CHUNK_SIZE: usize = 256 * 1024;
+/// Chunk size for transfers in low-memory mode.
+const LOW_MEMORY_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Stream a remote file straight to `local_path`, invoking `on_progress`
+/// with the cumulative bytes written. Returns the total size.
+pub async fn download_to_path<F>(
+    session: &Arc<SshSession>,
+    remote_path: &str,
+    local_path: &std::path::Path,
+    on_progress: F,
+) -> AppResult<u64>
+where
+    F: Fn(u64),
+{
+    download_from_offset(session, remote_path, local_path, 0, on_progress).await
+}
+
+/// Like [`download_to_path`], but keep the first `offset` bytes already in
+/// `local_path` and fetch only the rest. Progress and the returned size
+/// include the kept bytes. Over SCP the whole file is fetched again.
+pub async fn download_from_offset<F>(
+    session: &Arc<SshSession>,
+    remote_path: &str,
+    local_path: &std::path::Path,
+    offset: u64,
+    on_progress: F,
+) -> AppResult<u64>
+where
+    F: Fn(u64),
+{
+    use tokio::io::AsyncReadExt;
+
+    let _op = session.ops.track("download", remote_path);
+    let write_err = |e: std::io::Error| AppError::Io(format!("Failed to write local file: {e}"));
+
+    match session.transport().await? {
+        // Not split into ranges: a name like this is rare and the file
+        // usually small.
+        Transport::Sftp(_) if raw_sftp::is_raw(remote_path) => {
+            let mut local = open_local_at(local_path, offset).await?;
+            let path = raw_sftp::path_bytes(remote_path);
+            raw_sftp::read_file(session, &path, offset, &mut local, on_progress).await
+        }
+        Transport::Sftp(sftp) => {
+            let open = async {
+                sftp.open(remote_path)
+                    .await
+                    .map_err(|e| AppError::Sftp(format!("Failed to open file: {e}")))
+            };
+            let mut remote = session.timed(timeouts::metadata(), "open", open).await?;
+            if offset == 0 {
+                let size = remote
+                    .metadata()
+                    .await
+                    .ok()
+                    .and_then(|m| m.size)
+                    .unwrap_or(0);
+                let streams = session.range_streams(size);
+                if streams > 1 {
+                    drop(remote);
+                    return download_ranges(
+                        session,
+                        sftp,
+                        remote_path,
+                        local_path,
+                        size,
+                        streams,
+                        on_progress,
+                    )
+                    .await;
+                }
+            }
+            if offset > 0 {
+                remote
+                    .seek(std::io::SeekFrom::Start(offset))
+                    .await
+                    .map_err(|e| AppError::Sftp(format!("Failed to seek file: {e}")))?;
+            }
+            let mut local = open_local_at(local_path, offset).await?;
+
+            let chunk = memory::pick(DOWNLOAD_CHUNK_SIZE, LOW_MEMORY_CHUNK_SIZE);
+            let mut buf = vec![0u8; session.chunk_size(chunk)];
+            let mut total = offset;
+            loop {
+                let read = async {
+                    remote
+                        .read(&mut buf)
+                        .await
+                        .map_err(|e| AppError::Sftp(format!("Failed to download file: {e}")))
+                };
+                let n = session
+                    .timed(timeouts::transfer(), "download", read)
+                    .await?;
+                if n == 0 {
+                    break;
+                }
+                local.write_all(&buf[..n]).await.map_err(write_err)?;
+                total += n as u64;
+                on_progress(total);
+            }
+            local.flush().await.map_err(write_err)?;
+            Ok(total)
+        }
+        Transport::Scp => {
+            // `scp -f` cannot start mid-file, so the kept bytes are fetched
+            // again. Reads are timed individually inside the SCP transfer,
+            // and each chunk goes to disk before the next is read.
+            let mut local = open_local_at(local_path, 0).await?;
+            scp::download_to(session, remote_path, &mut local, on_progress).await
+        }
+    }
+}
+
+/// Open `local_path` for writing at `offset`, keeping the bytes before it
+/// and dropping any after; a zero offset starts a new file.
+async fn open_local_at(local_path: &std::path::Path, offset: u64) -> AppResult<tokio::fs::File> {
+    use tokio::io::AsyncSeekExt;
+
+    let write_err = |e: std::io::Error| AppError::Io(format!("Failed to write local file: {e}"));
+    if offset == 0 {
+        return tokio::fs::File::create(local_path).await.map_err(write_err);
+    }
+    let mut file = tokio::fs::OpenOptions::new()
+        .write(true)
+        .open(local_path)
         .await
-        .map_err(|e| AppError::Sftp(format!("Failed to read directory: {e}")))?;
-    let readdir_ms = readdir_start.elapsed().as_secs_f64() * 1000.0;
+        .map_err(write_err)?;
+    file.set_len(offset).await.map_err(write_err)?;
+    file.seek(std::io::SeekFrom::Start(offset))
+        .await
+        .map_err(write_err)?;
+    Ok(file)
+}
 
-    let mut files: Vec<FileEntry> = Vec::new();
-    for entry in entries {
-        let name = entry.file_name();
-        if name == "." || name == ".." {
-            continue;
+/// One range of a ranged transfer, driven by [`drive_ranges`].
+type RangeFuture<'a> = Pin<Box<dyn Future<Output = AppResult<()>> + Send + 'a>>;
+
+/// Split `size` bytes into at most `streams` contiguous `(start, end)` ranges.
+fn split_ranges(size: u64, streams: usize) -> Vec<(u64, u64)> {
+    let len = size.div_ceil(streams.max(1) as u64).max(1);
+    (0..size)
+        .step_by(len as usize)
+        .map(|start| (start, (start + len).min(size)))
+        .collect()
+}
+
+/// Run the range transfers concurrently on the current task, reporting the
+/// bytes moved by all of them through `on_progress`. The first error stops
+/// the rest.
+async fn drive_ranges<F>(
+    mut ranges: Vec<RangeFuture<'_>>,
+    done: &AtomicU64,
+    on_progress: &F,
+) -> AppResult<()>
+where
+    F: Fn(u64),
+{
+    let mut reported = 0;
+    std::future::poll_fn(|cx| {
+        let mut i = 0;
+        while i < ranges.len() {
+            match ranges[i].as_mut().poll(cx) {
+                Poll::Ready(Ok(())) => drop(ranges.swap_remove(i)),
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Pending => i += 1,
+            }
+        }
+        let now = done.load(Ordering::Relaxed);
+        if now != reported {
+            reported = now;
+            on_progress(now);
         }
-        let full_path = if path.ends_with('/') {
-            format!("{path}{name}")
+        if ranges.is_empty() {
+            Poll::Ready(Ok(()))
         } else {
-            format!("{path}/{name}")
+            Poll::Pending
+        }
+    })
+    .await
+}
+
+/// Download `size` bytes of `remote_path` as `streams` concurrent ranges,
+/// each over its own SFTP handle and written at the same position in
+/// `local_path`. Round trips overlap, so long-haul links get closer to
+/// their bandwidth than with one stream.
+async fn download_ranges<F>(
+    session: &Arc<SshSession>,
+    sftp: &SftpSession,
+    remote_path: &str,
+    local_path: &std::path::Path,
+    size: u64,
+    streams: usize,
+    on_progress: F,
+) -> AppResult<u64>
+where
+    F: Fn(u64),
+{
+    let start = std::time::Instant::now();
+    let write_err = |e: std::io::Error| AppError::Io(format!("Failed to write local file: {e}"));
+    drop(
+        tokio::fs::File::create(local_path)
+            .await
+            .map_err(write_err)?,
+    );
+
+    let done = AtomicU64::new(0);
+    let spans = split_ranges(size, streams);
+    let written: Vec<AtomicU64> = spans.iter().map(|_| AtomicU64::new(0)).collect();
+    let ranges = spans
+        .iter()
+        .zip(&written)
+        .map(|(&(from, to), written)| {
+            let range = download_range(
+                session,
+                sftp,
+                remote_path,
+                local_path,
+                from,
+                to,
+                &done,
+                written,
+            );
+            Box::pin(range) as RangeFuture
+        })
+        .collect();
+    if let Err(e) = drive_ranges(ranges, &done, &on_progress).await {
+        // Later ranges may have landed past a gap; keep only the leading
+        // bytes that are all present so a retry can append to them.
+        let mut prefix = 0;
+        for (&(from, to), written) in spans.iter().zip(&written) {
+            let n = written.load(Ordering::Relaxed);
+            prefix = from + n;
+            if n < to - from {
+                break;
+            }
+        }
+        if let Ok(file) = tokio::fs::OpenOptions::new()
+            .write(true)
+            .open(local_path)
+            .await
+        {
+            file.set_len(prefix).await.ok();
+        }
+        return Err(e);
+    }
+
+    log::info!(
+        "[PERF] ranged download \"{}\" — {:.2}ms | size: {} bytes | streams: {}",
+        remote_path,
+        start.elapsed().as_secs_f64() * 1000.0,
+        size,
+        streams,
+    );
+    Ok(size)
+}
+
+/// Copy bytes `from..to` of `remote_path` to the same range of `local_path`,
+/// counting the bytes of this range saved so far in `written`.
+#[allow(clippy::too_many_arguments)]
+async fn download_range(
+    session: &Arc<SshSession>,
+    sftp: &SftpSession,
+    remote_path: &str,
+    local_path: &std::path::Path,
+    from: u64,
+    to: u64,
+    done: &AtomicU64,
+    written: &AtomicU64,
+) -> AppResult<()> {
+    use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+    let write_err = |e: std::io::Error| AppError::Io(format!("Failed to write local file: {e}"));
+    let open = async {
+        sftp.open(remote_path)
+            .await
+            .map_err(|e| AppError::Sftp(format!("Failed to open file: {e}")))
+    };
+    let mut remote = session.timed(timeouts::metadata(), "open", open).await?;
+    remote
+        .seek(std::io::SeekFrom::Start(from))
+        .await
+        .map_err(|e| AppError::Sftp(format!("Failed to seek file: {e}")))?;
+    let mut local = tokio::fs::OpenOptions::new()
+        .write(true)
+        .open(local_path)
+        .await
+        .map_err(write_err)?;
+    local
+        .seek(std::io::SeekFrom::Start(from))
+        .await
+        .map_err(write_err)?;
+
+    let chunk = memory::pick(DOWNLOAD_CHUNK_SIZE, LOW_MEMORY_CHUNK_SIZE);
+    let mut buf = vec![0u8; session.chunk_size(chunk)];
+    let mut pos = from;
+    while pos < to {
+        let want = ((to - pos) as usize).min(buf.len());
+        let read = async {
+            remote
+                .read(&mut buf[..want])
+                .await
+                .map_err(|e| AppError::Sftp(format!("Failed to download file: {e}")))
         };
+        let n = session
+            .timed(timeouts::transfer(), "download", read)
+            .await?;
+        if n == 0 {
+            return Err(AppError::Sftp(format!(
+                "{remote_path} shrank during the download"
+            )));
+        }
+        local.write_all(&buf[..n]).await.map_err(write_err)?;
+        pos += n as u64;
+        done.fetch_add(n as u64, Ordering::Relaxed);
+        written.fetch_add(n as u64, Ordering::Relaxed);
+    }
+    local.flush().await.map_err(write_err)
+}
 
-        let attrs = &entry.metadata();
-        let is_dir = attrs.is_dir();
-        let size = attrs.size.unwrap_or(0);
-        let modified = attrs.mtime.map(|t| {
-            chrono::DateTime::from_timestamp(t as i64, 0)
-                .map(|dt| dt.to_rfc3339())
-                .unwrap_or_default()
-        });
+/// Write `data` to `remote_path` as `streams` concurrent ranges, each over
+/// its own SFTP handle. The file is created (or truncated) first.
+async fn upload_ranges<F>(
+    session: &Arc<SshSession>,
+    sftp: &SftpSession,
+    remote_path: &str,
+    data: &[u8],
+    streams: usize,
+    on_progress: F,
+) -> AppResult<()>
+where
+    F: Fn(u64),
+{
+    let create = async {
+        sftp.create(remote_path)
+            .await
+            .map_err(|e| AppError::Sftp(format!("Failed to create file for upload: {e}")))
+    };
+    drop(
+        session
+            .timed(timeouts::metadata(), "create", create)
+            .await?,
+    );
 
-        let is_image = if is_dir { false } else { is_image_ext(&name) };
-        files.push(FileEntry {
-            name,
-            path: full_path,
-            is_dir,
-            size,
-            modified,
-            is_image,
-        });
+    let done = AtomicU64::new(0);
+    let ranges = split_ranges(data.len() as u64, streams)
+        .into_iter()
+        .map(|(from, to)| {
+            let slice = &data[from as usize..to as usize];
+            let range = upload_range(session, sftp, remote_path, slice, from, &done);
+            Box::pin(range) as RangeFuture
+        })
+        .collect();
+    drive_ranges(ranges, &done, &on_progress).await
+}
+
+/// Write `data` at position `from` of the existing file `remote_path`.
+async fn upload_range(
+    session: &Arc<SshSession>,
+    sftp: &SftpSession,
+    remote_path: &str,
+    data: &[u8],
+    from: u64,
+    done: &AtomicU64,
+) -> AppResult<()> {
+    use russh_sftp::protocol::OpenFlags;
+    use tokio::io::AsyncSeekExt;
+
+    let open = async {
+        sftp.open_with_flags(remote_path, OpenFlags::WRITE)
+            .await
+            .map_err(|e| AppError::Sftp(format!("Failed to open file for upload: {e}")))
+    };
+    let mut file = session.timed(timeouts::metadata(), "open", open).await?;
+    file.seek(std::io::SeekFrom::Start(from))
+        .await
+        .map_err(|e| AppError::Sftp(format!("Failed to seek file: {e}")))?;
+    let chunk_size = memory::pick(UPLOAD_CHUNK_SIZE, LOW_MEMORY_CHUNK_SIZE);
+    for chunk in data.chunks(session.chunk_size(chunk_size)) {
+        let write = async {
+            file.write_all(chunk)
+                .await
+                .map_err(|e| AppError::Sftp(format!("Failed to write file data: {e}")))
+        };
+        session.timed(timeouts::transfer(), "upload", write).await?;
+        done.fetch_add(chunk.len() as u64, Ordering::Relaxed);
     }
+    file.shutdown()
+        .await
+        .map_err(|e| AppError::Sftp(format!("Failed to close file: {e}")))
+}
 
-    // Sort: directories first, then by name
-    files.sort_by(|a, b| {
-        b.is_dir
-            .cmp(&a.is_dir)
-            .then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase()))
-    });
+/// Refuse files larger than `limit` bytes. `None` disables the check.
+pub async fn check_size_limit(
+    session: &Arc<SshSession>,
+    path: &str,
+    limit: Option<u64>,
+) -> AppResult<()> {
+    let Some(limit) = limit else {
+        return Ok(());
+    };
+    match file_size(session, path).await? {
+        Some(size) if size > limit => Err(AppError::TooLarge { size, limit }),
+        _ => Ok(()),
+    }
+}
+
+/// Read a whole remote file, via SFTP or the SCP fallback.
+async fn read_all(session: &Arc<SshSession>, path: &str, what: &str) -> AppResult<Vec<u8>> {
+    let _op = session.ops.track("read", path);
+    match session.transport().await? {
+        Transport::Sftp(_) if raw_sftp::is_raw(path) => {
+            let mut data = Vec::new();
+            let bytes = raw_sftp::path_bytes(path);
+            raw_sftp::read_file(session, &bytes, 0, &mut data, |_| {}).await?;
+            Ok(data)
+        }
+        Transport::Sftp(sftp) => {
+            let read = async {
+                sftp.read(path)
+                    .await
+                    .map_err(|e| AppError::Sftp(format!("Failed to {what}: {e}")))
+            };
+            session.timed(timeouts::transfer(), what, read).await
+        }
+        // Timed per read, so a large file is not cut off mid-transfer.
+        Transport::Scp => scp::download(session, path).await,
+    }
+}
+
+/// List directory contents via SFTP.
+pub async fn list_dir(session: &Arc<SshSession>, path: &str) -> AppResult<Vec<FileEntry>> {
+    let total_start = std::time::Instant::now();
+    let _op = session.ops.track("list_dir", path);
+
+    let sftp_acquire_start = std::time::Instant::now();
+    let transport = session.transport().await?;
+    let sftp_acquire_ms = sftp_acquire_start.elapsed().as_secs_f64() * 1000.0;
+
+    let readdir_start = std::time::Instant::now();
+    let mut files: Vec<FileEntry> = match transport {
+        Transport::Sftp(sftp) => {
+            let read_dir = async {
+                sftp.read_dir(path)
+                    .await
+                    .map_err(|e| AppError::Sftp(format!("Failed to read directory: {e}")))
+            };
+            let entries = session
+                .timed(timeouts::metadata(), "list_dir", read_dir)
+                .await?;
+
+            let mut files = Vec::new();
+            for entry in entries {
+                let name = entry.file_name();
+                if name == "." || name == ".." {
+                    continue;
+                }
+                let attrs = &entry.metadata();
+                let mut file = make_entry(
+                    path,
+                    &name,
+                    attrs.is_dir(),
+                    attrs.size.unwrap_or(0),
+                    attrs.mtime,
+                );
+                file.is_symlink = is_link_mode(attrs.permissions);
+                file.mode = attrs.permissions.map(|p| p & 0o7777);
+                (file.uid, file.gid) = (attrs.uid, attrs.gid);
+                files.push(file);
+            }
+            raw_sftp::RawNames::default()
+                .mark(session, path, &mut files)
+                .await;
+            files
+        }
+        Transport::Scp => {
+            session
+                .timed(
+                    timeouts::metadata(),
+                    "list_dir",
+                    scp::list_dir(session, path),
+                )
+                .await?
+        }
+    };
+    let readdir_ms = readdir_start.elapsed().as_secs_f64() * 1000.0;
+    resolve_symlinks(session, &mut files).await;
+    sort_listing(&mut files);
 
     let total_ms = total_start.elapsed().as_secs_f64() * 1000.0;
     log::info!(
@@ -160,6 +1770,163 @@ pub async fn list_dir(session: &Arc<SshSession>, path: &str) -> AppResult<Vec<Fi
     Ok(files)
 }
 
+/// Sort a listing: directories first, then by name.
+fn sort_listing(files: &mut [FileEntry]) {
+    files.sort_by(|a, b| {
+        b.is_dir
+            .cmp(&a.is_dir)
+            .then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase()))
+    });
+}
+
+/// Interval between `ListProgress::Waiting` events.
+const LIST_HEARTBEAT: std::time::Duration = std::time::Duration::from_millis(500);
+
+/// Progress of a directory listing (safe to send to JS).
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ListProgress {
+    /// The server has not answered yet; sent periodically while it is silent.
+    Waiting { path: String, elapsed_ms: u64 },
+    /// Another batch arrived: `batch` holds its entries (symlinks not yet
+    /// resolved) and `entries` counts everything received so far.
+    Received {
+        path: String,
+        batch: Vec<FileEntry>,
+        entries: usize,
+        elapsed_ms: u64,
+    },
+}
+
+/// Await `work`, calling `on_tick` with the time since `start` every
+/// `LIST_HEARTBEAT` until it finishes.
+async fn with_heartbeat<T>(
+    work: impl std::future::Future<Output = T>,
+    start: std::time::Instant,
+    on_tick: impl Fn(u64),
+) -> T {
+    tokio::pin!(work);
+    let mut ticker = tokio::time::interval(LIST_HEARTBEAT);
+    ticker.tick().await;
+    loop {
+        tokio::select! {
+            out = &mut work => return out,
+            _ = ticker.tick() => on_tick(start.elapsed().as_millis() as u64),
+        }
+    }
+}
+
+/// [`list_dir`] for slow servers: entries are read batch by batch on the
+/// listing channel, with a `Received` event after each batch and `Waiting`
+/// events while the server is silent. Without a free listing channel (or
+/// over SCP) the plain listing runs with `Waiting` events only.
+pub async fn list_dir_with_progress<F: Fn(ListProgress)>(
+    session: &Arc<SshSession>,
+    path: &str,
+    on_progress: F,
+) -> AppResult<Vec<FileEntry>> {
+    let start = std::time::Instant::now();
+    let waiting = |elapsed_ms| {
+        on_progress(ListProgress::Waiting {
+            path: path.to_string(),
+            elapsed_ms,
+        })
+    };
+    let raw = match session.listing_sftp().await {
+        Ok(raw) => raw,
+        Err(e) => {
+            log::debug!(
+                "[SFTP] no listing channel ({}) — listing without batches",
+                e
+            );
+            return with_heartbeat(list_dir(session, path), start, waiting).await;
+        }
+    };
+    let _op = session.ops.track("list_dir", path);
+    let sftp_err = |e: russh_sftp::client::error::Error| {
+        AppError::Sftp(format!("Failed to read directory: {e}"))
+    };
+
+    let open = async { raw.opendir(path).await.map_err(sftp_err) };
+    let open = with_heartbeat(open, start, waiting);
+    let handle = session
+        .timed(timeouts::metadata(), "list_dir", open)
+        .await?
+        .handle;
+
+    let mut files = Vec::new();
+    let mut raw_names = raw_sftp::RawNames::default();
+    let listed = loop {
+        let batch = async {
+            match raw.readdir(handle.as_str()).await {
+                Ok(name) => Ok(Some(name.files)),
+                Err(russh_sftp::client::error::Error::Status(status))
+                    if status.status_code == russh_sftp::protocol::StatusCode::Eof =>
+                {
+                    Ok(None)
+                }
+                Err(e) => Err(sftp_err(e)),
+            }
+        };
+        let batch = with_heartbeat(batch, start, waiting);
+        match session.timed(timeouts::metadata(), "list_dir", batch).await {
+            Ok(Some(batch)) => {
+                let received = files.len();
+                for entry in batch {
+                    if entry.filename == "." || entry.filename == ".." {
+                        continue;
+                    }
+                    let attrs = &entry.attrs;
+                    let mut file = make_entry(
+                        path,
+                        &entry.filename,
+                        attrs.is_dir(),
+                        attrs.size.unwrap_or(0),
+                        attrs.mtime,
+                    );
+                    file.is_symlink = is_link_mode(attrs.permissions);
+                    file.mode = attrs.permissions.map(|p| p & 0o7777);
+                    (file.uid, file.gid) = (attrs.uid, attrs.gid);
+                    files.push(file);
+                }
+                raw_names.mark(session, path, &mut files[received..]).await;
+                on_progress(ListProgress::Received {
+                    path: path.to_string(),
+                    batch: files[received..].to_vec(),
+                    entries: files.len(),
+                    elapsed_ms: start.elapsed().as_millis() as u64,
+                });
+            }
+            Ok(None) => break Ok(()),
+            Err(e) => break Err(e),
+        }
+    };
+    raw.close(handle).await.ok();
+    listed?;
+    resolve_symlinks(session, &mut files).await;
+    sort_listing(&mut files);
+
+    log::info!(
+        "[PERF] list_dir_with_progress \"{}\" — total: {:.2}ms | entries: {}",
+        path,
+        start.elapsed().as_secs_f64() * 1000.0,
+        files.len(),
+    );
+    Ok(files)
+}
+
+/// List a directory and diff it against the snapshot identified by
+/// `previous_hash`, so unchanged refreshes return almost nothing.
+pub async fn list_dir_diff(
+    session: &Arc<SshSession>,
+    path: &str,
+    previous_hash: Option<&str>,
+) -> AppResult<DirDiff> {
+    let mut entries = list_dir(session, path).await?;
+    ignore::rules_for(&session.host).retain(&mut entries);
+    Ok(session.dir_snapshots.diff(path, previous_hash, entries))
+}
+
 /// Read a file preview (first N bytes).
 pub async fn read_file_preview(
     session: &Arc<SshSession>,
@@ -167,12 +1934,7 @@ pub async fn read_file_preview(
     max_bytes: usize,
 ) -> AppResult<FilePreview> {
     let start = std::time::Instant::now();
-    let sftp = session.sftp().await?;
-
-    let data = sftp
-        .read(path)
-        .await
-        .map_err(|e| AppError::Sftp(format!("Failed to read file: {e}")))?;
+    let data = read_all(session, path, "read file").await?;
 
     log::info!(
         "[PERF] read_file_preview \"{}\" — {:.2}ms | size: {} bytes",
@@ -181,84 +1943,205 @@ pub async fn read_file_preview(
         data.len(),
     );
 
+    Ok(preview_from_bytes(&data, max_bytes))
+}
+
+/// Build a preview of the first `max_bytes` of `data`, as text or base64.
+pub fn preview_from_bytes(data: &[u8], max_bytes: usize) -> FilePreview {
     let truncated = data.len() > max_bytes;
-    let preview_data = if truncated { &data[..max_bytes] } else { &data };
+    let preview_data = if truncated { &data[..max_bytes] } else { data };
 
     // Try to detect if it's text or binary
     let is_text = preview_data
         .iter()
         .all(|&b| b == b'\n' || b == b'\r' || b == b'\t' || (b >= 0x20 && b <= 0x7E) || b >= 0x80);
 
-    if is_text {
-        let text = String::from_utf8_lossy(preview_data).to_string();
-        Ok(FilePreview {
-            content: text,
-            is_text: true,
-            truncated,
-            total_size: data.len() as u64,
-        })
+    if is_text {
+        let text = String::from_utf8_lossy(preview_data).to_string();
+        FilePreview {
+            content: text,
+            is_text: true,
+            truncated,
+            total_size: data.len() as u64,
+            matches: Vec::new(),
+            chunks: 0,
+        }
+    } else {
+        let b64 = base64::Engine::encode(&base64::engine::general_purpose::STANDARD, preview_data);
+        FilePreview {
+            content: b64,
+            is_text: false,
+            truncated,
+            total_size: data.len() as u64,
+            matches: Vec::new(),
+            chunks: 0,
+        }
+    }
+}
+
+/// Fill `placeholder_color` for image and font entries that already have a
+/// cached thumbnail.
+pub fn enrich_placeholder_colors(entries: &mut [FileEntry], thumbs: &DiskCache) {
+    let has_thumb = |e: &FileEntry| e.is_image || e.category == FileCategory::Fonts;
+    for entry in entries.iter_mut().filter(|e| has_thumb(e)) {
+        entry.placeholder_color = thumbs.meta(&cache::key(&entry.path)).and_then(|m| m.color);
+    }
+}
+
+/// Preview cache key for the first `max_bytes` of `path` on `user@host`.
+pub(crate) fn preview_key(host: &str, user: &str, path: &str, max_bytes: usize) -> String {
+    cache::key(&format!("{user}@{host}:{path}#{max_bytes}"))
+}
+
+/// [`read_file_preview`] through `cache`. Entries are keyed by host, path
+/// and `max_bytes` and validated against the remote mtime and size, so
+/// reopening an unchanged file is served locally. `limit` is the in-memory
+/// size check applied before a download.
+pub async fn cached_file_preview(
+    session: &Arc<SshSession>,
+    path: &str,
+    max_bytes: usize,
+    cache: &DiskCache,
+    validator: Validator,
+    limit: Option<u64>,
+) -> AppResult<FilePreview> {
+    let key = preview_key(&session.host, &session.user, path, max_bytes);
+    let (lookup, validator) =
+        cache_lookup(session, path, &key, cache, PREVIEW_EXT, validator).await;
+    if let Lookup::Fresh(entry) | Lookup::Unvalidated(entry) = lookup {
+        let cached = tokio::fs::read(&entry.path)
+            .await
+            .ok()
+            .and_then(|data| serde_json::from_slice::<FilePreview>(&data).ok());
+        if let Some(preview) = cached {
+            log::info!(
+                "[CACHE] preview cache hit for \"{}\" — skipping download",
+                path
+            );
+            return Ok(preview);
+        }
+    }
+
+    check_size_limit(session, path, limit).await?;
+    let preview = read_file_preview(session, path, max_bytes).await?;
+    if validator.is_known() {
+        let stored = match serde_json::to_vec(&preview) {
+            Ok(json) => {
+                let meta = EntryMeta {
+                    validator,
+                    color: None,
+                };
+                cache
+                    .store(&key, PREVIEW_EXT, &json, &meta)
+                    .await
+                    .map(|_| ())
+            }
+            Err(e) => Err(AppError::Other(e.to_string())),
+        };
+        if let Err(e) = stored {
+            log::warn!("[CACHE] failed to cache preview of \"{}\": {}", path, e);
+        }
+    }
+    Ok(preview)
+}
+
+/// Look up `key` (an entry built from `path`) in `cache`, first resolving
+/// an unknown validator with a remote stat (a conditional check). Returns
+/// the lookup and the validator to store a refreshed entry under.
+async fn cache_lookup(
+    session: &Arc<SshSession>,
+    path: &str,
+    key: &str,
+    cache: &DiskCache,
+    ext: &str,
+    validator: Validator,
+) -> (Lookup, Validator) {
+    let validator = if validator.is_known() {
+        validator
     } else {
-        let b64 = base64::Engine::encode(&base64::engine::general_purpose::STANDARD, preview_data);
-        Ok(FilePreview {
-            content: b64,
-            is_text: false,
-            truncated,
-            total_size: data.len() as u64,
-        })
-    }
+        // Unreachable remote: the lookup comes back Unvalidated and callers
+        // keep serving what they have.
+        stat(session, path)
+            .await
+            .map(|st| Validator {
+                mtime: st.mtime.map(u64::from),
+                size: st.size,
+            })
+            .unwrap_or_default()
+    };
+    (cache.lookup(key, ext, validator), validator)
 }
 
-/// Fetch a small slice of an image for thumbnail display.
-/// Downloads up to 10MB of the file and uses libvips to decode and generate
-/// a fast WebP thumbnail natively, returning a base64 string.
+/// Return a base64 WebP thumbnail for an image, from the cache when fresh.
+///
+/// With `stale_ok`, an outdated cached thumbnail is returned immediately and
+/// regenerated in the background (stale-while-revalidate). Low-memory mode
+/// skips the background work and regenerates inline instead.
 pub async fn get_thumbnail(
     session: &Arc<SshSession>,
     path: &str,
-    _max_bytes: usize, // Ignored, we cap at 10MB now.
-    cache_dir: &std::path::Path,
-    remote_mtime: Option<u64>,
+    cache: &DiskCache,
+    validator: Validator,
+    stale_ok: bool,
 ) -> AppResult<String> {
-    use tokio::io::AsyncReadExt;
-
-    // Build a stable cache filename
-    let safe_key = base64::Engine::encode(
-        &base64::engine::general_purpose::URL_SAFE_NO_PAD,
-        path.as_bytes(),
-    );
-    let cache_file = cache_dir.join(format!("{safe_key}_thumb.webp"));
-
-    // Mtime-based freshness: reuse cached thumbnail only if it was written
-    // after the remote file was last modified.
-    if cache_file.exists() {
-        let fresh = if let Some(remote_mt) = remote_mtime {
-            std::fs::metadata(&cache_file)
-                .ok()
-                .and_then(|m| m.modified().ok())
-                .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
-                .map(|d| d.as_secs() >= remote_mt)
-                .unwrap_or(false)
-        } else {
-            true // no mtime info — trust existing cache
-        };
-
-        if fresh {
-            if let Ok(data) = tokio::fs::read(&cache_file).await {
-                let b64 =
-                    base64::Engine::encode(&base64::engine::general_purpose::STANDARD, &data);
-                log::info!(
-                    "[CACHE] thumbnail cache hit for \"{}\" — skipping download",
-                    path
-                );
-                return Ok(b64);
-            }
-        } else {
+    let key = cache::key(path);
+    let (lookup, validator) = cache_lookup(session, path, &key, cache, THUMB_EXT, validator).await;
+    let cached = match lookup {
+        Lookup::Fresh(entry) | Lookup::Unvalidated(entry) => Some(entry),
+        Lookup::Stale(entry) if stale_ok && !memory::is_low_memory() => {
+            let (owned, path_owned, cache) = (session.clone(), path.to_string(), cache.clone());
+            session.ops.spawn(async move {
+                if let Err(e) = build_thumbnail(&owned, &path_owned, &cache, validator).await {
+                    log::warn!("[CACHE] background thumbnail refresh failed: {}", e);
+                }
+            });
+            Some(entry)
+        }
+        Lookup::Stale(_) => {
+            log::info!("[CACHE] thumbnail stale for \"{}\" — regenerating", path);
+            None
+        }
+        Lookup::Miss => None,
+    };
+    if let Some(entry) = cached {
+        if let Ok(data) = tokio::fs::read(&entry.path).await {
             log::info!(
-                "[CACHE] thumbnail stale for \"{}\" — remote mtime is newer, regenerating",
+                "[CACHE] thumbnail cache hit for \"{}\" — skipping download",
                 path
             );
+            return Ok(base64::Engine::encode(
+                &base64::engine::general_purpose::STANDARD,
+                &data,
+            ));
         }
     }
 
+    let webp_data = build_thumbnail(session, path, cache, validator).await?;
+    Ok(base64::Engine::encode(
+        &base64::engine::general_purpose::STANDARD,
+        &webp_data,
+    ))
+}
+
+/// Download up to 10MB of the image (JPEGs only as much of the prefix as
+/// decodes successfully), generate a WebP thumbnail (libvips shrink-on-load
+/// when available, see [`thumbnail`]) and store it in `cache`. Fonts are
+/// rendered by [`build_font_thumbnail`] instead.
+async fn build_thumbnail(
+    session: &Arc<SshSession>,
+    path: &str,
+    cache: &DiskCache,
+    validator: Validator,
+) -> AppResult<Vec<u8>> {
+    use tokio::io::AsyncReadExt;
+
+    // Thumbnails are background work: yield the connection to exclusive operations.
+    session.ops.wait_background().await;
+    let _op = session.ops.track("thumbnail", path);
+    if file_types::is_font(path) {
+        return build_font_thumbnail(session, path, cache, validator).await;
+    }
+
     let start = std::time::Instant::now();
     let sftp = session.sftp().await?;
 
@@ -267,172 +2150,181 @@ pub async fn get_thumbnail(
         .await
         .map_err(|e| AppError::Sftp(format!("Failed to open image for thumbnail: {e}")))?;
 
-    // Download up to 10MB
-    let limit: u64 = 10 * 1024 * 1024;
+    // JPEGs usually decode from a prefix (progressive scans, or a partial
+    // baseline image), so fetch them in growing steps; others get one read.
+    let limit = memory::pick(THUMB_MAX_READ_BYTES, THUMB_MAX_READ_BYTES_LOW);
+    let is_jpeg = matches!(
+        path.rsplit('.')
+            .next()
+            .map(|e| e.to_ascii_lowercase())
+            .as_deref(),
+        Some("jpg" | "jpeg")
+    );
+    let mut want = if is_jpeg {
+        THUMB_PARTIAL_START_BYTES
+    } else {
+        limit
+    };
     let mut buf = Vec::new();
-    let n = file
-        .take(limit)
-        .read_to_end(&mut buf)
+    let (webp_data, color) = loop {
+        let missing = want - buf.len() as u64;
+        let got = (&mut file)
+            .take(missing)
+            .read_to_end(&mut buf)
+            .await
+            .map_err(|e| AppError::Sftp(format!("Failed to read thumbnail bytes: {e}")))?;
+        let at_eof = (got as u64) < missing;
+
+        // Spawn blocking task for CPU-intensive image processing
+        let (returned, result) = tokio::task::spawn_blocking(move || {
+            let process_start = std::time::Instant::now();
+            let result = thumbnail::webp_thumbnail(&buf).map(|webp_data| {
+                let color = thumbnail::average_color(&webp_data);
+                (webp_data, color)
+            });
+            log::info!(
+                "[PERF] thumbnail processing — {:.2}ms | input: {} bytes",
+                process_start.elapsed().as_secs_f64() * 1000.0,
+                buf.len(),
+            );
+            (buf, result)
+        })
         .await
-        .map_err(|e| AppError::Sftp(format!("Failed to read thumbnail bytes: {e}")))?;
-
-    let read_ms = start.elapsed().as_secs_f64() * 1000.0;
-
-    // Spawn blocking task for CPU-intensive image processing
-    let (b64, webp_data) = tokio::task::spawn_blocking(move || {
-        let process_start = std::time::Instant::now();
-
-        // 1. Decode image from raw bytes
-        let img = image::load_from_memory(&buf)
-            .map_err(|e| AppError::Sftp(format!("Image decode failed: {e}")))?;
-
-        // 2. Setup fast_image_resize Source image
-        let width = img.width().max(1);
-        let height = img.height().max(1);
-        let src_image = fast_image_resize::images::Image::from_vec_u8(
-            width,
-            height,
-            img.to_rgba8().into_raw(),
-            fast_image_resize::PixelType::U8x4,
-        )
-        .map_err(|e| AppError::Sftp(format!("Failed to create fir source image: {e}")))?;
-
-        // 3. Setup fast_image_resize Destination image (256x256 max bounds, maintaining aspect ratio)
-        let aspect_ratio = img.width() as f32 / img.height() as f32;
-        let (dst_width, dst_height) = if aspect_ratio > 1.0 {
-            (256, (256.0 / aspect_ratio).round() as u32)
-        } else {
-            ((256.0 * aspect_ratio).round() as u32, 256)
-        };
-        let dst_width = dst_width.max(1);
-        let dst_height = dst_height.max(1);
-
-        let mut dst_image = fast_image_resize::images::Image::new(
-            dst_width,
-            dst_height,
-            fast_image_resize::PixelType::U8x4,
-        );
+        .map_err(|e| AppError::Sftp(format!("Thumbnail task panicked: {e}")))?;
+        buf = returned;
+
+        match result {
+            Ok(thumb) => break thumb,
+            Err(e) if !at_eof && want < limit => {
+                log::debug!(
+                    "[CACHE] partial thumbnail decode failed at {} bytes, fetching more: {}",
+                    buf.len(),
+                    e
+                );
+                want = (want * 2).min(limit);
+            }
+            Err(e) => {
+                log::error!("[CMD] sftp_get_thumbnail Error \"{}\": {}", path, e);
+                return Err(e);
+            }
+        }
+    };
+    let n = buf.len();
 
-        // 4. Resize using Bilinear filter for speed
-        let mut resizer = fast_image_resize::Resizer::new();
-        resizer
-            .resize(
-                &src_image,
-                &mut dst_image,
-                &fast_image_resize::ResizeOptions::new().resize_alg(
-                    fast_image_resize::ResizeAlg::Convolution(
-                        fast_image_resize::FilterType::Bilinear,
-                    ),
-                ),
-            )
-            .map_err(|e| AppError::Sftp(format!("Image resize failed: {e}")))?;
-
-        // 5. Convert back to image crate types and encode WebP
-        let resized_img = image::ImageBuffer::<image::Rgba<u8>, _>::from_raw(
-            dst_width,
-            dst_height,
-            dst_image.into_vec(),
-        )
-        .ok_or_else(|| AppError::Sftp("Failed to convert resized buffer".into()))?;
+    log::info!(
+        "[PERF] get_thumbnail \"{}\" — total: {:.2}ms | bytes_read: {} (up to 10MB)",
+        path,
+        start.elapsed().as_secs_f64() * 1000.0,
+        n,
+    );
 
-        let dynamic_img = image::DynamicImage::ImageRgba8(resized_img);
-        let mut webp_buf = std::io::Cursor::new(Vec::new());
-        // Using `write_to` with standard WebP format (which we enabled in Cargo.toml via webp feature)
-        dynamic_img
-            .write_to(&mut webp_buf, image::ImageFormat::WebP)
-            .map_err(|e| AppError::Sftp(format!("WebP encoding failed: {e}")))?;
+    let meta = EntryMeta { validator, color };
+    if let Err(e) = cache
+        .store(&cache::key(path), THUMB_EXT, &webp_data, &meta)
+        .await
+    {
+        log::warn!("Failed to save thumbnail to cache: {}", e);
+    }
 
-        let webp_data = webp_buf.into_inner();
-        let b64_str =
-            base64::Engine::encode(&base64::engine::general_purpose::STANDARD, &webp_data);
+    Ok(webp_data)
+}
 
-        log::info!(
-            "[PERF] fast_image_resize processing — {:.2}ms",
-            process_start.elapsed().as_secs_f64() * 1000.0
-        );
+/// Download a font (up to 8MB), render a sample string with it as a WebP
+/// preview (see [`font_thumbnail`]) and store it in `cache`.
+async fn build_font_thumbnail(
+    session: &Arc<SshSession>,
+    path: &str,
+    cache: &DiskCache,
+    validator: Validator,
+) -> AppResult<Vec<u8>> {
+    let start = std::time::Instant::now();
+    let buf = read_head(session, path, FONT_MAX_READ_BYTES + 1).await?;
+    if buf.len() as u64 > FONT_MAX_READ_BYTES {
+        return Err(AppError::Other(format!(
+            "Font is larger than {} MB, not previewed",
+            FONT_MAX_READ_BYTES / (1024 * 1024)
+        )));
+    }
+    let n = buf.len();
 
-        Ok::<_, AppError>((b64_str, webp_data))
+    let (webp_data, color) = tokio::task::spawn_blocking(move || {
+        font_thumbnail::webp_thumbnail(&buf).map(|webp_data| {
+            let color = thumbnail::average_color(&webp_data);
+            (webp_data, color)
+        })
     })
     .await
     .map_err(|e| AppError::Sftp(format!("Thumbnail task panicked: {e}")))?
-    .map_err(|e| {
-        log::error!("[CMD] sftp_get_thumbnail Error \"{}\": {}", path, e);
-        e
-    })?;
+    .inspect_err(|e| log::error!("[CMD] sftp_get_thumbnail Error \"{}\": {}", path, e))?;
 
     log::info!(
-        "[PERF] get_thumbnail \"{}\" — total: {:.2}ms | bytes_read: {} (up to 10MB)",
+        "[PERF] get_thumbnail (font) \"{}\" — total: {:.2}ms | bytes_read: {}",
         path,
         start.elapsed().as_secs_f64() * 1000.0,
         n,
     );
 
-    // Write to cache in the background (we can just await it since it's tiny)
-    if let Err(e) = tokio::fs::write(&cache_file, &webp_data).await {
+    let meta = EntryMeta { validator, color };
+    if let Err(e) = cache
+        .store(&cache::key(path), THUMB_EXT, &webp_data, &meta)
+        .await
+    {
         log::warn!("Failed to save thumbnail to cache: {}", e);
     }
 
-    // Background LRU eviction — keep thumbnail dir under THUMB_CACHE_MAX_BYTES
-    if !THUMB_EVICTION_RUNNING.swap(true, Ordering::Relaxed) {
-        let dir = cache_dir.to_path_buf();
-        tokio::task::spawn_blocking(move || {
-            evict_cache_lru(&dir, THUMB_CACHE_MAX_BYTES);
-            THUMB_EVICTION_RUNNING.store(false, Ordering::Relaxed);
-        });
-    }
-
-    Ok(b64)
+    Ok(webp_data)
 }
 
-/// Download a full image to the local cache dir and return the cached path.
-/// Uses mtime-based freshness: skips download if the cached file's mtime matches the remote.
+/// Download a full image to the image cache and return the cached path,
+/// reusing the cached copy while it is fresh.
+///
+/// Images larger than `bypass_over` bytes are streamed to the cache's
+/// bypass directory instead and never enter the LRU. `None` caches any size.
 pub async fn cache_image(
     session: &Arc<SshSession>,
     path: &str,
-    cache_dir: &std::path::Path,
-    remote_mtime: Option<u64>,
+    cache: &DiskCache,
+    validator: Validator,
+    bypass_over: Option<u64>,
 ) -> AppResult<String> {
     let start = std::time::Instant::now();
-
-    // Build a stable cache filename: sha256 is heavy, so we use a URL-safe base64 of the path.
     let ext = path.rsplit('.').next().unwrap_or("bin");
-    let safe_key = base64::Engine::encode(
-        &base64::engine::general_purpose::URL_SAFE_NO_PAD,
-        path.as_bytes(),
-    );
-    let cache_file = cache_dir.join(format!("{safe_key}.{ext}"));
-
-    // Check freshness: if cached file exists and mtime matches, skip download.
-    if cache_file.exists() {
-        if let Some(remote_mt) = remote_mtime {
-            if let Ok(meta) = std::fs::metadata(&cache_file) {
-                if let Ok(modified) = meta.modified() {
-                    let cached_ts = modified
-                        .duration_since(std::time::UNIX_EPOCH)
-                        .map(|d| d.as_secs())
-                        .unwrap_or(0);
-                    if cached_ts >= remote_mt {
-                        log::info!("[CACHE] cache hit for \"{}\" — skipping download", path);
-                        return Ok(cache_file.to_string_lossy().to_string());
-                    }
-                }
+
+    let key = cache::key(path);
+    let (lookup, validator) = cache_lookup(session, path, &key, cache, ext, validator).await;
+    match lookup {
+        Lookup::Fresh(entry) | Lookup::Unvalidated(entry) => {
+            log::info!("[CACHE] cache hit for \"{}\" — skipping download", path);
+            return Ok(entry.path.to_string_lossy().to_string());
+        }
+        Lookup::Stale(_) | Lookup::Miss => {}
+    }
+
+    if let (Some(size), Some(limit)) = (validator.size, bypass_over) {
+        if size > limit {
+            let local = cache.bypass_path(&key, ext)?;
+            if let Err(e) = download_to_path(session, path, &local, |_| {}).await {
+                tokio::fs::remove_file(&local).await.ok();
+                return Err(e);
             }
-        } else {
-            // No mtime info — trust the existing cached file.
-            log::info!("[CACHE] cache hit (no mtime) for \"{}\"", path);
-            return Ok(cache_file.to_string_lossy().to_string());
+            log::info!(
+                "[PERF] cache_image \"{}\" — {:.2}ms | size: {} bytes | bypassed cache (limit: {})",
+                path,
+                start.elapsed().as_secs_f64() * 1000.0,
+                size,
+                limit,
+            );
+            return Ok(local.to_string_lossy().to_string());
         }
     }
 
     // Download full image.
-    let sftp = session.sftp().await?;
-    let data = sftp
-        .read(path)
-        .await
-        .map_err(|e| AppError::Sftp(format!("Failed to download image: {e}")))?;
-    tokio::fs::write(&cache_file, &data)
-        .await
-        .map_err(|e| AppError::Sftp(format!("Failed to write cached image: {e}")))?;
+    let data = read_all(session, path, "download image").await?;
+    let meta = EntryMeta {
+        validator,
+        color: None,
+    };
+    let cache_file = cache.store(&key, ext, &data, &meta).await?;
 
     log::info!(
         "[PERF] cache_image \"{}\" — {:.2}ms | size: {} bytes",
@@ -441,42 +2333,188 @@ pub async fn cache_image(
         data.len(),
     );
 
-    // Background LRU eviction — keep image cache dir under IMAGE_CACHE_MAX_BYTES
-    if !IMAGE_EVICTION_RUNNING.swap(true, Ordering::Relaxed) {
-        let dir = cache_dir.to_path_buf();
-        tokio::task::spawn_blocking(move || {
-            evict_cache_lru(&dir, IMAGE_CACHE_MAX_BYTES);
-            IMAGE_EVICTION_RUNNING.store(false, Ordering::Relaxed);
-        });
-    }
-
     Ok(cache_file.to_string_lossy().to_string())
 }
 
 /// Delete a remote file via SFTP.
 pub async fn delete_file(session: &Arc<SshSession>, path: &str) -> AppResult<()> {
+    let _op = session.ops.track("delete", path);
     let start = std::time::Instant::now();
     let sftp = session.sftp().await?;
-    sftp.remove_file(path)
-        .await
-        .map_err(|e| AppError::Sftp(format!("Failed to delete file: {e}")))?;
+    if raw_sftp::is_raw(path) {
+        raw_sftp::remove(session, &raw_sftp::path_bytes(path), false).await?;
+    } else {
+        let remove = async {
+            sftp.remove_file(path)
+                .await
+                .map_err(|e| AppError::Sftp(format!("Failed to delete file: {e}")))
+        };
+        session
+            .timed(timeouts::metadata(), "delete", remove)
+            .await?;
+    }
     log::info!(
         "[PERF] delete_file \"{}\" — {:.2}ms",
-        path,
+        raw_sftp::lossy_path(path),
         start.elapsed().as_secs_f64() * 1000.0,
     );
     Ok(())
 }
 
-/// Download a file via SFTP and return the bytes.
-pub async fn download_file(session: &Arc<SshSession>, path: &str) -> AppResult<Vec<u8>> {
+/// Rename `from` to `to` with the `posix-rename@openssh.com` extension,
+/// which replaces an existing `to` as POSIX rename does. `None` when the
+/// server doesn't have the extension.
+async fn posix_rename(session: &Arc<SshSession>, from: &str, to: &str) -> Option<AppResult<()>> {
+    fn put_string(buf: &mut Vec<u8>, s: &[u8]) {
+        buf.extend_from_slice(&(s.len() as u32).to_be_bytes());
+        buf.extend_from_slice(s);
+    }
+
+    let raw = session.listing_sftp().await.ok()?;
+    let mut request = Vec::new();
+    put_string(&mut request, from.as_bytes());
+    put_string(&mut request, to.as_bytes());
+    let rename = async {
+        raw.extended(raw_sftp::POSIX_RENAME, request)
+            .await
+            .map_err(|e| AppError::Sftp(format!("Failed to rename: {e}")))
+    };
+    match session.timed(timeouts::metadata(), "rename", rename).await {
+        Ok(russh_sftp::protocol::Packet::Status(status)) => match status.status_code {
+            russh_sftp::protocol::StatusCode::Ok => Some(Ok(())),
+            russh_sftp::protocol::StatusCode::OpUnsupported => None,
+            _ => Some(Err(AppError::Sftp(format!(
+                "Failed to rename: {}",
+                status.error_message
+            )))),
+        },
+        Ok(_) => None,
+        Err(e) => Some(Err(e)),
+    }
+}
+
+/// Rename or move `from` to `to`. Refuses to replace an existing `to`
+/// unless `overwrite` is set.
+///
+/// Uses the `posix-rename@openssh.com` extension, which replaces `to` as
+/// POSIX rename does. Servers without it get a plain SFTP rename, which
+/// they reject when the target exists, so `mv -f` over exec stands in
+/// for it there.
+///
+/// When `from` and the destination directory are on different
+/// filesystems this fails with [`AppError::CrossDevice`] instead, so the
+/// caller can copy with progress (see `transfer::move_by_copy`).
+pub async fn rename(
+    session: &Arc<SshSession>,
+    from: &str,
+    to: &str,
+    overwrite: bool,
+) -> AppResult<()> {
+    let _op = session.ops.track("rename", from);
     let start = std::time::Instant::now();
-    let sftp = session.sftp().await?;
+    if !overwrite && stat(session, to).await.is_ok() {
+        return Err(AppError::Sftp(format!("{to} already exists")));
+    }
 
-    let data = sftp
-        .read(path)
-        .await
-        .map_err(|e| AppError::Sftp(format!("Failed to download file: {e}")))?;
+    let mut via = "posix-rename";
+    let extension = match session.transport().await? {
+        Transport::Sftp(_) if raw_sftp::is_raw(from) || raw_sftp::is_raw(to) => {
+            let (from, to) = (raw_sftp::path_bytes(from), raw_sftp::path_bytes(to));
+            Some(raw_sftp::rename(session, &from, &to).await)
+        }
+        Transport::Sftp(_) => posix_rename(session, from, to).await,
+        Transport::Scp => None,
+    };
+    let can_fall_back = extension.is_none();
+    let renamed = match extension {
+        Some(renamed) => renamed,
+        None => {
+            via = "sftp";
+            match session.transport().await? {
+                Transport::Sftp(sftp) => {
+                    let rename = async {
+                        sftp.rename(from, to)
+                            .await
+                            .map_err(|e| AppError::Sftp(format!("Failed to rename: {e}")))
+                    };
+                    session.timed(timeouts::metadata(), "rename", rename).await
+                }
+                Transport::Scp => Err(AppError::SftpUnavailable(session.host.clone())),
+            }
+        }
+    };
+    if let Err(e) = renamed {
+        if crosses_filesystems(session, from, to).await == Some(true) {
+            log::info!(
+                "[SFTP] rename \"{}\" → \"{}\" crosses filesystems",
+                from,
+                to
+            );
+            return Err(AppError::CrossDevice(format!("{from} → {to}")));
+        }
+        if !can_fall_back {
+            return Err(e);
+        }
+        log::warn!(
+            "[SFTP] rename \"{}\" failed ({}) — falling back to mv",
+            from,
+            e
+        );
+        // `mv` moves into an existing directory instead of replacing it,
+        // which is not what a rename means.
+        if stat(session, to).await.is_ok_and(|st| st.is_dir) {
+            return Err(AppError::Sftp(format!(
+                "Failed to rename: {to} is a directory"
+            )));
+        }
+        via = "mv";
+        let command = format!(
+            "mv -f -- {} {}",
+            raw_sftp::shell_word(from)?,
+            raw_sftp::shell_word(to)?
+        );
+        let output = session.exec(&command).await?;
+        if output.exit_status != Some(0) {
+            return Err(AppError::Sftp(format!(
+                "Failed to rename: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+    }
+
+    log::info!(
+        "[PERF] rename \"{}\" → \"{}\" — {:.2}ms | via: {}",
+        from,
+        to,
+        start.elapsed().as_secs_f64() * 1000.0,
+        via,
+    );
+    Ok(())
+}
+
+/// Whether `from` and the directory `to` would be created in are on
+/// different filesystems, via `stat` over exec. `None` when that fails.
+async fn crosses_filesystems(session: &Arc<SshSession>, from: &str, to: &str) -> Option<bool> {
+    let command = format!(
+        "stat -c %d -- {} {}",
+        raw_sftp::shell_word(from).ok()?,
+        raw_sftp::shell_word(&raw_sftp::parent_path(to)).ok()?
+    );
+    let output = session.exec(&command).await.ok()?;
+    if output.exit_status != Some(0) {
+        return None;
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut devices = stdout.lines().map(str::trim);
+    let (src, dest) = (devices.next()?, devices.next()?);
+    Some(src != dest)
+}
+
+/// Download a whole file into memory. Callers bound the size first with
+/// [`check_size_limit`]; large files go through [`save_file`] instead.
+pub async fn download_file(session: &Arc<SshSession>, path: &str) -> AppResult<Vec<u8>> {
+    let start = std::time::Instant::now();
+    let data = read_all(session, path, "download file").await?;
 
     log::info!(
         "[PERF] download_file \"{}\" — {:.2}ms | size: {} bytes",
@@ -488,45 +2526,190 @@ pub async fn download_file(session: &Arc<SshSession>, path: &str) -> AppResult<V
     Ok(data)
 }
 
-/// Download a remote file via SFTP and save it to a local path.
-pub async fn save_file(
-    session: &Arc<SshSession>,
-    remote_path: &str,
-    local_path: &str,
-) -> AppResult<u64> {
-    let start = std::time::Instant::now();
-    let sftp = session.sftp().await?;
+/// Written next to a `.part` download: the remote file it is a prefix of,
+/// so a retry only appends when that file has not changed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PartialDownload {
+    pub remote_path: String,
+    pub size: u64,
+    pub mtime: Option<u32>,
+    /// Bytes already saved; the download continues from here.
+    pub offset: u64,
+    /// The `.part` file is being filled as concurrent ranges, so its length
+    /// says nothing about which bytes are present until a failure has cut
+    /// it back to the complete prefix.
+    #[serde(default)]
+    pub ranged: bool,
+}
 
-    let data = sftp
-        .read(remote_path)
-        .await
-        .map_err(|e| AppError::Sftp(format!("Failed to download file: {e}")))?;
+/// `local_path` with `suffix` appended to the file name.
+fn with_suffix(local_path: &std::path::Path, suffix: &str) -> std::path::PathBuf {
+    let mut name = local_path.file_name().unwrap_or_default().to_os_string();
+    name.push(suffix);
+    local_path.with_file_name(name)
+}
+
+/// Where a download to `local_path` is written until it completes.
+pub fn part_path(local_path: &std::path::Path) -> std::path::PathBuf {
+    with_suffix(local_path, ".part")
+}
+
+fn sidecar_path(local_path: &std::path::Path) -> std::path::PathBuf {
+    with_suffix(local_path, ".part.json")
+}
+
+/// The sidecar of an unfinished download to `local_path`, if any.
+pub fn partial_download(local_path: &std::path::Path) -> Option<PartialDownload> {
+    let data = std::fs::read(sidecar_path(local_path)).ok()?;
+    serde_json::from_slice(&data).ok()
+}
+
+fn write_sidecar(local_path: &std::path::Path, partial: &PartialDownload) -> AppResult<()> {
+    let data = serde_json::to_vec(partial)
+        .map_err(|e| AppError::Other(format!("Failed to encode partial download: {e}")))?;
+    std::fs::write(sidecar_path(local_path), data)
+        .map_err(|e| AppError::Io(format!("Failed to write partial download: {e}")))
+}
+
+/// Remove the `.part` file and sidecar of a download to `local_path`.
+pub fn discard_partial(local_path: &std::path::Path) {
+    std::fs::remove_file(part_path(local_path)).ok();
+    std::fs::remove_file(sidecar_path(local_path)).ok();
+}
 
-    let size = data.len() as u64;
+/// Stat `remote_path` and work out where a download of it to `local_path`
+/// starts: after the bytes of an earlier `.part` file if its sidecar shows
+/// the remote size and mtime unchanged, otherwise from the beginning.
+pub async fn prepare_partial(
+    session: &Arc<SshSession>,
+    remote_path: &str,
+    local_path: &std::path::Path,
+) -> AppResult<PartialDownload> {
+    let st = stat(session, remote_path).await?;
+    let size = st.size.unwrap_or(0);
+    let on_disk = std::fs::metadata(part_path(local_path)).map_or(0, |m| m.len());
+    let offset = match partial_download(local_path) {
+        Some(p) if p.ranged => {
+            log::info!(
+                "[TRANSFER] \"{}\" was interrupted mid ranged download, starting over",
+                remote_path
+            );
+            0
+        }
+        Some(p)
+            if p.remote_path == remote_path
+                && p.size == size
+                && p.mtime.is_some()
+                && p.mtime == st.mtime
+                && on_disk <= size =>
+        {
+            on_disk
+        }
+        Some(_) => {
+            log::info!(
+                "[TRANSFER] \"{}\" changed since the partial download, starting over",
+                remote_path
+            );
+            0
+        }
+        None => 0,
+    };
+    Ok(PartialDownload {
+        remote_path: remote_path.to_string(),
+        size,
+        mtime: st.mtime,
+        offset,
+        ranged: false,
+    })
+}
 
-    tokio::fs::write(local_path, &data)
+/// Stream a remote file to a local path in chunks, invoking `on_progress`
+/// with the cumulative bytes written.
+///
+/// Data goes to a `.part` file next to `local_path`, renamed into place
+/// once complete. If the download fails the `.part` file and its sidecar
+/// are kept, and a later call with the same [`prepare_partial`] result
+/// appends from `partial.offset` (see [`download_from_offset`]).
+pub async fn save_file<F>(
+    session: &Arc<SshSession>,
+    local_path: &std::path::Path,
+    mut partial: PartialDownload,
+    on_progress: F,
+) -> AppResult<u64>
+where
+    F: Fn(u64),
+{
+    let start = std::time::Instant::now();
+    let part = part_path(local_path);
+    partial.ranged = partial.offset == 0 && session.range_streams(partial.size) > 1;
+    write_sidecar(local_path, &partial)?;
+    let result = download_from_offset(
+        session,
+        &partial.remote_path,
+        &part,
+        partial.offset,
+        on_progress,
+    )
+    .await;
+    let size = match result {
+        Ok(size) => size,
+        Err(e) => {
+            // A failed ranged download has been cut back to its complete
+            // prefix, so the length is a safe resume point either way.
+            partial.offset = std::fs::metadata(&part).map_or(0, |m| m.len());
+            partial.ranged = false;
+            write_sidecar(local_path, &partial).ok();
+            return Err(e);
+        }
+    };
+    tokio::fs::rename(&part, local_path)
         .await
-        .map_err(|e| AppError::Sftp(format!("Failed to write local file: {e}")))?;
+        .map_err(|e| AppError::Io(format!("Failed to move finished download: {e}")))?;
+    std::fs::remove_file(sidecar_path(local_path)).ok();
 
     log::info!(
-        "[PERF] save_file \"{}\" -> \"{}\" — {:.2}ms | size: {} bytes",
-        remote_path,
-        local_path,
+        "[PERF] save_file \"{}\" -> \"{}\" — {:.2}ms | size: {} bytes | resumed at: {}",
+        partial.remote_path,
+        local_path.display(),
         start.elapsed().as_secs_f64() * 1000.0,
         size,
+        partial.offset,
     );
 
     Ok(size)
 }
 
+/// Apply explicit permission bits to a remote path via setstat.
+async fn set_mode(session: &Arc<SshSession>, path: &str, mode: u32) -> AppResult<()> {
+    let sftp = session.sftp().await?;
+    let attrs = russh_sftp::protocol::FileAttributes {
+        permissions: Some(mode & 0o7777),
+        ..Default::default()
+    };
+    sftp.set_metadata(path, attrs)
+        .await
+        .map_err(|e| AppError::Sftp(format!("Failed to set permissions: {e}")))
+}
+
 /// Create a directory on the remote server via SFTP.
-pub async fn create_dir(session: &Arc<SshSession>, path: &str) -> AppResult<()> {
+/// If `mode` is given it is applied after creation, overriding the umask.
+pub async fn create_dir(session: &Arc<SshSession>, path: &str, mode: Option<u32>) -> AppResult<()> {
     let start = std::time::Instant::now();
+    let _op = session.ops.track("create_dir", path);
     let sftp = session.sftp().await?;
 
-    sftp.create_dir(path)
-        .await
-        .map_err(|e| AppError::Sftp(format!("Failed to create directory: {e}")))?;
+    let mkdir = async {
+        sftp.create_dir(path)
+            .await
+            .map_err(|e| AppError::Sftp(format!("Failed to create directory: {e}")))
+    };
+    session
+        .timed(timeouts::metadata(), "create_dir", mkdir)
+        .await?;
+
+    if let Some(mode) = mode {
+        set_mode(session, path, mode).await?;
+    }
 
     log::info!(
         "[PERF] create_dir \"{}\" — {:.2}ms",
@@ -537,39 +2720,279 @@ pub async fn create_dir(session: &Arc<SshSession>, path: &str) -> AppResult<()>
     Ok(())
 }
 
+/// Create a new file at `path` holding `content` (empty for a blank file).
+/// Fails if anything already exists there, rather than overwriting it.
+pub async fn create_file(
+    session: &Arc<SshSession>,
+    path: &str,
+    content: &[u8],
+    mode: Option<u32>,
+) -> AppResult<()> {
+    let start = std::time::Instant::now();
+    let _op = session.ops.track("create_file", path);
+
+    match session.transport().await? {
+        Transport::Sftp(sftp) => {
+            use russh_sftp::protocol::OpenFlags;
+
+            // EXCLUDE makes the server refuse an existing path atomically.
+            let open = async {
+                sftp.open_with_flags(
+                    path,
+                    OpenFlags::CREATE | OpenFlags::EXCLUDE | OpenFlags::WRITE,
+                )
+                .await
+                .map_err(|e| AppError::Sftp(format!("Failed to create file (does it exist?): {e}")))
+            };
+            let mut file = session.timed(timeouts::metadata(), "create", open).await?;
+            let write = async {
+                file.write_all(content)
+                    .await
+                    .map_err(|e| AppError::Sftp(format!("Failed to write file data: {e}")))?;
+                file.shutdown()
+                    .await
+                    .map_err(|e| AppError::Sftp(format!("Failed to close file: {e}")))
+            };
+            session
+                .timed(timeouts::transfer(), "create_file", write)
+                .await?;
+            if let Some(mode) = mode {
+                set_mode(session, path, mode).await?;
+            }
+        }
+        Transport::Scp => {
+            if stat(session, path).await.is_ok() {
+                return Err(AppError::Sftp(format!("{path} already exists")));
+            }
+            let upload = scp::upload(session, path, content, mode.unwrap_or(0o644));
+            session
+                .timed(timeouts::transfer(), "create_file", upload)
+                .await?;
+        }
+    }
+
+    log::info!(
+        "[PERF] create_file \"{}\" — {:.2}ms | size: {} bytes",
+        path,
+        start.elapsed().as_secs_f64() * 1000.0,
+        content.len(),
+    );
+    Ok(())
+}
+
 /// Upload file data to a remote path via SFTP.
+/// If `mode` is given it is applied after the write, overriding the umask.
 pub async fn upload_file(
     session: &Arc<SshSession>,
     remote_path: &str,
     data: &[u8],
+    mode: Option<u32>,
 ) -> AppResult<()> {
+    upload_file_with_progress(session, remote_path, data, mode, |_| {}).await
+}
+
+/// Chunk size for progress-reporting uploads.
+const UPLOAD_CHUNK_SIZE: usize = 256 * 1024;
+
+/// Upload file data, invoking `on_progress` with the cumulative bytes written
+/// after each chunk.
+pub async fn upload_file_with_progress<F>(
+    session: &Arc<SshSession>,
+    remote_path: &str,
+    data: &[u8],
+    mode: Option<u32>,
+    on_progress: F,
+) -> AppResult<()>
+where
+    F: Fn(u64),
+{
+    upload_file_at(session, remote_path, data, 0, mode, on_progress).await
+}
+
+/// Where an interrupted upload of `data` to `remote_path` can continue:
+/// the size already on the server, or 0 if nothing is there, it is larger
+/// than `data`, or its last bytes differ from the same bytes of `data`
+/// (so it is not a prefix of this upload).
+pub async fn upload_resume_offset(
+    session: &Arc<SshSession>,
+    remote_path: &str,
+    data: &[u8],
+) -> AppResult<u64> {
+    let size = match stat(session, remote_path).await {
+        Ok(st) if st.is_dir => return Err(AppError::Sftp(format!("{remote_path} is a directory"))),
+        Ok(st) => st
+            .size
+            .filter(|&size| size <= data.len() as u64)
+            .unwrap_or(0),
+        Err(_) => return Ok(0),
+    };
+    if size == 0 {
+        return Ok(0);
+    }
+    let from = size.saturating_sub(RESUME_CHECK_BYTES);
+    let tail = read_range(session, remote_path, from, size - from).await?;
+    if tail != data[from as usize..size as usize] {
+        log::info!(
+            "[SFTP] \"{}\" on the server is not a prefix of this upload, starting over",
+            remote_path
+        );
+        return Ok(0);
+    }
+    Ok(size)
+}
+
+/// Upload `data`, keeping the first `offset` bytes already on the server
+/// and writing only the rest. With an offset the file is opened without
+/// truncation and written from that position, so an interrupted upload
+/// continues from the last confirmed byte. `on_progress` gets the
+/// cumulative size, counting the kept bytes.
+///
+/// The SCP fallback cannot write at an offset and uploads the whole file.
+pub async fn upload_file_at<F>(
+    session: &Arc<SshSession>,
+    remote_path: &str,
+    data: &[u8],
+    offset: u64,
+    mode: Option<u32>,
+    on_progress: F,
+) -> AppResult<()>
+where
+    F: Fn(u64),
+{
+    use russh_sftp::protocol::OpenFlags;
+    use tokio::io::AsyncSeekExt;
+
     let start = std::time::Instant::now();
-    let sftp = session.sftp().await?;
+    let _op = session.ops.track("upload", remote_path);
+    if offset > data.len() as u64 {
+        return Err(AppError::Sftp(format!(
+            "Upload offset {offset} is past the end of the data ({} bytes)",
+            data.len()
+        )));
+    }
 
-    let mut file = sftp
-        .create(remote_path)
-        .await
-        .map_err(|e| AppError::Sftp(format!("Failed to create file for upload: {e}")))?;
+    match session.transport().await? {
+        Transport::Sftp(sftp) if offset == 0 && session.range_streams(data.len() as u64) > 1 => {
+            let streams = session.range_streams(data.len() as u64);
+            upload_ranges(session, sftp, remote_path, data, streams, on_progress).await?;
+            if let Some(mode) = mode {
+                set_mode(session, remote_path, mode).await?;
+            }
+        }
+        Transport::Sftp(sftp) => {
+            let create = async {
+                // Without TRUNCATE the bytes already uploaded are kept.
+                let file = if offset > 0 {
+                    sftp.open_with_flags(remote_path, OpenFlags::CREATE | OpenFlags::WRITE)
+                        .await
+                } else {
+                    sftp.create(remote_path).await
+                };
+                file.map_err(|e| AppError::Sftp(format!("Failed to create file for upload: {e}")))
+            };
+            let mut file = session
+                .timed(timeouts::metadata(), "create", create)
+                .await?;
+            if offset > 0 {
+                file.seek(std::io::SeekFrom::Start(offset))
+                    .await
+                    .map_err(|e| AppError::Sftp(format!("Failed to seek file: {e}")))?;
+            }
 
-    file.write_all(data)
-        .await
-        .map_err(|e| AppError::Sftp(format!("Failed to write file data: {e}")))?;
+            let mut written = offset;
+            let chunk_size = memory::pick(UPLOAD_CHUNK_SIZE, LOW_MEMORY_CHUNK_SIZE);
+            for chunk in data[offset as usize..].chunks(session.chunk_size(chunk_size)) {
+                let write = async {
+                    file.write_all(chunk)
+                        .await
+                        .map_err(|e| AppError::Sftp(format!("Failed to write file data: {e}")))
+                };
+                session.timed(timeouts::transfer(), "upload", write).await?;
+                written += chunk.len() as u64;
+                on_progress(written);
+            }
+            if offset > 0 {
+                // Opened without TRUNCATE: drop anything past the new end.
+                let attrs = russh_sftp::protocol::FileAttributes {
+                    size: Some(data.len() as u64),
+                    ..Default::default()
+                };
+                let truncate = async {
+                    file.set_metadata(attrs)
+                        .await
+                        .map_err(|e| AppError::Sftp(format!("Failed to truncate file: {e}")))
+                };
+                session
+                    .timed(timeouts::metadata(), "truncate", truncate)
+                    .await?;
+            }
+
+            if let Some(mode) = mode {
+                set_mode(session, remote_path, mode).await?;
+            }
+        }
+        Transport::Scp => {
+            if offset > 0 {
+                log::info!(
+                    "[SFTP] SCP cannot resume \"{}\" at {}, uploading it whole",
+                    remote_path,
+                    offset
+                );
+            }
+            let upload = scp::upload(session, remote_path, data, mode.unwrap_or(0o644));
+            session
+                .timed(timeouts::transfer(), "upload", upload)
+                .await?;
+            on_progress(data.len() as u64);
+        }
+    }
 
     log::info!(
-        "[PERF] upload_file \"{}\" — {:.2}ms | size: {} bytes",
+        "[PERF] upload_file \"{}\" — {:.2}ms | size: {} bytes | resumed at: {}",
         remote_path,
         start.elapsed().as_secs_f64() * 1000.0,
         data.len(),
+        offset,
     );
 
     Ok(())
 }
 
 /// Preview result returned to the frontend.
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FilePreview {
     pub content: String,
     pub is_text: bool,
     pub truncated: bool,
     pub total_size: u64,
+    /// Hits of the search query the preview was opened with, if any.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub matches: Vec<PreviewMatch>,
+    /// Number of `PreviewChunk`s the content was streamed in instead of
+    /// being inlined here; 0 when `content` is complete.
+    #[serde(default)]
+    pub chunks: usize,
+}
+
+/// One piece of a preview streamed over a Tauri channel (safe to send to JS).
+#[derive(Debug, Clone, Serialize)]
+pub struct PreviewChunk {
+    pub index: usize,
+    pub data: String,
+}
+
+/// Split `content` into pieces of at most `max` bytes on char boundaries.
+pub fn chunk_text(content: &str, max: usize) -> Vec<&str> {
+    let mut chunks = Vec::new();
+    let mut rest = content;
+    while !rest.is_empty() {
+        let mut end = max.min(rest.len());
+        while !rest.is_char_boundary(end) {
+            end -= 1;
+        }
+        let (chunk, tail) = rest.split_at(end);
+        chunks.push(chunk);
+        rest = tail;
+    }
+    chunks
 }
@@ -0,0 +1,519 @@
+//! SFTP protocol tracing for bug reports. Every SFTP channel of a session
+//! is wrapped in a [`TracedStream`] that splits the byte stream into
+//! packets; while tracing is on, each request is written to a JSON-lines
+//! file with its reply, size and latency. Packets are handed to a writer
+//! thread, so the stream itself never waits on the disk.
+//!
+//! Traces are redacted: file contents are never recorded and each path
+//! component is replaced by a salted hash (extensions are kept), so a trace
+//! can be attached to an issue without revealing the server's layout.
+
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::Instant;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::sync::mpsc;
+
+use crate::errors::{AppError, AppResult};
+
+/// Bytes of each packet kept for decoding; the rest (file data) is skipped.
+const PACKET_PREFIX_BYTES: usize = 1024;
+
+// ─── Packet Scanning ───────────────────────────────────────────────────
+
+/// Splits a byte stream into SFTP packets (`u32` length, then the body),
+/// keeping only the first [`PACKET_PREFIX_BYTES`] of each body.
+#[derive(Default)]
+struct Scanner {
+    header: [u8; 4],
+    header_len: usize,
+    body: Vec<u8>,
+    remaining: usize,
+}
+
+impl Scanner {
+    fn feed(&mut self, mut data: &[u8], mut on_packet: impl FnMut(&[u8])) {
+        while !data.is_empty() {
+            if self.header_len < 4 {
+                let n = (4 - self.header_len).min(data.len());
+                self.header[self.header_len..self.header_len + n].copy_from_slice(&data[..n]);
+                self.header_len += n;
+                data = &data[n..];
+                if self.header_len < 4 {
+                    return;
+                }
+                self.remaining = u32::from_be_bytes(self.header) as usize;
+                self.body.clear();
+            }
+            let n = self.remaining.min(data.len());
+            let keep = n.min(PACKET_PREFIX_BYTES.saturating_sub(self.body.len()));
+            self.body.extend_from_slice(&data[..keep]);
+            self.remaining -= n;
+            data = &data[n..];
+            if self.remaining == 0 {
+                on_packet(&self.body);
+                self.header_len = 0;
+            }
+        }
+    }
+}
+
+/// Reads SSH wire types from a (possibly truncated) packet body.
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn u8(&mut self) -> Option<u8> {
+        let (&b, rest) = self.0.split_first()?;
+        self.0 = rest;
+        Some(b)
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        let bytes = self.0.get(..4)?;
+        self.0 = &self.0[4..];
+        Some(u32::from_be_bytes(bytes.try_into().ok()?))
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        let bytes = self.0.get(..8)?;
+        self.0 = &self.0[8..];
+        Some(u64::from_be_bytes(bytes.try_into().ok()?))
+    }
+
+    /// Length of the next string, and its bytes if they were kept.
+    fn string(&mut self) -> Option<(u32, Option<&'a [u8]>)> {
+        let len = self.u32()?;
+        let bytes = self.0.get(..len as usize);
+        self.0 = self.0.get(len as usize..).unwrap_or_default();
+        Some((len, bytes))
+    }
+
+    fn text(&mut self) -> Option<String> {
+        let (_, bytes) = self.string()?;
+        Some(String::from_utf8_lossy(bytes?).into_owned())
+    }
+}
+
+fn packet_name(kind: u8) -> &'static str {
+    match kind {
+        1 => "init",
+        2 => "version",
+        3 => "open",
+        4 => "close",
+        5 => "read",
+        6 => "write",
+        7 => "lstat",
+        8 => "fstat",
+        9 => "setstat",
+        10 => "fsetstat",
+        11 => "opendir",
+        12 => "readdir",
+        13 => "remove",
+        14 => "mkdir",
+        15 => "rmdir",
+        16 => "realpath",
+        17 => "stat",
+        18 => "rename",
+        19 => "readlink",
+        20 => "symlink",
+        101 => "status",
+        102 => "handle",
+        103 => "data",
+        104 => "name",
+        105 => "attrs",
+        200 => "extended",
+        201 => "extended_reply",
+        _ => "unknown",
+    }
+}
+
+fn status_name(code: u32) -> String {
+    let name = match code {
+        0 => "ok",
+        1 => "eof",
+        2 => "no_such_file",
+        3 => "permission_denied",
+        4 => "failure",
+        5 => "bad_message",
+        6 => "no_connection",
+        7 => "connection_lost",
+        8 => "op_unsupported",
+        _ => return format!("status_{code}"),
+    };
+    name.to_string()
+}
+
+// ─── Trace Records ─────────────────────────────────────────────────────
+
+/// One line of a trace file.
+#[derive(Debug, Serialize)]
+struct TraceRecord {
+    /// Milliseconds since tracing started, when the request was sent.
+    at_ms: f64,
+    channel: &'static str,
+    id: Option<u32>,
+    op: &'static str,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    paths: Vec<String>,
+    /// Extension name for `extended` requests.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    extension: Option<String>,
+    /// Bytes requested (`read`) or sent (`write`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    size: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    offset: Option<u64>,
+    /// Reply type, or `None` if none arrived before tracing stopped.
+    reply: Option<&'static str>,
+    /// Status for `status` replies.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status: Option<String>,
+    /// Bytes returned (`data`) or entries (`name`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reply_size: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    latency_ms: Option<f64>,
+}
+
+struct Pending {
+    record: TraceRecord,
+    sent: Instant,
+}
+
+/// A packet seen on a traced stream, on its way to the writer.
+struct TracePacket {
+    channel: &'static str,
+    outgoing: bool,
+    /// The first [`PACKET_PREFIX_BYTES`] of the body.
+    body: Vec<u8>,
+    at: Instant,
+}
+
+/// Owned by the writer thread: decodes packets and writes the records.
+struct TraceSink {
+    file: std::io::BufWriter<std::fs::File>,
+    path: PathBuf,
+    started: Instant,
+    salt: String,
+    pending: HashMap<(&'static str, u32), Pending>,
+}
+
+impl TraceSink {
+    /// Hash each path component, keeping separators and the extension.
+    fn redact(&self, path: &str) -> String {
+        path.split('/')
+            .map(|part| {
+                if part.is_empty() || part == "." || part == ".." || part == "~" {
+                    return part.to_string();
+                }
+                let digest = Sha256::digest(format!("{}{part}", self.salt));
+                let hash: String = digest[..4].iter().map(|b| format!("{b:02x}")).collect();
+                match part.rsplit_once('.') {
+                    Some((stem, ext)) if !stem.is_empty() && ext.len() <= 8 => {
+                        format!("{hash}.{ext}")
+                    }
+                    _ => hash,
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    fn write(&mut self, record: &TraceRecord) {
+        let line = serde_json::to_string(record).unwrap_or_default();
+        if let Err(e) = writeln!(self.file, "{line}") {
+            log::warn!(
+                "[SFTP] failed to write trace {}: {}",
+                self.path.display(),
+                e
+            );
+        }
+    }
+
+    fn at_ms(&self, at: Instant) -> f64 {
+        at.duration_since(self.started).as_secs_f64() * 1000.0
+    }
+
+    fn request(&mut self, channel: &'static str, body: &[u8], at: Instant) {
+        let mut r = Reader(body);
+        let Some(kind) = r.u8() else { return };
+        let mut record = TraceRecord {
+            at_ms: self.at_ms(at),
+            channel,
+            id: None,
+            op: packet_name(kind),
+            paths: Vec::new(),
+            extension: None,
+            size: None,
+            offset: None,
+            reply: None,
+            status: None,
+            reply_size: None,
+            latency_ms: None,
+        };
+        if kind == 1 {
+            // INIT carries the version instead of a request id.
+            record.size = r.u32().map(u64::from);
+            self.write(&record);
+            return;
+        }
+        let Some(id) = r.u32() else { return };
+        record.id = Some(id);
+        match kind {
+            // Path-first requests; rename and symlink carry a second path.
+            3 | 7 | 9 | 11 | 13 | 14 | 15 | 16 | 17 | 19 => {
+                record.paths.extend(r.text().map(|p| self.redact(&p)));
+            }
+            18 | 20 => {
+                record.paths.extend(r.text().map(|p| self.redact(&p)));
+                record.paths.extend(r.text().map(|p| self.redact(&p)));
+            }
+            5 => {
+                r.string();
+                record.offset = r.u64();
+                record.size = r.u32().map(u64::from);
+            }
+            6 => {
+                r.string();
+                record.offset = r.u64();
+                record.size = r.string().map(|(len, _)| u64::from(len));
+            }
+            200 => record.extension = r.text(),
+            _ => {}
+        }
+        self.pending
+            .insert((channel, id), Pending { record, sent: at });
+    }
+
+    fn reply(&mut self, channel: &'static str, body: &[u8], at: Instant) {
+        let mut r = Reader(body);
+        let Some(kind) = r.u8() else { return };
+        if kind == 2 {
+            // VERSION: the server's protocol version and extension names.
+            let version = r.u32().unwrap_or(0);
+            let mut extensions = Vec::new();
+            while let Some(name) = r.text() {
+                extensions.push(name);
+                r.string();
+            }
+            let line = serde_json::json!({
+                "at_ms": self.at_ms(at),
+                "channel": channel,
+                "op": "version",
+                "version": version,
+                "extensions": extensions,
+            });
+            if let Err(e) = writeln!(self.file, "{line}") {
+                log::warn!(
+                    "[SFTP] failed to write trace {}: {}",
+                    self.path.display(),
+                    e
+                );
+            }
+            return;
+        }
+        let Some(id) = r.u32() else { return };
+        let Some(Pending { mut record, sent }) = self.pending.remove(&(channel, id)) else {
+            return;
+        };
+        record.reply = Some(packet_name(kind));
+        record.latency_ms = Some(at.duration_since(sent).as_secs_f64() * 1000.0);
+        match kind {
+            101 => record.status = r.u32().map(status_name),
+            103 => record.reply_size = r.string().map(|(len, _)| u64::from(len)),
+            104 => record.reply_size = r.u32().map(u64::from),
+            _ => {}
+        }
+        self.write(&record);
+    }
+
+    /// Record packets until every sender is gone, then finish the file.
+    fn run(mut self, mut packets: mpsc::UnboundedReceiver<TracePacket>) {
+        while let Some(packet) = packets.blocking_recv() {
+            if packet.outgoing {
+                self.request(packet.channel, &packet.body, packet.at);
+            } else {
+                self.reply(packet.channel, &packet.body, packet.at);
+            }
+        }
+        self.finish();
+    }
+
+    /// Write out requests still waiting for a reply and flush the file.
+    fn finish(mut self) {
+        let mut pending: Vec<Pending> = self.pending.drain().map(|(_, p)| p).collect();
+        pending.sort_by(|a, b| a.record.at_ms.total_cmp(&b.record.at_ms));
+        for p in pending {
+            self.write(&p.record);
+        }
+        if let Err(e) = self.file.flush() {
+            log::warn!(
+                "[SFTP] failed to flush trace {}: {}",
+                self.path.display(),
+                e
+            );
+        }
+    }
+}
+
+// ─── Tracer ────────────────────────────────────────────────────────────
+
+/// A running trace: the way to its writer thread and the file it writes.
+struct TraceWriter {
+    packets: mpsc::UnboundedSender<TracePacket>,
+    done: tokio::task::JoinHandle<()>,
+    path: PathBuf,
+}
+
+/// Per-session tracing switch and the running trace, if any.
+#[derive(Default)]
+pub struct SftpTracer {
+    enabled: AtomicBool,
+    writer: Mutex<Option<TraceWriter>>,
+}
+
+impl SftpTracer {
+    /// Start writing a trace to `path`. Replaces a trace already running,
+    /// which finishes in the background.
+    pub fn start(&self, path: &Path) -> AppResult<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let file = std::fs::File::create(path)
+            .map_err(|e| AppError::Io(format!("Failed to create trace file: {e}")))?;
+        let sink = TraceSink {
+            file: std::io::BufWriter::new(file),
+            path: path.to_path_buf(),
+            started: Instant::now(),
+            salt: uuid::Uuid::new_v4().to_string(),
+            pending: HashMap::new(),
+        };
+        let (packets, rx) = mpsc::unbounded_channel();
+        let writer = TraceWriter {
+            packets,
+            done: tokio::task::spawn_blocking(move || sink.run(rx)),
+            path: path.to_path_buf(),
+        };
+        // Dropping the old sender lets its writer finish on its own.
+        self.writer.lock().unwrap().replace(writer);
+        self.enabled.store(true, Ordering::Relaxed);
+        log::info!("[SFTP] tracing to {}", path.display());
+        Ok(())
+    }
+
+    /// Stop tracing. Returns the trace file, if one was open, once its
+    /// writer has finished it.
+    pub async fn stop(&self) -> Option<PathBuf> {
+        self.enabled.store(false, Ordering::Relaxed);
+        let TraceWriter {
+            packets,
+            done,
+            path,
+        } = self.writer.lock().unwrap().take()?;
+        drop(packets);
+        if let Err(e) = done.await {
+            log::warn!("[SFTP] trace writer for {} failed: {}", path.display(), e);
+        }
+        log::info!("[SFTP] trace written to {}", path.display());
+        Some(path)
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    fn packet(&self, channel: &'static str, outgoing: bool, body: &[u8]) {
+        if let Some(writer) = self.writer.lock().unwrap().as_ref() {
+            let packet = TracePacket {
+                channel,
+                outgoing,
+                body: body.to_vec(),
+                at: Instant::now(),
+            };
+            // Fails only once the writer is gone, when the trace is over.
+            writer.packets.send(packet).ok();
+        }
+    }
+}
+
+// ─── Stream Wrapper ────────────────────────────────────────────────────
+
+/// An SFTP channel stream that reports each packet to the session's tracer.
+/// Packets are always delimited, so tracing can start mid-stream.
+pub struct TracedStream<S> {
+    inner: S,
+    tracer: Arc<SftpTracer>,
+    channel: &'static str,
+    sent: Scanner,
+    received: Scanner,
+}
+
+impl<S> TracedStream<S> {
+    pub fn new(inner: S, tracer: Arc<SftpTracer>, channel: &'static str) -> Self {
+        Self {
+            inner,
+            tracer,
+            channel,
+            sent: Scanner::default(),
+            received: Scanner::default(),
+        }
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for TracedStream<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        let before = buf.filled().len();
+        let result = Pin::new(&mut this.inner).poll_read(cx, buf);
+        if let Poll::Ready(Ok(())) = result {
+            let (tracer, channel) = (&this.tracer, this.channel);
+            let tracing = tracer.is_enabled();
+            this.received.feed(&buf.filled()[before..], |body| {
+                if tracing {
+                    tracer.packet(channel, false, body);
+                }
+            });
+        }
+        result
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for TracedStream<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        let this = self.get_mut();
+        let result = Pin::new(&mut this.inner).poll_write(cx, buf);
+        if let Poll::Ready(Ok(n)) = result {
+            let (tracer, channel) = (&this.tracer, this.channel);
+            let tracing = tracer.is_enabled();
+            this.sent.feed(&buf[..n], |body| {
+                if tracing {
+                    tracer.packet(channel, true, body);
+                }
+            });
+        }
+        result
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_shutdown(cx)
+    }
+}
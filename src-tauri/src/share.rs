@@ -0,0 +1,92 @@
+//! Shareable connection strings for server profiles, so the same
+//! connection can be set up on another device by scanning a QR code.
+//!
+//! Only the address is shared: passwords, key names and key material are
+//! never part of the string.
+
+use qrcode::render::svg;
+use qrcode::QrCode;
+use serde::Serialize;
+
+use crate::errors::{AppError, AppResult};
+
+/// Rendered QR code edge length in pixels.
+const QR_SIZE: u32 = 256;
+
+// ─── Data Structures ───────────────────────────────────────────────────
+
+/// A profile's connection string and its QR code (safe to send to JS).
+#[derive(Debug, Clone, Serialize)]
+pub struct ProfileShare {
+    /// `sftp://user@host:port/path#name`
+    pub uri: String,
+    /// The URI as an SVG QR code.
+    pub qr_svg: String,
+}
+
+// ─── Encoding ──────────────────────────────────────────────────────────
+
+/// Percent-encode everything outside RFC 3986 unreserved characters,
+/// keeping `keep` as-is (e.g. `/` in paths).
+fn percent_encode(text: &str, keep: &[u8]) -> String {
+    let mut out = String::with_capacity(text.len());
+    for &b in text.as_bytes() {
+        if b.is_ascii_alphanumeric() || b"-._~".contains(&b) || keep.contains(&b) {
+            out.push(b as char);
+        } else {
+            out.push_str(&format!("%{b:02X}"));
+        }
+    }
+    out
+}
+
+/// Build the `sftp://` URI for a profile. The default port and an empty
+/// path are left out; IPv6 literals are bracketed.
+fn connection_uri(name: &str, host: &str, port: u16, user: &str, path: Option<&str>) -> String {
+    let host = if host.contains(':') && !host.starts_with('[') {
+        format!("[{host}]")
+    } else {
+        host.to_string()
+    };
+    let mut uri = format!("sftp://{}@{}", percent_encode(user, &[]), host);
+    if port != 22 {
+        uri.push_str(&format!(":{port}"));
+    }
+    match path.filter(|p| !p.is_empty()) {
+        Some(p) => {
+            if !p.starts_with('/') {
+                uri.push('/');
+            }
+            uri.push_str(&percent_encode(p, b"/"));
+        }
+        None => uri.push('/'),
+    }
+    if !name.is_empty() {
+        uri.push('#');
+        uri.push_str(&percent_encode(name, &[]));
+    }
+    uri
+}
+
+/// Connection string and QR code for a profile.
+pub fn profile_share(
+    name: &str,
+    host: &str,
+    port: u16,
+    user: &str,
+    path: Option<&str>,
+) -> AppResult<ProfileShare> {
+    if host.trim().is_empty() || user.trim().is_empty() {
+        return Err(AppError::Other(
+            "A host and user are required to share".into(),
+        ));
+    }
+    let uri = connection_uri(name, host, port, user, path);
+    let code = QrCode::new(uri.as_bytes())
+        .map_err(|e| AppError::Other(format!("Failed to build QR code: {e}")))?;
+    let qr_svg = code
+        .render::<svg::Color>()
+        .min_dimensions(QR_SIZE, QR_SIZE)
+        .build();
+    Ok(ProfileShare { uri, qr_svg })
+}
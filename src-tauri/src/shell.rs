@@ -0,0 +1,198 @@
+//! Interactive PTY shells on a session. Output is streamed to the webview
+//! as `shell-event` events; input and resizes go through a per-shell queue
+//! owned by the task that drives the channel.
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+use russh::ChannelMsg;
+
+use crate::errors::{AppError, AppResult};
+use crate::ssh_manager::{shell_quote, SshSession, MAX_MOTD_BYTES};
+
+/// Output a new shell prints within this window is taken as the login MOTD.
+const MOTD_WINDOW: std::time::Duration = std::time::Duration::from_secs(2);
+
+// ─── Data Structures ───────────────────────────────────────────────────
+
+/// Events emitted to the webview for an open shell.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ShellEvent {
+    Output {
+        shell_id: String,
+        data: String,
+    },
+    Exit {
+        shell_id: String,
+        exit_status: Option<u32>,
+    },
+}
+
+/// Input queued for a shell's driver task.
+pub(crate) enum ShellInput {
+    Data(Vec<u8>),
+    Resize { cols: u32, rows: u32 },
+    Close,
+}
+
+/// Open shells on one session, by shell ID.
+#[derive(Default)]
+pub struct ShellRegistry {
+    shells: Mutex<HashMap<String, mpsc::UnboundedSender<ShellInput>>>,
+}
+
+impl ShellRegistry {
+    /// Queue input for a shell.
+    pub(crate) fn send(&self, shell_id: &str, input: ShellInput) -> AppResult<()> {
+        let shells = self.shells.lock().unwrap();
+        let tx = shells
+            .get(shell_id)
+            .ok_or_else(|| AppError::Ssh(format!("Shell not found: {shell_id}")))?;
+        tx.send(input)
+            .map_err(|_| AppError::Ssh(format!("Shell {shell_id} has exited")))
+    }
+
+    /// Ask every shell to close, e.g. when the session is being dropped.
+    pub(crate) fn close_all(&self) {
+        for tx in self.shells.lock().unwrap().values() {
+            tx.send(ShellInput::Close).ok();
+        }
+    }
+
+    /// IDs of the shells still running.
+    pub fn ids(&self) -> Vec<String> {
+        self.shells.lock().unwrap().keys().cloned().collect()
+    }
+
+    fn remove(&self, shell_id: &str) {
+        self.shells.lock().unwrap().remove(shell_id);
+    }
+}
+
+// ─── Shell ─────────────────────────────────────────────────────────────
+
+/// Command that starts the user's login shell, in `dir` if given. If the
+/// directory cannot be entered, `cd` prints why and the shell starts in `$HOME`.
+fn shell_command(dir: Option<&str>) -> String {
+    let login = "exec \"${SHELL:-/bin/sh}\" -l";
+    match dir {
+        Some(dir) => format!("cd -- {}; {login}", shell_quote(dir)),
+        None => login.to_string(),
+    }
+}
+
+/// Split off the longest valid UTF-8 prefix, keeping a trailing partial
+/// character in `pending` for the next chunk.
+fn take_utf8(pending: &mut Vec<u8>) -> String {
+    let valid = match std::str::from_utf8(pending) {
+        Ok(_) => pending.len(),
+        Err(e) if e.error_len().is_none() => e.valid_up_to(),
+        Err(_) => pending.len(),
+    };
+    let text = String::from_utf8_lossy(&pending[..valid]).to_string();
+    pending.drain(..valid);
+    text
+}
+
+/// Open a PTY shell (already `cd`-ed into `dir`, if given) and drive it
+/// until it exits. Returns the new shell ID; every event goes through `emit`.
+pub async fn open_shell<F>(
+    session: &Arc<SshSession>,
+    dir: Option<&str>,
+    cols: u32,
+    rows: u32,
+    emit: F,
+) -> AppResult<String>
+where
+    F: Fn(ShellEvent) + Send + 'static,
+{
+    let slot = session.channel_slot().await?;
+    let mut channel = session
+        .open_pty_channel(&shell_command(dir), cols, rows)
+        .await?;
+    let shell_id = Uuid::new_v4().to_string();
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    session
+        .shells
+        .shells
+        .lock()
+        .unwrap()
+        .insert(shell_id.clone(), tx);
+    log::info!(
+        "[SSH] shell {} opened in \"{}\" ({}x{})",
+        shell_id,
+        dir.unwrap_or("~"),
+        cols,
+        rows
+    );
+
+    let session = session.clone();
+    let id = shell_id.clone();
+    tokio::spawn(async move {
+        // Keep the channel slot until the shell exits.
+        let _slot = slot;
+        let mut pending = Vec::new();
+        let mut exit_status = None;
+        let mut closing = false;
+        let motd_deadline = tokio::time::Instant::now() + MOTD_WINDOW;
+        let mut greeting = Some(String::new());
+        loop {
+            tokio::select! {
+                // Hand the login greeting to the session, minus the prompt
+                // on its unfinished last line.
+                _ = tokio::time::sleep_until(motd_deadline), if greeting.is_some() => {
+                    let mut text = greeting.take().unwrap_or_default();
+                    text.truncate(text.rfind('\n').unwrap_or(0));
+                    session.capture_motd(text);
+                }
+                msg = channel.wait() => match msg {
+                    Some(ChannelMsg::Data { ref data })
+                    | Some(ChannelMsg::ExtendedData { ref data, .. }) => {
+                        pending.extend_from_slice(data);
+                        let text = take_utf8(&mut pending);
+                        if let Some(g) = greeting.as_mut().filter(|g| g.len() < MAX_MOTD_BYTES) {
+                            g.push_str(&text);
+                        }
+                        if !text.is_empty() {
+                            emit(ShellEvent::Output { shell_id: id.clone(), data: text });
+                        }
+                    }
+                    Some(ChannelMsg::ExitStatus { exit_status: status }) => {
+                        exit_status = Some(status);
+                    }
+                    Some(ChannelMsg::Close) | None => break,
+                    Some(_) => {}
+                },
+                input = rx.recv(), if !closing => {
+                    let result = match input {
+                        Some(ShellInput::Data(bytes)) => channel.data(&bytes[..]).await,
+                        Some(ShellInput::Resize { cols, rows }) => {
+                            channel.window_change(cols, rows, 0, 0).await
+                        }
+                        Some(ShellInput::Close) | None => {
+                            closing = true;
+                            channel.eof().await.ok();
+                            channel.close().await
+                        }
+                    };
+                    if let Err(e) = result {
+                        log::warn!("[SSH] shell {} input failed: {}", id, e);
+                    }
+                }
+            }
+        }
+
+        session.shells.remove(&id);
+        log::info!("[SSH] shell {} exited — status={:?}", id, exit_status);
+        emit(ShellEvent::Exit {
+            shell_id: id,
+            exit_status,
+        });
+    });
+
+    Ok(shell_id)
+}
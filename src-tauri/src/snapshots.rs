@@ -0,0 +1,155 @@
+//! Read-only snapshots of previously visited directories, for browsing
+//! while disconnected.
+//!
+//! Each successful `sftp_list_dir` is saved in the listings cache, keyed by
+//! server and path. With no session, directories are listed from there and
+//! files are opened from offline pins or the preview cache. Every result is
+//! flagged with where it came from so the UI can mark it stale.
+
+use serde::{Deserialize, Serialize};
+
+use crate::cache::{self, DiskCache, Lookup, Validator};
+use crate::errors::{AppError, AppResult};
+use crate::offline::OfflineStore;
+use crate::sftp_ops::{self, FileEntry, FilePreview};
+
+const LISTING_EXT: &str = "json";
+
+// ─── Data Structures ───────────────────────────────────────────────────
+
+/// A directory listing as it was when last visited (safe to send to JS).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DirSnapshot {
+    pub path: String,
+    pub entries: Vec<FileEntry>,
+    /// RFC 3339 timestamp of the listing.
+    pub captured_at: String,
+}
+
+/// Where an offline file was found.
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SnapshotSource {
+    /// Pinned with `pin_offline`; a full copy.
+    Pin,
+    /// A preview cached when the file was last opened.
+    PreviewCache,
+}
+
+/// A file opened with no session (safe to send to JS).
+#[derive(Debug, Clone, Serialize)]
+pub struct OfflineFile {
+    pub path: String,
+    pub source: SnapshotSource,
+    /// Preview of text or binary content; `None` for cached images.
+    pub preview: Option<FilePreview>,
+    /// Local copy, for pins and cached images.
+    pub local_path: Option<String>,
+    /// When the copy was taken, where known.
+    pub captured_at: Option<String>,
+}
+
+fn listing_key(host: &str, user: &str, path: &str) -> String {
+    cache::key(&format!("{user}@{host}:{path}"))
+}
+
+// ─── Listings ──────────────────────────────────────────────────────────
+
+/// Save a fresh listing of `path` for offline browsing. Failures are
+/// logged; they never fail the listing itself.
+pub async fn record(
+    listings: &DiskCache,
+    host: &str,
+    user: &str,
+    path: &str,
+    entries: &[FileEntry],
+) {
+    let snapshot = DirSnapshot {
+        path: path.to_string(),
+        entries: entries.to_vec(),
+        captured_at: chrono::Utc::now().to_rfc3339(),
+    };
+    let stored = match serde_json::to_vec(&snapshot) {
+        Ok(json) => listings
+            .store(
+                &listing_key(host, user, path),
+                LISTING_EXT,
+                &json,
+                &Default::default(),
+            )
+            .await
+            .map(|_| ()),
+        Err(e) => Err(AppError::Other(e.to_string())),
+    };
+    if let Err(e) = stored {
+        log::warn!("[CACHE] failed to save listing of \"{}\": {}", path, e);
+    }
+}
+
+/// The last saved listing of `path` on `user@host`.
+pub async fn list_dir(
+    listings: &DiskCache,
+    host: &str,
+    user: &str,
+    path: &str,
+) -> AppResult<DirSnapshot> {
+    let not_saved = || AppError::Other(format!("{path} was not visited before going offline"));
+    let entry = match listings.lookup(
+        &listing_key(host, user, path),
+        LISTING_EXT,
+        Validator::default(),
+    ) {
+        Lookup::Fresh(entry) | Lookup::Unvalidated(entry) | Lookup::Stale(entry) => entry,
+        Lookup::Miss => return Err(not_saved()),
+    };
+    let data = tokio::fs::read(&entry.path)
+        .await
+        .map_err(|_| not_saved())?;
+    serde_json::from_slice(&data).map_err(|_| not_saved())
+}
+
+// ─── Files ─────────────────────────────────────────────────────────────
+
+/// Open `path` on `user@host:port` from whatever local copy exists: a pin
+/// first, then a cached preview of `max_bytes`. The image cache is keyed by
+/// path alone, so it could hand back another server's file and is not used.
+pub async fn open_file(
+    pins: &OfflineStore,
+    previews: &DiskCache,
+    host: &str,
+    port: u16,
+    user: &str,
+    path: &str,
+    max_bytes: usize,
+) -> AppResult<OfflineFile> {
+    if let Some(pin) = pins.find(host, port, user, path) {
+        return Ok(OfflineFile {
+            path: path.to_string(),
+            source: SnapshotSource::Pin,
+            preview: Some(pins.preview(&pin.id, max_bytes).await?),
+            local_path: Some(pin.local_path),
+            captured_at: Some(pin.synced_at),
+        });
+    }
+
+    let key = sftp_ops::preview_key(host, user, path, max_bytes);
+    if let Lookup::Unvalidated(entry) =
+        previews.lookup(&key, sftp_ops::PREVIEW_EXT, Validator::default())
+    {
+        let preview = tokio::fs::read(&entry.path)
+            .await
+            .ok()
+            .and_then(|data| serde_json::from_slice::<FilePreview>(&data).ok());
+        if let Some(preview) = preview {
+            return Ok(OfflineFile {
+                path: path.to_string(),
+                source: SnapshotSource::PreviewCache,
+                preview: Some(preview),
+                local_path: None,
+                captured_at: None,
+            });
+        }
+    }
+
+    Err(AppError::Other(format!("{path} is not available offline")))
+}
@@ -0,0 +1,217 @@
+//! Split a remote file into fixed-size parts and join parts back together,
+//! for moving large files through size-limited channels.
+//!
+//! Parts are named `<file>.part000`, `<file>.part001`, … . Remote `split` and
+//! `cat` are used when exec works; otherwise the data is copied with ranged
+//! SFTP reads and writes.
+
+use serde::Serialize;
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+
+use crate::errors::{AppError, AppResult};
+use crate::sftp_ops;
+use crate::ssh_manager::{shell_quote, SshSession, Transport, BULK_EXEC_TIMEOUT};
+
+/// Parts are numbered with this many digits.
+const SUFFIX_DIGITS: usize = 3;
+
+// ─── Data Structures ───────────────────────────────────────────────────
+
+/// Result of splitting a file (safe to send to JS).
+#[derive(Debug, Clone, Serialize)]
+pub struct SplitResult {
+    pub parts: Vec<String>,
+    pub part_size: u64,
+    pub total_size: u64,
+}
+
+/// Result of joining parts (safe to send to JS).
+#[derive(Debug, Clone, Serialize)]
+pub struct JoinResult {
+    pub dest: String,
+    pub parts: usize,
+    pub total_size: u64,
+}
+
+// ─── Split ─────────────────────────────────────────────────────────────
+
+fn part_name(path: &str, index: u64) -> String {
+    format!("{path}.part{index:0width$}", width = SUFFIX_DIGITS)
+}
+
+/// Copy `len` bytes from `offset` of `src` into a new file `dest` over SFTP.
+async fn copy_range_sftp(
+    sftp: &russh_sftp::client::SftpSession,
+    src: &str,
+    offset: u64,
+    len: u64,
+    dest: &str,
+) -> AppResult<()> {
+    let mut reader = sftp
+        .open(src)
+        .await
+        .map_err(|e| AppError::Sftp(format!("Failed to open file: {e}")))?;
+    reader
+        .seek(std::io::SeekFrom::Start(offset))
+        .await
+        .map_err(|e| AppError::Sftp(format!("Failed to seek file: {e}")))?;
+    let mut writer = sftp
+        .create(dest)
+        .await
+        .map_err(|e| AppError::Sftp(format!("Failed to create {dest}: {e}")))?;
+    tokio::io::copy(&mut reader.take(len), &mut writer)
+        .await
+        .map_err(|e| AppError::Sftp(format!("Failed to write {dest}: {e}")))?;
+    writer
+        .shutdown()
+        .await
+        .map_err(|e| AppError::Sftp(format!("Failed to close {dest}: {e}")))?;
+    Ok(())
+}
+
+/// Split remote `path` into parts of `chunk_size` bytes next to it.
+pub async fn split_file(
+    session: &Arc<SshSession>,
+    path: &str,
+    chunk_size: u64,
+) -> AppResult<SplitResult> {
+    if chunk_size == 0 {
+        return Err(AppError::Other(
+            "Part size must be greater than zero".into(),
+        ));
+    }
+    let _op = session.ops.track("split", path);
+    let start = std::time::Instant::now();
+    let total_size = sftp_ops::file_size(session, path)
+        .await?
+        .ok_or_else(|| AppError::Sftp("Could not determine file size".into()))?;
+    if total_size == 0 {
+        return Err(AppError::Other("Nothing to split: file is empty".into()));
+    }
+    let count = total_size.div_ceil(chunk_size);
+    let parts: Vec<String> = (0..count).map(|i| part_name(path, i)).collect();
+
+    let command = format!(
+        "split -b {} -d -a {} -- {} {}",
+        chunk_size,
+        SUFFIX_DIGITS,
+        shell_quote(path),
+        shell_quote(&format!("{path}.part")),
+    );
+    let via_exec = match session.exec_within(&command, BULK_EXEC_TIMEOUT).await {
+        Ok(output) if output.exit_status == Some(0) => true,
+        Ok(output) => {
+            log::warn!(
+                "[SFTP] split exited with {:?}: {} — copying over SFTP",
+                output.exit_status,
+                String::from_utf8_lossy(&output.stderr).trim(),
+            );
+            false
+        }
+        Err(e) => {
+            log::warn!("[SFTP] split unavailable ({}) — copying over SFTP", e);
+            false
+        }
+    };
+
+    if !via_exec {
+        let Transport::Sftp(sftp) = session.transport().await? else {
+            return Err(AppError::Sftp("Splitting needs either exec or SFTP".into()));
+        };
+        for (i, part) in parts.iter().enumerate() {
+            let offset = i as u64 * chunk_size;
+            let len = chunk_size.min(total_size - offset);
+            copy_range_sftp(sftp, path, offset, len, part).await?;
+        }
+    }
+
+    log::info!(
+        "[PERF] split_file \"{}\" — {:.2}ms | parts: {} | exec: {}",
+        path,
+        start.elapsed().as_secs_f64() * 1000.0,
+        parts.len(),
+        via_exec,
+    );
+    Ok(SplitResult {
+        parts,
+        part_size: chunk_size,
+        total_size,
+    })
+}
+
+// ─── Join ──────────────────────────────────────────────────────────────
+
+/// Concatenate remote `parts`, in the given order, into `dest`.
+pub async fn join_files(
+    session: &Arc<SshSession>,
+    parts: &[String],
+    dest: &str,
+) -> AppResult<JoinResult> {
+    if parts.is_empty() {
+        return Err(AppError::Other("No parts to join".into()));
+    }
+    if parts.iter().any(|p| p == dest) {
+        return Err(AppError::Other(
+            "Destination cannot be one of the parts".into(),
+        ));
+    }
+    let _op = session.ops.track("join", dest);
+    let start = std::time::Instant::now();
+
+    let quoted: Vec<String> = parts.iter().map(|p| shell_quote(p)).collect();
+    let command = format!("cat -- {} > {}", quoted.join(" "), shell_quote(dest));
+    let via_exec = match session.exec_within(&command, BULK_EXEC_TIMEOUT).await {
+        Ok(output) if output.exit_status == Some(0) => true,
+        Ok(output) => {
+            log::warn!(
+                "[SFTP] cat exited with {:?}: {} — copying over SFTP",
+                output.exit_status,
+                String::from_utf8_lossy(&output.stderr).trim(),
+            );
+            false
+        }
+        Err(e) => {
+            log::warn!("[SFTP] cat unavailable ({}) — copying over SFTP", e);
+            false
+        }
+    };
+
+    if !via_exec {
+        let Transport::Sftp(sftp) = session.transport().await? else {
+            return Err(AppError::Sftp("Joining needs either exec or SFTP".into()));
+        };
+        let mut writer = sftp
+            .create(dest)
+            .await
+            .map_err(|e| AppError::Sftp(format!("Failed to create {dest}: {e}")))?;
+        for part in parts {
+            let mut reader = sftp
+                .open(part)
+                .await
+                .map_err(|e| AppError::Sftp(format!("Failed to open {part}: {e}")))?;
+            tokio::io::copy(&mut reader, &mut writer)
+                .await
+                .map_err(|e| AppError::Sftp(format!("Failed to append {part}: {e}")))?;
+        }
+        writer
+            .shutdown()
+            .await
+            .map_err(|e| AppError::Sftp(format!("Failed to close {dest}: {e}")))?;
+    }
+
+    let total_size = sftp_ops::file_size(session, dest).await?.unwrap_or(0);
+    log::info!(
+        "[PERF] join_files \"{}\" — {:.2}ms | parts: {} | size: {} | exec: {}",
+        dest,
+        start.elapsed().as_secs_f64() * 1000.0,
+        parts.len(),
+        total_size,
+        via_exec,
+    );
+    Ok(JoinResult {
+        dest: dest.to_string(),
+        parts: parts.len(),
+        total_size,
+    })
+}
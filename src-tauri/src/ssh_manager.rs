@@ -1,44 +1,944 @@
+use serde::Serialize;
 use std::collections::HashMap;
-use std::net::ToSocketAddrs;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
-use tokio::sync::{Mutex, OnceCell};
+use tokio::sync::{Mutex, OnceCell, OwnedSemaphorePermit, Semaphore};
 use uuid::Uuid;
 
-use russh::client;
+use russh::client::{self, KeyboardInteractiveAuthResponse};
 use russh::keys::key::PrivateKeyWithHashAlg;
 use russh::keys::PrivateKey;
-use russh_sftp::client::SftpSession;
+use russh::ChannelMsg;
+use russh_sftp::client::{RawSftpSession, SftpSession};
 
+use crate::dir_diff::SnapshotCache;
 use crate::errors::{AppError, AppResult};
+use crate::events::{EventLog, SessionEventKind};
 use crate::key_store::KeyStore;
+use crate::known_hosts::{HostKeyInfo, HostKeyPin, HostKeyStatus, KnownHosts};
+use crate::memory;
+use crate::op_tracker::OpTracker;
+use crate::settings::{HostLimits, Keepalive};
+use crate::sftp_trace::{SftpTracer, TracedStream};
+use crate::shell::ShellRegistry;
+use crate::tail::TailRegistry;
 
-/// Client handler for russh — accepts all server host keys.
-pub(crate) struct ClientHandler;
+/// Default seconds between keepalive requests on an idle connection.
+const KEEPALIVE_INTERVAL_SECS: u64 = 30;
+/// Default unanswered keepalives before the connection is considered lost.
+const KEEPALIVE_MAX_MISSED: usize = 3;
+/// How long to wait for a free channel under a host's `max_channels` limit.
+const CHANNEL_SLOT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+/// Largest banner or MOTD kept per session.
+pub(crate) const MAX_MOTD_BYTES: usize = 16 * 1024;
+/// Largest stdout or stderr kept from a command run with `ssh_exec`.
+const MAX_EXEC_OUTPUT_BYTES: usize = 1024 * 1024;
+/// Time limit for an exec command (including `ssh_exec`) when the caller
+/// gives none.
+pub(crate) const DEFAULT_EXEC_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(60);
+/// Time limit for exec commands that read or write whole files or trees.
+pub(crate) const BULK_EXEC_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30 * 60);
+/// Time limits for each phase of opening a connection, so an unreachable
+/// or silent host fails with the phase it stalled in.
+const DNS_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+const TCP_CONNECT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(15);
+const HANDSHAKE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(20);
+const AUTH_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+/// How long a liveness probe waits for the server to answer.
+const PROBE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+/// Files read over exec for the MOTD when no shell output was captured.
+const MOTD_FILES: &[&str] = &["/run/motd.dynamic", "/etc/motd"];
+/// Files at least this large are transferred as several concurrent ranges.
+const RANGED_TRANSFER_MIN_BYTES: u64 = 32 * 1024 * 1024;
+/// SFTP handles reading or writing one file at once.
+const RANGED_TRANSFER_STREAMS: usize = 4;
+
+/// Pre-auth banner shared between a `ClientHandler` and its session.
+type SharedBanner = Arc<std::sync::Mutex<Option<String>>>;
+
+/// Host key check outcome shared between a `ClientHandler` and the caller.
+#[derive(Default)]
+struct HostKeyOutcome {
+    /// The key the server presented.
+    presented: Option<HostKeyInfo>,
+    /// Why the key was refused, reported instead of russh's generic error.
+    rejection: Option<AppError>,
+}
+type SharedHostKey = Arc<std::sync::Mutex<HostKeyOutcome>>;
+
+/// Cut `text` to at most `max` bytes on a character boundary.
+pub(crate) fn truncate_text(text: &mut String, max: usize) {
+    if text.len() > max {
+        let mut end = max;
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        text.truncate(end);
+    }
+}
+
+/// Client handler for russh — verifies host keys against the profile's pin
+/// or the known-hosts store, learning keys for hosts seen for the first time.
+pub(crate) struct ClientHandler {
+    host: String,
+    port: u16,
+    known_hosts: Arc<KnownHosts>,
+    pin: HostKeyPin,
+    events: Arc<EventLog>,
+    banner: SharedBanner,
+    host_key: SharedHostKey,
+}
+
+impl ClientHandler {
+    /// Decide whether to trust `key`, returning the error to report if not.
+    fn verify_host_key(&self, key: &russh::keys::PublicKey) -> AppResult<()> {
+        let presented = HostKeyInfo::of(key)
+            .ok_or_else(|| AppError::Ssh("Cannot encode server host key".into()))?;
+        self.host_key.lock().unwrap().presented = Some(presented.clone());
+
+        match self.pin.matches(&presented) {
+            Some(true) => return Ok(()),
+            Some(false) if self.pin.strict => {
+                return Err(AppError::HostKeyRejected(format!(
+                    "{}:{} presented {}, which is not the pinned key",
+                    self.host, self.port, presented.fingerprint
+                )))
+            }
+            Some(false) => {
+                return Err(AppError::HostKeyMismatch {
+                    expected: self.pin.pinned().unwrap_or_default().to_string(),
+                    presented: presented.key,
+                })
+            }
+            None => {}
+        }
+
+        match self.known_hosts.check(&self.host, self.port, key) {
+            HostKeyStatus::Known => Ok(()),
+            HostKeyStatus::Unknown if self.pin.strict => Err(AppError::HostKeyRejected(format!(
+                "{}:{} is unknown and no key is pinned ({})",
+                self.host, self.port, presented.fingerprint
+            ))),
+            HostKeyStatus::Unknown => {
+                // Trust on first use.
+                if let Err(e) = self.known_hosts.learn(&self.host, self.port, key) {
+                    log::warn!("[SSH] could not record host key: {}", e);
+                }
+                Ok(())
+            }
+            status => Err(AppError::Ssh(format!(
+                "Host key for {}:{} rejected — {:?}",
+                self.host, self.port, status
+            ))),
+        }
+    }
+}
 
 impl client::Handler for ClientHandler {
     type Error = russh::Error;
 
     async fn check_server_key(
         &mut self,
-        _server_public_key: &russh::keys::PublicKey,
+        server_public_key: &russh::keys::PublicKey,
     ) -> Result<bool, Self::Error> {
-        // Accept all host keys for now.
-        // TODO: implement known-hosts verification for production.
-        Ok(true)
+        match self.verify_host_key(server_public_key) {
+            Ok(()) => Ok(true),
+            Err(e) => {
+                log::error!("[SSH] {}", e);
+                self.host_key.lock().unwrap().rejection = Some(e);
+                Ok(false)
+            }
+        }
+    }
+
+    async fn auth_banner(
+        &mut self,
+        banner: &str,
+        _session: &mut client::Session,
+    ) -> Result<(), Self::Error> {
+        let mut text = banner.to_string();
+        truncate_text(&mut text, MAX_MOTD_BYTES);
+        log::info!("[SSH] {} sent a {}-byte banner", self.host, text.len());
+        *self.banner.lock().unwrap() = Some(text);
+        Ok(())
+    }
+
+    async fn disconnected(
+        &mut self,
+        reason: client::DisconnectReason<Self::Error>,
+    ) -> Result<(), Self::Error> {
+        match reason {
+            client::DisconnectReason::ReceivedDisconnect(info) => {
+                self.events.record(
+                    SessionEventKind::Disconnected,
+                    format!("{:?}: {}", info.reason_code, info.message),
+                );
+                Ok(())
+            }
+            client::DisconnectReason::Error(e) => {
+                // Covers missed keepalives as well as transport errors.
+                log::warn!("[SSH] connection to {} lost: {}", self.host, e);
+                self.events
+                    .record(SessionEventKind::ConnectionLost, e.to_string());
+                Err(e)
+            }
+        }
     }
 }
 
 /// Holds an active SSH session handle with a pooled SFTP channel.
+///
+/// Sessions to the same account share one connection: each has its own
+/// channels, and the connection closes with the last of them.
 pub struct SshSession {
-    handle: client::Handle<ClientHandler>,
+    handle: Arc<client::Handle<ClientHandler>>,
     pub(crate) host: String,
     pub(crate) user: String,
     sftp: OnceCell<SftpSession>,
+    /// Low-level SFTP channel for listings with progress; see `listing_sftp`.
+    listing_sftp: OnceCell<RawSftpSession>,
+    /// Set once the server has refused the SFTP subsystem.
+    sftp_unavailable: AtomicBool,
+    /// Per-session scratch directory on the remote, created on first use.
+    temp_dir: OnceCell<String>,
+    /// Last listing per directory, for `sftp_list_dir_diff`.
+    pub(crate) dir_snapshots: SnapshotCache,
+    /// Results of the profile's post-connect commands.
+    startup_output: std::sync::Mutex<Vec<StartupOutput>>,
+    /// Local directory paired with this connection, where downloads land
+    /// when no other directory is given.
+    local_dir: std::sync::Mutex<Option<String>>,
+    /// Banner the server sent before authentication.
+    banner: SharedBanner,
+    /// Host key the server presented, for capturing a pin.
+    pub(crate) host_key: HostKeyInfo,
+    /// Login message, from the first shell's output or the MOTD files.
+    motd: std::sync::OnceLock<Option<String>>,
+    /// Remote environment details, probed on first use.
+    remote_env: OnceCell<RemoteEnv>,
+    /// In-flight operations and the exclusive flag.
+    pub(crate) ops: OpTracker,
+    /// Interactive PTY shells opened on this session.
+    pub(crate) shells: ShellRegistry,
+    /// Remote files being followed.
+    pub(crate) tails: TailRegistry,
+    /// SFTP protocol tracing, off unless turned on for a bug report.
+    pub(crate) tracer: Arc<SftpTracer>,
+    /// Connection timeline, shared with the `ClientHandler`.
+    pub(crate) events: Arc<EventLog>,
+    /// Per-host limits in effect for this session.
+    pub(crate) limits: HostLimits,
+    /// One permit per open channel when `limits.max_channels` is set.
+    channel_slots: Option<Arc<Semaphore>>,
+    /// Unix time of the last `get_session` lookup, for the stale sweeper.
+    last_active: AtomicU64,
+    /// An operation timed out; the sweeper probes the session on its next pass.
+    suspect: AtomicBool,
+    /// What the session was opened with, for re-dialing a dropped connection.
+    params: ConnectParams,
+}
+
+/// How to log in again when a session's connection drops. Held in memory
+/// only, for the lifetime of the session.
+#[derive(Clone, PartialEq, Eq)]
+enum Redial {
+    Key {
+        name: String,
+        passphrase: Option<String>,
+    },
+    Password(String),
+}
+
+/// Connection details a session was opened with. `redial` is `None` when
+/// logging in again needs the user (keyboard-interactive).
+#[derive(Clone)]
+struct ConnectParams {
+    host: String,
+    port: u16,
+    user: String,
+    pin: HostKeyPin,
+    limits: HostLimits,
+    keepalive: Keepalive,
+    redial: Option<Redial>,
+}
+
+/// An open transport with its event log, banner and presented host key.
+type Dialed = (
+    client::Handle<ClientHandler>,
+    Arc<EventLog>,
+    SharedBanner,
+    HostKeyInfo,
+);
+
+/// An authenticated connection and the state that goes with it, shared by
+/// every session opened on it.
+struct Connection {
+    handle: Arc<client::Handle<ClientHandler>>,
+    events: Arc<EventLog>,
+    banner: SharedBanner,
+    host_key: HostKeyInfo,
+    channel_slots: Option<Arc<Semaphore>>,
+}
+
+impl Connection {
+    fn new(dialed: Dialed, limits: &HostLimits) -> Self {
+        let (handle, events, banner, host_key) = dialed;
+        Self {
+            handle: Arc::new(handle),
+            events,
+            banner,
+            host_key,
+            channel_slots: limits.max_channels.map(|n| Arc::new(Semaphore::new(n))),
+        }
+    }
+}
+
+/// Optional helper binaries whose presence gates features.
+const HELPER_BINARIES: &[&str] = &["rsync", "tar", "unzip", "inotifywait", "docker"];
+
+/// Remote shell environment and available helper binaries (safe to send to JS).
+#[derive(Debug, Clone, Serialize)]
+pub struct RemoteEnv {
+    pub home: Option<String>,
+    pub shell: Option<String>,
+    /// Kernel name from `uname -s` (e.g. "Linux", "Darwin", "FreeBSD").
+    pub os: Option<String>,
+    /// Distribution id from `/etc/os-release` (e.g. "ubuntu", "alpine").
+    pub os_flavor: Option<String>,
+    /// Helper binary name → whether it is on `$PATH`.
+    pub helpers: std::collections::BTreeMap<String, bool>,
+}
+
+/// File transfer backend available on a session.
+pub(crate) enum Transport<'a> {
+    Sftp(&'a SftpSession),
+    /// The server refused the SFTP subsystem — fall back to SCP / shell commands.
+    Scp,
+}
+
+/// Server banner and message of the day for a session (safe to send to JS).
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionMotd {
+    pub banner: Option<String>,
+    pub motd: Option<String>,
+}
+
+/// Captured result of one post-connect startup command (safe to send to JS).
+#[derive(Debug, Clone, Serialize)]
+pub struct StartupOutput {
+    pub command: String,
+    pub stdout: String,
+    pub stderr: String,
+    /// `None` if the command could not be started or reported no status.
+    pub exit_status: Option<u32>,
+}
+
+/// Result of a command run with `ssh_exec` (safe to send to JS).
+#[derive(Debug, Clone, Serialize)]
+pub struct CommandOutput {
+    pub command: String,
+    pub stdout: String,
+    pub stderr: String,
+    /// `None` if the command reported no status (e.g. killed by a signal).
+    pub exit_status: Option<u32>,
+    /// stdout or stderr was cut to `MAX_EXEC_OUTPUT_BYTES`.
+    pub truncated: bool,
+    pub duration_ms: f64,
+}
+
+/// Output of a remote command run over an exec channel.
+#[derive(Debug, Default)]
+pub(crate) struct ExecOutput {
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+    pub exit_status: Option<u32>,
+}
+
+/// Read an exec channel to the end, splitting stdout and stderr.
+async fn collect_output(channel: &mut russh::Channel<client::Msg>) -> ExecOutput {
+    collect_output_capped(channel, usize::MAX).await
+}
+
+/// Like [`collect_output`], but stop reading and close the channel once
+/// stdout or stderr holds more than `cap` bytes, so a runaway command
+/// cannot fill memory. The buffer that crossed the cap is longer than
+/// `cap`, which tells the caller the output was cut.
+async fn collect_output_capped(
+    channel: &mut russh::Channel<client::Msg>,
+    cap: usize,
+) -> ExecOutput {
+    let mut output = ExecOutput::default();
+    while let Some(msg) = channel.wait().await {
+        match msg {
+            ChannelMsg::Data { ref data } => output.stdout.extend_from_slice(data),
+            ChannelMsg::ExtendedData { ref data, ext: 1 } => output.stderr.extend_from_slice(data),
+            ChannelMsg::ExitStatus { exit_status } => output.exit_status = Some(exit_status),
+            _ => {}
+        }
+        if output.stdout.len() > cap || output.stderr.len() > cap {
+            channel.close().await.ok();
+            break;
+        }
+    }
+    output
+}
+
+/// How a timed-out exec is named in logs and errors: its program name,
+/// so arguments such as paths stay out of the message.
+fn exec_label(command: &str) -> String {
+    let program = command
+        .split_whitespace()
+        .find(|w| !w.contains('='))
+        .unwrap_or("command");
+    format!("exec {program}")
+}
+
+/// Quote a string for safe interpolation into a POSIX shell command.
+pub(crate) fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "'\\''"))
 }
 
 impl SshSession {
+    /// Returns the SFTP session, or `Transport::Scp` if the server has no
+    /// SFTP subsystem.
+    pub(crate) async fn transport(&self) -> AppResult<Transport<'_>> {
+        match self.sftp().await {
+            Ok(sftp) => Ok(Transport::Sftp(sftp)),
+            Err(AppError::SftpUnavailable(_)) => Ok(Transport::Scp),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn touch(&self) {
+        self.last_active.store(unix_now(), Ordering::Relaxed);
+    }
+
+    /// Whether the connection is known to be down: russh closed it (e.g.
+    /// after `KEEPALIVE_MAX_MISSED` unanswered keepalives), or an operation
+    /// timed out and a liveness probe now fails too.
+    async fn is_dropped(&self) -> bool {
+        if self.handle.is_closed() {
+            return true;
+        }
+        if !self.suspect.load(Ordering::Relaxed) {
+            return false;
+        }
+        if !self.is_alive().await {
+            return true;
+        }
+        self.suspect.store(false, Ordering::Relaxed);
+        false
+    }
+
+    /// Whether the connection still answers: a session channel is opened
+    /// and closed within `PROBE_TIMEOUT`. A refusal counts as an answer, as
+    /// the server had to be there to send it. A keepalive would only be
+    /// queued, so it can't tell a dead link from a live one.
+    async fn is_alive(&self) -> bool {
+        if self.handle.is_closed() {
+            return false;
+        }
+        match tokio::time::timeout(PROBE_TIMEOUT, self.handle.channel_open_session()).await {
+            Ok(Ok(channel)) => {
+                channel.close().await.ok();
+                true
+            }
+            Ok(Err(russh::Error::ChannelOpenFailure(_))) => true,
+            Ok(Err(e)) => {
+                log::info!("[SSH] liveness probe failed (host={}): {}", self.host, e);
+                false
+            }
+            Err(_) => {
+                log::info!(
+                    "[SSH] liveness probe got no answer within {}s (host={})",
+                    PROBE_TIMEOUT.as_secs(),
+                    self.host,
+                );
+                false
+            }
+        }
+    }
+
+    /// Run `fut` with a time limit. On expiry the session is marked suspect
+    /// so the stale sweeper checks whether the connection is still alive.
+    pub(crate) async fn timed<T, F>(
+        &self,
+        limit: std::time::Duration,
+        what: &str,
+        fut: F,
+    ) -> AppResult<T>
+    where
+        F: std::future::Future<Output = AppResult<T>>,
+    {
+        match tokio::time::timeout(limit, fut).await {
+            Ok(result) => result,
+            Err(_) => {
+                log::warn!(
+                    "[SFTP] {} timed out after {}s (host={})",
+                    what,
+                    limit.as_secs(),
+                    self.host,
+                );
+                self.suspect.store(true, Ordering::Relaxed);
+                self.events.record(
+                    SessionEventKind::Timeout,
+                    format!("{what} after {}s", limit.as_secs()),
+                );
+                Err(AppError::Timeout(format!(
+                    "{what} did not finish within {}s",
+                    limit.as_secs()
+                )))
+            }
+        }
+    }
+
+    /// Abort in-flight work and close the SFTP channel and any shells of a
+    /// session being dropped, so lingering `Arc` clones don't keep them open.
+    async fn release(&self) {
+        self.ops.close();
+        self.shells.close_all();
+        if let Some(sftp) = self.sftp.get() {
+            sftp.close().await.ok();
+        }
+        self.tracer.stop().await;
+    }
+
+    /// The connection this session runs on, for opening another on it.
+    fn connection(&self) -> Connection {
+        Connection {
+            handle: self.handle.clone(),
+            events: self.events.clone(),
+            banner: self.banner.clone(),
+            host_key: self.host_key.clone(),
+            channel_slots: self.channel_slots.clone(),
+        }
+    }
+
+    /// Whether a session for `params` may reuse this one's connection: the
+    /// same account with the same credentials, and a host key that
+    /// satisfies any pin.
+    fn can_share(&self, params: &ConnectParams) -> bool {
+        let ours = &self.params;
+        ours.host == params.host
+            && ours.port == params.port
+            && ours.user == params.user
+            && ours.redial == params.redial
+            && params.pin.matches(&self.host_key) != Some(false)
+    }
+
+    /// Wait for a free channel under the host's `max_channels` limit. Hold
+    /// the permit for as long as the channel stays open.
+    pub(crate) async fn channel_slot(&self) -> AppResult<Option<OwnedSemaphorePermit>> {
+        let Some(slots) = &self.channel_slots else {
+            return Ok(None);
+        };
+        let limit_reached = || {
+            AppError::Ssh(format!(
+                "Channel limit ({}) reached for {}",
+                self.limits.max_channels.unwrap_or_default(),
+                self.host,
+            ))
+        };
+        tokio::time::timeout(CHANNEL_SLOT_TIMEOUT, slots.clone().acquire_owned())
+            .await
+            .map_err(|_| limit_reached())?
+            .map(Some)
+            .map_err(|_| limit_reached())
+    }
+
+    /// Parallel transfers allowed on this host, capped at `default`.
+    pub(crate) fn max_parallel(&self, default: usize) -> usize {
+        self.limits
+            .max_parallel_transfers
+            .map_or(default, |max| max.min(default))
+            .max(1)
+    }
+
+    /// Concurrent range streams for transferring a file of `size` bytes:
+    /// one for small files, otherwise several within the host's limit.
+    pub(crate) fn range_streams(&self, size: u64) -> usize {
+        if size < RANGED_TRANSFER_MIN_BYTES {
+            return 1;
+        }
+        let streams = memory::pick(RANGED_TRANSFER_STREAMS, memory::LOW_MEMORY_MAX_CONCURRENCY);
+        self.max_parallel(streams)
+    }
+
+    /// SFTP request size for this host, or `default`.
+    pub(crate) fn chunk_size(&self, default: usize) -> usize {
+        self.limits.chunk_size.unwrap_or(default)
+    }
+
+    /// Open a new session channel and start `command` on it.
+    pub(crate) async fn open_exec_channel(
+        &self,
+        command: &str,
+    ) -> AppResult<russh::Channel<client::Msg>> {
+        let channel = self
+            .handle
+            .channel_open_session()
+            .await
+            .map_err(|e| AppError::Ssh(format!("Failed to open channel: {e}")))?;
+        channel
+            .exec(true, command)
+            .await
+            .map_err(|e| AppError::Ssh(format!("Failed to exec command: {e}")))?;
+        self.events.record(SessionEventKind::ChannelOpened, "exec");
+        Ok(channel)
+    }
+
+    /// Open a new session channel with a PTY of `cols`×`rows` and start
+    /// `command` on it.
+    pub(crate) async fn open_pty_channel(
+        &self,
+        command: &str,
+        cols: u32,
+        rows: u32,
+    ) -> AppResult<russh::Channel<client::Msg>> {
+        let channel = self
+            .handle
+            .channel_open_session()
+            .await
+            .map_err(|e| AppError::Ssh(format!("Failed to open channel: {e}")))?;
+        channel
+            .request_pty(true, "xterm-256color", cols, rows, 0, 0, &[])
+            .await
+            .map_err(|e| AppError::Ssh(format!("Failed to request PTY: {e}")))?;
+        channel
+            .exec(true, command)
+            .await
+            .map_err(|e| AppError::Ssh(format!("Failed to start shell: {e}")))?;
+        self.events.record(SessionEventKind::ChannelOpened, "pty");
+        Ok(channel)
+    }
+
+    /// Run `command` to completion and collect its output, giving up after
+    /// `DEFAULT_EXEC_TIMEOUT`.
+    pub(crate) async fn exec(&self, command: &str) -> AppResult<ExecOutput> {
+        self.exec_within(command, DEFAULT_EXEC_TIMEOUT).await
+    }
+
+    /// [`Self::exec`] with its own time limit, for commands whose run time
+    /// grows with the data (e.g. `BULK_EXEC_TIMEOUT` for `cp` or
+    /// `sha256sum`).
+    pub(crate) async fn exec_within(
+        &self,
+        command: &str,
+        limit: std::time::Duration,
+    ) -> AppResult<ExecOutput> {
+        let run = async {
+            let _slot = self.channel_slot().await?;
+            let mut channel = self.open_exec_channel(command).await?;
+            Ok(collect_output(&mut channel).await)
+        };
+        self.timed(limit, &exec_label(command), run).await
+    }
+
+    /// Run a user-entered `command` on its own exec channel and return its
+    /// output as text. Gives up after `limit` or when the session
+    /// disconnects.
+    pub async fn run_command(
+        &self,
+        command: &str,
+        limit: std::time::Duration,
+    ) -> AppResult<CommandOutput> {
+        let _op = self.ops.track("exec", command);
+        let start = std::time::Instant::now();
+        let run = async {
+            let _slot = self.channel_slot().await?;
+            let mut channel = self.open_exec_channel(command).await?;
+            Ok(collect_output_capped(&mut channel, MAX_EXEC_OUTPUT_BYTES).await)
+        };
+        let run = self.ops.until_closed(run);
+        let output = tokio::time::timeout(limit, run).await.map_err(|_| {
+            AppError::Timeout(format!(
+                "command did not finish within {}s",
+                limit.as_secs()
+            ))
+        })??;
+
+        let mut stdout = String::from_utf8_lossy(&output.stdout).to_string();
+        let mut stderr = String::from_utf8_lossy(&output.stderr).to_string();
+        let truncated =
+            stdout.len() > MAX_EXEC_OUTPUT_BYTES || stderr.len() > MAX_EXEC_OUTPUT_BYTES;
+        truncate_text(&mut stdout, MAX_EXEC_OUTPUT_BYTES);
+        truncate_text(&mut stderr, MAX_EXEC_OUTPUT_BYTES);
+        let duration_ms = start.elapsed().as_secs_f64() * 1000.0;
+        log::info!(
+            "[SSH] exec \"{}\" — exit: {:?} | {:.2}ms",
+            command,
+            output.exit_status,
+            duration_ms,
+        );
+        Ok(CommandOutput {
+            command: command.to_string(),
+            stdout,
+            stderr,
+            exit_status: output.exit_status,
+            truncated,
+            duration_ms,
+        })
+    }
+
+    /// Run `command` with `input` written to its stdin (then closed), and
+    /// collect its output. Keeps secrets such as a sudo password off the
+    /// remote command line. Gives up after `DEFAULT_EXEC_TIMEOUT`.
+    pub(crate) async fn exec_with_input(
+        &self,
+        command: &str,
+        input: &[u8],
+    ) -> AppResult<ExecOutput> {
+        let run = async {
+            let _slot = self.channel_slot().await?;
+            let mut channel = self.open_exec_channel(command).await?;
+            channel
+                .data(input)
+                .await
+                .map_err(|e| AppError::Ssh(format!("Failed to write stdin: {e}")))?;
+            channel.eof().await.ok();
+            Ok(collect_output(&mut channel).await)
+        };
+        self.timed(DEFAULT_EXEC_TIMEOUT, &exec_label(command), run)
+            .await
+    }
+
+    /// Run each startup command in order over its own exec channel, keeping
+    /// the output on the session. Each gets `DEFAULT_EXEC_TIMEOUT`; failures
+    /// are captured, never propagated.
+    pub(crate) async fn run_startup_commands(&self, commands: &[String]) -> Vec<StartupOutput> {
+        let mut results = Vec::with_capacity(commands.len());
+        for command in commands {
+            let start = std::time::Instant::now();
+            // A command that never exits must not hold up the others.
+            let result = match self.run_command(command, DEFAULT_EXEC_TIMEOUT).await {
+                Ok(out) => StartupOutput {
+                    command: command.clone(),
+                    stdout: out.stdout,
+                    stderr: out.stderr,
+                    exit_status: out.exit_status,
+                },
+                Err(e) => StartupOutput {
+                    command: command.clone(),
+                    stdout: String::new(),
+                    stderr: e.to_string(),
+                    exit_status: None,
+                },
+            };
+            log::info!(
+                "[SSH] startup command \"{}\" — exit: {:?} | {:.2}ms",
+                command,
+                result.exit_status,
+                start.elapsed().as_secs_f64() * 1000.0,
+            );
+            results.push(result);
+        }
+        *self.startup_output.lock().unwrap() = results.clone();
+        results
+    }
+
+    /// Output of the startup commands run after connect, if any.
+    pub fn startup_output(&self) -> Vec<StartupOutput> {
+        self.startup_output.lock().unwrap().clone()
+    }
+
+    /// The server's SSH port.
+    pub fn port(&self) -> u16 {
+        self.params.port
+    }
+
+    /// The local directory paired with this connection, if any.
+    pub fn local_dir(&self) -> Option<String> {
+        self.local_dir.lock().unwrap().clone()
+    }
+
+    /// Pair `dir` with this connection, or clear the pairing with `None`.
+    pub fn set_local_dir(&self, dir: Option<String>) {
+        *self.local_dir.lock().unwrap() = dir;
+    }
+
+    /// Keep `text` as the MOTD if none has been captured yet. Called with
+    /// the opening output of the first shell, where login messages appear.
+    pub(crate) fn capture_motd(&self, mut text: String) {
+        truncate_text(&mut text, MAX_MOTD_BYTES);
+        let text = text.trim().to_string();
+        if self.motd.set((!text.is_empty()).then_some(text)).is_ok() {
+            log::info!(
+                "[SSH] captured MOTD from first shell output on {}",
+                self.host
+            );
+        }
+    }
+
+    /// Pre-auth banner and MOTD. Without a captured shell greeting the MOTD
+    /// files are read once over exec.
+    pub async fn motd(&self) -> SessionMotd {
+        let banner = self.banner.lock().unwrap().clone();
+        if let Some(motd) = self.motd.get() {
+            return SessionMotd {
+                banner,
+                motd: motd.clone(),
+            };
+        }
+        let script = format!("cat {} 2>/dev/null", MOTD_FILES.join(" "));
+        let text = match self.exec(&script).await {
+            Ok(out) => String::from_utf8_lossy(&out.stdout).to_string(),
+            Err(e) => {
+                log::warn!("[SSH] could not read MOTD on {}: {}", self.host, e);
+                // Leave it unset so a later call can retry.
+                return SessionMotd { banner, motd: None };
+            }
+        };
+        let mut text = text.trim().to_string();
+        truncate_text(&mut text, MAX_MOTD_BYTES);
+        let motd = self
+            .motd
+            .get_or_init(|| (!text.is_empty()).then_some(text))
+            .clone();
+        SessionMotd { banner, motd }
+    }
+
+    /// Probe `$HOME`, `$SHELL`, the OS and helper binaries once per session.
+    pub(crate) async fn remote_env(&self) -> AppResult<&RemoteEnv> {
+        self.remote_env
+            .get_or_try_init(|| async {
+                let start = std::time::Instant::now();
+                let script = format!(
+                    "echo \"home=$HOME\"; echo \"shell=$SHELL\"; echo \"os=$(uname -s)\"; \
+                     (. /etc/os-release 2>/dev/null && echo \"flavor=$ID\"); \
+                     for b in {}; do command -v \"$b\" >/dev/null 2>&1 && echo \"have=$b\"; done",
+                    HELPER_BINARIES.join(" "),
+                );
+                let output = self.exec(&script).await?;
+                if output.exit_status.is_none() && output.stdout.is_empty() {
+                    return Err(AppError::Ssh("Remote shell is unavailable".into()));
+                }
+
+                let stdout = String::from_utf8_lossy(&output.stdout);
+                let mut env = RemoteEnv {
+                    home: None,
+                    shell: None,
+                    os: None,
+                    os_flavor: None,
+                    helpers: HELPER_BINARIES
+                        .iter()
+                        .map(|b| (b.to_string(), false))
+                        .collect(),
+                };
+                for line in stdout.lines() {
+                    let Some((key, value)) = line.split_once('=') else {
+                        continue;
+                    };
+                    let value = (!value.is_empty()).then(|| value.to_string());
+                    match key {
+                        "home" => env.home = value,
+                        "shell" => env.shell = value,
+                        "os" => env.os = value,
+                        "flavor" => env.os_flavor = value,
+                        "have" => {
+                            if let Some(b) = value {
+                                env.helpers.insert(b, true);
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                log::info!(
+                    "[SSH] remote env probed in {:.2}ms — os: {:?} | flavor: {:?}",
+                    start.elapsed().as_secs_f64() * 1000.0,
+                    env.os,
+                    env.os_flavor,
+                );
+                Ok(env)
+            })
+            .await
+    }
+
+    /// Returns this session's remote temp directory, creating it on first
+    /// call. Used for staging uploads, archives and edits. Removed on disconnect.
+    pub(crate) async fn temp_dir(&self) -> AppResult<&str> {
+        let dir = self
+            .temp_dir
+            .get_or_try_init(|| async {
+                let output = self.exec("mktemp -d /tmp/oxidock.XXXXXXXX").await;
+                if let Ok(out) = output {
+                    let path = String::from_utf8_lossy(&out.stdout).trim().to_string();
+                    if out.exit_status == Some(0) && path.starts_with('/') {
+                        return Ok(path);
+                    }
+                }
+
+                // No usable shell — fall back to SFTP mkdir with a random name.
+                let path = format!("/tmp/oxidock.{}", Uuid::new_v4().simple());
+                let sftp = self.sftp().await?;
+                sftp.create_dir(&path)
+                    .await
+                    .map_err(|e| AppError::Sftp(format!("Failed to create temp dir: {e}")))?;
+                let attrs = russh_sftp::protocol::FileAttributes {
+                    permissions: Some(0o700),
+                    ..Default::default()
+                };
+                sftp.set_metadata(&path, attrs).await.ok();
+                Ok::<_, AppError>(path)
+            })
+            .await?;
+        log::debug!("[SSH] session temp dir (host={}): {}", self.host, dir);
+        Ok(dir)
+    }
+
+    /// Remove the session temp directory, if one was created.
+    pub(crate) async fn cleanup_temp_dir(&self) {
+        let Some(dir) = self.temp_dir.get() else {
+            return;
+        };
+        match self.exec(&format!("rm -rf -- {}", shell_quote(dir))).await {
+            Ok(_) => log::info!("[SSH] removed session temp dir {}", dir),
+            Err(e) => log::warn!("[SSH] failed to remove session temp dir {}: {}", dir, e),
+        }
+    }
+
+    /// Open a channel and start the SFTP subsystem on it. A refusal marks
+    /// the session as SFTP-less, so later calls fall back to SCP at once.
+    pub(crate) async fn open_sftp_channel(&self) -> AppResult<russh::Channel<client::Msg>> {
+        let mut channel = self
+            .handle
+            .channel_open_session()
+            .await
+            .map_err(|e| AppError::Sftp(format!("Failed to open channel: {e}")))?;
+
+        channel
+            .request_subsystem(true, "sftp")
+            .await
+            .map_err(|e| AppError::Sftp(format!("Failed to request sftp subsystem: {e}")))?;
+
+        // Wait for the server's reply so a refused subsystem can be
+        // told apart from other failures.
+        loop {
+            match channel.wait().await {
+                Some(ChannelMsg::Success) => return Ok(channel),
+                Some(ChannelMsg::Failure) | Some(ChannelMsg::Close) | None => {
+                    log::warn!(
+                        "[SFTP] Subsystem request failed (host={}) — falling back to SCP",
+                        self.host,
+                    );
+                    self.sftp_unavailable.store(true, Ordering::Relaxed);
+                    self.events.record(
+                        SessionEventKind::SftpFallback,
+                        "SFTP subsystem refused; using SCP",
+                    );
+                    return Err(AppError::SftpUnavailable(self.host.clone()));
+                }
+                Some(_) => continue,
+            }
+        }
+    }
+
     /// Returns a reusable SFTP session, creating one on first call.
     pub(crate) async fn sftp(&self) -> AppResult<&SftpSession> {
+        if self.sftp_unavailable.load(Ordering::Relaxed) {
+            return Err(AppError::SftpUnavailable(self.host.clone()));
+        }
+
         let already_initialized = self.sftp.initialized();
         if already_initialized {
             log::debug!(
@@ -58,50 +958,375 @@ impl SshSession {
                 );
                 let start = std::time::Instant::now();
 
-                let channel = self
-                    .handle
-                    .channel_open_session()
-                    .await
-                    .map_err(|e| AppError::Sftp(format!("Failed to open channel: {e}")))?;
-
-                channel
-                    .request_subsystem(true, "sftp")
-                    .await
-                    .map_err(|e| {
-                        AppError::Sftp(format!("Failed to request sftp subsystem: {e}"))
-                    })?;
-
-                let session = SftpSession::new(channel.into_stream())
+                let slot = self.channel_slot().await?;
+                let channel = self.open_sftp_channel().await?;
+                let stream = TracedStream::new(channel.into_stream(), self.tracer.clone(), "sftp");
+                let session = SftpSession::new(stream)
                     .await
                     .map_err(|e| AppError::Sftp(format!("Failed to init SFTP session: {e}")))?;
+                // The pooled channel lives as long as the session, so its
+                // slot is never given back. A failed open above drops the
+                // permit instead.
+                if let Some(slot) = slot {
+                    slot.forget();
+                }
 
                 log::info!(
                     "[SFTP] New channel created in {:.2}ms",
                     start.elapsed().as_secs_f64() * 1000.0,
                 );
+                self.events.record(SessionEventKind::ChannelOpened, "sftp");
                 Ok(session)
             })
             .await;
 
         result
     }
+
+    /// A second, low-level SFTP channel for listings that report progress
+    /// batch by batch, which the pooled session cannot. Opened on first use
+    /// and only if a channel is free right away; callers fall back to a
+    /// plain listing otherwise.
+    pub(crate) async fn listing_sftp(&self) -> AppResult<&RawSftpSession> {
+        if self.sftp_unavailable.load(Ordering::Relaxed) {
+            return Err(AppError::SftpUnavailable(self.host.clone()));
+        }
+        self.listing_sftp
+            .get_or_try_init(|| async {
+                let slot = match &self.channel_slots {
+                    Some(slots) => Some(
+                        slots
+                            .clone()
+                            .try_acquire_owned()
+                            .map_err(|_| AppError::Sftp("No free channel for listings".into()))?,
+                    ),
+                    None => None,
+                };
+                let channel = self.open_sftp_channel().await?;
+                let stream =
+                    TracedStream::new(channel.into_stream(), self.tracer.clone(), "listings");
+                let session = RawSftpSession::new(stream);
+                session
+                    .init()
+                    .await
+                    .map_err(|e| AppError::Sftp(format!("Failed to init SFTP session: {e}")))?;
+                // Kept for the session's lifetime, like the pooled channel's.
+                if let Some(slot) = slot {
+                    slot.forget();
+                }
+                self.events
+                    .record(SessionEventKind::ChannelOpened, "sftp (listings)");
+                Ok(session)
+            })
+            .await
+    }
+}
+
+/// Most keyboard-interactive rounds answered before giving up.
+const MAX_KBD_INTERACTIVE_ROUNDS: usize = 8;
+
+fn is_new_password_prompt(prompt: &str) -> bool {
+    let prompt = prompt.to_ascii_lowercase();
+    prompt.contains("password") && prompt.contains("new")
+}
+
+fn is_password_expired(text: &str) -> bool {
+    let text = text.to_ascii_lowercase();
+    text.contains("password has expired")
+        || text.contains("password expired")
+        || text.contains("change your password")
+}
+
+fn is_account_expired(notice: &str) -> bool {
+    let notice = notice.to_ascii_lowercase();
+    notice.contains("account has expired") || notice.contains("account expired")
+}
+
+/// Explain a refused login from the methods the server still offers.
+/// `key_offered` is whether the attempt used a key rather than a password.
+fn auth_rejected(
+    user: &str,
+    remaining: &russh::MethodSet,
+    partial_success: bool,
+    key_offered: bool,
+) -> AppError {
+    let methods: Vec<String> = remaining
+        .iter()
+        .map(|m| <&str>::from(m).to_string())
+        .collect();
+    let offers = |method: &str| methods.iter().any(|m| m == method);
+    let key_attempted = key_offered && offers("publickey");
+
+    let mut hints: Vec<String> = Vec::new();
+    if partial_success {
+        hints.push("This step passed, but the server also requires a method above".into());
+    }
+    if key_attempted {
+        hints.push(format!(
+            "The key was refused — check that its public key is in ~/.ssh/authorized_keys \
+             for \"{user}\" and that \"{user}\" is the right user"
+        ));
+    } else if key_offered {
+        hints.push("The server does not accept key authentication for this user".into());
+    } else if !offers("password") && !offers("keyboard-interactive") {
+        hints.push("The server does not accept passwords for this user; use a key".into());
+    } else {
+        hints.push(format!(
+            "Check the password and that \"{user}\" is the right user"
+        ));
+    }
+    log::warn!(
+        "[AUTH] login for {} rejected — remaining methods {:?}, key attempted {}",
+        user,
+        methods,
+        key_attempted,
+    );
+    AppError::AuthRejected {
+        methods_remaining: methods,
+        key_attempted,
+        partial_success,
+        hints,
+    }
+}
+
+/// Run one phase of a connect, failing with a timeout that names the phase.
+async fn connect_phase<T>(
+    phase: &str,
+    limit: std::time::Duration,
+    work: impl Future<Output = AppResult<T>>,
+) -> AppResult<T> {
+    tokio::time::timeout(limit, work)
+        .await
+        .map_err(|_| AppError::Timeout(format!("{phase} timed out after {}s", limit.as_secs())))?
+}
+
+/// Authenticate with a decoded private key, using the server's preferred
+/// RSA hash where it matters.
+async fn authenticate_with_key(
+    handle: &mut client::Handle<ClientHandler>,
+    user: &str,
+    private_key: PrivateKey,
+) -> AppResult<()> {
+    let hash_alg = handle
+        .best_supported_rsa_hash()
+        .await
+        .ok()
+        .flatten()
+        .flatten();
+
+    let key_with_hash = PrivateKeyWithHashAlg::new(Arc::new(private_key), hash_alg);
+
+    let auth_result = handle
+        .authenticate_publickey(user, key_with_hash)
+        .await
+        .map_err(|e| AppError::Ssh(format!("Auth failed: {e}")))?;
+
+    match auth_result {
+        client::AuthResult::Success => Ok(()),
+        client::AuthResult::Failure {
+            remaining_methods,
+            partial_success,
+        } => Err(auth_rejected(
+            user,
+            &remaining_methods,
+            partial_success,
+            true,
+        )),
+    }
+}
+
+/// Authenticate with a password. If plain password auth is refused and the
+/// server offers keyboard-interactive, retry over it so a PAM "change your
+/// password" or "account expired" exchange is reported as such, and — when
+/// `new_password` is given — the change is completed. An expiry or change
+/// notice is reported before the password is sent again.
+async fn authenticate_with_password(
+    handle: &mut client::Handle<ClientHandler>,
+    user: &str,
+    password: &str,
+    new_password: Option<&str>,
+) -> AppResult<()> {
+    let auth_err = |e: russh::Error| AppError::Ssh(format!("Auth failed: {e}"));
+
+    let auth_result = handle
+        .authenticate_password(user, password)
+        .await
+        .map_err(auth_err)?;
+    let (remaining, partial_success) = match auth_result {
+        client::AuthResult::Success => return Ok(()),
+        client::AuthResult::Failure {
+            remaining_methods,
+            partial_success,
+        } => (remaining_methods, partial_success),
+    };
+    let rejected = || auth_rejected(user, &remaining, partial_success, false);
+    let offers_kbd = remaining
+        .iter()
+        .any(|m| *m == russh::MethodKind::KeyboardInteractive);
+    if !offers_kbd {
+        return Err(rejected());
+    }
+
+    let mut response = handle
+        .authenticate_keyboard_interactive_start(user, None)
+        .await
+        .map_err(auth_err)?;
+    let mut changing = false;
+    for _ in 0..MAX_KBD_INTERACTIVE_ROUNDS {
+        let (name, instructions, prompts) = match response {
+            KeyboardInteractiveAuthResponse::Success => {
+                if changing {
+                    log::info!("[SSH] password changed for {}", user);
+                }
+                return Ok(());
+            }
+            KeyboardInteractiveAuthResponse::Failure { .. } if changing => {
+                return Err(AppError::Ssh("Password change rejected by server".into()));
+            }
+            KeyboardInteractiveAuthResponse::Failure { .. } => return Err(rejected()),
+            KeyboardInteractiveAuthResponse::InfoRequest {
+                name,
+                instructions,
+                prompts,
+            } => (name, instructions, prompts),
+        };
+
+        let notice = format!("{name} {instructions}").trim().to_string();
+        if is_account_expired(&notice) {
+            return Err(AppError::AccountExpired(notice));
+        }
+        let expired = std::iter::once(notice.as_str())
+            .chain(prompts.iter().map(|p| p.prompt.as_str()))
+            .any(is_password_expired);
+        if expired && new_password.is_none() {
+            let prompts: Vec<&str> = prompts.iter().map(|p| p.prompt.trim()).collect();
+            let message = format!("{notice} {}", prompts.join(" "));
+            return Err(AppError::PasswordChangeRequired(message.trim().to_string()));
+        }
+        let mut answers = Vec::with_capacity(prompts.len());
+        for p in &prompts {
+            let answer = if is_new_password_prompt(&p.prompt) {
+                let Some(new_password) = new_password else {
+                    let message = format!("{notice} {}", p.prompt.trim());
+                    return Err(AppError::PasswordChangeRequired(message.trim().to_string()));
+                };
+                changing = true;
+                new_password
+            } else if p.prompt.to_ascii_lowercase().contains("password") {
+                password
+            } else {
+                ""
+            };
+            answers.push(answer.to_string());
+        }
+        response = handle
+            .authenticate_keyboard_interactive_respond(answers)
+            .await
+            .map_err(auth_err)?;
+    }
+    Err(rejected())
+}
+
+/// How long a keyboard-interactive prompt waits for the user's answers.
+const KBD_PROMPT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(300);
+
+/// One field of a keyboard-interactive prompt (safe to send to JS).
+#[derive(Debug, Clone, Serialize)]
+pub struct AuthPromptField {
+    pub prompt: String,
+    /// Whether the answer may be shown as typed (e.g. a username, not an OTP).
+    pub echo: bool,
+}
+
+/// A keyboard-interactive round the user has to answer, sent to the UI as
+/// an `auth-prompt` event (safe to send to JS).
+#[derive(Debug, Clone, Serialize)]
+pub struct AuthPrompt {
+    /// Id the answers are sent back under via `ssh_auth_respond`.
+    pub auth_id: String,
+    pub name: String,
+    pub instructions: String,
+    pub prompts: Vec<AuthPromptField>,
+}
+
+/// Connects in progress that can be aborted, keyed by attempt id.
+type PendingAttempts = std::sync::Mutex<HashMap<String, tokio::sync::oneshot::Sender<()>>>;
+
+/// Keyboard-interactive rounds waiting on the user, keyed by auth id.
+/// `None` as the answer cancels the login.
+type PendingPrompts =
+    std::sync::Mutex<HashMap<String, tokio::sync::oneshot::Sender<Option<Vec<String>>>>>;
+
+/// Forgets the keyboard-interactive round under `auth_id` when dropped.
+struct PendingPromptGuard<'a> {
+    prompts: &'a PendingPrompts,
+    auth_id: &'a str,
+}
+
+impl Drop for PendingPromptGuard<'_> {
+    fn drop(&mut self) {
+        self.prompts.lock().unwrap().remove(self.auth_id);
+    }
+}
+
+/// A session dropped by [`SshSessionManager::sweep_stale`] (safe to send to JS).
+#[derive(Debug, Clone, Serialize)]
+pub struct StaleSession {
+    pub session_id: String,
+    pub host: String,
+    pub user: String,
+}
+
+fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
 }
 
 /// Manages active SSH sessions with pooling.
 pub struct SshSessionManager {
     sessions: Arc<Mutex<HashMap<String, Arc<SshSession>>>>,
     key_store: Arc<KeyStore>,
+    known_hosts: Arc<KnownHosts>,
+    prompts: PendingPrompts,
+    attempts: PendingAttempts,
+    /// Held while a dropped session is re-dialed, so concurrent lookups
+    /// don't dial it twice.
+    redialing: Mutex<()>,
+    /// Called with the session id after a transparent reconnect.
+    on_reconnect: std::sync::OnceLock<Box<dyn Fn(&str) + Send + Sync>>,
 }
 
 impl SshSessionManager {
-    pub fn new(key_store: Arc<KeyStore>) -> Self {
+    pub fn new(key_store: Arc<KeyStore>, known_hosts: Arc<KnownHosts>) -> Self {
         Self {
             sessions: Arc::new(Mutex::new(HashMap::new())),
             key_store,
+            known_hosts,
+            prompts: PendingPrompts::default(),
+            attempts: PendingAttempts::default(),
+            redialing: Mutex::new(()),
+            on_reconnect: std::sync::OnceLock::new(),
         }
     }
 
+    /// Register the callback run after a session is transparently
+    /// reconnected (e.g. to notify the UI). Only the first call takes effect.
+    pub fn set_reconnect_listener<F>(&self, listener: F)
+    where
+        F: Fn(&str) + Send + Sync + 'static,
+    {
+        self.on_reconnect.set(Box::new(listener)).ok();
+    }
+
+    /// Import entries from an OpenSSH `known_hosts` file.
+    pub fn import_known_hosts(&self, text: &str) -> AppResult<usize> {
+        self.known_hosts.import(text)
+    }
+
     /// Connect to an SSH server using a stored key.
+    #[allow(clippy::too_many_arguments)]
     pub async fn connect_with_key(
         &self,
         host: &str,
@@ -109,94 +1334,440 @@ impl SshSessionManager {
         user: &str,
         key_name: &str,
         passphrase: Option<&str>,
+        limits: HostLimits,
+        keepalive: Keepalive,
+        pin: HostKeyPin,
     ) -> AppResult<String> {
-        let pem = self.key_store.retrieve_key_pem(key_name).await?;
-
-        let private_key = if let Some(pass) = passphrase {
-            PrivateKey::from_openssh(pem.as_bytes())
-                .and_then(|k| k.decrypt(pass))
-                .map_err(|e| AppError::Ssh(format!("Failed to decode key: {e}")))?
-        } else {
-            PrivateKey::from_openssh(pem.as_bytes())
-                .map_err(|e| AppError::Ssh(format!("Failed to decode key: {e}")))?
+        let params = ConnectParams {
+            host: host.to_string(),
+            port,
+            user: user.to_string(),
+            pin,
+            limits,
+            keepalive,
+            redial: Some(Redial::Key {
+                name: key_name.to_string(),
+                passphrase: passphrase.map(str::to_string),
+            }),
         };
-
-        let mut handle = self.establish_connection(host, port).await?;
-
-        let hash_alg = handle
-            .best_supported_rsa_hash()
-            .await
-            .ok()
-            .flatten()
-            .flatten();
-
-        let key_with_hash = PrivateKeyWithHashAlg::new(Arc::new(private_key), hash_alg);
-
-        let auth_result = handle
-            .authenticate_publickey(user, key_with_hash)
-            .await
-            .map_err(|e| AppError::Ssh(format!("Auth failed: {e}")))?;
-
-        if !auth_result.success() {
-            return Err(AppError::Ssh("Authentication rejected by server".into()));
+        if let Some(session_id) = self.connect_shared(&params).await {
+            return Ok(session_id);
         }
-
-        self.store_session(handle, host, user).await
+        let dialed = self.dial(&params).await?;
+        self.store_session(Connection::new(dialed, &params.limits), params)
+            .await
     }
 
     /// Connect to an SSH server using a password.
+    #[allow(clippy::too_many_arguments)]
     pub async fn connect_with_password(
         &self,
         host: &str,
         port: u16,
         user: &str,
         password: &str,
+        new_password: Option<&str>,
+        limits: HostLimits,
+        keepalive: Keepalive,
+        pin: HostKeyPin,
     ) -> AppResult<String> {
-        let mut handle = self.establish_connection(host, port).await?;
+        let params = ConnectParams {
+            host: host.to_string(),
+            port,
+            user: user.to_string(),
+            pin,
+            limits,
+            keepalive,
+            // After a forced change only the new password is valid.
+            redial: Some(Redial::Password(
+                new_password.unwrap_or(password).to_string(),
+            )),
+        };
+        if new_password.is_none() {
+            if let Some(session_id) = self.connect_shared(&params).await {
+                return Ok(session_id);
+            }
+        }
+        let (mut handle, events, banner, host_key) = self
+            .establish_connection(host, port, params.pin.clone(), keepalive)
+            .await?;
+        connect_phase(
+            "Authentication",
+            AUTH_TIMEOUT,
+            authenticate_with_password(&mut handle, user, password, new_password),
+        )
+        .await?;
+        let connection = Connection::new((handle, events, banner, host_key), &params.limits);
+        self.store_session(connection, params).await
+    }
 
-        let auth_result = handle
-            .authenticate_password(user, password)
+    /// Connect to an SSH server with keyboard-interactive authentication,
+    /// e.g. for OTP prompts. Each round is passed to `on_prompt` and its
+    /// answers are awaited from [`Self::answer_prompt`].
+    ///
+    /// An existing connection to the account is reused only with `share`:
+    /// that skips the prompts, and with them any second factor, so the
+    /// caller has to ask for it explicitly.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn connect_keyboard_interactive<F>(
+        &self,
+        host: &str,
+        port: u16,
+        user: &str,
+        auth_id: &str,
+        limits: HostLimits,
+        keepalive: Keepalive,
+        pin: HostKeyPin,
+        share: bool,
+        on_prompt: F,
+    ) -> AppResult<String>
+    where
+        F: Fn(&AuthPrompt),
+    {
+        let params = ConnectParams {
+            host: host.to_string(),
+            port,
+            user: user.to_string(),
+            pin,
+            limits,
+            keepalive,
+            redial: None,
+        };
+        // Reusing an interactive login spares the user another round of
+        // prompts (e.g. a fresh OTP), when they asked for that.
+        if share {
+            if let Some(session_id) = self.connect_shared(&params).await {
+                return Ok(session_id);
+            }
+        }
+        let (mut handle, events, banner, host_key) = self
+            .establish_connection(host, port, params.pin.clone(), keepalive)
+            .await?;
+        // Removes a waiting round's entry however the login ends, including
+        // when this future is dropped by an abort.
+        let _pending = PendingPromptGuard {
+            prompts: &self.prompts,
+            auth_id,
+        };
+        self.authenticate_keyboard_interactive(&mut handle, user, auth_id, on_prompt)
+            .await?;
+        let connection = Connection::new((handle, events, banner, host_key), &params.limits);
+        self.store_session(connection, params).await
+    }
+
+    /// Run keyboard-interactive rounds until the server accepts or refuses.
+    /// Rounds without prompts (informational messages) are acknowledged
+    /// without asking the user.
+    async fn authenticate_keyboard_interactive<F>(
+        &self,
+        handle: &mut client::Handle<ClientHandler>,
+        user: &str,
+        auth_id: &str,
+        on_prompt: F,
+    ) -> AppResult<()>
+    where
+        F: Fn(&AuthPrompt),
+    {
+        let auth_err = |e: russh::Error| AppError::Ssh(format!("Auth failed: {e}"));
+
+        let mut response = handle
+            .authenticate_keyboard_interactive_start(user, None)
             .await
-            .map_err(|e| AppError::Ssh(format!("Auth failed: {e}")))?;
+            .map_err(auth_err)?;
+        for round in 0..MAX_KBD_INTERACTIVE_ROUNDS {
+            let (name, instructions, prompts) = match response {
+                KeyboardInteractiveAuthResponse::Success => return Ok(()),
+                KeyboardInteractiveAuthResponse::Failure {
+                    remaining_methods,
+                    partial_success,
+                } => {
+                    return Err(auth_rejected(
+                        user,
+                        &remaining_methods,
+                        partial_success,
+                        false,
+                    ));
+                }
+                KeyboardInteractiveAuthResponse::InfoRequest {
+                    name,
+                    instructions,
+                    prompts,
+                } => (name, instructions, prompts),
+            };
+
+            let notice = format!("{name} {instructions}").trim().to_string();
+            if is_account_expired(&notice) {
+                return Err(AppError::AccountExpired(notice));
+            }
+            let answers = if prompts.is_empty() {
+                Vec::new()
+            } else {
+                let (tx, rx) = tokio::sync::oneshot::channel();
+                self.prompts.lock().unwrap().insert(auth_id.to_string(), tx);
+                log::info!(
+                    "[SSH] keyboard-interactive round {} for {} — {} prompt(s)",
+                    round + 1,
+                    user,
+                    prompts.len(),
+                );
+                on_prompt(&AuthPrompt {
+                    auth_id: auth_id.to_string(),
+                    name,
+                    instructions,
+                    prompts: prompts
+                        .into_iter()
+                        .map(|p| AuthPromptField {
+                            prompt: p.prompt,
+                            echo: p.echo,
+                        })
+                        .collect(),
+                });
+                match tokio::time::timeout(KBD_PROMPT_TIMEOUT, rx).await {
+                    Ok(Ok(Some(answers))) => answers,
+                    Ok(Ok(None)) | Ok(Err(_)) => {
+                        return Err(AppError::Cancelled("Login prompt dismissed".into()));
+                    }
+                    Err(_) => {
+                        return Err(AppError::Timeout("No answer to the login prompt".into()));
+                    }
+                }
+            };
+            response = handle
+                .authenticate_keyboard_interactive_respond(answers)
+                .await
+                .map_err(auth_err)?;
+        }
+        Err(AppError::Ssh("Authentication rejected by server".into()))
+    }
 
-        if !auth_result.success() {
-            return Err(AppError::Ssh("Authentication rejected by server".into()));
+    /// Deliver the user's answers to the keyboard-interactive round waiting
+    /// under `auth_id`; `None` cancels the login. Returns `false` if no
+    /// round is waiting.
+    pub fn answer_prompt(&self, auth_id: &str, answers: Option<Vec<String>>) -> bool {
+        match self.prompts.lock().unwrap().remove(auth_id) {
+            Some(tx) => tx.send(answers).is_ok(),
+            None => false,
         }
+    }
+
+    /// Run a connect under `attempt_id` until it finishes or
+    /// [`Self::abort_connect`] is called with the same id, at whatever phase
+    /// it has reached. Without an id the connect cannot be aborted.
+    pub async fn abortable<T>(
+        &self,
+        attempt_id: Option<&str>,
+        connect: impl Future<Output = AppResult<T>>,
+    ) -> AppResult<T> {
+        let Some(attempt_id) = attempt_id else {
+            return connect.await;
+        };
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        self.attempts
+            .lock()
+            .unwrap()
+            .insert(attempt_id.to_string(), tx);
+        let result = tokio::select! {
+            result = connect => result,
+            Ok(()) = rx => {
+                log::info!("[SSH] connect attempt {} aborted", attempt_id);
+                Err(AppError::Cancelled("Connection attempt aborted".into()))
+            }
+        };
+        self.attempts.lock().unwrap().remove(attempt_id);
+        result
+    }
 
-        self.store_session(handle, host, user).await
+    /// Abort the connect running under `attempt_id`. Returns `false` if no
+    /// such attempt is in progress.
+    pub fn abort_connect(&self, attempt_id: &str) -> bool {
+        match self.attempts.lock().unwrap().remove(attempt_id) {
+            Some(tx) => tx.send(()).is_ok(),
+            None => false,
+        }
     }
 
+    /// Open the transport and verify the host key against `pin` or the
+    /// known-hosts store. Returns the presented key alongside the handle.
+    /// DNS lookup, each address's TCP connect and the SSH handshake are
+    /// each time-bound.
     async fn establish_connection(
         &self,
         host: &str,
         port: u16,
-    ) -> AppResult<client::Handle<ClientHandler>> {
-        let addr = format!("{host}:{port}")
-            .to_socket_addrs()
-            .map_err(|e| AppError::Ssh(format!("Failed to resolve host: {e}")))?
-            .next()
-            .ok_or_else(|| AppError::Ssh("Could not resolve host address".into()))?;
+        pin: HostKeyPin,
+        keepalive: Keepalive,
+    ) -> AppResult<Dialed> {
+        let addrs: Vec<std::net::SocketAddr> = connect_phase("DNS lookup", DNS_TIMEOUT, async {
+            tokio::net::lookup_host((host, port))
+                .await
+                .map(Iterator::collect)
+                .map_err(|e| AppError::Ssh(format!("DNS lookup for {host} failed: {e}")))
+        })
+        .await?;
+        if addrs.is_empty() {
+            return Err(AppError::Ssh(format!(
+                "DNS lookup for {host} returned no address"
+            )));
+        }
 
-        let config = Arc::new(client::Config::default());
+        // Each address gets its own limit, so one that drops packets
+        // doesn't use up the time meant for the next.
+        let mut stream = None;
+        let mut last_err = None;
+        for addr in &addrs {
+            let connect = async {
+                tokio::net::TcpStream::connect(addr)
+                    .await
+                    .map_err(|e| AppError::Ssh(e.to_string()))
+            };
+            match connect_phase("TCP connect", TCP_CONNECT_TIMEOUT, connect).await {
+                Ok(connected) => {
+                    stream = Some(connected);
+                    break;
+                }
+                Err(e) => last_err = Some(format!("{addr}: {e}")),
+            }
+        }
+        let Some(stream) = stream else {
+            return Err(AppError::Ssh(format!(
+                "TCP connect to {host} failed — {}",
+                last_err.unwrap_or_default()
+            )));
+        };
+        stream.set_nodelay(true).ok();
 
-        client::connect(config, addr, ClientHandler)
-            .await
-            .map_err(|e| AppError::Ssh(format!("Connection failed: {e}")))
+        // Keepalives let a dead link surface as `ConnectionLost` instead of
+        // hanging until the next operation times out.
+        let interval = keepalive.interval_secs.unwrap_or(KEEPALIVE_INTERVAL_SECS);
+        let config = Arc::new(client::Config {
+            keepalive_interval: (interval > 0).then(|| std::time::Duration::from_secs(interval)),
+            keepalive_max: keepalive.max_missed.unwrap_or(KEEPALIVE_MAX_MISSED),
+            ..Default::default()
+        });
+
+        let events = Arc::new(EventLog::default());
+        let banner = SharedBanner::default();
+        let host_key = SharedHostKey::default();
+        let handler = ClientHandler {
+            host: host.to_string(),
+            port,
+            known_hosts: self.known_hosts.clone(),
+            pin,
+            events: events.clone(),
+            banner: banner.clone(),
+            host_key: host_key.clone(),
+        };
+        let connected = tokio::time::timeout(
+            HANDSHAKE_TIMEOUT,
+            client::connect_stream(config, stream, handler),
+        )
+        .await;
+        let mut outcome = std::mem::take(&mut *host_key.lock().unwrap());
+        let handle = match (connected, outcome.rejection.take()) {
+            (Ok(Ok(handle)), None) => handle,
+            (_, Some(rejection)) => return Err(rejection),
+            (Ok(Err(e)), None) => return Err(AppError::Ssh(format!("SSH handshake failed: {e}"))),
+            (Err(_), None) => {
+                return Err(AppError::Timeout(format!(
+                    "SSH handshake timed out after {}s",
+                    HANDSHAKE_TIMEOUT.as_secs()
+                )))
+            }
+        };
+        let presented = outcome
+            .presented
+            .ok_or_else(|| AppError::Ssh("Server presented no host key".into()))?;
+        Ok((handle, events, banner, presented))
+    }
+
+    /// Decode the stored key `key_name`, unlocking it with `passphrase`.
+    async fn decode_key(&self, key_name: &str, passphrase: Option<&str>) -> AppResult<PrivateKey> {
+        let pem = self.key_store.retrieve_key_pem(key_name).await?;
+        let decoded = match passphrase {
+            Some(pass) => PrivateKey::from_openssh(pem.as_bytes()).and_then(|k| k.decrypt(pass)),
+            None => PrivateKey::from_openssh(pem.as_bytes()),
+        };
+        decoded.map_err(|e| AppError::Ssh(format!("Failed to decode key: {e}")))
+    }
+
+    /// Open a connection for `params` and log in with its stored credentials.
+    async fn dial(&self, params: &ConnectParams) -> AppResult<Dialed> {
+        let Some(redial) = &params.redial else {
+            return Err(AppError::Ssh(
+                "Connection lost; reconnect to answer the login prompts again".into(),
+            ));
+        };
+        let (mut handle, events, banner, host_key) = self
+            .establish_connection(
+                &params.host,
+                params.port,
+                params.pin.clone(),
+                params.keepalive,
+            )
+            .await?;
+        let user = &params.user;
+        match redial {
+            Redial::Key { name, passphrase } => {
+                let key = self.decode_key(name, passphrase.as_deref()).await?;
+                let auth = authenticate_with_key(&mut handle, user, key);
+                connect_phase("Authentication", AUTH_TIMEOUT, auth).await?;
+            }
+            Redial::Password(password) => {
+                let auth = authenticate_with_password(&mut handle, user, password, None);
+                connect_phase("Authentication", AUTH_TIMEOUT, auth).await?;
+            }
+        }
+        Ok((handle, events, banner, host_key))
+    }
+
+    fn new_session(&self, connection: Connection, params: ConnectParams) -> Arc<SshSession> {
+        let Connection {
+            handle,
+            events,
+            banner,
+            host_key,
+            channel_slots,
+        } = connection;
+        let limits = params.limits.clone();
+        Arc::new(SshSession {
+            handle,
+            host: params.host.clone(),
+            user: params.user.clone(),
+            sftp: OnceCell::new(),
+            listing_sftp: OnceCell::new(),
+            sftp_unavailable: AtomicBool::new(false),
+            temp_dir: OnceCell::new(),
+            dir_snapshots: SnapshotCache::default(),
+            startup_output: std::sync::Mutex::new(Vec::new()),
+            local_dir: std::sync::Mutex::new(None),
+            banner,
+            host_key,
+            motd: std::sync::OnceLock::new(),
+            remote_env: OnceCell::new(),
+            ops: OpTracker::default(),
+            shells: ShellRegistry::default(),
+            tails: TailRegistry::default(),
+            tracer: Arc::default(),
+            events,
+            channel_slots,
+            limits,
+            last_active: AtomicU64::new(unix_now()),
+            suspect: AtomicBool::new(false),
+            params,
+        })
     }
 
     async fn store_session(
         &self,
-        handle: client::Handle<ClientHandler>,
-        host: &str,
-        user: &str,
+        connection: Connection,
+        params: ConnectParams,
     ) -> AppResult<String> {
         let session_id = Uuid::new_v4().to_string();
-        let session = Arc::new(SshSession {
-            handle,
-            host: host.to_string(),
-            user: user.to_string(),
-            sftp: OnceCell::new(),
-        });
+        let session = self.new_session(connection, params);
+        session.events.record(
+            SessionEventKind::Connected,
+            format!("{}@{}", session.user, session.host),
+        );
 
         let mut sessions = self.sessions.lock().await;
         sessions.insert(session_id.clone(), session);
@@ -204,22 +1775,194 @@ impl SshSessionManager {
         Ok(session_id)
     }
 
-    /// Get an active session by ID.
+    /// A live session whose connection a session for `params` can share.
+    async fn find_shared(&self, params: &ConnectParams) -> Option<Arc<SshSession>> {
+        let candidates: Vec<Arc<SshSession>> = self
+            .sessions
+            .lock()
+            .await
+            .values()
+            .filter(|s| s.can_share(params))
+            .cloned()
+            .collect();
+        for session in candidates {
+            if !session.is_dropped().await {
+                return Some(session);
+            }
+        }
+        None
+    }
+
+    /// Open a session for `params` on an existing connection to the same
+    /// account, skipping the TCP and authentication handshakes. `None` if
+    /// there is no such connection.
+    async fn connect_shared(&self, params: &ConnectParams) -> Option<String> {
+        let existing = self.find_shared(params).await?;
+        log::info!(
+            "[SSH] sharing the existing connection to {}@{}:{}",
+            params.user,
+            params.host,
+            params.port,
+        );
+        self.store_session(existing.connection(), params.clone())
+            .await
+            .ok()
+    }
+
+    /// Get an active session by ID. A session whose connection has dropped
+    /// is re-dialed with the parameters it was opened with and swapped in
+    /// under the same ID, so callers don't see the blip.
     pub async fn get_session(&self, session_id: &str) -> AppResult<Arc<SshSession>> {
-        let sessions = self.sessions.lock().await;
-        sessions
+        let session = self
+            .sessions
+            .lock()
+            .await
             .get(session_id)
             .cloned()
-            .ok_or_else(|| AppError::SessionNotFound(session_id.to_string()))
+            .ok_or_else(|| AppError::SessionNotFound(session_id.to_string()))?;
+        let session = if session.is_dropped().await {
+            self.reconnect(session_id, &session).await?
+        } else {
+            session
+        };
+        session.touch();
+        Ok(session)
+    }
+
+    /// Re-dial `dropped`, the session stored under `session_id`, and replace
+    /// it. In-flight work on the old connection is aborted; shells, the
+    /// SFTP channel and per-session caches start afresh.
+    async fn reconnect(
+        &self,
+        session_id: &str,
+        dropped: &Arc<SshSession>,
+    ) -> AppResult<Arc<SshSession>> {
+        let _redialing = self.redialing.lock().await;
+        // Another lookup may have reconnected (or removed) it meanwhile.
+        match self.sessions.lock().await.get(session_id) {
+            Some(current) if !Arc::ptr_eq(current, dropped) => return Ok(current.clone()),
+            Some(_) => {}
+            None => return Err(AppError::SessionNotFound(session_id.to_string())),
+        }
+
+        let start = std::time::Instant::now();
+        log::warn!(
+            "[SSH] session {} ({}@{}) dropped — reconnecting",
+            session_id,
+            dropped.user,
+            dropped.host,
+        );
+        dropped
+            .events
+            .record(SessionEventKind::ConnectionLost, "re-dialing");
+        // A sibling on the same connection may already have re-dialed.
+        let connection = match self.find_shared(&dropped.params).await {
+            Some(sibling) => sibling.connection(),
+            None => Connection::new(self.dial(&dropped.params).await?, &dropped.params.limits),
+        };
+        let session = self.new_session(connection, dropped.params.clone());
+        session.set_local_dir(dropped.local_dir());
+        session.events.record(
+            SessionEventKind::Reconnected,
+            format!("{}@{}", session.user, session.host),
+        );
+        self.sessions
+            .lock()
+            .await
+            .insert(session_id.to_string(), session.clone());
+        dropped.release().await;
+
+        log::info!(
+            "[SSH] session {} reconnected in {:.2}ms",
+            session_id,
+            start.elapsed().as_secs_f64() * 1000.0,
+        );
+        if let Some(listener) = self.on_reconnect.get() {
+            listener(session_id);
+        }
+        Ok(session)
     }
 
-    /// Disconnect and remove a session.
+    /// Probe sessions idle for at least `idle` (and any that timed out or
+    /// whose connection has closed), removing the dead ones and freeing their channels.
+    /// Dead sessions used within `idle` are re-dialed instead where possible.
+    pub async fn sweep_stale(&self, idle: std::time::Duration) -> Vec<StaleSession> {
+        let cutoff = unix_now().saturating_sub(idle.as_secs());
+        let candidates: Vec<(String, Arc<SshSession>)> = self
+            .sessions
+            .lock()
+            .await
+            .iter()
+            .filter(|(_, s)| {
+                let idle = s.last_active.load(Ordering::Relaxed) <= cutoff;
+                s.handle.is_closed()
+                    || s.suspect.load(Ordering::Relaxed)
+                    || (idle && s.ops.pending().is_empty())
+            })
+            .map(|(id, s)| (id.clone(), s.clone()))
+            .collect();
+
+        let mut removed = Vec::new();
+        for (session_id, session) in candidates {
+            if session.is_alive().await {
+                session.suspect.store(false, Ordering::Relaxed);
+                continue;
+            }
+            // Recently used sessions are re-dialed rather than dropped.
+            let idle = session.last_active.load(Ordering::Relaxed) <= cutoff;
+            if !idle && session.params.redial.is_some() {
+                match self.reconnect(&session_id, &session).await {
+                    Ok(_) => continue,
+                    Err(e) => log::warn!("[SSH] reconnect of {} failed: {}", session_id, e),
+                }
+            }
+            if self.sessions.lock().await.remove(&session_id).is_none() {
+                continue;
+            }
+            log::warn!(
+                "[SSH] removing dead session {} ({}@{})",
+                session_id,
+                session.user,
+                session.host,
+            );
+            session
+                .events
+                .record(SessionEventKind::ConnectionLost, "removed by stale sweep");
+            session.release().await;
+            removed.push(StaleSession {
+                session_id,
+                host: session.host.clone(),
+                user: session.user.clone(),
+            });
+        }
+        removed
+    }
+
+    /// Disconnect and remove a session, cleaning up its remote temp dir.
+    /// The connection is closed unless another session still shares it.
     pub async fn disconnect(&self, session_id: &str) -> AppResult<()> {
-        let mut sessions = self.sessions.lock().await;
-        if sessions.remove(session_id).is_some() {
-            Ok(())
-        } else {
-            Err(AppError::SessionNotFound(session_id.to_string()))
+        let (removed, shared) = {
+            let mut sessions = self.sessions.lock().await;
+            let removed = sessions.remove(session_id);
+            let shared = removed
+                .as_ref()
+                .is_some_and(|r| sessions.values().any(|s| Arc::ptr_eq(&s.handle, &r.handle)));
+            (removed, shared)
+        };
+        match removed {
+            Some(session) => {
+                session.cleanup_temp_dir().await;
+                session.release().await;
+                if !shared {
+                    session
+                        .handle
+                        .disconnect(russh::Disconnect::ByApplication, "", "en")
+                        .await
+                        .ok();
+                }
+                Ok(())
+            }
+            None => Err(AppError::SessionNotFound(session_id.to_string())),
         }
     }
 
@@ -231,39 +1974,17 @@ impl SshSessionManager {
         user: &str,
         key_name: &str,
         passphrase: Option<&str>,
+        pin: HostKeyPin,
     ) -> AppResult<()> {
-        let pem = self.key_store.retrieve_key_pem(key_name).await?;
-
-        let private_key = if let Some(pass) = passphrase {
-            PrivateKey::from_openssh(pem.as_bytes())
-                .and_then(|k| k.decrypt(pass))
-                .map_err(|e| AppError::Ssh(format!("Failed to decode key: {e}")))?
-        } else {
-            PrivateKey::from_openssh(pem.as_bytes())
-                .map_err(|e| AppError::Ssh(format!("Failed to decode key: {e}")))?
-        };
-
-        let mut handle = self.establish_connection(host, port).await?;
-
-        let hash_alg = handle
-            .best_supported_rsa_hash()
-            .await
-            .ok()
-            .flatten()
-            .flatten();
+        let private_key = self.decode_key(key_name, passphrase).await?;
+        let (mut handle, ..) = self
+            .establish_connection(host, port, pin, Keepalive::default())
+            .await?;
+        authenticate_with_key(&mut handle, user, private_key).await?;
 
-        let key_with_hash = PrivateKeyWithHashAlg::new(Arc::new(private_key), hash_alg);
-
-        let auth_result = handle
-            .authenticate_publickey(user, key_with_hash)
-            .await
-            .map_err(|e| AppError::Ssh(format!("Auth failed: {e}")))?;
-
-        if !auth_result.success() {
-            return Err(AppError::Ssh("Authentication rejected by server".into()));
-        }
-
-        let _ = handle.disconnect(russh::Disconnect::ByApplication, "", "en").await;
+        let _ = handle
+            .disconnect(russh::Disconnect::ByApplication, "", "en")
+            .await;
         Ok(())
     }
 
@@ -274,19 +1995,16 @@ impl SshSessionManager {
         port: u16,
         user: &str,
         password: &str,
+        pin: HostKeyPin,
     ) -> AppResult<()> {
-        let mut handle = self.establish_connection(host, port).await?;
+        let (mut handle, ..) = self
+            .establish_connection(host, port, pin, Keepalive::default())
+            .await?;
+        authenticate_with_password(&mut handle, user, password, None).await?;
 
-        let auth_result = handle
-            .authenticate_password(user, password)
-            .await
-            .map_err(|e| AppError::Ssh(format!("Auth failed: {e}")))?;
-
-        if !auth_result.success() {
-            return Err(AppError::Ssh("Authentication rejected by server".into()));
-        }
-
-        let _ = handle.disconnect(russh::Disconnect::ByApplication, "", "en").await;
+        let _ = handle
+            .disconnect(russh::Disconnect::ByApplication, "", "en")
+            .await;
         Ok(())
     }
 
@@ -0,0 +1,183 @@
+//! JSON/YAML previews: parsed on the Rust side into pretty-printed text and
+//! a collapsed structural outline for a tree view.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::Arc;
+
+use crate::errors::{AppError, AppResult};
+use crate::file_types::{self, FileCategory};
+use crate::sftp_ops;
+use crate::ssh_manager::SshSession;
+
+/// Largest file we will parse for a structured preview.
+const MAX_PARSE_BYTES: u64 = 4 * 1024 * 1024;
+/// Outline depth beyond which containers are shown collapsed.
+const MAX_OUTLINE_DEPTH: usize = 8;
+/// Object keys listed per level before the rest are summarized.
+const MAX_OUTLINE_KEYS: usize = 200;
+/// Scalar values are shown up to this many characters.
+const MAX_VALUE_CHARS: usize = 80;
+
+// ─── Data Structures ───────────────────────────────────────────────────
+
+/// One node of a document outline (safe to send to JS).
+#[derive(Debug, Clone, Serialize)]
+pub struct OutlineNode {
+    /// Object key, or `None` for the root and array items.
+    pub key: Option<String>,
+    /// "object", "array", "string", "number", "bool" or "null".
+    pub kind: &'static str,
+    /// Number of keys or items for objects and arrays.
+    pub len: Option<usize>,
+    /// Short rendering of scalar values.
+    pub value: Option<String>,
+    /// Object members, or the first item of an array as a sample of its shape.
+    pub children: Vec<OutlineNode>,
+}
+
+/// Parsed preview of a JSON or YAML file.
+#[derive(Debug, Clone, Serialize)]
+pub struct StructuredPreview {
+    /// "json" or "yaml".
+    pub format: &'static str,
+    pub pretty: String,
+    pub outline: OutlineNode,
+    /// Number of documents in a multi-document YAML stream.
+    pub documents: usize,
+}
+
+// ─── Outline ───────────────────────────────────────────────────────────
+
+fn outline(key: Option<String>, value: &Value, depth: usize) -> OutlineNode {
+    let (kind, len, scalar) = match value {
+        Value::Object(map) => ("object", Some(map.len()), None),
+        Value::Array(items) => ("array", Some(items.len()), None),
+        Value::String(s) => ("string", None, Some(s.clone())),
+        Value::Number(n) => ("number", None, Some(n.to_string())),
+        Value::Bool(b) => ("bool", None, Some(b.to_string())),
+        Value::Null => ("null", None, None),
+    };
+
+    let children = if depth >= MAX_OUTLINE_DEPTH {
+        Vec::new()
+    } else {
+        match value {
+            Value::Object(map) => map
+                .iter()
+                .take(MAX_OUTLINE_KEYS)
+                .map(|(k, v)| outline(Some(k.clone()), v, depth + 1))
+                .collect(),
+            Value::Array(items) => items
+                .first()
+                .map(|first| vec![outline(None, first, depth + 1)])
+                .unwrap_or_default(),
+            _ => Vec::new(),
+        }
+    };
+
+    OutlineNode {
+        key,
+        kind,
+        len,
+        value: scalar.map(|s| match s.char_indices().nth(MAX_VALUE_CHARS) {
+            Some((cut, _)) => format!("{}…", &s[..cut]),
+            None => s,
+        }),
+        children,
+    }
+}
+
+// ─── Parsing ───────────────────────────────────────────────────────────
+
+fn parse_json(data: &[u8]) -> AppResult<(Value, String)> {
+    let value: Value =
+        serde_json::from_slice(data).map_err(|e| AppError::Other(format!("Invalid JSON: {e}")))?;
+    let pretty = serde_json::to_string_pretty(&value)
+        .map_err(|e| AppError::Other(format!("Failed to format JSON: {e}")))?;
+    Ok((value, pretty))
+}
+
+/// Parse a YAML stream; several documents become an array of documents.
+fn parse_yaml(data: &[u8]) -> AppResult<(Value, String, usize)> {
+    let mut docs = Vec::new();
+    for doc in serde_yaml::Deserializer::from_slice(data) {
+        let value =
+            Value::deserialize(doc).map_err(|e| AppError::Other(format!("Invalid YAML: {e}")))?;
+        docs.push(value);
+    }
+
+    let mut pretty = String::new();
+    for (i, doc) in docs.iter().enumerate() {
+        if i > 0 {
+            pretty.push_str("---\n");
+        }
+        let text = serde_yaml::to_string(doc)
+            .map_err(|e| AppError::Other(format!("Failed to format YAML: {e}")))?;
+        pretty.push_str(&text);
+    }
+
+    let count = docs.len();
+    let value = match count {
+        0 => Value::Null,
+        1 => docs.remove(0),
+        _ => Value::Array(docs),
+    };
+    Ok((value, pretty, count))
+}
+
+/// Download and parse a `.json`/`.yaml`/`.yml` file, returning pretty text
+/// and an outline of its structure.
+pub async fn preview_structured(
+    session: &Arc<SshSession>,
+    path: &str,
+) -> AppResult<StructuredPreview> {
+    let start = std::time::Instant::now();
+    let ext = file_types::extension(path).unwrap_or_default();
+    let format = match ext.as_str() {
+        "json" => "json",
+        "yaml" | "yml" => "yaml",
+        _ => {
+            return Err(AppError::Other(format!(
+                "No structured preview for .{ext} files"
+            )))
+        }
+    };
+    // A suffix the user has mapped away from code/text is not parsed.
+    if !matches!(
+        file_types::classify(path),
+        FileCategory::Code | FileCategory::Text
+    ) {
+        return Err(AppError::Other(format!(
+            "No structured preview for {path}: not classified as code or text"
+        )));
+    }
+
+    sftp_ops::check_size_limit(session, path, Some(MAX_PARSE_BYTES)).await?;
+    let data = sftp_ops::download_file(session, path).await?;
+
+    let (value, pretty, documents) = tokio::task::spawn_blocking(move || {
+        if format == "json" {
+            parse_json(&data).map(|(v, p)| (v, p, 1))
+        } else {
+            parse_yaml(&data)
+        }
+    })
+    .await
+    .map_err(|e| AppError::Other(format!("Parse task panicked: {e}")))??;
+    let outline = outline(None, &value, 0);
+
+    log::info!(
+        "[PERF] preview_structured \"{}\" — {:.2}ms | format: {} | documents: {}",
+        path,
+        start.elapsed().as_secs_f64() * 1000.0,
+        format,
+        documents,
+    );
+    Ok(StructuredPreview {
+        format,
+        pretty,
+        outline,
+        documents,
+    })
+}
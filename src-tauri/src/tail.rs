@@ -0,0 +1,209 @@
+//! Follow mode for remote log files, like `tail -f`. The end of the file
+//! is sent first, then the file is re-stat'ed on an interval and appended
+//! lines are streamed as they arrive.
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::oneshot;
+
+use crate::errors::{AppError, AppResult};
+use crate::sftp_ops;
+use crate::ssh_manager::SshSession;
+
+/// How much of the end of the file is read to find the first lines.
+const TAIL_INITIAL_BYTES: u64 = 64 * 1024;
+/// Interval between checks for new data.
+const TAIL_POLL: std::time::Duration = std::time::Duration::from_secs(1);
+/// Most bytes read per check, so a burst of output arrives in pieces.
+const TAIL_MAX_READ_BYTES: u64 = 1024 * 1024;
+/// A partial last line is sent anyway once it grows past this.
+const MAX_PARTIAL_LINE_BYTES: usize = 64 * 1024;
+
+// ─── Data Structures ───────────────────────────────────────────────────
+
+/// Events emitted to the webview for a followed file.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TailEvent {
+    /// Complete lines, in order. The first event holds the initial tail.
+    Data { tail_id: String, lines: Vec<String> },
+    /// The file shrank (truncated or rotated); following restarts at its start.
+    Truncated { tail_id: String },
+    /// Following ended: stopped, or failed with `error`.
+    Stopped {
+        tail_id: String,
+        error: Option<String>,
+    },
+}
+
+/// Followed files on one session, by tail ID.
+#[derive(Default)]
+pub struct TailRegistry {
+    tails: Mutex<HashMap<String, oneshot::Sender<()>>>,
+}
+
+impl TailRegistry {
+    /// Stop following. Returns `false` if no tail has that id.
+    pub fn stop(&self, tail_id: &str) -> bool {
+        match self.tails.lock().unwrap().remove(tail_id) {
+            Some(tx) => {
+                tx.send(()).ok();
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn remove(&self, tail_id: &str) {
+        self.tails.lock().unwrap().remove(tail_id);
+    }
+}
+
+// ─── Following ─────────────────────────────────────────────────────────
+
+/// Split complete lines off `pending`, leaving a trailing partial line
+/// unless it has grown too long to hold back.
+fn take_lines(pending: &mut Vec<u8>) -> Vec<String> {
+    let cut = match pending.iter().rposition(|&b| b == b'\n') {
+        Some(i) => i + 1,
+        None if pending.len() > MAX_PARTIAL_LINE_BYTES => pending.len(),
+        None => return Vec::new(),
+    };
+    let text: Vec<u8> = pending.drain(..cut).collect();
+    text.strip_suffix(b"\n")
+        .unwrap_or(&text)
+        .split(|&b| b == b'\n')
+        .map(|line| {
+            let line = line.strip_suffix(b"\r").unwrap_or(line);
+            String::from_utf8_lossy(line).into_owned()
+        })
+        .collect()
+}
+
+/// Read the last `lines` lines of `path` and return them with the offset
+/// to follow from.
+async fn initial_tail(
+    session: &Arc<SshSession>,
+    path: &str,
+    lines: usize,
+) -> AppResult<(Vec<String>, u64)> {
+    let size = sftp_ops::stat(session, path).await?.size.unwrap_or(0);
+    let start = size.saturating_sub(TAIL_INITIAL_BYTES);
+    let mut data = sftp_ops::read_range(session, path, start, size - start).await?;
+    // Follow from the end of the last complete line.
+    let partial = data.iter().rev().take_while(|&&b| b != b'\n').count();
+    let offset = start + (data.len() - partial) as u64;
+    data.truncate(data.len() - partial);
+    if start > 0 {
+        // Drop the first, probably partial, line.
+        let first = data
+            .iter()
+            .position(|&b| b == b'\n')
+            .map_or(data.len(), |i| i + 1);
+        data.drain(..first);
+    }
+    let mut all = take_lines(&mut data);
+    let skip = all.len().saturating_sub(lines);
+    all.drain(..skip);
+    Ok((all, offset))
+}
+
+/// Send the appended data from `offset` on until `stop` fires.
+async fn follow<F>(
+    session: &Arc<SshSession>,
+    tail_id: &str,
+    path: &str,
+    mut offset: u64,
+    mut stop: oneshot::Receiver<()>,
+    emit: &F,
+) -> AppResult<()>
+where
+    F: Fn(TailEvent),
+{
+    let mut pending = Vec::new();
+    loop {
+        tokio::select! {
+            _ = &mut stop => return Ok(()),
+            _ = tokio::time::sleep(TAIL_POLL) => {}
+        }
+        let size = sftp_ops::stat(session, path).await?.size.unwrap_or(0);
+        if size < offset {
+            log::info!("[SFTP] tail {} — \"{}\" shrank, restarting", tail_id, path);
+            offset = 0;
+            pending.clear();
+            emit(TailEvent::Truncated {
+                tail_id: tail_id.to_string(),
+            });
+        }
+        if size == offset {
+            continue;
+        }
+        let len = (size - offset).min(TAIL_MAX_READ_BYTES);
+        let data = sftp_ops::read_range(session, path, offset, len).await?;
+        offset += data.len() as u64;
+        pending.extend_from_slice(&data);
+        let lines = take_lines(&mut pending);
+        if !lines.is_empty() {
+            emit(TailEvent::Data {
+                tail_id: tail_id.to_string(),
+                lines,
+            });
+        }
+    }
+}
+
+/// Start following `path` under `tail_id`: the last `lines` lines are sent
+/// first, then new lines as they are appended. Every event goes through
+/// `emit`, ending with `Stopped`. The caller picks the ID so it can match
+/// events that arrive before this returns.
+pub async fn start_tail<F>(
+    session: &Arc<SshSession>,
+    tail_id: String,
+    path: &str,
+    lines: usize,
+    emit: F,
+) -> AppResult<String>
+where
+    F: Fn(TailEvent) + Send + Sync + 'static,
+{
+    let (initial, offset) = initial_tail(session, path, lines).await?;
+    let (tx, rx) = oneshot::channel();
+    {
+        let mut tails = session.tails.tails.lock().unwrap();
+        if tails.contains_key(&tail_id) {
+            return Err(AppError::Other(format!(
+                "Tail {tail_id} is already running"
+            )));
+        }
+        tails.insert(tail_id.clone(), tx);
+    }
+    log::info!(
+        "[SFTP] tail {} started on \"{}\" at offset {}",
+        tail_id,
+        path,
+        offset
+    );
+    emit(TailEvent::Data {
+        tail_id: tail_id.clone(),
+        lines: initial,
+    });
+
+    let session = session.clone();
+    let id = tail_id.clone();
+    let path = path.to_string();
+    tokio::spawn(async move {
+        let _op = session.ops.track("tail", &path);
+        let work = follow(&session, &id, &path, offset, rx, &emit);
+        let result = session.ops.until_closed(work).await;
+        session.tails.remove(&id);
+        if let Err(e) = &result {
+            log::warn!("[SFTP] tail {} on \"{}\" ended: {}", id, path, e);
+        }
+        emit(TailEvent::Stopped {
+            tail_id: id,
+            error: result.err().map(|e: AppError| e.to_string()),
+        });
+    });
+    Ok(tail_id)
+}
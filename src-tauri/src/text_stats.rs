@@ -0,0 +1,175 @@
+//! Line, word and byte counts for a remote text file, so a giant log can be
+//! sized up before it is opened in the preview.
+//!
+//! Counting runs on the server (`awk`) when exec is available. Otherwise the
+//! file is streamed over SFTP and counted here, up to `MAX_STREAM_BYTES`.
+
+use serde::Serialize;
+use std::sync::Arc;
+use tokio::io::AsyncReadExt;
+
+use crate::errors::{AppError, AppResult};
+use crate::ssh_manager::{shell_quote, SshSession, Transport, BULK_EXEC_TIMEOUT};
+use crate::timeouts;
+
+/// Stop client-side counting after this many bytes.
+const MAX_STREAM_BYTES: u64 = 256 * 1024 * 1024;
+const STREAM_CHUNK_SIZE: usize = 256 * 1024;
+
+// ─── Data Structures ───────────────────────────────────────────────────
+
+/// Counts for one text file (safe to send to JS). Lengths are in bytes, as
+/// `wc` counts them in the C locale; a final line without a newline counts.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TextStats {
+    pub path: String,
+    pub lines: u64,
+    pub words: u64,
+    pub bytes: u64,
+    pub longest_line: u64,
+    /// 1-based number of the first longest line; `None` for an empty file.
+    pub longest_line_number: Option<u64>,
+    /// Counting stopped at `MAX_STREAM_BYTES`; the counts cover a prefix.
+    pub truncated: bool,
+}
+
+// ─── Counting ──────────────────────────────────────────────────────────
+
+/// Running counts over a byte stream.
+#[derive(Default)]
+struct Counter {
+    stats: TextStats,
+    line_len: u64,
+    in_word: bool,
+}
+
+impl Counter {
+    fn feed(&mut self, data: &[u8]) {
+        self.stats.bytes += data.len() as u64;
+        for &b in data {
+            if b == b'\n' {
+                self.end_line();
+            } else {
+                self.line_len += 1;
+            }
+            let space = b.is_ascii_whitespace();
+            if !space && !self.in_word {
+                self.stats.words += 1;
+            }
+            self.in_word = !space;
+        }
+    }
+
+    fn end_line(&mut self) {
+        self.stats.lines += 1;
+        if self.line_len > self.stats.longest_line || self.stats.longest_line_number.is_none() {
+            self.stats.longest_line = self.line_len;
+            self.stats.longest_line_number = Some(self.stats.lines);
+        }
+        self.line_len = 0;
+    }
+
+    fn finish(mut self) -> TextStats {
+        if self.line_len > 0 {
+            self.end_line();
+        }
+        self.stats
+    }
+}
+
+/// Parse the `lines words longest at bytes` line printed by `count_remote`.
+fn parse_counts(path: &str, stdout: &[u8]) -> Option<TextStats> {
+    let text = String::from_utf8_lossy(stdout);
+    let mut fields = text.split_whitespace().map(|f| f.parse::<u64>().ok());
+    let (lines, words, longest, at, bytes) = (
+        fields.next()??,
+        fields.next()??,
+        fields.next()??,
+        fields.next()??,
+        fields.next()??,
+    );
+    Some(TextStats {
+        path: path.to_string(),
+        lines,
+        words,
+        bytes,
+        longest_line: longest,
+        longest_line_number: (at > 0).then_some(at),
+        truncated: false,
+    })
+}
+
+/// Count on the server with one `awk` pass plus `wc -c` for the size.
+async fn count_remote(session: &Arc<SshSession>, path: &str) -> AppResult<TextStats> {
+    let quoted = shell_quote(path);
+    let command = format!(
+        "LC_ALL=C awk '{{ w += NF; if (length($0) > m || NR == 1) {{ m = length($0); at = NR }} }} \
+         END {{ printf \"%d %d %d %d \", NR, w, m, at }}' {quoted} && wc -c < {quoted}"
+    );
+    let output = session.exec_within(&command, BULK_EXEC_TIMEOUT).await?;
+    if output.exit_status != Some(0) {
+        return Err(AppError::Ssh(format!(
+            "awk failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    parse_counts(path, &output.stdout)
+        .ok_or_else(|| AppError::Ssh("Unexpected output from awk/wc".into()))
+}
+
+/// Stream the file over SFTP and count it here.
+async fn count_streamed(session: &Arc<SshSession>, path: &str) -> AppResult<TextStats> {
+    let Transport::Sftp(sftp) = session.transport().await? else {
+        return Err(AppError::Sftp("Counting needs either exec or SFTP".into()));
+    };
+    let open = async {
+        sftp.open(path)
+            .await
+            .map_err(|e| AppError::Sftp(format!("Failed to open file: {e}")))
+    };
+    let mut file = session.timed(timeouts::metadata(), "open", open).await?;
+
+    let mut counter = Counter::default();
+    let mut buf = vec![0u8; session.chunk_size(STREAM_CHUNK_SIZE)];
+    loop {
+        let read = async {
+            file.read(&mut buf)
+                .await
+                .map_err(|e| AppError::Sftp(format!("Failed to read file: {e}")))
+        };
+        let n = session.timed(timeouts::transfer(), "read", read).await?;
+        if n == 0 {
+            break;
+        }
+        counter.feed(&buf[..n]);
+        if counter.stats.bytes >= MAX_STREAM_BYTES {
+            counter.stats.truncated = true;
+            break;
+        }
+    }
+    let mut stats = counter.finish();
+    stats.path = path.to_string();
+    Ok(stats)
+}
+
+/// Line, word and byte counts plus the longest line of the file at `path`.
+pub async fn text_stats(session: &Arc<SshSession>, path: &str) -> AppResult<TextStats> {
+    let start = std::time::Instant::now();
+    let _op = session.ops.track("text_stats", path);
+    let (stats, via) = match count_remote(session, path).await {
+        Ok(stats) => (stats, "exec"),
+        Err(e) => {
+            log::warn!("[SFTP] remote count failed ({}) — streaming instead", e);
+            (count_streamed(session, path).await?, "stream")
+        }
+    };
+    log::info!(
+        "[PERF] text_stats \"{}\" — {:.2}ms | lines: {} | bytes: {} | via: {}",
+        path,
+        start.elapsed().as_secs_f64() * 1000.0,
+        stats.lines,
+        stats.bytes,
+        via,
+    );
+    Ok(stats)
+}
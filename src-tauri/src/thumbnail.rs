@@ -0,0 +1,156 @@
+//! Thumbnail generation: libvips shrink-on-load when built with the `vips`
+//! feature, with the `image` + `fast_image_resize` pipeline as fallback.
+
+use crate::errors::{AppError, AppResult};
+
+/// Longest side of generated thumbnails, in pixels.
+const THUMB_SIZE: u32 = 256;
+
+/// Produce a WebP thumbnail (at most `THUMB_SIZE` on each side) from encoded
+/// image bytes.
+pub fn webp_thumbnail(buf: &[u8]) -> AppResult<Vec<u8>> {
+    #[cfg(feature = "vips")]
+    match vips::webp_thumbnail(buf) {
+        Ok(data) => return Ok(data),
+        Err(e) => log::warn!("[CACHE] libvips thumbnail failed, falling back: {}", e),
+    }
+    resize_with_image_crate(buf)
+}
+
+/// Alpha-weighted average colour of an encoded image as `#rrggbb`. Meant
+/// for small inputs such as generated thumbnails.
+pub fn average_color(encoded: &[u8]) -> Option<String> {
+    let img = image::load_from_memory(encoded).ok()?.to_rgba8();
+    let (mut r, mut g, mut b, mut weight) = (0u64, 0u64, 0u64, 0u64);
+    for px in img.pixels() {
+        let [pr, pg, pb, a] = px.0;
+        let a = a as u64;
+        r += pr as u64 * a;
+        g += pg as u64 * a;
+        b += pb as u64 * a;
+        weight += a;
+    }
+    if weight == 0 {
+        return None;
+    }
+    Some(format!(
+        "#{:02x}{:02x}{:02x}",
+        r / weight,
+        g / weight,
+        b / weight
+    ))
+}
+
+/// Full decode with the `image` crate, then a fast_image_resize downscale.
+fn resize_with_image_crate(buf: &[u8]) -> AppResult<Vec<u8>> {
+    // 1. Decode image from raw bytes
+    let img = image::load_from_memory(buf)
+        .map_err(|e| AppError::Sftp(format!("Image decode failed: {e}")))?;
+
+    // 2. Setup fast_image_resize Source image
+    let width = img.width().max(1);
+    let height = img.height().max(1);
+    let src_image = fast_image_resize::images::Image::from_vec_u8(
+        width,
+        height,
+        img.to_rgba8().into_raw(),
+        fast_image_resize::PixelType::U8x4,
+    )
+    .map_err(|e| AppError::Sftp(format!("Failed to create fir source image: {e}")))?;
+
+    // 3. Setup fast_image_resize Destination image (max bounds, keeping aspect ratio)
+    let aspect_ratio = img.width() as f32 / img.height() as f32;
+    let (dst_width, dst_height) = if aspect_ratio > 1.0 {
+        (
+            THUMB_SIZE,
+            (THUMB_SIZE as f32 / aspect_ratio).round() as u32,
+        )
+    } else {
+        (
+            (THUMB_SIZE as f32 * aspect_ratio).round() as u32,
+            THUMB_SIZE,
+        )
+    };
+    let dst_width = dst_width.max(1);
+    let dst_height = dst_height.max(1);
+
+    let mut dst_image = fast_image_resize::images::Image::new(
+        dst_width,
+        dst_height,
+        fast_image_resize::PixelType::U8x4,
+    );
+
+    // 4. Resize using Bilinear filter for speed
+    let mut resizer = fast_image_resize::Resizer::new();
+    resizer
+        .resize(
+            &src_image,
+            &mut dst_image,
+            &fast_image_resize::ResizeOptions::new().resize_alg(
+                fast_image_resize::ResizeAlg::Convolution(fast_image_resize::FilterType::Bilinear),
+            ),
+        )
+        .map_err(|e| AppError::Sftp(format!("Image resize failed: {e}")))?;
+
+    // 5. Convert back to image crate types and encode WebP
+    let resized_img = image::ImageBuffer::<image::Rgba<u8>, _>::from_raw(
+        dst_width,
+        dst_height,
+        dst_image.into_vec(),
+    )
+    .ok_or_else(|| AppError::Sftp("Failed to convert resized buffer".into()))?;
+
+    let dynamic_img = image::DynamicImage::ImageRgba8(resized_img);
+    let mut webp_buf = std::io::Cursor::new(Vec::new());
+    // Using `write_to` with standard WebP format (which we enabled in Cargo.toml via webp feature)
+    dynamic_img
+        .write_to(&mut webp_buf, image::ImageFormat::WebP)
+        .map_err(|e| AppError::Sftp(format!("WebP encoding failed: {e}")))?;
+
+    Ok(webp_buf.into_inner())
+}
+
+#[cfg(feature = "vips")]
+mod vips {
+    use super::THUMB_SIZE;
+    use crate::errors::{AppError, AppResult};
+    use libvips::ops;
+    use std::sync::OnceLock;
+
+    /// Initialise libvips once per process. The app handle is leaked on
+    /// purpose: dropping it would shut libvips down for every other thread.
+    fn init() -> AppResult<()> {
+        static READY: OnceLock<bool> = OnceLock::new();
+        let ready = *READY.get_or_init(|| match libvips::VipsApp::new("oxidock", false) {
+            Ok(app) => {
+                std::mem::forget(app);
+                true
+            }
+            Err(e) => {
+                log::error!("[CACHE] libvips init failed: {}", e);
+                false
+            }
+        });
+        if ready {
+            Ok(())
+        } else {
+            Err(AppError::Other("libvips is unavailable".into()))
+        }
+    }
+
+    /// `thumbnail_buffer` decodes with shrink-on-load (JPEG DCT scaling,
+    /// WebP/HEIF sub-resolution) so full-size pixels are never materialised.
+    pub fn webp_thumbnail(buf: &[u8]) -> AppResult<Vec<u8>> {
+        init()?;
+        let mut input = buf.to_vec();
+        let options = ops::ThumbnailBufferOptions {
+            height: THUMB_SIZE as i32,
+            size: ops::Size::Down,
+            ..ops::ThumbnailBufferOptions::default()
+        };
+        let thumb = ops::thumbnail_buffer_with_opts(&mut input, THUMB_SIZE as i32, &options)
+            .map_err(|e| AppError::Sftp(format!("libvips thumbnail failed: {e}")))?;
+        ops::webpsave_buffer(&thumb)
+            .map_err(|e| AppError::Sftp(format!("libvips WebP encoding failed: {e}")))
+    }
+}
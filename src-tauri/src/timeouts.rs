@@ -0,0 +1,36 @@
+//! Per-operation timeouts for remote file operations, so a hung server
+//! produces an error instead of an await that never returns.
+//!
+//! Metadata calls (stat, listing, mkdir, delete) get a short limit; transfer
+//! limits apply per chunk, so large files are fine as long as data flows.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+pub const DEFAULT_METADATA_TIMEOUT_SECS: u64 = 20;
+pub const DEFAULT_TRANSFER_TIMEOUT_SECS: u64 = 120;
+
+static METADATA_SECS: AtomicU64 = AtomicU64::new(DEFAULT_METADATA_TIMEOUT_SECS);
+static TRANSFER_SECS: AtomicU64 = AtomicU64::new(DEFAULT_TRANSFER_TIMEOUT_SECS);
+
+/// Limit for a single metadata request.
+pub fn metadata() -> Duration {
+    Duration::from_secs(METADATA_SECS.load(Ordering::Relaxed))
+}
+
+/// Limit for one transfer chunk (or a whole in-memory read).
+pub fn transfer() -> Duration {
+    Duration::from_secs(TRANSFER_SECS.load(Ordering::Relaxed))
+}
+
+/// Apply the timeout settings; `None` restores a default.
+pub fn apply(metadata_secs: Option<u64>, transfer_secs: Option<u64>) {
+    METADATA_SECS.store(
+        metadata_secs.unwrap_or(DEFAULT_METADATA_TIMEOUT_SECS),
+        Ordering::Relaxed,
+    );
+    TRANSFER_SECS.store(
+        transfer_secs.unwrap_or(DEFAULT_TRANSFER_TIMEOUT_SECS),
+        Ordering::Relaxed,
+    );
+}
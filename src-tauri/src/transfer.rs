@@ -0,0 +1,1574 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::future::Future;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, UNIX_EPOCH};
+use tokio::sync::Semaphore;
+
+use crate::errors::{AppError, AppResult};
+use crate::events::SessionEventKind;
+use crate::ignore::{self, IgnoreRules};
+use crate::memory;
+use crate::network;
+use crate::post_actions::{self, PostActionResult};
+use crate::receipts::UploadReceipt;
+use crate::sftp_ops::{self, ChecksumAlgo, FileEntry, PartialDownload, WalkControl, WalkOptions};
+use crate::ssh_manager::{shell_quote, SshSession};
+use crate::timeouts;
+use crate::tree_ops;
+
+/// Default number of files uploaded concurrently per batch.
+pub const DEFAULT_UPLOAD_CONCURRENCY: usize = 3;
+
+/// Upper bound on concurrent uploads to avoid flooding one SFTP channel.
+const MAX_UPLOAD_CONCURRENCY: usize = 16;
+
+/// Bytes compared by the optional quick-hash check.
+const QUICK_HASH_BYTES: u64 = 64 * 1024;
+/// Smaller files are always uploaded: hashing them is not worth a copy.
+const DEDUP_MIN_BYTES: u64 = 64 * 1024;
+
+// ─── Data Structures ───────────────────────────────────────────────────
+
+/// A single file queued for upload: `data`, or the file at `local_path`
+/// (read before the batch starts; see `sftp_upload_files`).
+#[derive(Debug, Clone, Deserialize)]
+pub struct UploadItem {
+    pub remote_path: String,
+    #[serde(default)]
+    pub data: Vec<u8>,
+    #[serde(default)]
+    pub local_path: Option<String>,
+    #[serde(default)]
+    pub mode: Option<u32>,
+}
+
+/// Progress events emitted while a batch transfer runs.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TransferEvent {
+    /// Bytes written so far for one file.
+    FileProgress {
+        transfer_id: String,
+        index: usize,
+        remote_path: String,
+        bytes_done: u64,
+        bytes_total: u64,
+    },
+    /// One file finished, successfully or not.
+    FileDone {
+        transfer_id: String,
+        index: usize,
+        remote_path: String,
+        error: Option<String>,
+    },
+    /// A file failed with a transient error and is being retried.
+    FileRetry {
+        transfer_id: String,
+        index: usize,
+        path: String,
+        attempt: u32,
+        error: String,
+    },
+    /// One file is waiting for an unmetered network before it starts.
+    FilePaused {
+        transfer_id: String,
+        index: usize,
+        path: String,
+    },
+    /// One file was skipped because the destination already matches.
+    FileSkipped {
+        transfer_id: String,
+        index: usize,
+        path: String,
+    },
+    /// Totals across the whole batch.
+    Aggregate {
+        transfer_id: String,
+        files_done: usize,
+        files_skipped: usize,
+        files_total: usize,
+        bytes_done: u64,
+        bytes_total: u64,
+    },
+    /// Bytes written so far by a single-file download, with its rate.
+    Download {
+        transfer_id: String,
+        remote_path: String,
+        bytes_done: u64,
+        bytes_total: u64,
+        bytes_per_sec: f64,
+    },
+    /// Size of a recursive download, computed before any file is fetched.
+    Planned {
+        transfer_id: String,
+        files_total: usize,
+        bytes_total: u64,
+    },
+    /// Files compared so far by a backup verification.
+    VerifyProgress {
+        transfer_id: String,
+        files_checked: usize,
+        files_total: usize,
+    },
+    /// Archive bytes written and entries packed so far by a tar backup.
+    BackupProgress {
+        transfer_id: String,
+        bytes_done: u64,
+        entries_done: usize,
+        /// The entry `tar` reported last.
+        current: String,
+    },
+    /// Post-download actions finished for a completed download.
+    PostActions {
+        transfer_id: String,
+        local_path: String,
+        results: Vec<PostActionResult>,
+    },
+}
+
+/// Options for recursive transfers.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SyncOptions {
+    /// Skip files whose size and mtime already match the destination.
+    pub skip_unchanged: bool,
+    /// Also require the first `QUICK_HASH_BYTES` to match before skipping.
+    pub quick_hash: bool,
+    /// Permission bits applied to uploaded files.
+    pub mode: Option<u32>,
+    /// The user accepted a download over a confirmation threshold.
+    pub confirm_large: bool,
+    /// Downloads larger than this need `confirm_large`.
+    pub confirm_threshold: Option<u64>,
+    /// Compare each transferred file's SHA-256 with the server's; a file
+    /// that differs, or cannot be hashed remotely, fails.
+    pub verify: bool,
+}
+
+/// Per-item retry behaviour for transfers.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// Total tries per item, including the first.
+    pub attempts: u32,
+    /// Delay before the first retry; doubled for each subsequent one.
+    pub backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            attempts: 3,
+            backoff: Duration::from_millis(500),
+        }
+    }
+}
+
+/// A file that could not be transferred.
+#[derive(Debug, Clone, Serialize)]
+pub struct FailedItem {
+    pub path: String,
+    pub error: String,
+    pub attempts: u32,
+}
+
+/// Outcome of a batch or recursive transfer.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TransferSummary {
+    /// Transferred on the first attempt.
+    pub succeeded: Vec<String>,
+    /// Transferred after one or more retries.
+    pub retried: Vec<String>,
+    pub skipped: Vec<String>,
+    /// Written by copying an identical file already uploaded in the same
+    /// run on the server, instead of sending the bytes again. Also listed
+    /// under `succeeded`.
+    pub deduplicated: Vec<String>,
+    /// Failed permanently (non-transient error or retries exhausted).
+    pub failed: Vec<FailedItem>,
+    pub bytes_transferred: u64,
+    /// Integrity receipts for the uploaded files, when receipts are enabled.
+    pub receipts: Vec<UploadReceipt>,
+}
+
+impl TransferSummary {
+    fn record(&mut self, path: &str, result: &AppResult<u64>, attempts: u32) {
+        match result {
+            Ok(n) => {
+                self.bytes_transferred += n;
+                if attempts > 1 {
+                    self.retried.push(path.to_string());
+                } else {
+                    self.succeeded.push(path.to_string());
+                }
+            }
+            Err(e) => self.failed.push(FailedItem {
+                path: path.to_string(),
+                error: e.to_string(),
+                attempts,
+            }),
+        }
+    }
+
+    /// Remote paths that were written, on the first try or after retries.
+    pub fn uploaded(&self) -> Vec<String> {
+        self.succeeded
+            .iter()
+            .chain(&self.retried)
+            .cloned()
+            .collect()
+    }
+
+    /// Add a `TransferFailed` entry to the session timeline if anything failed.
+    fn log_failures(&self, session: &SshSession, label: &str) {
+        let Some(first) = self.failed.first() else {
+            return;
+        };
+        session.events.record(
+            SessionEventKind::TransferFailed,
+            format!(
+                "{label}: {} failed (first: \"{}\" — {})",
+                self.failed.len(),
+                first.path,
+                first.error,
+            ),
+        );
+    }
+}
+
+/// How one file differs between a remote tree and its local backup.
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VerifyIssue {
+    /// On the remote but absent locally.
+    Missing,
+    /// Present locally but not on the remote.
+    Extra,
+    SizeMismatch,
+    /// Sizes match but the SHA-256 digests differ.
+    HashMismatch,
+}
+
+/// A file that failed verification.
+#[derive(Debug, Clone, Serialize)]
+pub struct VerifyFinding {
+    /// Path relative to both roots, `/`-separated.
+    pub path: String,
+    pub issue: VerifyIssue,
+    pub remote_size: Option<u64>,
+    pub local_size: Option<u64>,
+}
+
+/// Result of comparing a remote tree with a local backup.
+#[derive(Debug, Clone, Serialize)]
+pub struct VerifyReport {
+    pub remote_root: String,
+    pub local_root: String,
+    pub checked: usize,
+    pub matched: usize,
+    pub findings: Vec<VerifyFinding>,
+}
+
+/// Result of a tar backup (safe to send to JS).
+#[derive(Debug, Clone, Serialize)]
+pub struct BackupSummary {
+    pub remote_dir: String,
+    pub local_path: String,
+    /// Size of the `.tar.gz`.
+    pub bytes: u64,
+    pub entries: usize,
+    /// Messages from `tar`, e.g. files that changed or vanished while read.
+    pub warnings: Vec<String>,
+}
+
+// ─── Retry ─────────────────────────────────────────────────────────────
+
+/// Error text that marks a dropped link or a server that was briefly busy,
+/// matched lower-cased against the message.
+const TRANSIENT_MARKERS: &[&str] = &[
+    "timed out",
+    "timeout",
+    "connection reset",
+    "connection closed",
+    "connection lost",
+    "connection aborted",
+    "broken pipe",
+    "disconnect",
+    "channel closed",
+    "unexpected eof",
+    "unexpectedeof",
+    "resource temporarily unavailable",
+    "would block",
+    "eagain",
+];
+
+/// Errors worth retrying: timeouts, a lost connection and EAGAIN. Anything
+/// else (permissions, missing files, full disks, cancellation) fails at once.
+fn is_transient(e: &AppError) -> bool {
+    match e {
+        AppError::Timeout(_) => true,
+        AppError::Ssh(msg) | AppError::Sftp(msg) | AppError::Io(msg) => {
+            let msg = msg.to_lowercase();
+            TRANSIENT_MARKERS.iter().any(|marker| msg.contains(marker))
+        }
+        _ => false,
+    }
+}
+
+/// Run `op` until it succeeds, fails permanently, or runs out of attempts.
+/// `on_retry` is called with the failed attempt number before each retry.
+/// Returns the final result and the number of attempts made.
+async fn with_retry<T, F, Fut, R>(policy: RetryPolicy, op: F, on_retry: R) -> (AppResult<T>, u32)
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = AppResult<T>>,
+    R: Fn(u32, &AppError),
+{
+    let mut attempt = 1;
+    loop {
+        match op().await {
+            Err(e) if attempt < policy.attempts && is_transient(&e) => {
+                on_retry(attempt, &e);
+                tokio::time::sleep(policy.backoff * 2u32.saturating_pow(attempt - 1)).await;
+                attempt += 1;
+            }
+            result => return (result, attempt),
+        }
+    }
+}
+
+/// Callback used to publish transfer events (e.g. to the webview).
+pub type EventSink = Arc<dyn Fn(TransferEvent) + Send + Sync>;
+
+// ─── Batch Upload ──────────────────────────────────────────────────────
+
+/// Upload `items` with up to `concurrency` files in flight at once.
+///
+/// Individual failures do not abort the batch; transient errors are retried
+/// per `retry`, and each file's outcome is reported in the summary and via
+/// `FileDone`. Disconnecting the session cancels the files not yet done,
+/// which are reported as failed.
+pub async fn upload_batch(
+    session: Arc<SshSession>,
+    transfer_id: String,
+    items: Vec<UploadItem>,
+    concurrency: usize,
+    retry: RetryPolicy,
+    sink: EventSink,
+) -> AppResult<TransferSummary> {
+    let start = std::time::Instant::now();
+    let max = memory::pick(MAX_UPLOAD_CONCURRENCY, memory::LOW_MEMORY_MAX_CONCURRENCY);
+    let concurrency = concurrency.clamp(1, session.max_parallel(max));
+    let files_total = items.len();
+    let bytes_total: u64 = items.iter().map(|i| i.data.len() as u64).sum();
+
+    let semaphore = Arc::new(Semaphore::new(concurrency));
+    let bytes_done = Arc::new(AtomicU64::new(0));
+    let files_done = Arc::new(AtomicUsize::new(0));
+
+    let mut tasks = tokio::task::JoinSet::new();
+    for (index, item) in items.into_iter().enumerate() {
+        let session = session.clone();
+        let transfer_id = transfer_id.clone();
+        let semaphore = semaphore.clone();
+        let bytes_done = bytes_done.clone();
+        let files_done = files_done.clone();
+        let sink = sink.clone();
+
+        tasks.spawn(async move {
+            let file_total = item.data.len() as u64;
+            let wait = async {
+                network::wait_for_transfer(file_total, || {
+                    sink(TransferEvent::FilePaused {
+                        transfer_id: transfer_id.clone(),
+                        index,
+                        path: item.remote_path.clone(),
+                    })
+                })
+                .await;
+                Ok(semaphore.acquire_owned().await.ok())
+            };
+            let ready = session.ops.until_closed(wait).await;
+            let last = AtomicU64::new(0);
+
+            let (session, path, data, mode) = (
+                &session,
+                item.remote_path.as_str(),
+                item.data.as_slice(),
+                item.mode,
+            );
+            let (transfer_id, sink, last, bytes_done, files_done) =
+                (&transfer_id, &sink, &last, &*bytes_done, &*files_done);
+
+            let on_progress = move |written: u64| {
+                let delta = written - last.swap(written, Ordering::Relaxed);
+                let agg = bytes_done.fetch_add(delta, Ordering::Relaxed) + delta;
+                sink(TransferEvent::FileProgress {
+                    transfer_id: transfer_id.clone(),
+                    index,
+                    remote_path: path.to_string(),
+                    bytes_done: written,
+                    bytes_total: file_total,
+                });
+                sink(TransferEvent::Aggregate {
+                    transfer_id: transfer_id.clone(),
+                    files_done: files_done.load(Ordering::Relaxed),
+                    files_skipped: 0,
+                    files_total,
+                    bytes_done: agg,
+                    bytes_total,
+                });
+            };
+            let on_retry = move |attempt: u32, e: &AppError| {
+                // Un-count the partial attempt; the retry restarts from zero.
+                bytes_done.fetch_sub(last.swap(0, Ordering::Relaxed), Ordering::Relaxed);
+                sink(TransferEvent::FileRetry {
+                    transfer_id: transfer_id.clone(),
+                    index,
+                    path: path.to_string(),
+                    attempt,
+                    error: e.to_string(),
+                });
+            };
+
+            let (result, attempts) = match ready {
+                Ok(_permit) => {
+                    with_retry(
+                        retry,
+                        move || {
+                            let upload = sftp_ops::upload_file_with_progress(
+                                session,
+                                path,
+                                data,
+                                mode,
+                                on_progress,
+                            );
+                            session.ops.until_closed(upload)
+                        },
+                        on_retry,
+                    )
+                    .await
+                }
+                // Disconnected before the file started.
+                Err(e) => (Err(e), 0),
+            };
+            let result = result.map(|()| file_total);
+
+            if result.is_err() {
+                // Count the unsent remainder so the aggregate still reaches 100%.
+                let rest = file_total - last.load(Ordering::Relaxed);
+                bytes_done.fetch_add(rest, Ordering::Relaxed);
+            }
+            let done = files_done.fetch_add(1, Ordering::Relaxed) + 1;
+
+            sink(TransferEvent::FileDone {
+                transfer_id: transfer_id.clone(),
+                index,
+                remote_path: path.to_string(),
+                error: result.as_ref().err().map(|e| e.to_string()),
+            });
+            sink(TransferEvent::Aggregate {
+                transfer_id: transfer_id.clone(),
+                files_done: done,
+                files_skipped: 0,
+                files_total,
+                bytes_done: bytes_done.load(Ordering::Relaxed),
+                bytes_total,
+            });
+
+            (index, item.remote_path, result, attempts)
+        });
+    }
+
+    let mut outcomes = Vec::with_capacity(files_total);
+    while let Some(joined) = tasks.join_next().await {
+        match joined {
+            Ok(outcome) => outcomes.push(outcome),
+            Err(e) => log::error!("[TRANSFER] upload task panicked: {}", e),
+        }
+    }
+    outcomes.sort_by_key(|(index, ..)| *index);
+
+    let mut summary = TransferSummary::default();
+    for (_, path, result, attempts) in &outcomes {
+        summary.record(path, result, *attempts);
+    }
+
+    log::info!(
+        "[PERF] upload_batch {} — {:.2}ms | files: {} | retried: {} | failed: {} | bytes: {} | concurrency: {}",
+        transfer_id,
+        start.elapsed().as_secs_f64() * 1000.0,
+        files_total,
+        summary.retried.len(),
+        summary.failed.len(),
+        bytes_total,
+        concurrency,
+    );
+    summary.log_failures(&session, "upload_batch");
+    Ok(summary)
+}
+
+// ─── Recursive Transfers ───────────────────────────────────────────────
+
+fn quick_hash(data: &[u8]) -> u64 {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    data.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn local_quick_hash(path: &Path) -> Option<u64> {
+    use std::io::Read;
+    let mut buf = Vec::new();
+    std::fs::File::open(path)
+        .ok()?
+        .take(QUICK_HASH_BYTES)
+        .read_to_end(&mut buf)
+        .ok()?;
+    Some(quick_hash(&buf))
+}
+
+async fn remote_quick_hash(session: &Arc<SshSession>, path: &str) -> Option<u64> {
+    let head = sftp_ops::read_head(session, path, QUICK_HASH_BYTES)
+        .await
+        .ok()?;
+    Some(quick_hash(&head))
+}
+
+fn local_mtime(meta: &std::fs::Metadata) -> Option<u32> {
+    let secs = meta
+        .modified()
+        .ok()?
+        .duration_since(UNIX_EPOCH)
+        .ok()?
+        .as_secs();
+    u32::try_from(secs).ok()
+}
+
+/// Whether a transfer can be skipped: size and mtime match, and the quick
+/// hash too when requested.
+async fn unchanged(
+    session: &Arc<SshSession>,
+    opts: SyncOptions,
+    local: &Path,
+    remote: &str,
+    remote_size: Option<u64>,
+    remote_mtime: Option<u32>,
+) -> bool {
+    if !opts.skip_unchanged {
+        return false;
+    }
+    let Ok(meta) = std::fs::metadata(local) else {
+        return false;
+    };
+    if !meta.is_file() || Some(meta.len()) != remote_size {
+        return false;
+    }
+    if remote_mtime.is_none() || local_mtime(&meta) != remote_mtime {
+        return false;
+    }
+    if opts.quick_hash {
+        let local_hash = local_quick_hash(local);
+        return local_hash.is_some() && local_hash == remote_quick_hash(session, remote).await;
+    }
+    true
+}
+
+fn join_remote(dir: &str, rel: &str) -> String {
+    format!("{}/{}", dir.trim_end_matches('/'), rel)
+}
+
+/// Recursively list every file below `root`, paired with its path relative
+/// to `root` (built from raw on-disk names), sorted by that path. Ignored
+/// entries are skipped.
+async fn walk_remote(session: &Arc<SshSession>, root: &str) -> AppResult<Vec<(FileEntry, String)>> {
+    let mut files = Vec::new();
+    let opts = WalkOptions {
+        ignore: ignore::rules_for(&session.host),
+        ..Default::default()
+    };
+    sftp_ops::walk(session, root, &opts, |item| {
+        if !item.entry.is_dir {
+            files.push((item.entry, item.rel));
+        }
+        WalkControl::Continue
+    })
+    .await?;
+    files.sort_by(|a, b| a.1.cmp(&b.1));
+    Ok(files)
+}
+
+/// Where `rel`, a path built from names the server sent, lands below
+/// `local_dir`. Anything but plain names (`..`, a root, a drive prefix) is
+/// refused so a hostile listing cannot write outside the target folder.
+fn local_target(local_dir: &Path, rel: &str) -> AppResult<PathBuf> {
+    let rel_path = Path::new(rel);
+    let plain = rel_path
+        .components()
+        .all(|c| matches!(c, std::path::Component::Normal(_)));
+    if rel.is_empty() || !plain {
+        return Err(AppError::Other(format!(
+            "Refusing to download to unsafe path \"{rel}\""
+        )));
+    }
+    Ok(local_dir.join(rel_path))
+}
+
+/// A local file found by `walk_local`.
+struct LocalFile {
+    path: PathBuf,
+    /// Relative path using `/` separators.
+    rel: String,
+    size: u64,
+    mtime: Option<u32>,
+}
+
+/// Recursively list every file below a local directory, skipping entries
+/// that match `ignore`.
+fn walk_local(root: &Path, ignore: &IgnoreRules) -> AppResult<Vec<LocalFile>> {
+    let mut files = Vec::new();
+    let mut pending = vec![(root.to_path_buf(), String::new())];
+    while let Some((dir, rel_dir)) = pending.pop() {
+        for entry in std::fs::read_dir(&dir)?.filter_map(|e| e.ok()) {
+            let Ok(meta) = entry.metadata() else {
+                continue;
+            };
+            let name = entry.file_name().to_string_lossy().to_string();
+            if ignore.matches(&name) {
+                continue;
+            }
+            let rel = if rel_dir.is_empty() {
+                name
+            } else {
+                format!("{rel_dir}/{name}")
+            };
+            if meta.is_dir() {
+                pending.push((entry.path(), rel));
+            } else if meta.is_file() {
+                files.push(LocalFile {
+                    path: entry.path(),
+                    rel,
+                    size: meta.len(),
+                    mtime: local_mtime(&meta),
+                });
+            }
+        }
+    }
+    Ok(files)
+}
+
+/// Tracks progress counters and emits events for a sequential transfer.
+struct Progress {
+    transfer_id: String,
+    sink: EventSink,
+    files_total: usize,
+    bytes_total: u64,
+    files_done: usize,
+    files_skipped: usize,
+    bytes_done: u64,
+}
+
+impl Progress {
+    fn aggregate(&self, in_flight: u64) {
+        (self.sink)(TransferEvent::Aggregate {
+            transfer_id: self.transfer_id.clone(),
+            files_done: self.files_done,
+            files_skipped: self.files_skipped,
+            files_total: self.files_total,
+            bytes_done: self.bytes_done + in_flight,
+            bytes_total: self.bytes_total,
+        });
+    }
+
+    fn file_progress(&self, index: usize, path: &str, done: u64, total: u64) {
+        (self.sink)(TransferEvent::FileProgress {
+            transfer_id: self.transfer_id.clone(),
+            index,
+            remote_path: path.to_string(),
+            bytes_done: done,
+            bytes_total: total,
+        });
+        self.aggregate(done);
+    }
+
+    fn retrying(&self, index: usize, path: &str, attempt: u32, error: &AppError) {
+        (self.sink)(TransferEvent::FileRetry {
+            transfer_id: self.transfer_id.clone(),
+            index,
+            path: path.to_string(),
+            attempt,
+            error: error.to_string(),
+        });
+    }
+
+    /// Hold the file until the network policy lets it start. Fails if the
+    /// session is disconnected meanwhile.
+    async fn wait_for_network(
+        &self,
+        session: &SshSession,
+        index: usize,
+        path: &str,
+        size: u64,
+    ) -> AppResult<()> {
+        let wait = network::wait_for_transfer(size, || {
+            (self.sink)(TransferEvent::FilePaused {
+                transfer_id: self.transfer_id.clone(),
+                index,
+                path: path.to_string(),
+            })
+        });
+        session.ops.until_closed(async { Ok(wait.await) }).await
+    }
+
+    fn skipped(&mut self, index: usize, path: &str, size: u64, summary: &mut TransferSummary) {
+        self.files_skipped += 1;
+        self.files_done += 1;
+        self.bytes_done += size;
+        (self.sink)(TransferEvent::FileSkipped {
+            transfer_id: self.transfer_id.clone(),
+            index,
+            path: path.to_string(),
+        });
+        self.aggregate(0);
+        summary.skipped.push(path.to_string());
+    }
+
+    fn finished(
+        &mut self,
+        index: usize,
+        path: &str,
+        size: u64,
+        (result, attempts): (AppResult<u64>, u32),
+        summary: &mut TransferSummary,
+    ) {
+        self.files_done += 1;
+        self.bytes_done += size;
+        summary.record(path, &result, attempts);
+        (self.sink)(TransferEvent::FileDone {
+            transfer_id: self.transfer_id.clone(),
+            index,
+            remote_path: path.to_string(),
+            error: result.err().map(|e| e.to_string()),
+        });
+        self.aggregate(0);
+    }
+}
+
+/// Download one file to `local`, then stamp it with the remote mtime.
+async fn download_one<F: Fn(u64)>(
+    session: &Arc<SshSession>,
+    remote: &str,
+    local: &Path,
+    opts: SyncOptions,
+    mtime: Option<u32>,
+    on_progress: F,
+) -> AppResult<u64> {
+    if let Some(parent) = local.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    let n = sftp_ops::download_to_path(session, remote, local, on_progress).await?;
+    if opts.verify {
+        verify_copy(session, remote, &post_actions::local_sha256(local).await?).await?;
+    }
+    if let Some(mtime) = mtime {
+        let time = UNIX_EPOCH + Duration::from_secs(mtime as u64);
+        std::fs::File::options()
+            .write(true)
+            .open(local)
+            .and_then(|f| f.set_modified(time))?;
+    }
+    Ok(n)
+}
+
+/// Interval between `Download` events; the final one is always sent.
+const DOWNLOAD_EVENT_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Stream one remote file to `local_path`, emitting `Download` events with
+/// the transfer rate. Runs under `transfer_id` so it can be cancelled.
+///
+/// The download starts at `partial.offset` (see
+/// [`sftp_ops::prepare_partial`]). If it fails or the session drops, the
+/// `.part` file is kept so a retry can append to it; a cancelled download
+/// is removed.
+pub async fn save_file(
+    session: &Arc<SshSession>,
+    transfer_id: &str,
+    local_path: &Path,
+    partial: PartialDownload,
+    sink: EventSink,
+) -> AppResult<u64> {
+    let start = std::time::Instant::now();
+    let (remote_path, offset, bytes_total) =
+        (partial.remote_path.clone(), partial.offset, partial.size);
+    let last_event = std::sync::Mutex::new(start);
+    let on_progress = |done: u64| {
+        let now = std::time::Instant::now();
+        let mut last = last_event.lock().unwrap();
+        if done < bytes_total && now - *last < DOWNLOAD_EVENT_INTERVAL {
+            return;
+        }
+        *last = now;
+        drop(last);
+        sink(TransferEvent::Download {
+            transfer_id: transfer_id.to_string(),
+            remote_path: remote_path.clone(),
+            bytes_done: done,
+            bytes_total: bytes_total.max(done),
+            bytes_per_sec: done.saturating_sub(offset) as f64
+                / start.elapsed().as_secs_f64().max(0.001),
+        });
+    };
+
+    // Large files wait for an unmetered network, like directory transfers.
+    let download = async {
+        network::wait_for_transfer(bytes_total.saturating_sub(offset), || {
+            sink(TransferEvent::FilePaused {
+                transfer_id: transfer_id.to_string(),
+                index: 0,
+                path: remote_path.clone(),
+            })
+        })
+        .await;
+        sftp_ops::save_file(session, local_path, partial, on_progress).await
+    };
+    let download = session.ops.until_closed(download);
+    let result = session.ops.run_transfer(transfer_id, download).await;
+    match &result {
+        Err(AppError::Cancelled(_)) if !session.ops.is_closed() => {
+            log::info!("[TRANSFER] save_file \"{}\" cancelled", remote_path);
+            sftp_ops::discard_partial(local_path);
+        }
+        Err(e) => {
+            log::info!(
+                "[TRANSFER] save_file \"{}\" failed, partial kept: {}",
+                remote_path,
+                e
+            );
+        }
+        Ok(_) => {}
+    }
+    result
+}
+
+/// Check that the server's SHA-256 of `remote` is `local_hash`.
+async fn verify_copy(session: &Arc<SshSession>, remote: &str, local_hash: &str) -> AppResult<()> {
+    let remote_hash = sftp_ops::checksum(session, remote, ChecksumAlgo::Sha256)
+        .await
+        .map_err(|e| AppError::Other(format!("Could not verify {remote}: {e}")))?;
+    if remote_hash.digest != local_hash {
+        return Err(AppError::Other(format!(
+            "Checksum mismatch for {remote}: local {local_hash}, remote {}",
+            remote_hash.digest
+        )));
+    }
+    Ok(())
+}
+
+/// Upload one local file to `remote`, then stamp it with the local mtime.
+async fn upload_one<F: Fn(u64)>(
+    session: &Arc<SshSession>,
+    local: &Path,
+    remote: &str,
+    opts: SyncOptions,
+    mtime: Option<u32>,
+    on_progress: F,
+) -> AppResult<u64> {
+    let data = tokio::fs::read(local).await?;
+    sftp_ops::upload_file_with_progress(session, remote, &data, opts.mode, on_progress).await?;
+    if opts.verify {
+        verify_copy(session, remote, &post_actions::sha256_hex(&data)).await?;
+    }
+    if let Some(mtime) = mtime {
+        sftp_ops::set_mtime(session, remote, mtime).await?;
+    }
+    Ok(data.len() as u64)
+}
+
+/// Recursively download `remote_dir` into `local_dir`, preserving mtimes so
+/// re-runs can skip unchanged files. On disconnect the remaining files are
+/// reported as cancelled.
+pub async fn download_dir(
+    session: Arc<SshSession>,
+    transfer_id: String,
+    remote_dir: &str,
+    local_dir: &Path,
+    opts: SyncOptions,
+    retry: RetryPolicy,
+    sink: EventSink,
+) -> AppResult<TransferSummary> {
+    let start = std::time::Instant::now();
+    let files = walk_remote(&session, remote_dir).await?;
+
+    // Report the size up front and stop for confirmation on large pulls.
+    let bytes_total: u64 = files.iter().map(|(e, _)| e.size).sum();
+    sink(TransferEvent::Planned {
+        transfer_id: transfer_id.clone(),
+        files_total: files.len(),
+        bytes_total,
+    });
+    if let Some(limit) = opts.confirm_threshold.filter(|l| bytes_total > *l) {
+        if !opts.confirm_large {
+            log::info!(
+                "[TRANSFER] download_dir \"{}\" needs confirmation — {} files, {} bytes",
+                remote_dir,
+                files.len(),
+                bytes_total,
+            );
+            return Err(AppError::ConfirmDownload {
+                size: bytes_total,
+                limit,
+            });
+        }
+    }
+    network::check_download(bytes_total, opts.confirm_large)?;
+
+    let mut progress = Progress {
+        transfer_id: transfer_id.clone(),
+        sink,
+        files_total: files.len(),
+        bytes_total,
+        files_done: 0,
+        files_skipped: 0,
+        bytes_done: 0,
+    };
+    let mut summary = TransferSummary::default();
+
+    for (index, (entry, rel)) in files.iter().enumerate() {
+        let local = match local_target(local_dir, rel) {
+            Ok(local) => local,
+            Err(e) => {
+                progress.finished(index, &entry.path, entry.size, (Err(e), 0), &mut summary);
+                continue;
+            }
+        };
+        let remote_mtime = sftp_ops::entry_mtime(entry);
+
+        let size = Some(entry.size);
+        if unchanged(&session, opts, &local, &entry.path, size, remote_mtime).await {
+            progress.skipped(index, &entry.path, entry.size, &mut summary);
+            continue;
+        }
+        let (session, path) = (&session, entry.path.as_str());
+        if let Err(e) = progress
+            .wait_for_network(session, index, path, entry.size)
+            .await
+        {
+            progress.finished(index, path, entry.size, (Err(e), 0), &mut summary);
+            continue;
+        }
+
+        let (local, p) = (local.as_path(), &progress);
+        let outcome = with_retry(
+            retry,
+            move || {
+                let download =
+                    download_one(session, path, local, opts, remote_mtime, move |done| {
+                        p.file_progress(index, path, done, entry.size)
+                    });
+                session.ops.until_closed(download)
+            },
+            move |attempt, e| p.retrying(index, path, attempt, e),
+        )
+        .await;
+        progress.finished(index, path, entry.size, outcome, &mut summary);
+    }
+
+    log::info!(
+        "[PERF] download_dir \"{}\" — {:.2}ms | ok: {} | retried: {} | skipped: {} | failed: {}",
+        remote_dir,
+        start.elapsed().as_secs_f64() * 1000.0,
+        summary.succeeded.len(),
+        summary.retried.len(),
+        summary.skipped.len(),
+        summary.failed.len(),
+    );
+    summary.log_failures(&session, "download_dir");
+    Ok(summary)
+}
+
+/// Finish `dest`, just copied on the server from an identical file, the
+/// way [`upload_one`] finishes an upload: `opts.mode`, the `opts.verify`
+/// check against `local_hash`, then the local file's mtime.
+async fn finish_duplicate(
+    session: &Arc<SshSession>,
+    dest: &str,
+    opts: SyncOptions,
+    local_hash: &str,
+    mtime: Option<u32>,
+) -> AppResult<()> {
+    if let Some(mode) = opts.mode {
+        sftp_ops::set_permissions(session, dest, mode).await?;
+    }
+    if opts.verify {
+        verify_copy(session, dest, local_hash).await?;
+    }
+    if let Some(mtime) = mtime {
+        sftp_ops::set_mtime(session, dest, mtime).await?;
+    }
+    Ok(())
+}
+
+/// Recursively upload `local_dir` into `remote_dir`, preserving mtimes so
+/// re-runs can skip unchanged files.
+///
+/// Files with the same size and SHA-256 as one already uploaded earlier in
+/// the run, or found unchanged and confirmed by the server's hash, are
+/// copied on the server with `cp` instead of being sent again; without
+/// `cp` they are uploaded as usual. Copies get the same mode, verification
+/// and mtime as uploads. Streaming the copy over SFTP would move the bytes
+/// twice, costing more than uploading them.
+pub async fn upload_dir(
+    session: Arc<SshSession>,
+    transfer_id: String,
+    local_dir: &Path,
+    remote_dir: &str,
+    opts: SyncOptions,
+    retry: RetryPolicy,
+    sink: EventSink,
+) -> AppResult<TransferSummary> {
+    let start = std::time::Instant::now();
+    let root = local_dir.to_path_buf();
+    let rules = ignore::rules_for(&session.host);
+    let files = tokio::task::spawn_blocking(move || walk_local(&root, &rules))
+        .await
+        .map_err(|e| AppError::Other(format!("Local walk task panicked: {e}")))??;
+
+    // Create remote directories parents-first (a parent sorts before its children).
+    let mut dirs = BTreeSet::new();
+    for file in &files {
+        for (i, _) in file.rel.match_indices('/') {
+            dirs.insert(&file.rel[..i]);
+        }
+    }
+    let dir_paths = std::iter::once(remote_dir.to_string())
+        .chain(dirs.into_iter().map(|d| join_remote(remote_dir, d)));
+    for path in dir_paths {
+        if sftp_ops::stat(&session, &path).await.is_err() {
+            sftp_ops::create_dir(&session, &path, None).await?;
+        }
+    }
+
+    let mut progress = Progress {
+        transfer_id: transfer_id.clone(),
+        sink,
+        files_total: files.len(),
+        bytes_total: files.iter().map(|f| f.size).sum(),
+        files_done: 0,
+        files_skipped: 0,
+        bytes_done: 0,
+    };
+    let mut summary = TransferSummary::default();
+
+    // Only files sharing their size with another one can be duplicates, so
+    // only those are hashed.
+    let mut size_counts: HashMap<u64, usize> = HashMap::new();
+    for file in files.iter().filter(|f| f.size >= DEDUP_MIN_BYTES) {
+        *size_counts.entry(file.size).or_default() += 1;
+    }
+    // (size, hash) of files now on the server, to the remote path holding them.
+    let mut seen: HashMap<(u64, String), String> = HashMap::new();
+    // Skipped files matched on size and mtime only; their remote hash is
+    // checked before the first copy from them.
+    let mut unconfirmed: HashMap<(u64, String), String> = HashMap::new();
+    let mut cp_works = true;
+
+    for (index, file) in files.iter().enumerate() {
+        let remote = join_remote(remote_dir, &file.rel);
+        let content = match size_counts.get(&file.size) {
+            Some(&n) if n > 1 => post_actions::local_sha256(&file.path)
+                .await
+                .ok()
+                .map(|hash| (file.size, hash)),
+            _ => None,
+        };
+
+        if opts.skip_unchanged {
+            if let Ok(st) = sftp_ops::stat(&session, &remote).await {
+                if unchanged(&session, opts, &file.path, &remote, st.size, st.mtime).await {
+                    progress.skipped(index, &remote, file.size, &mut summary);
+                    if let Some(content) = content.filter(|c| !seen.contains_key(c)) {
+                        unconfirmed.entry(content).or_insert(remote);
+                    }
+                    continue;
+                }
+            }
+        }
+        let (session, path) = (&session, remote.as_str());
+        if let Some(content) = content
+            .as_ref()
+            .filter(|c| cp_works && !seen.contains_key(c))
+        {
+            if let Some(candidate) = unconfirmed.remove(content) {
+                match verify_copy(session, &candidate, &content.1).await {
+                    Ok(()) => {
+                        seen.insert(content.clone(), candidate);
+                    }
+                    Err(e) => log::info!("[TRANSFER] not copying from \"{}\": {}", candidate, e),
+                }
+            }
+        }
+        let duplicate_of = content.as_ref().and_then(|c| Some((seen.get(c)?, &c.1)));
+        if let Some((src, local_hash)) = duplicate_of.filter(|_| cp_works) {
+            let _op = session.ops.track("copy", path);
+            match sftp_ops::copy_on_server(session, src, path).await {
+                Ok(()) => {
+                    let finished =
+                        finish_duplicate(session, path, opts, local_hash, file.mtime).await;
+                    match finished {
+                        Ok(()) => {
+                            log::info!(
+                                "[TRANSFER] \"{}\" is a duplicate of \"{}\", copied",
+                                path,
+                                src
+                            );
+                            summary.deduplicated.push(path.to_string());
+                            progress.finished(index, path, file.size, (Ok(0), 1), &mut summary);
+                            continue;
+                        }
+                        // The upload below replaces the copy.
+                        Err(e) => log::warn!(
+                            "[TRANSFER] copied \"{}\" failed its checks, uploading instead: {}",
+                            path,
+                            e
+                        ),
+                    }
+                }
+                Err(e) => {
+                    // Without `cp` the rest of the run uploads duplicates too.
+                    log::warn!(
+                        "[TRANSFER] copying \"{}\" failed, uploading instead: {}",
+                        src,
+                        e
+                    );
+                    cp_works = false;
+                }
+            }
+        }
+        if let Err(e) = progress
+            .wait_for_network(session, index, path, file.size)
+            .await
+        {
+            progress.finished(index, path, file.size, (Err(e), 0), &mut summary);
+            continue;
+        }
+
+        let p = &progress;
+        let outcome = with_retry(
+            retry,
+            move || {
+                let on_progress = move |done| p.file_progress(index, path, done, file.size);
+                let upload = upload_one(session, &file.path, path, opts, file.mtime, on_progress);
+                session.ops.until_closed(upload)
+            },
+            move |attempt, e| p.retrying(index, path, attempt, e),
+        )
+        .await;
+        if let (Ok(_), Some(content)) = (&outcome.0, content) {
+            seen.entry(content).or_insert_with(|| path.to_string());
+        }
+        progress.finished(index, path, file.size, outcome, &mut summary);
+    }
+
+    log::info!(
+        "[PERF] upload_dir \"{}\" — {:.2}ms | ok: {} | retried: {} | skipped: {} | deduplicated: {} | failed: {}",
+        remote_dir,
+        start.elapsed().as_secs_f64() * 1000.0,
+        summary.succeeded.len(),
+        summary.retried.len(),
+        summary.skipped.len(),
+        summary.deduplicated.len(),
+        summary.failed.len(),
+    );
+    summary.log_failures(&session, "upload_dir");
+    Ok(summary)
+}
+
+// ─── Cross-Filesystem Move ─────────────────────────────────────────────
+
+/// Move `from` to `to` where the server cannot rename across filesystems:
+/// copy every file with progress, check each copy's size, then delete the
+/// source. If a copy fails or is cancelled, whatever was copied is removed
+/// and the source is left as it was. `to` must not exist yet, so that
+/// cleanup never touches anything but the copy.
+pub async fn move_by_copy(
+    session: &Arc<SshSession>,
+    transfer_id: &str,
+    from: &str,
+    to: &str,
+    sink: EventSink,
+) -> AppResult<()> {
+    let start = std::time::Instant::now();
+    if sftp_ops::stat(session, to).await.is_ok() {
+        return Err(AppError::Sftp(format!(
+            "{to} already exists on another filesystem; remove it before moving"
+        )));
+    }
+    // A symlinked root moves as the link; its target is left alone.
+    let root_is_link = sftp_ops::is_symlink(session, from).await?;
+    let root_is_dir = !root_is_link && sftp_ops::stat(session, from).await?.is_dir;
+    let mut dirs = Vec::new();
+    let mut links = Vec::new();
+    let mut files = Vec::new();
+    if root_is_link {
+        files.push((from.to_string(), to.to_string(), 0));
+    } else if root_is_dir {
+        sftp_ops::walk(session, from, &WalkOptions::default(), |item| {
+            let dest = join_remote(to, &item.rel);
+            // `walk` marks a link to a directory as a directory; it is
+            // recreated as a link, never as an empty real directory.
+            if item.entry.is_symlink {
+                links.push((item.entry.path, dest));
+            } else if item.entry.is_dir {
+                dirs.push(dest);
+            } else {
+                files.push((item.entry.path, dest, item.entry.size));
+            }
+            WalkControl::Continue
+        })
+        .await?;
+        // Parents sort before their children.
+        dirs.sort();
+        files.sort_by(|a, b| a.1.cmp(&b.1));
+    } else {
+        let size = sftp_ops::stat(session, from).await?.size.unwrap_or(0);
+        files.push((from.to_string(), to.to_string(), size));
+    }
+
+    let bytes_total = files.iter().map(|f| f.2).sum();
+    sink(TransferEvent::Planned {
+        transfer_id: transfer_id.to_string(),
+        files_total: files.len(),
+        bytes_total,
+    });
+    let mut progress = Progress {
+        transfer_id: transfer_id.to_string(),
+        sink,
+        files_total: files.len(),
+        bytes_total,
+        files_done: 0,
+        files_skipped: 0,
+        bytes_done: 0,
+    };
+
+    let copy = async {
+        if root_is_dir {
+            sftp_ops::create_dir(session, to, None).await?;
+            for dir in &dirs {
+                sftp_ops::create_dir(session, dir, None).await?;
+            }
+            for (src, dest) in &links {
+                let target = sftp_ops::read_link(session, src).await?;
+                sftp_ops::create_symlink(session, &target, dest).await?;
+            }
+        }
+        for (index, (src, dest, size)) in files.iter().enumerate() {
+            let p = &progress;
+            let copied = sftp_ops::copy_streamed(session, src, dest, |done| {
+                p.file_progress(index, src, done, *size)
+            })
+            .await?;
+            // A recreated link has nothing to measure.
+            let landed = if sftp_ops::is_symlink(session, dest).await? {
+                Some(copied)
+            } else {
+                sftp_ops::stat(session, dest).await?.size
+            };
+            if landed != Some(copied) || copied != *size {
+                return Err(AppError::Sftp(format!(
+                    "Copy of {src} is incomplete: {copied} of {size} bytes"
+                )));
+            }
+            progress.files_done += 1;
+            progress.bytes_done += size;
+            (progress.sink)(TransferEvent::FileDone {
+                transfer_id: transfer_id.to_string(),
+                index,
+                remote_path: src.clone(),
+                error: None,
+            });
+            progress.aggregate(0);
+        }
+        Ok(())
+    };
+    let copied = session
+        .ops
+        .run_transfer(transfer_id, session.ops.until_closed(copy))
+        .await;
+    if let Err(e) = copied {
+        log::info!("[TRANSFER] move_by_copy \"{}\" failed: {}", from, e);
+        if let Err(cleanup) = tree_ops::delete_recursive(session, to).await {
+            log::warn!(
+                "[TRANSFER] could not remove partial copy \"{}\": {}",
+                to,
+                cleanup
+            );
+        }
+        return Err(e);
+    }
+
+    let removed = tree_ops::delete_recursive(session, from).await?;
+    if let Some(failed) = removed.failed.first() {
+        return Err(AppError::Sftp(format!(
+            "Copied to {to}, but {} could not be removed: {}",
+            failed.path, failed.error
+        )));
+    }
+
+    log::info!(
+        "[PERF] move_by_copy \"{}\" → \"{}\" — {:.2}ms | files: {} | bytes: {}",
+        from,
+        to,
+        start.elapsed().as_secs_f64() * 1000.0,
+        progress.files_done,
+        progress.bytes_done,
+    );
+    Ok(())
+}
+
+// ─── Backup Verification ───────────────────────────────────────────────
+
+/// Walk `remote_root` and `local_root` and report files missing from the
+/// backup, extra local files, and size mismatches. With `hash`, files of
+/// equal size also have their whole-file SHA-256 digests compared.
+pub async fn verify_backup(
+    session: Arc<SshSession>,
+    transfer_id: String,
+    remote_root: &str,
+    local_root: &Path,
+    hash: bool,
+    sink: EventSink,
+) -> AppResult<VerifyReport> {
+    let start = std::time::Instant::now();
+    let remote = walk_remote(&session, remote_root).await?;
+    let root = local_root.to_path_buf();
+    let rules = ignore::rules_for(&session.host);
+    let local = tokio::task::spawn_blocking(move || walk_local(&root, &rules))
+        .await
+        .map_err(|e| AppError::Other(format!("Local walk panicked: {e}")))??;
+    let mut local: std::collections::HashMap<String, LocalFile> =
+        local.into_iter().map(|f| (f.rel.clone(), f)).collect();
+
+    // Files on both sides count once.
+    let extra_count = local.len().saturating_sub(
+        remote
+            .iter()
+            .filter(|(_, rel)| local.contains_key(rel))
+            .count(),
+    );
+    let files_total = remote.len() + extra_count;
+    let mut findings = Vec::new();
+    let mut matched = 0;
+    let mut checked = 0;
+    let report_progress = |checked: usize| {
+        sink(TransferEvent::VerifyProgress {
+            transfer_id: transfer_id.clone(),
+            files_checked: checked,
+            files_total,
+        })
+    };
+
+    for (entry, rel) in &remote {
+        checked += 1;
+        let issue = match local.remove(rel) {
+            None => Some((VerifyIssue::Missing, None)),
+            Some(file) if file.size != entry.size => {
+                Some((VerifyIssue::SizeMismatch, Some(file.size)))
+            }
+            Some(file) if hash => {
+                let local_hash = post_actions::local_sha256(&file.path).await.ok();
+                let remote_hash = sftp_ops::checksum(&session, &entry.path, ChecksumAlgo::Sha256)
+                    .await
+                    .ok()
+                    .map(|c| c.digest);
+                (local_hash.is_none() || local_hash != remote_hash)
+                    .then_some((VerifyIssue::HashMismatch, Some(file.size)))
+            }
+            Some(_) => None,
+        };
+        match issue {
+            Some((issue, local_size)) => findings.push(VerifyFinding {
+                path: rel.clone(),
+                issue,
+                remote_size: Some(entry.size),
+                local_size,
+            }),
+            None => matched += 1,
+        }
+        report_progress(checked);
+    }
+
+    // Whatever is left locally has no remote counterpart.
+    let mut extra: Vec<LocalFile> = local.into_values().collect();
+    extra.sort_by(|a, b| a.rel.cmp(&b.rel));
+    for file in extra {
+        checked += 1;
+        findings.push(VerifyFinding {
+            path: file.rel,
+            issue: VerifyIssue::Extra,
+            remote_size: None,
+            local_size: Some(file.size),
+        });
+    }
+    report_progress(checked);
+
+    log::info!(
+        "[PERF] verify_backup \"{}\" — {:.2}ms | checked: {} | matched: {} | findings: {}",
+        remote_root,
+        start.elapsed().as_secs_f64() * 1000.0,
+        checked,
+        matched,
+        findings.len(),
+    );
+    Ok(VerifyReport {
+        remote_root: remote_root.to_string(),
+        local_root: local_root.to_string_lossy().to_string(),
+        checked,
+        matched,
+        findings,
+    })
+}
+
+// ─── Tar Backup ────────────────────────────────────────────────────────
+
+/// Interval between `BackupProgress` events; the final one is always sent.
+const BACKUP_EVENT_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Archive `remote_dir` with `tar cz` on the server and stream it into
+/// `local_path` over one exec channel — far faster than per-file SFTP for
+/// large trees. Entries are counted from `tar v`'s listing on stderr.
+///
+/// The archive is written to a `.part` file and renamed once `tar` exits;
+/// on failure or cancellation (under `transfer_id`) the partial is removed.
+pub async fn backup_directory(
+    session: &Arc<SshSession>,
+    transfer_id: &str,
+    remote_dir: &str,
+    local_path: &Path,
+    sink: EventSink,
+) -> AppResult<BackupSummary> {
+    let start = std::time::Instant::now();
+    let part = sftp_ops::part_path(local_path);
+    let work = stream_tar(session, transfer_id, remote_dir, &part, &sink);
+    let work = session.ops.until_closed(work);
+    let result = session.ops.run_transfer(transfer_id, work).await;
+    let (bytes, entries, warnings) = match result {
+        Ok(streamed) => streamed,
+        Err(e) => {
+            log::info!("[TRANSFER] backup of \"{}\" failed: {}", remote_dir, e);
+            tokio::fs::remove_file(&part).await.ok();
+            return Err(e);
+        }
+    };
+    tokio::fs::rename(&part, local_path).await?;
+
+    log::info!(
+        "[PERF] backup_directory \"{}\" — {:.2}ms | bytes: {} | entries: {} | warnings: {}",
+        remote_dir,
+        start.elapsed().as_secs_f64() * 1000.0,
+        bytes,
+        entries,
+        warnings.len(),
+    );
+    Ok(BackupSummary {
+        remote_dir: remote_dir.to_string(),
+        local_path: local_path.to_string_lossy().to_string(),
+        bytes,
+        entries,
+        warnings,
+    })
+}
+
+/// Run `tar` and copy its stdout into `part`. Returns the bytes written,
+/// the entries listed and `tar`'s own messages.
+async fn stream_tar(
+    session: &Arc<SshSession>,
+    transfer_id: &str,
+    remote_dir: &str,
+    part: &Path,
+    sink: &EventSink,
+) -> AppResult<(u64, usize, Vec<String>)> {
+    use russh::ChannelMsg;
+    use tokio::io::AsyncWriteExt;
+
+    let _op = session.ops.track("backup", remote_dir);
+    let gnu_tar = session
+        .exec("tar --version")
+        .await
+        .is_ok_and(|out| String::from_utf8_lossy(&out.stdout).contains("GNU tar"));
+    let _slot = session.channel_slot().await?;
+    let command = format!("tar -czvf - -C {} .", shell_quote(remote_dir));
+    let mut channel = session.open_exec_channel(&command).await?;
+    let file = tokio::fs::File::create(part)
+        .await
+        .map_err(|e| AppError::Io(format!("Cannot create {}: {e}", part.display())))?;
+    let mut out = tokio::io::BufWriter::new(file);
+
+    let (mut bytes, mut entries) = (0u64, 0usize);
+    let mut warnings = Vec::new();
+    let mut current = String::new();
+    let mut stderr = Vec::new();
+    let mut exit_status = None;
+    let mut last_event = std::time::Instant::now();
+    let mut report = |bytes: u64, entries: usize, current: &str, force: bool| {
+        if !force && last_event.elapsed() < BACKUP_EVENT_INTERVAL {
+            return;
+        }
+        last_event = std::time::Instant::now();
+        sink(TransferEvent::BackupProgress {
+            transfer_id: transfer_id.to_string(),
+            bytes_done: bytes,
+            entries_done: entries,
+            current: current.to_string(),
+        });
+    };
+
+    // tar may go quiet while it reads a large file, but not for longer
+    // than a transfer chunk would.
+    loop {
+        let next = async { Ok(channel.wait().await) };
+        let Some(msg) = session.timed(timeouts::transfer(), "backup", next).await? else {
+            break;
+        };
+        match msg {
+            ChannelMsg::Data { ref data } => {
+                out.write_all(data).await?;
+                bytes += data.len() as u64;
+            }
+            ChannelMsg::ExtendedData { ref data, ext: 1 } => {
+                stderr.extend_from_slice(data);
+                // Complete lines only; a partial one waits for the rest.
+                while let Some(end) = stderr.iter().position(|&b| b == b'\n') {
+                    let line: Vec<u8> = stderr.drain(..=end).collect();
+                    let line = String::from_utf8_lossy(&line).trim_end().to_string();
+                    if line.starts_with("tar: ") {
+                        warnings.push(line);
+                    } else if !line.is_empty() {
+                        entries += 1;
+                        current = line;
+                    }
+                }
+            }
+            ChannelMsg::ExitStatus {
+                exit_status: status,
+            } => exit_status = Some(status),
+            _ => {}
+        }
+        report(bytes, entries, &current, false);
+    }
+    out.flush().await?;
+    report(bytes, entries, &current, true);
+
+    // GNU tar exits 1 when files changed while being read; the archive is
+    // still usable, and the messages say which. bsdtar uses 1 for fatal
+    // errors, so only GNU tar gets that allowance.
+    match exit_status {
+        Some(0) => Ok((bytes, entries, warnings)),
+        Some(1) if gnu_tar => Ok((bytes, entries, warnings)),
+        status => Err(AppError::Ssh(format!(
+            "tar failed ({}): {}",
+            status.map_or("no exit status".into(), |s| format!("exit {s}")),
+            warnings.last().map(String::as_str).unwrap_or("no output"),
+        ))),
+    }
+}
@@ -0,0 +1,270 @@
+use serde::Serialize;
+use std::sync::Arc;
+
+use crate::errors::{AppError, AppResult};
+use crate::raw_sftp;
+use crate::sftp_ops::{self, TreeNode};
+use crate::ssh_manager::{shell_quote, SshSession, Transport, BULK_EXEC_TIMEOUT};
+
+/// Refuse to change trees larger than this in one operation.
+const MAX_TREE_NODES: usize = 200_000;
+
+// ─── Data Structures ───────────────────────────────────────────────────
+
+/// Attributes to apply across a tree. `None` fields are left untouched.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TreeChange {
+    pub file_mode: Option<u32>,
+    pub dir_mode: Option<u32>,
+    pub uid: Option<u32>,
+    pub gid: Option<u32>,
+}
+
+/// A path whose attributes could not be changed.
+#[derive(Debug, Clone, Serialize)]
+pub struct FailedPath {
+    pub path: String,
+    pub error: String,
+}
+
+/// Outcome (or, for a dry run, preview) of a recursive change.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TreeChangeSummary {
+    pub dry_run: bool,
+    pub files: usize,
+    pub dirs: usize,
+    /// Nodes whose attributes differ from the requested ones.
+    pub to_change: usize,
+    pub changed: usize,
+    pub failed: Vec<FailedPath>,
+}
+
+/// Outcome of a recursive delete.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DeleteSummary {
+    pub files_deleted: usize,
+    pub dirs_deleted: usize,
+    pub failed: Vec<FailedPath>,
+}
+
+// ─── Recursive Change ──────────────────────────────────────────────────
+
+impl TreeChange {
+    fn mode_for(&self, node: &TreeNode) -> Option<u32> {
+        if node.is_dir {
+            self.dir_mode
+        } else {
+            self.file_mode
+        }
+    }
+
+    fn needs_change(&self, node: &TreeNode) -> bool {
+        self.mode_for(node).is_some_and(|m| m & 0o7777 != node.mode)
+            || self.uid.is_some_and(|u| node.uid != Some(u))
+            || self.gid.is_some_and(|g| node.gid != Some(g))
+    }
+}
+
+/// Apply `change` to `root` and everything below it (symlinks are not
+/// followed). With `dry_run` only the counts are computed.
+/// `on_progress` receives `(done, total)` after each changed node.
+pub async fn apply_recursive<F: Fn(usize, usize)>(
+    session: &Arc<SshSession>,
+    root: &str,
+    change: TreeChange,
+    dry_run: bool,
+    on_progress: F,
+) -> AppResult<TreeChangeSummary> {
+    let start = std::time::Instant::now();
+    let (nodes, truncated) = sftp_ops::walk_tree(session, root, MAX_TREE_NODES).await?;
+    if truncated {
+        return Err(AppError::Other(format!(
+            "Tree has more than {MAX_TREE_NODES} entries — refusing to change it in one go"
+        )));
+    }
+
+    let targets: Vec<&TreeNode> = nodes.iter().filter(|n| change.needs_change(n)).collect();
+    let mut summary = TreeChangeSummary {
+        dry_run,
+        files: nodes.iter().filter(|n| !n.is_dir).count(),
+        dirs: nodes.iter().filter(|n| n.is_dir).count(),
+        to_change: targets.len(),
+        ..Default::default()
+    };
+    if dry_run || targets.is_empty() {
+        return Ok(summary);
+    }
+
+    let total = targets.len();
+    match session.transport().await? {
+        Transport::Sftp(sftp) => {
+            for (i, node) in targets.into_iter().enumerate() {
+                let attrs = russh_sftp::protocol::FileAttributes {
+                    permissions: change.mode_for(node).map(|m| m & 0o7777),
+                    uid: change.uid,
+                    gid: change.gid,
+                    ..Default::default()
+                };
+                match sftp.set_metadata(&node.path, attrs).await {
+                    Ok(()) => summary.changed += 1,
+                    Err(e) => summary.failed.push(FailedPath {
+                        path: node.path.clone(),
+                        error: format!("Failed to set attributes: {e}"),
+                    }),
+                }
+                on_progress(i + 1, total);
+            }
+        }
+        Transport::Scp => {
+            // One exec per class of change; per-node failures show up on stderr only.
+            let quoted = shell_quote(root);
+            let mut commands = Vec::new();
+            if let Some(m) = change.dir_mode {
+                commands.push(format!("find {quoted} -type d -exec chmod {m:o} {{}} +"));
+            }
+            if let Some(m) = change.file_mode {
+                commands.push(format!("find {quoted} -type f -exec chmod {m:o} {{}} +"));
+            }
+            if change.uid.is_some() || change.gid.is_some() {
+                let owner = format!(
+                    "{}:{}",
+                    change.uid.map(|u| u.to_string()).unwrap_or_default(),
+                    change.gid.map(|g| g.to_string()).unwrap_or_default(),
+                );
+                commands.push(format!("chown -R -h {owner} -- {quoted}"));
+            }
+            let output = session
+                .exec_within(&commands.join("; "), BULK_EXEC_TIMEOUT)
+                .await?;
+            if output.exit_status.unwrap_or(0) == 0 {
+                summary.changed = total;
+            } else {
+                summary.failed.push(FailedPath {
+                    path: root.to_string(),
+                    error: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+                });
+            }
+            on_progress(total, total);
+        }
+    }
+
+    log::info!(
+        "[PERF] apply_recursive \"{}\" — {:.2}ms | changed: {} | failed: {}",
+        root,
+        start.elapsed().as_secs_f64() * 1000.0,
+        summary.changed,
+        summary.failed.len(),
+    );
+    Ok(summary)
+}
+
+// ─── Recursive Delete ──────────────────────────────────────────────────
+
+/// Delete `root` and everything below it: files and symlinks (which are
+/// not followed) first, then directories deepest first. A failure is
+/// recorded for its path and the rest carry on; the directories above it
+/// then fail as non-empty.
+///
+/// An opaque `root` (see `raw_sftp`) cannot be walked; a file is removed
+/// as is and a directory with `rm -rf` over exec.
+pub async fn delete_recursive(session: &Arc<SshSession>, root: &str) -> AppResult<DeleteSummary> {
+    let start = std::time::Instant::now();
+    if root.trim_end_matches('/').is_empty() {
+        return Err(AppError::Other(
+            "Refusing to delete the root directory".into(),
+        ));
+    }
+    if raw_sftp::is_raw(root) {
+        return delete_raw(session, root).await;
+    }
+    let (nodes, truncated) = sftp_ops::walk_tree(session, root, MAX_TREE_NODES).await?;
+    if truncated {
+        return Err(AppError::Other(format!(
+            "Tree has more than {MAX_TREE_NODES} entries — refusing to delete it in one go"
+        )));
+    }
+    let _op = session.ops.track("delete_recursive", root);
+
+    let (mut dirs, files): (Vec<&TreeNode>, Vec<&TreeNode>) = nodes.iter().partition(|n| n.is_dir);
+    dirs.sort_by_key(|d| std::cmp::Reverse(d.path.matches('/').count()));
+    let mut summary = DeleteSummary::default();
+
+    match session.transport().await? {
+        Transport::Sftp(sftp) => {
+            for node in files {
+                match sftp.remove_file(&node.path).await {
+                    Ok(()) => summary.files_deleted += 1,
+                    Err(e) => summary.failed.push(FailedPath {
+                        path: node.path.clone(),
+                        error: format!("Failed to delete file: {e}"),
+                    }),
+                }
+            }
+            for node in dirs {
+                match sftp.remove_dir(&node.path).await {
+                    Ok(()) => summary.dirs_deleted += 1,
+                    Err(e) => summary.failed.push(FailedPath {
+                        path: node.path.clone(),
+                        error: format!("Failed to delete directory: {e}"),
+                    }),
+                }
+            }
+        }
+        Transport::Scp => {
+            // One exec; per-path failures show up on stderr only.
+            let output = session
+                .exec_within(
+                    &format!("rm -rf -- {}", shell_quote(root)),
+                    BULK_EXEC_TIMEOUT,
+                )
+                .await?;
+            if output.exit_status.unwrap_or(0) == 0 {
+                summary.files_deleted = files.len();
+                summary.dirs_deleted = dirs.len();
+            } else {
+                summary.failed.push(FailedPath {
+                    path: root.to_string(),
+                    error: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+                });
+            }
+        }
+    }
+
+    log::info!(
+        "[PERF] delete_recursive \"{}\" — {:.2}ms | files: {} | dirs: {} | failed: {}",
+        root,
+        start.elapsed().as_secs_f64() * 1000.0,
+        summary.files_deleted,
+        summary.dirs_deleted,
+        summary.failed.len(),
+    );
+    Ok(summary)
+}
+
+/// [`delete_recursive`] for an opaque `root`.
+async fn delete_raw(session: &Arc<SshSession>, root: &str) -> AppResult<DeleteSummary> {
+    let _op = session.ops.track("delete_recursive", root);
+    let bytes = raw_sftp::path_bytes(root);
+    let mut summary = DeleteSummary::default();
+    let is_dir = raw_sftp::stat(session, &bytes, true).await?.is_dir();
+    if !is_dir {
+        raw_sftp::remove(session, &bytes, false).await?;
+        summary.files_deleted = 1;
+        return Ok(summary);
+    }
+    let output = session
+        .exec_within(
+            &format!("rm -rf -- {}", raw_sftp::shell_word(root)?),
+            BULK_EXEC_TIMEOUT,
+        )
+        .await?;
+    if output.exit_status.unwrap_or(0) == 0 {
+        summary.dirs_deleted = 1;
+    } else {
+        summary.failed.push(FailedPath {
+            path: raw_sftp::lossy_path(root),
+            error: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        });
+    }
+    Ok(summary)
+}
@@ -0,0 +1,139 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::errors::AppResult;
+use crate::file_types::{self, FileCategory};
+use crate::sftp_ops::{self, WalkControl, WalkErrorPolicy, WalkOptions};
+use crate::ssh_manager::SshSession;
+
+/// Stop counting after this many entries so huge trees stay responsive.
+const MAX_STATS_ENTRIES: usize = 200_000;
+
+/// Extensions listed per category in the breakdown; the rest are summed.
+const MAX_EXTENSIONS: usize = 50;
+
+// ─── Data Structures ───────────────────────────────────────────────────
+
+/// File count and total size for one extension or category.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TypeTotals {
+    pub files: u64,
+    pub bytes: u64,
+}
+
+/// Totals for one extension (lower-cased, `""` for none).
+#[derive(Debug, Clone, Serialize)]
+pub struct ExtensionStats {
+    pub extension: String,
+    #[serde(flatten)]
+    pub totals: TypeTotals,
+}
+
+/// Totals for a category, with its largest extensions.
+#[derive(Debug, Clone, Serialize)]
+pub struct CategoryStats {
+    pub category: FileCategory,
+    #[serde(flatten)]
+    pub totals: TypeTotals,
+    /// Largest first, at most `MAX_EXTENSIONS`.
+    pub extensions: Vec<ExtensionStats>,
+}
+
+/// Storage breakdown of a directory tree (safe to send to JS).
+#[derive(Debug, Clone, Serialize)]
+pub struct TypeStats {
+    pub root: String,
+    pub total: TypeTotals,
+    pub dirs: u64,
+    /// Largest category first; empty categories are left out.
+    pub categories: Vec<CategoryStats>,
+    /// Directories that could not be listed.
+    pub unreadable: usize,
+    /// True if counting stopped at the entry limit.
+    pub truncated: bool,
+}
+
+// ─── Classification ────────────────────────────────────────────────────
+
+fn extension_of(name: &str) -> String {
+    match name.rsplit_once('.') {
+        // A leading dot marks a hidden file, not an extension.
+        Some((stem, ext)) if !stem.is_empty() => ext.to_ascii_lowercase(),
+        _ => String::new(),
+    }
+}
+
+fn category_of(ext: &str) -> FileCategory {
+    file_types::classify(&format!("x.{ext}"))
+}
+
+// ─── Stats ─────────────────────────────────────────────────────────────
+
+/// Count files and bytes below `root`, grouped by category and extension.
+pub async fn type_stats(session: &Arc<SshSession>, root: &str) -> AppResult<TypeStats> {
+    let start = std::time::Instant::now();
+    let opts = WalkOptions {
+        on_error: WalkErrorPolicy::Skip,
+        ..Default::default()
+    };
+
+    let mut by_ext: HashMap<String, TypeTotals> = HashMap::new();
+    let mut total = TypeTotals::default();
+    let mut dirs = 0u64;
+    let mut seen = 0usize;
+    let walk = sftp_ops::walk(session, root, &opts, |item| {
+        seen += 1;
+        if seen > MAX_STATS_ENTRIES {
+            return WalkControl::Stop;
+        }
+        if item.entry.is_dir {
+            dirs += 1;
+            return WalkControl::Continue;
+        }
+        let totals = by_ext.entry(extension_of(&item.entry.name)).or_default();
+        totals.files += 1;
+        totals.bytes += item.entry.size;
+        total.files += 1;
+        total.bytes += item.entry.size;
+        WalkControl::Continue
+    })
+    .await?;
+
+    let mut categories: HashMap<FileCategory, CategoryStats> = HashMap::new();
+    for (extension, totals) in by_ext {
+        let category = category_of(&extension);
+        let stats = categories.entry(category).or_insert_with(|| CategoryStats {
+            category,
+            totals: TypeTotals::default(),
+            extensions: Vec::new(),
+        });
+        stats.totals.files += totals.files;
+        stats.totals.bytes += totals.bytes;
+        stats.extensions.push(ExtensionStats { extension, totals });
+    }
+    let mut categories: Vec<CategoryStats> = categories.into_values().collect();
+    for c in &mut categories {
+        c.extensions
+            .sort_by(|a, b| b.totals.bytes.cmp(&a.totals.bytes));
+        c.extensions.truncate(MAX_EXTENSIONS);
+    }
+    categories.sort_by(|a, b| b.totals.bytes.cmp(&a.totals.bytes));
+
+    log::info!(
+        "[PERF] type_stats \"{}\" — {:.2}ms | files: {} | bytes: {} | truncated: {}",
+        root,
+        start.elapsed().as_secs_f64() * 1000.0,
+        total.files,
+        total.bytes,
+        walk.stopped,
+    );
+    Ok(TypeStats {
+        root: root.to_string(),
+        total,
+        dirs,
+        categories,
+        unreadable: walk.errors.len(),
+        truncated: walk.stopped,
+    })
+}
@@ -0,0 +1,213 @@
+//! Storage backends for the key vault.
+//!
+//! The `KeyStore` keeps its lock state and key logic; a backend only loads
+//! and saves the whole key index. `JsonVault` is the original file format;
+//! `StrongholdVault` keeps the index in an IOTA Stronghold snapshot, for
+//! users who want a memory-hardened, password-encrypted vault.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::errors::{AppError, AppResult};
+use crate::key_store::KeyRecord;
+
+/// Where each backend keeps its files, under the app data directory.
+pub struct VaultPaths {
+    pub json: PathBuf,
+    pub snapshot: PathBuf,
+    pub salt: PathBuf,
+    pub unlock_salt: PathBuf,
+}
+
+impl VaultPaths {
+    pub fn new(app_dir: &Path) -> Self {
+        Self {
+            json: app_dir.join("ssh_keys.json"),
+            snapshot: app_dir.join("ssh_keys.stronghold"),
+            salt: app_dir.join("ssh_keys.salt"),
+            unlock_salt: app_dir.join("vault_unlock.salt"),
+        }
+    }
+}
+
+/// Stronghold client and store key holding the serialized index.
+const STRONGHOLD_CLIENT: &[u8] = b"oxidock-vault";
+const STRONGHOLD_INDEX_KEY: &[u8] = b"index";
+
+/// Which backend the vault uses, as stored in settings.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VaultBackendKind {
+    #[default]
+    Json,
+    Stronghold,
+}
+
+/// Loads and saves the key index.
+pub trait VaultBackend: Send + Sync {
+    fn kind(&self) -> VaultBackendKind;
+    fn load(&self) -> AppResult<HashMap<String, KeyRecord>>;
+    fn save(&self, index: &HashMap<String, KeyRecord>) -> AppResult<()>;
+}
+
+/// Argon2 verifier for the passphrase that unlocks a JSON vault, hex
+/// encoded. Only the verifier is kept in settings, never the passphrase.
+pub fn passphrase_verifier(salt_path: &Path, passphrase: &str) -> String {
+    tauri_plugin_stronghold::kdf::KeyDerivation::argon2(passphrase, salt_path)
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+/// Whether `passphrase` matches `verifier`, compared in constant time.
+pub fn verify_passphrase(salt_path: &Path, passphrase: &str, verifier: &str) -> bool {
+    let computed = passphrase_verifier(salt_path, passphrase);
+    computed.len() == verifier.len()
+        && computed
+            .bytes()
+            .zip(verifier.bytes())
+            .fold(0u8, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
+// ─── JSON ──────────────────────────────────────────────────────────────
+
+/// Index stored as a JSON file on disk.
+pub struct JsonVault {
+    path: PathBuf,
+}
+
+impl JsonVault {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    /// Remove the file, e.g. after migrating its keys elsewhere.
+    pub fn remove(&self) -> AppResult<()> {
+        match std::fs::remove_file(&self.path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(AppError::KeyStore(format!(
+                "Failed to remove old vault: {e}"
+            ))),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl VaultBackend for JsonVault {
+    fn kind(&self) -> VaultBackendKind {
+        VaultBackendKind::Json
+    }
+
+    fn load(&self) -> AppResult<HashMap<String, KeyRecord>> {
+        if !self.path.exists() {
+            return Ok(HashMap::new());
+        }
+        let data = std::fs::read_to_string(&self.path)
+            .map_err(|e| AppError::KeyStore(format!("Failed to read vault: {e}")))?;
+        if data.trim().is_empty() {
+            return Ok(HashMap::new());
+        }
+        serde_json::from_str(&data)
+            .map_err(|e| AppError::KeyStore(format!("Failed to parse vault: {e}")))
+    }
+
+    fn save(&self, index: &HashMap<String, KeyRecord>) -> AppResult<()> {
+        let data = serde_json::to_string_pretty(index)
+            .map_err(|e| AppError::KeyStore(format!("Failed to serialize vault: {e}")))?;
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent).ok();
+        }
+        std::fs::write(&self.path, data)
+            .map_err(|e| AppError::KeyStore(format!("Failed to write vault: {e}")))
+    }
+}
+
+// ─── Stronghold ────────────────────────────────────────────────────────
+
+/// Index stored in a Stronghold snapshot encrypted under a password-derived key.
+pub struct StrongholdVault {
+    stronghold: tauri_plugin_stronghold::stronghold::Stronghold,
+}
+
+fn stronghold_err(what: &str, e: impl std::fmt::Display) -> AppError {
+    AppError::KeyStore(format!("Stronghold: failed to {what}: {e}"))
+}
+
+impl StrongholdVault {
+    /// Open (or create) the snapshot at `snapshot_path`. The key is derived
+    /// from `password` with Argon2 using the salt kept at `salt_path`.
+    pub fn open(snapshot_path: &Path, salt_path: &Path, password: &str) -> AppResult<Self> {
+        let key = tauri_plugin_stronghold::kdf::KeyDerivation::argon2(password, salt_path);
+        let stronghold = tauri_plugin_stronghold::stronghold::Stronghold::new(snapshot_path, key)
+            .map_err(|e| stronghold_err("open snapshot", e))?;
+        let vault = Self { stronghold };
+        // Fail now on a wrong password rather than on first use.
+        vault.load()?;
+        log::info!(
+            "[KEYS] opened Stronghold vault at {}",
+            snapshot_path.display()
+        );
+        Ok(vault)
+    }
+
+    fn client(&self) -> AppResult<iota_stronghold::Client> {
+        self.stronghold
+            .load_client(STRONGHOLD_CLIENT)
+            .or_else(|_| self.stronghold.create_client(STRONGHOLD_CLIENT))
+            .map_err(|e| stronghold_err("open client", e))
+    }
+}
+
+impl VaultBackend for StrongholdVault {
+    fn kind(&self) -> VaultBackendKind {
+        VaultBackendKind::Stronghold
+    }
+
+    fn load(&self) -> AppResult<HashMap<String, KeyRecord>> {
+        let data = self
+            .client()?
+            .store()
+            .get(STRONGHOLD_INDEX_KEY)
+            .map_err(|e| stronghold_err("read index", e))?;
+        match data {
+            Some(data) => serde_json::from_slice(&data)
+                .map_err(|e| AppError::KeyStore(format!("Failed to parse vault: {e}"))),
+            None => Ok(HashMap::new()),
+        }
+    }
+
+    fn save(&self, index: &HashMap<String, KeyRecord>) -> AppResult<()> {
+        let data = serde_json::to_vec(index)
+            .map_err(|e| AppError::KeyStore(format!("Failed to serialize vault: {e}")))?;
+        self.client()?
+            .store()
+            .insert(STRONGHOLD_INDEX_KEY.to_vec(), data, None)
+            .map_err(|e| stronghold_err("write index", e))?;
+        self.stronghold
+            .write_client(STRONGHOLD_CLIENT)
+            .map_err(|e| stronghold_err("write client", e))?;
+        self.stronghold
+            .save()
+            .map_err(|e| stronghold_err("save snapshot", e))
+    }
+}
+
+// ─── Locked ────────────────────────────────────────────────────────────
+
+/// Placeholder for a Stronghold vault whose password has not been given yet.
+pub struct UnopenedVault;
+
+impl VaultBackend for UnopenedVault {
+    fn kind(&self) -> VaultBackendKind {
+        VaultBackendKind::Stronghold
+    }
+
+    fn load(&self) -> AppResult<HashMap<String, KeyRecord>> {
+        Err(AppError::VaultLocked)
+    }
+
+    fn save(&self, _index: &HashMap<String, KeyRecord>) -> AppResult<()> {
+        Err(AppError::VaultLocked)
+    }
+}
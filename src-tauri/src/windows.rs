@@ -0,0 +1,116 @@
+//! Multi-window support. Every window shares the app-wide managed state
+//! (sessions, keys, settings); this registry only records which session and
+//! folder each window is showing, so windows can find each other.
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Mutex;
+use tauri::Emitter;
+
+use crate::errors::{AppError, AppResult};
+
+/// Labels of secondary windows start with this (see `capabilities/default.json`).
+pub const WINDOW_LABEL_PREFIX: &str = "window-";
+
+// ─── Data Structures ───────────────────────────────────────────────────
+
+/// What one window is showing (safe to send to JS).
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct WindowView {
+    pub label: String,
+    pub session_id: Option<String>,
+    pub path: Option<String>,
+}
+
+/// Views of all open windows, by window label.
+#[derive(Default)]
+pub struct WindowRegistry {
+    next_id: AtomicU32,
+    views: Mutex<HashMap<String, WindowView>>,
+}
+
+// ─── Registry ──────────────────────────────────────────────────────────
+
+impl WindowRegistry {
+    /// All known views, sorted by label.
+    pub fn list(&self) -> Vec<WindowView> {
+        let mut views: Vec<WindowView> = self.views.lock().unwrap().values().cloned().collect();
+        views.sort_by(|a, b| a.label.cmp(&b.label));
+        views
+    }
+
+    pub fn get(&self, label: &str) -> WindowView {
+        self.views
+            .lock()
+            .unwrap()
+            .get(label)
+            .cloned()
+            .unwrap_or_else(|| WindowView {
+                label: label.to_string(),
+                ..Default::default()
+            })
+    }
+
+    /// Record what `label` is showing and tell every window.
+    pub fn set(&self, app: &tauri::AppHandle, view: WindowView) {
+        self.views.lock().unwrap().insert(view.label.clone(), view);
+        self.broadcast(app);
+    }
+
+    /// Forget a closed window and tell the rest.
+    pub fn remove(&self, app: &tauri::AppHandle, label: &str) {
+        if self.views.lock().unwrap().remove(label).is_some() {
+            self.broadcast(app);
+        }
+    }
+
+    fn broadcast(&self, app: &tauri::AppHandle) {
+        if let Err(e) = app.emit("windows-changed", self.list()) {
+            log::warn!("[CMD] failed to emit windows-changed: {}", e);
+        }
+    }
+
+    /// Open a new app window, optionally pre-seeded with a session and
+    /// folder that the window reads back on startup. Returns its label.
+    pub fn open(
+        &self,
+        app: &tauri::AppHandle,
+        session_id: Option<String>,
+        path: Option<String>,
+    ) -> AppResult<String> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let label = format!("{WINDOW_LABEL_PREFIX}{id}");
+        self.set(
+            app,
+            WindowView {
+                label: label.clone(),
+                session_id,
+                path,
+            },
+        );
+
+        #[cfg(desktop)]
+        let built = tauri::WebviewWindowBuilder::new(
+            app,
+            &label,
+            tauri::WebviewUrl::App("index.html".into()),
+        )
+        .title("OxiDock")
+        .inner_size(800.0, 600.0)
+        .build()
+        .map(|_| ())
+        .map_err(|e| AppError::Other(format!("Failed to open window: {e}")));
+        #[cfg(mobile)]
+        let built: AppResult<()> = Err(AppError::Other(
+            "Multiple windows are not supported on mobile".into(),
+        ));
+
+        if let Err(e) = built {
+            self.remove(app, &label);
+            return Err(e);
+        }
+        log::info!("[CMD] opened window {}", label);
+        Ok(label)
+    }
+}
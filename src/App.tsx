@@ -41,6 +41,9 @@ import FileBrowser, { type FileBrowserBackHandle } from "./components/FileBrowse
 import ViewOptionsPopover from "./components/ViewOptionsPopover";
 import FolderOptionsPopover from "./components/FolderOptionsPopover";
 import { useAppTheme } from "./theme/ThemeContext";
+import { startNetworkReporting } from "./lib/network";
+import { connectInteractive } from "./lib/interactiveAuth";
+import { errorText } from "./lib/errors";
 import {
   getDefaultServer,
   loadViewSettings,
@@ -101,6 +104,9 @@ function App() {
 
   const { themeName, setThemeName, availableThemes } = useAppTheme();
 
+  // Keep the backend's cellular transfer policies in step with the network
+  useEffect(() => startNetworkReporting(), []);
+
   // Android back gesture: navigate in-app, or exit on double-back at root
   useEffect(() => {
     let unlisten: Awaited<ReturnType<typeof registerBackEvent>> | undefined;
@@ -156,14 +162,21 @@ function App() {
     if (!server) return;
 
     setAutoConnecting(true);
-    invoke<string>("ssh_connect", {
-      host: server.host,
-      port: server.port,
-      user: server.username,
-      keyName: server.authMethod === "key" ? server.keyName : null,
-      passphrase: null,
-      password: server.authMethod === "password" ? server.password : null,
-    })
+    const connecting =
+      server.authMethod === "interactive"
+        ? connectInteractive(server)
+        : invoke<string>("ssh_connect", {
+            host: server.host,
+            port: server.port,
+            user: server.username,
+            keyName: server.authMethod === "key" ? server.keyName : null,
+            passphrase: null,
+            password: server.authMethod === "password" ? server.password : null,
+            startupCommands: server.startupCommands ?? null,
+            hostKeyPin: server.hostKeyPin ?? null,
+            hostKeyStrict: server.hostKeyStrict ?? false,
+          });
+    connecting
       .then((sessionId) => {
         setActiveSession({
           sessionId,
@@ -172,7 +185,7 @@ function App() {
         });
       })
       .catch((e) => {
-        setAutoConnectError(String(e));
+        setAutoConnectError(errorText(e));
       })
       .finally(() => {
         setAutoConnecting(false);
@@ -1,5 +1,7 @@
 import { useState, useEffect, useCallback, useMemo, useRef, useImperativeHandle } from "react";
 import { invoke } from "@tauri-apps/api/core";
+import { invokeConfirmingLargeDownload } from "../lib/network";
+import { invokePreview } from "../lib/preview";
 import {
   Box,
   Breadcrumbs,
@@ -30,13 +32,25 @@ import InsertDriveFileIcon from "@mui/icons-material/InsertDriveFile";
 import FolderOpenIcon from "@mui/icons-material/FolderOpen";
 import AddIcon from "@mui/icons-material/Add";
 import CreateNewFolderIcon from "@mui/icons-material/CreateNewFolder";
+import NoteAddIcon from "@mui/icons-material/NoteAdd";
 import FileUploadIcon from "@mui/icons-material/FileUpload";
 import OpenInNewIcon from "@mui/icons-material/OpenInNew";
 import DeleteIcon from "@mui/icons-material/Delete";
+import ContentCopyIcon from "@mui/icons-material/ContentCopy";
+import DriveFileRenameOutlineIcon from "@mui/icons-material/DriveFileRenameOutline";
 import PlayArrowIcon from "@mui/icons-material/PlayArrow";
-
-import type { FileEntry, FilePreview as FilePreviewType, ViewSettings, FolderSettings } from "../lib/types";
-import { getDirCached, getDirCachedCount, setDirCached, invalidateDirCache, prefetchChildren } from "../lib/dirCache";
+import OfflinePinIcon from "@mui/icons-material/OfflinePin";
+
+import type {
+  DeleteSummary,
+  FileEntry,
+  FilePreview as FilePreviewType,
+  ViewSettings,
+  FolderSettings,
+  OfflinePin,
+  QueuedDownload,
+} from "../lib/types";
+import { getDirCached, getDirCachedCount, setDirCached, invalidateDirCache, prefetchChildren, nextViewGeneration } from "../lib/dirCache";
 import { saveLastFolder } from "../lib/storage";
 import FilePreview from "./FilePreview";
 import ImageThumbnail from "./ImageThumbnail";
@@ -158,8 +172,12 @@ function FileBrowserInner({
   const [fabOpen, setFabOpen] = useState(false);
   const [createFolderDialogOpen, setCreateFolderDialogOpen] = useState(false);
   const [newFolderName, setNewFolderName] = useState("");
+  const [createKind, setCreateKind] = useState<"folder" | "file" | "rename">("folder");
+  const [renameTarget, setRenameTarget] = useState<FileEntry | null>(null);
   const [isCreatingFolder, setIsCreatingFolder] = useState(false);
   const [isUploading, setIsUploading] = useState(false);
+  const [interrupted, setInterrupted] = useState<QueuedDownload[]>([]);
+  const [isResuming, setIsResuming] = useState(false);
 
   const longPressTimer = useRef<ReturnType<typeof setTimeout> | null>(null);
   const longPressTriggered = useRef(false);
@@ -186,6 +204,14 @@ function FileBrowserInner({
   const handleCreateFolderStart = () => {
     setFabOpen(false);
     setNewFolderName("");
+    setCreateKind("folder");
+    setCreateFolderDialogOpen(true);
+  };
+
+  const handleCreateFileStart = () => {
+    setFabOpen(false);
+    setNewFolderName("");
+    setCreateKind("file");
     setCreateFolderDialogOpen(true);
   };
 
@@ -194,24 +220,49 @@ function FileBrowserInner({
     if (!newFolderName.trim()) return;
 
     setIsCreatingFolder(true);
+    if (createKind === "rename") {
+      await handleRenameSubmit();
+      return;
+    }
+    const kind = createKind === "file" ? "File" : "Folder";
     try {
-      const newDirPath = path === "/" ? `/${newFolderName}` : `${path}/${newFolderName}`;
-      await invoke("sftp_create_dir", {
-        sessionId,
-        path: newDirPath,
-      });
-      setSnackbar("Folder created successfully");
+      const newPath = path === "/" ? `/${newFolderName}` : `${path}/${newFolderName}`;
+      if (createKind === "file") {
+        await invoke("sftp_create_file", { sessionId, path: newPath, initialContent: null });
+      } else {
+        await invoke("sftp_create_dir", { sessionId, path: newPath });
+      }
+      setSnackbar(`${kind} created successfully`);
       setCreateFolderDialogOpen(false);
       invalidateDirCache(path);
       loadDir(path);
     } catch (err) {
-      setError(`Failed to create folder: ${err}`);
+      setError(`Failed to create ${kind.toLowerCase()}: ${err}`);
       setCreateFolderDialogOpen(false);
     } finally {
       setIsCreatingFolder(false);
     }
   };
 
+  // A bare name renames in place; an absolute path moves the entry.
+  const handleRenameSubmit = async () => {
+    const name = newFolderName.trim();
+    try {
+      if (!renameTarget) return;
+      const to = name.startsWith("/") ? name : path === "/" ? `/${name}` : `${path}/${name}`;
+      await invoke("sftp_rename", { sessionId, from: renameTarget.path, to });
+      setSnackbar(name.includes("/") ? `Moved to ${to}` : `Renamed to ${name}`);
+      invalidateDirCache(path);
+      loadDir(path);
+    } catch (err) {
+      setError(`Failed to rename: ${err}`);
+    } finally {
+      setCreateFolderDialogOpen(false);
+      setRenameTarget(null);
+      setIsCreatingFolder(false);
+    }
+  };
+
   const handleUploadFile = () => {
     setFabOpen(false);
     fileInputRef.current?.click();
@@ -254,6 +305,7 @@ function FileBrowserInner({
         saveLastFolder(dirPath);
       }
 
+      const token = nextViewGeneration();
       const cached = getDirCached(dirPath);
       if (cached) {
         setEntries(cached);
@@ -261,7 +313,7 @@ function FileBrowserInner({
         setError(null);
         currentPathRef.current = dirPath;
 
-        invoke<FileEntry[]>("sftp_list_dir", { sessionId, path: dirPath })
+        invoke<FileEntry[]>("sftp_list_dir", { sessionId, path: dirPath, ...token })
           .then((result) => {
             setDirCached(dirPath, result);
             if (currentPathRef.current === dirPath) {
@@ -279,6 +331,7 @@ function FileBrowserInner({
         const result = await invoke<FileEntry[]>("sftp_list_dir", {
           sessionId,
           path: dirPath,
+          ...token,
         });
         setDirCached(dirPath, result);
         setEntries(result);
@@ -298,6 +351,42 @@ function FileBrowserInner({
     loadDir(path);
   }, []);
 
+  // ─── Interrupted downloads ───────────────────────────────────────────────
+  useEffect(() => {
+    invoke<QueuedDownload[]>("download_queue_list", { sessionId })
+      .then(setInterrupted)
+      .catch((err) => console.warn("Failed to list interrupted downloads:", err));
+    // Refresh offline pins that changed while we were away.
+    invoke("pins_sync", { sessionId }).catch((err) =>
+      console.warn("Failed to sync offline pins:", err),
+    );
+  }, [sessionId]);
+
+  const handleResumeDownloads = async () => {
+    setIsResuming(true);
+    let resumed = 0;
+    for (const item of interrupted) {
+      try {
+        await invoke<string>("download_queue_resume", { sessionId, id: item.id });
+        resumed++;
+      } catch (err) {
+        setSnackbar(`Resume failed for ${item.remote_path}: ${err}`);
+      }
+    }
+    setIsResuming(false);
+    setInterrupted([]);
+    if (resumed > 0) {
+      setSnackbar(resumed === 1 ? "Download resumed and finished" : `${resumed} downloads finished`);
+    }
+  };
+
+  const handleDiscardDownloads = async () => {
+    for (const item of interrupted) {
+      await invoke("download_queue_discard", { id: item.id }).catch(() => {});
+    }
+    setInterrupted([]);
+  };
+
   // ─── Entry click ─────────────────────────────────────────────────────────
   const handleEntryClick = async (entry: FileEntry) => {
     if (entry.is_dir) {
@@ -308,11 +397,20 @@ function FileBrowserInner({
       setImageViewer({ images: imageEntries, index: idx >= 0 ? idx : 0 });
     } else {
       try {
-        const data = await invoke<FilePreviewType>("sftp_read_file_preview", {
+        const isMarkdown = /\.(md|markdown)$/i.test(entry.name);
+        const mtime = entry.modified
+          ? Math.floor(new Date(entry.modified).getTime() / 1000)
+          : undefined;
+        const args = {
           sessionId,
           path: entry.path,
           maxBytes: 65536,
-        });
+          remoteMtime: mtime,
+          remoteSize: entry.size,
+        };
+        const data = isMarkdown
+          ? await invoke<FilePreviewType>("sftp_preview_markdown", args)
+          : await invokePreview(args);
         setPreview({ data, name: entry.name });
       } catch (e) {
         setError(`Failed to preview: ${e}`);
@@ -372,7 +470,7 @@ function FileBrowserInner({
     setContextMenu(null);
     try {
       setDownloading(true);
-      const savedPath = await invoke<string>("sftp_save_file", {
+      const savedPath = await invokeConfirmingLargeDownload<string>("sftp_save_file", {
         sessionId,
         remotePath: entry.path,
         fileName: entry.name,
@@ -385,6 +483,42 @@ function FileBrowserInner({
     }
   };
 
+  const handleDuplicateFromMenu = async () => {
+    if (!contextMenu) return;
+    const entry = contextMenu.entry;
+    setContextMenu(null);
+    try {
+      const newPath = await invoke<string>("sftp_duplicate", { sessionId, path: entry.path });
+      setSnackbar(`Duplicated as ${newPath.split("/").pop()}`);
+      invalidateDirCache(path);
+      loadDir(path);
+    } catch (err) {
+      setSnackbar(`Duplicate failed: ${err}`);
+    }
+  };
+
+  const handlePinOfflineFromMenu = async () => {
+    if (!contextMenu) return;
+    const entry = contextMenu.entry;
+    setContextMenu(null);
+    try {
+      await invoke<OfflinePin>("pin_offline", { sessionId, path: entry.path });
+      setSnackbar(`${entry.name} is available offline`);
+    } catch (err) {
+      setSnackbar(`Keep offline failed: ${err}`);
+    }
+  };
+
+  const handleRenameFromMenu = () => {
+    if (!contextMenu) return;
+    const entry = contextMenu.entry;
+    setContextMenu(null);
+    setRenameTarget(entry);
+    setNewFolderName(entry.name);
+    setCreateKind("rename");
+    setCreateFolderDialogOpen(true);
+  };
+
   const handleDeleteFromMenu = () => {
     if (!contextMenu) return;
     setDeleteTarget(contextMenu.entry);
@@ -395,11 +529,27 @@ function FileBrowserInner({
     if (!deleteTarget) return;
     setIsDeleting(true);
     try {
-      await invoke("sftp_delete_file", {
-        sessionId,
-        path: deleteTarget.path,
-      });
-      setSnackbar(`Deleted ${deleteTarget.name}`);
+      if (deleteTarget.is_dir) {
+        const summary = await invoke<DeleteSummary>("sftp_delete_recursive", {
+          sessionId,
+          path: deleteTarget.path,
+        });
+        if (summary.failed.length > 0) {
+          const first = summary.failed[0];
+          setError(
+            `Deleted ${summary.files_deleted} files and ${summary.dirs_deleted} folders; ` +
+              `${summary.failed.length} failed (${first.path}: ${first.error})`,
+          );
+        } else {
+          setSnackbar(`Deleted ${deleteTarget.name}`);
+        }
+      } else {
+        await invoke("sftp_delete_file", {
+          sessionId,
+          path: deleteTarget.path,
+        });
+        setSnackbar(`Deleted ${deleteTarget.name}`);
+      }
       setDeleteTarget(null);
       invalidateDirCache(path);
       loadDir(path);
@@ -484,7 +634,7 @@ function FileBrowserInner({
       );
     }
 
-    if (entry.is_image) {
+    if (entry.is_image || entry.category === "fonts") {
       return (
         <ImageThumbnail
           sessionId={sessionId}
@@ -824,6 +974,20 @@ function FileBrowserInner({
           <DownloadIcon fontSize="small" sx={{ mr: 1.5, color: "success.main" }} />
           {downloading ? "Downloading…" : "Download"}
         </MenuItem>
+        {!contextMenu?.entry.is_dir && (
+          <MenuItem onClick={handlePinOfflineFromMenu}>
+            <OfflinePinIcon fontSize="small" sx={{ mr: 1.5 }} />
+            Keep Offline
+          </MenuItem>
+        )}
+        <MenuItem onClick={handleRenameFromMenu}>
+          <DriveFileRenameOutlineIcon fontSize="small" sx={{ mr: 1.5 }} />
+          Rename / Move
+        </MenuItem>
+        <MenuItem onClick={handleDuplicateFromMenu}>
+          <ContentCopyIcon fontSize="small" sx={{ mr: 1.5 }} />
+          Duplicate
+        </MenuItem>
         <MenuItem onClick={handleDeleteFromMenu} sx={{ color: "error.main" }}>
           <DeleteIcon fontSize="small" sx={{ mr: 1.5 }} />
           Delete
@@ -894,14 +1058,16 @@ function FileBrowserInner({
         fullWidth
         PaperProps={{ sx: { borderRadius: 3 } }}
       >
-        <DialogTitle sx={{ fontWeight: 600 }}>Delete File</DialogTitle>
+        <DialogTitle sx={{ fontWeight: 600 }}>
+          {deleteTarget?.is_dir ? "Delete Folder" : "Delete File"}
+        </DialogTitle>
         <DialogContent>
           <Typography variant="body2" color="text.secondary">
             Are you sure you want to delete{" "}
             <Box component="span" sx={{ fontWeight: 600, color: "text.primary" }}>
               {deleteTarget?.name}
             </Box>
-            ? This action cannot be undone.
+            {deleteTarget?.is_dir && " and everything in it"}? This action cannot be undone.
           </Typography>
         </DialogContent>
         <DialogActions sx={{ p: 2, pt: 0, gap: 1 }}>
@@ -926,6 +1092,61 @@ function FileBrowserInner({
         </DialogActions>
       </Dialog>
 
+      {/* Resume Interrupted Downloads Dialog */}
+      <Dialog
+        open={interrupted.length > 0}
+        onClose={() => !isResuming && setInterrupted([])}
+        maxWidth="xs"
+        fullWidth
+        PaperProps={{ sx: { borderRadius: 3 } }}
+      >
+        <DialogTitle sx={{ fontWeight: 600 }}>Resume Downloads</DialogTitle>
+        <DialogContent>
+          <Typography variant="body2" color="text.secondary" sx={{ mb: 1 }}>
+            {interrupted.length === 1
+              ? "A download was interrupted when the app closed."
+              : `${interrupted.length} downloads were interrupted when the app closed.`}
+          </Typography>
+          <List dense disablePadding>
+            {interrupted.map((item) => (
+              <ListItem key={item.id} disableGutters>
+                <ListItemIcon sx={{ minWidth: 32 }}>
+                  {item.kind === "dir" ? (
+                    <FolderIcon fontSize="small" />
+                  ) : (
+                    <InsertDriveFileIcon fontSize="small" />
+                  )}
+                </ListItemIcon>
+                <ListItemText
+                  primary={item.remote_path.split("/").pop() || item.remote_path}
+                  secondary={
+                    item.bytes_total > 0
+                      ? `${formatSize(item.bytes_done)} of ${formatSize(item.bytes_total)}`
+                      : formatSize(item.bytes_done)
+                  }
+                  primaryTypographyProps={{ noWrap: true }}
+                />
+              </ListItem>
+            ))}
+          </List>
+        </DialogContent>
+        <DialogActions sx={{ p: 2, pt: 0, gap: 1 }}>
+          <Button onClick={handleDiscardDownloads} disabled={isResuming} sx={{ borderRadius: 2 }}>
+            Discard
+          </Button>
+          <Button
+            onClick={handleResumeDownloads}
+            variant="contained"
+            disabled={isResuming}
+            disableElevation
+            sx={{ borderRadius: 2 }}
+            startIcon={isResuming ? <CircularProgress size={16} color="inherit" /> : <DownloadIcon />}
+          >
+            Resume
+          </Button>
+        </DialogActions>
+      </Dialog>
+
       {/* FAB Backdrop */}
       {fabOpen && (
         <Box
@@ -977,6 +1198,15 @@ function FileBrowserInner({
             </ListItemButton>
           </Card>
 
+          <Card elevation={4} sx={{ borderRadius: 8, bgcolor: "background.paper", overflow: "hidden" }}>
+            <ListItemButton onClick={handleCreateFileStart} sx={{ py: 1.5, px: 2.5 }}>
+              <ListItemIcon sx={{ minWidth: 40 }}>
+                <NoteAddIcon color="action" />
+              </ListItemIcon>
+              <ListItemText primary="File" primaryTypographyProps={{ fontWeight: 500 }} />
+            </ListItemButton>
+          </Card>
+
           <Card elevation={4} sx={{ borderRadius: 8, bgcolor: "background.paper", overflow: "hidden" }}>
             <ListItemButton onClick={handleUploadFile} sx={{ py: 1.5, px: 2.5 }}>
               <ListItemIcon sx={{ minWidth: 40 }}>
@@ -1015,7 +1245,7 @@ function FileBrowserInner({
         </Box>
       </Box>
 
-      {/* Create Folder Dialog */}
+      {/* Create Folder / File Dialog */}
       {createFolderDialogOpen && (
         <Box
           sx={{
@@ -1036,11 +1266,21 @@ function FileBrowserInner({
             <Box component="form" onSubmit={handleCreateFolderSubmit}>
               <CardContent sx={{ pt: 3, pb: 2 }}>
                 <Typography variant="h6" gutterBottom>
-                  New Folder
+                  {createKind === "rename"
+                    ? "Rename / Move"
+                    : createKind === "file"
+                      ? "New File"
+                      : "New Folder"}
                 </Typography>
                 <input
                   type="text"
-                  placeholder="Folder name"
+                  placeholder={
+                    createKind === "rename"
+                      ? "New name, or /path to move to"
+                      : createKind === "file"
+                        ? "File name"
+                        : "Folder name"
+                  }
                   value={newFolderName}
                   onChange={(e) => setNewFolderName(e.target.value)}
                   autoFocus
@@ -1098,7 +1338,7 @@ function FileBrowserInner({
                   }}
                 >
                   {isCreatingFolder && <CircularProgress size={16} color="inherit" />}
-                  Create
+                  {createKind === "rename" ? "Rename" : "Create"}
                 </Box>
               </Box>
             </Box>
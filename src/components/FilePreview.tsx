@@ -1,5 +1,5 @@
 import { useState } from "react";
-import { invoke } from "@tauri-apps/api/core";
+import { invokeConfirmingLargeDownload } from "../lib/network";
 import {
   AppBar,
   Box,
@@ -53,7 +53,7 @@ export default function FilePreview({
   const handleDownload = async () => {
     try {
       setDownloading(true);
-      const savedPath = await invoke<string>("sftp_save_file", {
+      const savedPath = await invokeConfirmingLargeDownload<string>("sftp_save_file", {
         sessionId,
         remotePath: filePath,
         fileName: filename,
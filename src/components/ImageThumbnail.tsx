@@ -3,6 +3,7 @@ import { invoke } from "@tauri-apps/api/core";
 import { Box, CircularProgress } from "@mui/material";
 import ImageIcon from "@mui/icons-material/Image";
 import type { FileEntry } from "../lib/types";
+import { viewToken } from "../lib/dirCache";
 import {
   getThumbnailCached,
   setThumbnailCached,
@@ -28,9 +29,14 @@ export default function ImageThumbnail({ sessionId, entry, onClick, onLongPress
   const longPressTimer = useRef<ReturnType<typeof setTimeout> | null>(null);
   const longPressTriggered = useRef(false);
 
-  // Derive MIME type from extension
+  // Derive MIME type from extension; font previews are rendered to WebP.
   const ext = entry.name.split(".").pop()?.toLowerCase() ?? "jpeg";
-  const mime = ext === "svg" ? "image/svg+xml" : `image/${ext === "jpg" ? "jpeg" : ext}`;
+  const mime =
+    entry.category === "fonts"
+      ? "image/webp"
+      : ext === "svg"
+        ? "image/svg+xml"
+        : `image/${ext === "jpg" ? "jpeg" : ext}`;
 
   useEffect(() => {
     // Already served from cache — no observer needed.
@@ -60,6 +66,8 @@ export default function ImageThumbnail({ sessionId, entry, onClick, onLongPress
           sessionId,
           path: entry.path,
           remoteMtime,
+          remoteSize: entry.size,
+          ...viewToken(),
         })
           .then((data) => {
             setThumbnailCached(entry.path, data);
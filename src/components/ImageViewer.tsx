@@ -85,6 +85,7 @@ export default function ImageViewer({ sessionId, images, initialIndex, onClose }
               sessionId,
               path: neighbour.path,
               remoteMtime: mtime,
+              prefetch: true,
             })
               .then((p) => setCached(neighbour.path, p))
               .catch(() => {/* silent */});
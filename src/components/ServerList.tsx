@@ -1,4 +1,4 @@
-import { useState, useEffect } from "react";
+import { useState, useEffect, useRef } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import {
   Alert,
@@ -34,14 +34,26 @@ import CloseIcon from "@mui/icons-material/Close";
 import StorageIcon from "@mui/icons-material/Storage";
 import VpnKeyIcon from "@mui/icons-material/VpnKey";
 import PasswordIcon from "@mui/icons-material/Password";
+import DialpadIcon from "@mui/icons-material/Dialpad";
 import NetworkCheckIcon from "@mui/icons-material/NetworkCheck";
-import type { AuthMethod, KeyInfo, ServerConfig } from "../lib/types";
+import QrCodeIcon from "@mui/icons-material/QrCode2";
+import type {
+  AuthMethod,
+  HostKeyInfo,
+  KeyInfo,
+  ProfileShare,
+  ServerConfig,
+} from "../lib/types";
 import {
   loadServers,
   addServer,
   removeServer,
+  updateServerHostKey,
+  updateServerPassword,
   generateId,
 } from "../lib/storage";
+import { errorText } from "../lib/errors";
+import { connectInteractive } from "../lib/interactiveAuth";
 
 // Styled icon wrapper matching reference file manager rounded-square style
 function IconWrap({ children, color = "primary.main", bgAlpha = "1a" }: {
@@ -81,6 +93,8 @@ export default function ServerList({ onConnect, variant = "page", onClose: _onCl
   const [keys, setKeys] = useState<KeyInfo[]>([]);
   const [modalOpen, setModalOpen] = useState(false);
   const [connecting, setConnecting] = useState<string | null>(null);
+  // Id of the connect in flight, so it can be aborted from the UI.
+  const attemptId = useRef<string | null>(null);
   const [error, setError] = useState<string | null>(null);
 
   // Add server form
@@ -93,8 +107,11 @@ export default function ServerList({ onConnect, variant = "page", onClose: _onCl
   const [newPassword, setNewPassword] = useState("");
   const [newMountPoint, setNewMountPoint] = useState("/home/");
   const [newIsDefault, setNewIsDefault] = useState(false);
+  const [newHostKeyPin, setNewHostKeyPin] = useState("");
+  const [newHostKeyStrict, setNewHostKeyStrict] = useState(false);
   const [testing, setTesting] = useState(false);
   const [testResult, setTestResult] = useState<{ ok: boolean; message: string } | null>(null);
+  const [share, setShare] = useState<(ProfileShare & { name: string }) | null>(null);
 
   useEffect(() => {
     setServers(loadServers());
@@ -110,7 +127,7 @@ export default function ServerList({ onConnect, variant = "page", onClose: _onCl
   }, [modalOpen]);
 
   const handleTestConnection = async () => {
-    if (!newHost || !newUser) return;
+    if (!newHost || !newUser || newAuthMethod === "interactive") return;
     if (newAuthMethod === "key" && !newKey) return;
     if (newAuthMethod === "password" && !newPassword) return;
 
@@ -124,10 +141,12 @@ export default function ServerList({ onConnect, variant = "page", onClose: _onCl
         keyName: newAuthMethod === "key" ? newKey : null,
         passphrase: null,
         password: newAuthMethod === "password" ? newPassword : null,
+        hostKeyPin: newHostKeyPin || null,
+        hostKeyStrict: newHostKeyStrict,
       });
       setTestResult({ ok: true, message: "Connection successful" });
     } catch (e) {
-      setTestResult({ ok: false, message: String(e) });
+      setTestResult({ ok: false, message: errorText(e) });
     } finally {
       setTesting(false);
     }
@@ -148,6 +167,8 @@ export default function ServerList({ onConnect, variant = "page", onClose: _onCl
       password: newAuthMethod === "password" ? newPassword : undefined,
       defaultMountPoint: newMountPoint || undefined,
       isDefault: newIsDefault,
+      hostKeyPin: newHostKeyPin.trim() || undefined,
+      hostKeyStrict: newHostKeyStrict || undefined,
     };
     setServers(addServer(server));
     setModalOpen(false);
@@ -160,9 +181,26 @@ export default function ServerList({ onConnect, variant = "page", onClose: _onCl
     setNewPassword("");
     setNewMountPoint("/home/");
     setNewIsDefault(false);
+    setNewHostKeyPin("");
+    setNewHostKeyStrict(false);
     setTestResult(null);
   };
 
+  const handleShare = async (server: ServerConfig) => {
+    try {
+      const result = await invoke<ProfileShare>("profile_share", {
+        name: server.name,
+        host: server.host,
+        port: server.port,
+        user: server.username,
+        path: server.defaultMountPoint ?? null,
+      });
+      setShare({ ...result, name: server.name });
+    } catch (e) {
+      setError(`Failed to share ${server.name}: ${e}`);
+    }
+  };
+
   const handleRemoveServer = (id: string) => {
     setServers(removeServer(id));
   };
@@ -170,23 +208,68 @@ export default function ServerList({ onConnect, variant = "page", onClose: _onCl
   const handleConnect = async (server: ServerConfig) => {
     setConnecting(server.id);
     setError(null);
+    const connect = (newPassword: string | null, hostKeyPin = server.hostKeyPin) => {
+      attemptId.current = crypto.randomUUID();
+      return server.authMethod === "interactive"
+        ? connectInteractive(server, hostKeyPin, attemptId.current)
+        : invoke<string>("ssh_connect", {
+            host: server.host,
+            port: server.port,
+            user: server.username,
+            keyName: server.authMethod === "key" ? server.keyName : null,
+            passphrase: null,
+            password: server.authMethod === "password" ? server.password : null,
+            newPassword,
+            startupCommands: server.startupCommands ?? null,
+            hostKeyPin: hostKeyPin ?? null,
+            hostKeyStrict: server.hostKeyStrict ?? false,
+            attemptId: attemptId.current,
+          });
+    };
     try {
-      const sessionId = await invoke<string>("ssh_connect", {
-        host: server.host,
-        port: server.port,
-        user: server.username,
-        keyName: server.authMethod === "key" ? server.keyName : null,
-        passphrase: null,
-        password: server.authMethod === "password" ? server.password : null,
-      });
+      let sessionId: string;
+      try {
+        sessionId = await connect(null);
+      } catch (e) {
+        const message = errorText(e);
+        if (message.startsWith("Host key mismatch:")) {
+          // Not strict: the user may accept the new key and re-pin it.
+          const presented = message.split("server presented ")[1];
+          if (!presented || !window.confirm(`${message}\n\nTrust the new key?`)) throw e;
+          sessionId = await connect(null, presented);
+          setServers(updateServerHostKey(server.id, presented));
+        } else if (message.startsWith("Password change required:")) {
+          // The server wants the (expired) password changed before login.
+          const newPassword = window.prompt(`${e}\n\nEnter a new password:`);
+          if (!newPassword) throw e;
+          sessionId = await connect(newPassword);
+          setServers(updateServerPassword(server.id, newPassword));
+        } else {
+          throw e;
+        }
+      }
+      if (!server.hostKeyPin) {
+        // Pin the key seen on first connect.
+        const hostKey = await invoke<HostKeyInfo>("ssh_session_host_key", { sessionId });
+        setServers(updateServerHostKey(server.id, hostKey.key));
+      }
       onConnect(sessionId, server.name, server.defaultMountPoint);
     } catch (e) {
-      setError(`Failed to connect to ${server.name}: ${e}`);
+      if (!errorText(e).startsWith("Cancelled:")) {
+        setError(`Failed to connect to ${server.name}: ${errorText(e)}`);
+      }
     } finally {
+      attemptId.current = null;
       setConnecting(null);
     }
   };
 
+  const handleAbortConnect = () => {
+    if (attemptId.current) {
+      invoke("abort_connect", { attemptId: attemptId.current }).catch(console.error);
+    }
+  };
+
   // ── Drawer variant: compact list for the sidebar ──
   if (variant === "drawer") {
     return (
@@ -206,17 +289,28 @@ export default function ServerList({ onConnect, variant = "page", onClose: _onCl
               key={server.id}
               disablePadding
               secondaryAction={
-                <IconButton
-                  edge="end"
-                  size="small"
-                  color="error"
-                  onClick={(e) => {
-                    e.stopPropagation();
-                    handleRemoveServer(server.id);
-                  }}
-                >
-                  <CloseIcon fontSize="small" />
-                </IconButton>
+                <>
+                  <IconButton
+                    size="small"
+                    onClick={(e) => {
+                      e.stopPropagation();
+                      handleShare(server);
+                    }}
+                  >
+                    <QrCodeIcon fontSize="small" />
+                  </IconButton>
+                  <IconButton
+                    edge="end"
+                    size="small"
+                    color="error"
+                    onClick={(e) => {
+                      e.stopPropagation();
+                      handleRemoveServer(server.id);
+                    }}
+                  >
+                    <CloseIcon fontSize="small" />
+                  </IconButton>
+                </>
               }
             >
               <ListItemButton
@@ -335,9 +429,17 @@ export default function ServerList({ onConnect, variant = "page", onClose: _onCl
                 disablePadding
                 secondaryAction={
                   connecting === server.id ? (
-                    <Typography variant="caption" color="primary">
-                      Connecting...
-                    </Typography>
+                    <Button
+                      size="small"
+                      color="inherit"
+                      onClick={(e) => {
+                        e.stopPropagation();
+                        handleAbortConnect();
+                      }}
+                      sx={{ textTransform: "none", mr: -1 }}
+                    >
+                      Connecting... Cancel
+                    </Button>
                   ) : (
                     <Button
                       size="small"
@@ -401,9 +503,39 @@ export default function ServerList({ onConnect, variant = "page", onClose: _onCl
       )}
 
       {renderAddDialog()}
+      {renderShareDialog()}
     </Box>
   );
 
+  // ── Share Profile Dialog (no credentials included) ──
+  function renderShareDialog() {
+    return (
+      <Dialog open={share !== null} onClose={() => setShare(null)} maxWidth="xs" fullWidth>
+        <DialogTitle>Share {share?.name}</DialogTitle>
+        <DialogContent>
+          <Stack spacing={2} alignItems="center">
+            {share && (
+              <Box
+                sx={{ bgcolor: "#fff", p: 1, borderRadius: 1, lineHeight: 0 }}
+                dangerouslySetInnerHTML={{ __html: share.qr_svg }}
+              />
+            )}
+            <Typography
+              variant="caption"
+              fontFamily="monospace"
+              sx={{ wordBreak: "break-all", userSelect: "text" }}
+            >
+              {share?.uri}
+            </Typography>
+            <Typography variant="caption" color="text.secondary">
+              Scan on another device. Passwords and keys are not included.
+            </Typography>
+          </Stack>
+        </DialogContent>
+      </Dialog>
+    );
+  }
+
   // ── Shared Add Server Dialog ──
   function renderAddDialog() {
     return (
@@ -492,8 +624,25 @@ export default function ServerList({ onConnect, variant = "page", onClose: _onCl
               >
                 <PasswordIcon fontSize="small" /> Password
               </ToggleButton>
+              <ToggleButton
+                value="interactive"
+                sx={{
+                  textTransform: "none",
+                  gap: 0.75,
+                  "&.Mui-selected": {
+                    borderColor: "primary.main",
+                    color: "primary.main",
+                  },
+                }}
+              >
+                <DialpadIcon fontSize="small" /> Prompts
+              </ToggleButton>
             </ToggleButtonGroup>
-            {newAuthMethod === "key" ? (
+            {newAuthMethod === "interactive" ? (
+              <Typography variant="caption" color="text.secondary">
+                The server's prompts (password, one-time code…) are asked on each connect.
+              </Typography>
+            ) : newAuthMethod === "key" ? (
               <>
                 <TextField
                   id="server-key-select"
@@ -549,6 +698,28 @@ export default function ServerList({ onConnect, variant = "page", onClose: _onCl
                 typography: { variant: "body2" },
               }}
             />
+            <TextField
+              id="server-host-key-input"
+              label="Pinned Host Key"
+              placeholder="ssh-ed25519 AAAA… or SHA256:…"
+              fullWidth
+              value={newHostKeyPin}
+              onChange={(e) => setNewHostKeyPin(e.target.value)}
+              helperText="Leave empty to pin the key seen on first connect"
+            />
+            <FormControlLabel
+              control={
+                <Switch
+                  checked={newHostKeyStrict}
+                  onChange={(e) => setNewHostKeyStrict(e.target.checked)}
+                  color="primary"
+                />
+              }
+              label="Strict: refuse any other host key"
+              slotProps={{
+                typography: { variant: "body2" },
+              }}
+            />
             {testResult && (
               <Alert
                 severity={testResult.ok ? "success" : "error"}
@@ -567,6 +738,7 @@ export default function ServerList({ onConnect, variant = "page", onClose: _onCl
                   testing ||
                   !newHost ||
                   !newUser ||
+                  newAuthMethod === "interactive" ||
                   (newAuthMethod === "key" ? !newKey : !newPassword)
                 }
                 startIcon={testing ? <CircularProgress size={18} /> : <NetworkCheckIcon />}
@@ -583,7 +755,8 @@ export default function ServerList({ onConnect, variant = "page", onClose: _onCl
                   !newName ||
                   !newHost ||
                   !newUser ||
-                  (newAuthMethod === "key" ? !newKey : !newPassword)
+                  (newAuthMethod === "key" && !newKey) ||
+                  (newAuthMethod === "password" && !newPassword)
                 }
                 sx={{ flex: 1 }}
               >
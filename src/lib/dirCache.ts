@@ -21,6 +21,22 @@ const inflightThumbs = new Set<string>();
 const MAX_PREFETCH_DIRS = 20;
 const MAX_PREFETCH_THUMBS_PER_DIR = 8;
 
+// ─── View generation ──────────────────────────────────────────────────────────
+
+// Each navigation bumps the generation; the backend aborts thumbnail and
+// prefetch work still tagged with an older one.
+const viewId = crypto.randomUUID();
+let generation = 0;
+
+export function viewToken(): { viewId: string; generation: number } {
+  return { viewId, generation };
+}
+
+export function nextViewGeneration(): { viewId: string; generation: number } {
+  generation += 1;
+  return viewToken();
+}
+
 // ─── Cache accessors ──────────────────────────────────────────────────────────
 
 export function getDirCached(path: string): FileEntry[] | null {
@@ -50,7 +66,7 @@ export function clearDirCache(): void {
 
 function prefetchThumbnails(entries: FileEntry[], sessionId: string): void {
   const images = entries
-    .filter((e) => e.is_image)
+    .filter((e) => e.is_image || e.category === "fonts")
     .slice(0, MAX_PREFETCH_THUMBS_PER_DIR);
 
   for (const img of images) {
@@ -60,7 +76,12 @@ function prefetchThumbnails(entries: FileEntry[], sessionId: string): void {
     const remoteMtime = img.modified
       ? Math.floor(new Date(img.modified).getTime() / 1000)
       : undefined;
-    invoke<string>("sftp_get_thumbnail", { sessionId, path: img.path, remoteMtime })
+    invoke<string>("sftp_get_thumbnail", {
+      sessionId,
+      path: img.path,
+      remoteMtime,
+      ...viewToken(),
+    })
       .then((b64) => setThumbnailCached(img.path, b64))
       .catch(() => {})
       .finally(() => inflightThumbs.delete(img.path));
@@ -80,7 +101,7 @@ export function prefetchChildren(entries: FileEntry[], sessionId: string): void
     if (dirCache.has(dir.path) || inflightDirs.has(dir.path)) continue;
     inflightDirs.add(dir.path);
 
-    invoke<FileEntry[]>("sftp_list_dir", { sessionId, path: dir.path })
+    invoke<FileEntry[]>("sftp_list_dir", { sessionId, path: dir.path, ...viewToken() })
       .then((childEntries) => {
         setDirCached(dir.path, childEntries);
         prefetchThumbnails(childEntries, sessionId);
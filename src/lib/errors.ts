@@ -0,0 +1,10 @@
+/**
+ * Text of an error thrown by `invoke`: most errors arrive as strings,
+ * structured ones (e.g. `AuthRejectedError`) carry a `message`.
+ */
+export function errorText(e: unknown): string {
+  if (typeof e === "object" && e !== null && "message" in e) {
+    return String((e as { message: unknown }).message);
+  }
+  return String(e);
+}
@@ -0,0 +1,48 @@
+import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
+import type { AuthPrompt, ServerConfig } from "./types";
+
+/** Ask for each field of a keyboard-interactive round; `null` if dismissed. */
+function askUser(prompt: AuthPrompt): string[] | null {
+  const header = [prompt.name, prompt.instructions].filter(Boolean).join("\n");
+  const answers: string[] = [];
+  for (const field of prompt.prompts) {
+    const answer = window.prompt(header ? `${header}\n\n${field.prompt}` : field.prompt);
+    if (answer === null) return null;
+    answers.push(answer);
+  }
+  return answers;
+}
+
+/**
+ * Connect to `server` with keyboard-interactive authentication (e.g. OTP
+ * prompts), answering each `auth-prompt` round the backend emits.
+ * Resolves to the session id. Passing `attemptId` lets the connect be
+ * aborted with `abort_connect`.
+ */
+export async function connectInteractive(
+  server: ServerConfig,
+  hostKeyPin = server.hostKeyPin,
+  attemptId: string | null = null,
+): Promise<string> {
+  const authId = crypto.randomUUID();
+  const unlisten = await listen<AuthPrompt>("auth-prompt", (event) => {
+    if (event.payload.auth_id !== authId) return;
+    const answers = askUser(event.payload);
+    invoke("ssh_auth_respond", { authId, answers }).catch(console.error);
+  });
+  try {
+    return await invoke<string>("ssh_connect_interactive", {
+      host: server.host,
+      port: server.port,
+      user: server.username,
+      authId,
+      startupCommands: server.startupCommands ?? null,
+      hostKeyPin: hostKeyPin ?? null,
+      hostKeyStrict: server.hostKeyStrict ?? false,
+      attemptId,
+    });
+  } finally {
+    unlisten();
+  }
+}
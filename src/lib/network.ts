@@ -0,0 +1,54 @@
+import { invoke } from "@tauri-apps/api/core";
+
+type NetworkType = "unknown" | "wifi" | "ethernet" | "cellular";
+
+// Network Information API (Android WebView); absent on most desktops.
+interface NetworkInformation extends EventTarget {
+  type?: string;
+}
+
+function currentType(): NetworkType {
+  const conn = (navigator as Navigator & { connection?: NetworkInformation }).connection;
+  switch (conn?.type) {
+    case "wifi":
+      return "wifi";
+    case "ethernet":
+      return "ethernet";
+    case "cellular":
+      return "cellular";
+    default:
+      return "unknown";
+  }
+}
+
+/**
+ * Report the network type to the backend now and on every change, so
+ * transfer policies (pausing large files, no prefetch) follow cellular.
+ * Returns a cleanup function.
+ */
+export function startNetworkReporting(): () => void {
+  const conn = (navigator as Navigator & { connection?: NetworkInformation }).connection;
+  const report = () => {
+    invoke("set_network_type", { network: currentType() }).catch(console.error);
+  };
+  report();
+  conn?.addEventListener("change", report);
+  return () => conn?.removeEventListener("change", report);
+}
+
+/**
+ * Invoke `command`, asking the user before retrying with `confirm: true`
+ * when the backend flags a download over a confirmation threshold.
+ */
+export async function invokeConfirmingLargeDownload<T>(
+  command: string,
+  args: Record<string, unknown>,
+): Promise<T> {
+  try {
+    return await invoke<T>(command, args);
+  } catch (e) {
+    if (!String(e).startsWith("Large download:")) throw e;
+    if (!window.confirm(`${e}\n\nDownload anyway?`)) throw e;
+    return invoke<T>(command, { ...args, confirm: true });
+  }
+}
@@ -0,0 +1,34 @@
+import { Channel, invoke } from "@tauri-apps/api/core";
+import type { FilePreview } from "./types";
+
+interface PreviewChunk {
+  index: number;
+  data: string;
+}
+
+/**
+ * Invoke `sftp_read_file_preview`, letting the backend stream large
+ * content over a channel instead of one JSON string, and reassemble it.
+ */
+export async function invokePreview(args: Record<string, unknown>): Promise<FilePreview> {
+  const parts: string[] = [];
+  let received = 0;
+  let expected: number | null = null;
+  let done: () => void = () => {};
+  const complete = new Promise<void>((resolve) => (done = resolve));
+
+  const onChunk = new Channel<PreviewChunk>();
+  onChunk.onmessage = (chunk) => {
+    parts[chunk.index] = chunk.data;
+    received += 1;
+    if (received === expected) done();
+  };
+
+  const preview = await invoke<FilePreview>("sftp_read_file_preview", { ...args, onChunk });
+  if (!preview.chunks) return preview;
+
+  // Chunks may still be in flight when the command resolves.
+  expected = preview.chunks;
+  if (received < expected) await complete;
+  return { ...preview, content: parts.join(""), chunks: 0 };
+}
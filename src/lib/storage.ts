@@ -100,6 +100,24 @@ export function removeServer(id: string): ServerConfig[] {
   return servers;
 }
 
+export function updateServerPassword(id: string, password: string): ServerConfig[] {
+  const servers = loadServers();
+  servers.forEach((s) => {
+    if (s.id === id) s.password = password;
+  });
+  saveServers(servers);
+  return servers;
+}
+
+export function updateServerHostKey(id: string, hostKeyPin: string): ServerConfig[] {
+  const servers = loadServers();
+  servers.forEach((s) => {
+    if (s.id === id) s.hostKeyPin = hostKeyPin;
+  });
+  saveServers(servers);
+  return servers;
+}
+
 export function generateId(): string {
   return `${Date.now()}-${Math.random().toString(36).substr(2, 9)}`;
 }
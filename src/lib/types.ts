@@ -10,12 +10,21 @@ export interface KeyInfo {
 }
 
 export interface FileEntry {
-  name: string;
-  path: string;
+  name: string;         // NFC-normalized display name
+  path: string;         // exact server path — pass back unchanged
+  invalid_name: boolean;
+  raw_name?: string;  // base64 name bytes when not UTF-8; path is then opaque
   is_dir: boolean;
   size: number;
   modified: string | null;
+  is_symlink: boolean;  // is_dir/size describe the link's target
+  link_target: string | null;
+  category: FileCategory; // "other" for directories
   is_image: boolean;
+  placeholder_color: string | null; // "#rrggbb" once a thumbnail is cached
+  mode: number | null;  // permission bits (0o7777 mask)
+  uid: number | null;
+  gid: number | null;
 }
 
 export interface ImageCacheEntry {
@@ -24,11 +33,20 @@ export interface ImageCacheEntry {
   cachedAt: number;    // Date.now() timestamp
 }
 
+export interface PreviewMatch {
+  line: number;   // 1-based
+  column: number; // UTF-16 offset within the line
+  start: number;  // UTF-16 offsets into `content`
+  end: number;
+}
+
 export interface FilePreview {
   content: string;
   is_text: boolean;
   truncated: boolean;
   total_size: number;
+  matches?: PreviewMatch[]; // present when opened with a search query
+  chunks: number; // > 0 when content was streamed over a channel
 }
 
 export interface SessionInfo {
@@ -54,7 +72,7 @@ export interface FolderSettings {
   showFoldersSize: boolean;
 }
 
-export type AuthMethod = "key" | "password";
+export type AuthMethod = "key" | "password" | "interactive";
 
 export interface ServerConfig {
   id: string;
@@ -67,4 +85,273 @@ export interface ServerConfig {
   password?: string;
   defaultMountPoint?: string;
   isDefault?: boolean;
+  startupCommands?: string[]; // run over exec right after connecting
+  hostKeyPin?: string;        // "type base64" or "SHA256:…"; captured on first connect
+  hostKeyStrict?: boolean;    // refuse any other key, with no override
+}
+
+export interface AuthPrompt {
+  auth_id: string;
+  name: string;
+  instructions: string;
+  prompts: { prompt: string; echo: boolean }[];
+}
+
+export interface HostKeyInfo {
+  key: string;         // "type base64", suitable for pinning
+  fingerprint: string; // "SHA256:…"
+}
+
+export interface ProfileShare {
+  uri: string;      // sftp://user@host:port/path#name, no credentials
+  qr_svg: string;
+}
+
+export interface OutlineNode {
+  key: string | null;   // object key; null for root and array items
+  kind: "object" | "array" | "string" | "number" | "bool" | "null";
+  len: number | null;   // key/item count for objects and arrays
+  value: string | null; // short rendering of scalars
+  children: OutlineNode[]; // array nodes carry only their first item
+}
+
+export interface StructuredPreview {
+  format: "json" | "yaml";
+  pretty: string;
+  outline: OutlineNode;
+  documents: number;
+}
+
+export interface ServiceUnit {
+  unit: string;
+  load: string;    // "loaded", "not-found", ...
+  active: string;  // "active", "inactive", "failed", ...
+  sub: string;     // "running", "exited", "dead", ...
+  description: string;
+}
+
+export type ServiceAction =
+  | "start"
+  | "stop"
+  | "restart"
+  | "reload"
+  | "enable"
+  | "disable"
+  | "status";
+
+export interface ServiceActionResult {
+  unit: string;
+  action: ServiceAction;
+  exit_status: number | null;
+  output: string;
+  state: ServiceUnit | null;
+}
+
+export interface Crontab {
+  user: string | null;        // null = the session's own user
+  content: string;
+  exists: boolean;
+  backup_path: string | null; // previous table, saved by ssh_crontab_set
+}
+
+export interface SessionMotd {
+  banner: string | null; // sent before authentication
+  motd: string | null;   // login message of the day
+}
+
+export interface SessionEvent {
+  at: string; // RFC 3339
+  kind:
+    | "connected"
+    | "channel_opened"
+    | "sftp_fallback"
+    | "connection_lost"
+    | "disconnected"
+    | "transfer_failed";
+  detail: string;
+}
+
+export type FileCategory =
+  | "images"
+  | "video"
+  | "audio"
+  | "documents"
+  | "text"
+  | "code"
+  | "archives"
+  | "fonts"
+  | "other";
+
+export interface TypeTotals {
+  files: number;
+  bytes: number;
+}
+
+export interface CategoryStats extends TypeTotals {
+  category: FileCategory;
+  extensions: (TypeTotals & { extension: string })[]; // largest first
+}
+
+export interface TypeStats {
+  root: string;
+  total: TypeTotals;
+  dirs: number;
+  categories: CategoryStats[]; // largest first
+  unreadable: number;
+  truncated: boolean;
+}
+
+export interface DeleteSummary {
+  files_deleted: number;
+  dirs_deleted: number;
+  failed: { path: string; error: string }[];
+}
+
+export interface QueuedDownload {
+  id: string;
+  kind: "file" | "dir";
+  host: string;
+  user: string;
+  remote_path: string;
+  local_path: string;
+  bytes_done: number;
+  bytes_total: number;
+  remote_mtime: number | null;
+  started_at: string;
+  post_actions: PostAction[]; // run once the download completes
+}
+
+export interface OfflinePin {
+  id: string;
+  host: string;
+  port: number;
+  user: string;
+  remote_path: string;
+  local_path: string;
+  size: number;
+  mtime: number | null;
+  pinned_at: string;
+  synced_at: string;
+}
+
+export interface DirSnapshot {
+  path: string;
+  entries: FileEntry[];
+  captured_at: string;
+}
+
+export type SnapshotSource = "pin" | "preview_cache";
+
+export interface OfflineFile {
+  path: string;
+  source: SnapshotSource;
+  preview: FilePreview | null;
+  local_path: string | null;
+  captured_at: string | null;
+}
+
+export type PostAction = "open" | "reveal" | "checksum" | "delete_remote";
+
+export interface PostActionResult {
+  action: PostAction;
+  error: string | null;
+  sha256: string | null;
+}
+
+/** A login the server refused, as thrown by the connect commands. */
+export interface AuthRejectedError {
+  kind: "auth_rejected";
+  message: string;
+  methods_remaining: string[];
+  key_attempted: boolean;
+  partial_success: boolean;
+  hints: string[];
+}
+
+/** Progress of a slow directory listing. */
+export type ListProgress =
+  | { kind: "waiting"; path: string; elapsed_ms: number }
+  | {
+      kind: "received";
+      path: string;
+      batch: FileEntry[];
+      entries: number;
+      elapsed_ms: number;
+    };
+
+/** One batch of a listing, emitted as `listing://partial`. */
+export interface ListingPartial {
+  session_id: string;
+  view_id: string | null;
+  path: string;
+  entries: FileEntry[];
+}
+
+export interface UserQuota {
+  filesystem: string;
+  used_bytes: number;
+  soft_limit_bytes: number; // 0 = no limit
+  hard_limit_bytes: number;
+  used_files: number;
+  soft_limit_files: number;
+  hard_limit_files: number;
+}
+
+export type SpaceWarning =
+  | { kind: "low_space"; free_bytes: number }
+  | { kind: "low_inodes"; free_inodes: number }
+  | { kind: "quota_bytes"; used_bytes: number; limit_bytes: number }
+  | { kind: "quota_files"; used_files: number; limit_files: number };
+
+export interface DiskUsage {
+  path: string;
+  filesystem: string;
+  mount_point: string;
+  total_bytes: number;
+  free_bytes: number;
+  total_inodes: number | null; // null on btrfs/ZFS
+  free_inodes: number | null;
+  quota: UserQuota | null;
+  warnings: SpaceWarning[];
+}
+
+export interface SearchOptions {
+  kind?: "glob" | "regex";
+  case_sensitive?: boolean;
+  max_depth?: number | null;
+  max_results?: number | null;
+  include_dirs?: boolean;
+  include_hidden?: boolean; // descend into dot-directories
+  include_ignored?: boolean; // search names matching the ignore patterns too
+}
+
+export interface SearchMatch {
+  entry: FileEntry;
+  rel: string; // relative to the search root
+  depth: number;
+}
+
+export interface SearchSummary {
+  root: string;
+  matches: number;
+  scanned: number;
+  dirs_listed: number;
+  unreadable: number;
+  truncated: boolean;
+}
+
+/** Payload of `tail://data` events. */
+export type TailEvent =
+  | { kind: "data"; tail_id: string; lines: string[] }
+  | { kind: "truncated"; tail_id: string }
+  | { kind: "stopped"; tail_id: string; error: string | null };
+
+export interface TextStats {
+  path: string;
+  lines: number;
+  words: number;
+  bytes: number;
+  longest_line: number;               // bytes
+  longest_line_number: number | null; // 1-based; null for an empty file
+  truncated: boolean;                 // counts cover only a prefix
 }